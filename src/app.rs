@@ -0,0 +1,191 @@
+use std::{fs::OpenOptions, io::Write, net::SocketAddr, path::{Path, PathBuf}, time::Duration};
+use axum::{serve as serve_http, Router};
+use tokio::net::TcpListener;
+use crate::config::{AppConfig, CorsConfig, Database, PaymentConfig, RateLimitConfig};
+use crate::routes::create_router;
+use crate::utils::{connect_db, InMemoryStore, Role, SqliteStore, Store};
+#[cfg(feature = "postgres")]
+use crate::utils::PostgresStore;
+use crate::{grpc, tls, webhooks};
+
+/// Builds the order API as an axum `Router`, to serve on its own or to nest or merge into another application.
+///
+/// Every resource, API keys included, lives in the store given to `new`. The router only answers requests:
+/// call `spawn_background_tasks` as well so webhooks get delivered.
+#[derive(Debug)]
+pub struct AppBuilder<S> {
+    store: S,
+    config: AppConfig,
+}
+
+impl<S: Store + Clone> AppBuilder<S> {
+    /// Builder using `store` and the default configuration
+    pub fn new(store: S) -> Self {
+        AppBuilder {
+            store,
+            config: AppConfig::default(),
+        }
+    }
+
+    pub fn build(self) -> Router {
+        create_router(self.store, self.config)
+    }
+
+    /// Replace the whole configuration, including any settings made so far
+    pub fn config(mut self, config: AppConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Per-client request budgets of the API endpoints
+    pub fn rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.config.rate_limit = rate_limit;
+        self
+    }
+
+    /// Origins, methods and headers allowed in cross-origin requests
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.config.cors = cors;
+        self
+    }
+
+    /// Largest request body accepted; larger ones are rejected with 413
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.config.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Longest a request may take; slower ones are aborted with 504
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.config.request_timeout = request_timeout;
+        self
+    }
+
+    /// Payment gateway and whether shipping requires a captured payment
+    pub fn payments(mut self, payments: PaymentConfig) -> Self {
+        self.config.payments = payments;
+        self
+    }
+
+    /// Start delivering webhooks from the store's outbox, for as long as the runtime lives
+    pub fn spawn_background_tasks(&self) -> tokio::task::JoinHandle<()> {
+        webhooks::spawn_webhook_dispatcher(self.store.clone(), self.config.webhooks)
+    }
+}
+
+/// File the generated bootstrap API key is written to when `ORDERS_API_KEY_FILE` is not set
+const DEFAULT_API_KEY_FILE: &str = "orders-api-key";
+
+/// Write `secret` to `path`, replacing any previous contents, with permissions allowing only the owner to read it
+fn write_secret_file(path: &Path, secret: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files, so tighten an existing one before writing to it
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let mut file = options.open(path)?;
+    writeln!(file, "{}", secret)
+}
+
+/// Run the API on its own: HTTP on port 3000 and gRPC on `config.grpc_port`, both on localhost and both
+/// over TLS when `config.tls` is set, with an admin API key taken from `ORDERS_API_KEY` or generated and
+/// written to the file named by `ORDERS_API_KEY_FILE` (`orders-api-key` by default), readable by the owner only.
+/// Everything is kept in the database `config.database` names.
+pub async fn serve(config: AppConfig) {
+    match config.database.clone() {
+        Database::Sqlite(url) => {
+            // Initialize the database
+            let db_pool = connect_db(&url).await.expect("Failed to initialize database");
+            serve_store(SqliteStore::new(db_pool), config).await
+        }
+        Database::Memory => serve_store(InMemoryStore::default(), config).await,
+        #[cfg(feature = "postgres")]
+        Database::Postgres(options) => serve_store(PostgresStore::connect_lazy(*options), config).await,
+    }
+}
+
+async fn serve_store<S: Store + Clone>(store: S, config: AppConfig) {
+    // Seed an admin API key so the order endpoints are reachable
+    match std::env::var("ORDERS_API_KEY") {
+        Ok(secret) => {
+            store.register_api_key("bootstrap", Role::Admin, &secret).await
+                .expect("Failed to register ORDERS_API_KEY");
+            println!("Registered API key from ORDERS_API_KEY");
+        }
+        Err(_) => {
+            let (_, secret) = store.create_api_key("bootstrap", Role::Admin).await
+                .expect("Failed to create bootstrap API key");
+            // Never log the secret: standard output ends up in container and journal logs
+            let path = PathBuf::from(std::env::var("ORDERS_API_KEY_FILE").unwrap_or_else(|_| DEFAULT_API_KEY_FILE.to_string()));
+            write_secret_file(&path, &secret).expect("Failed to write bootstrap API key file");
+            println!("Generated bootstrap admin API key, written to {}", path.display());
+        }
+    }
+
+    let tls_config = config.tls.clone();
+
+    if let Some(tls_config) = &tls_config {
+        for (subject, role) in &tls_config.client_identities {
+            store.register_certificate_identity(subject, subject, *role).await
+                .expect("Failed to register TLS client identity");
+        }
+    }
+
+    let builder = AppBuilder::new(store.clone()).config(config.clone());
+    builder.spawn_background_tasks();
+    let app = builder.build();
+
+    let grpc_addr = SocketAddr::from(([127, 0, 0, 1], config.grpc_port));
+    let grpc_listener = TcpListener::bind(grpc_addr).await.unwrap();
+    let grpc_scheme = if tls_config.is_some() { "https" } else { "http" };
+    println!("gRPC order service running at {}://{}", grpc_scheme, grpc_addr);
+    tokio::spawn(async move {
+        if let Err(e) = grpc::serve_grpc(grpc_listener, store, &config).await {
+            eprintln!("gRPC server error: {}", e);
+        }
+    });
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+
+    match tls_config {
+        Some(tls_config) => {
+            println!("Server running at https://{}", addr);
+            let listener = std::net::TcpListener::bind(addr).unwrap();
+            tls::serve_tls(listener, app, &tls_config, axum_server::Handle::new()).await.unwrap();
+        }
+        None => {
+            println!("Server running at http://{}", addr);
+            let listener = TcpListener::bind(addr).await.unwrap();
+            // Connection info lets the rate limiter fall back to the client IP
+            serve_http(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("rustapi-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("key");
+        std::fs::write(&path, "previous secret, world readable").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_secret_file(&path, "new-secret").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new-secret\n");
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
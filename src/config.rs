@@ -0,0 +1,388 @@
+use std::{fmt, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use sqlx::sqlite::SqliteConnectOptions;
+#[cfg(feature = "postgres")]
+use sqlx::postgres::PgConnectOptions;
+use crate::payments::{FakePaymentGateway, PaymentGateway};
+use crate::utils::{Role, DEFAULT_DATABASE_URL};
+
+/// Token bucket settings for one group of routes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Maximum number of requests that can be made in a burst
+    pub burst: u32,
+    /// Number of requests replenished per second
+    pub per_second: f64,
+}
+
+/// Rate limits applied to the order endpoints, per route group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    /// Limit for read requests (GET, HEAD)
+    pub read: RateLimit,
+    /// Limit for requests that modify orders (POST, PUT, PATCH, DELETE)
+    pub write: RateLimit,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            read: RateLimit { burst: 120, per_second: 20.0 },
+            write: RateLimit { burst: 30, per_second: 5.0 },
+        }
+    }
+}
+
+/// Cross-origin resource sharing settings for browser clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API; empty disables CORS, `*` allows any origin
+    pub allowed_origins: Vec<String>,
+    /// HTTP methods allowed in cross-origin requests
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                .iter().map(|m| m.to_string()).collect(),
+            allowed_headers: ["content-type", "authorization", "x-api-key"]
+                .iter().map(|h| h.to_string()).collect(),
+        }
+    }
+}
+
+/// HTTPS settings; when absent the server speaks plain HTTP
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file containing the server certificate chain
+    pub cert_path: PathBuf,
+    /// PEM file containing the server private key
+    pub key_path: PathBuf,
+    /// PEM file with the CA certificates trusted to sign client certificates.
+    /// When set, clients may authenticate with a certificate instead of an API key.
+    pub client_ca_path: Option<PathBuf>,
+    /// Client certificate common names and the role granted to each
+    pub client_identities: Vec<(String, Role)>,
+    /// How often the certificate files are checked for changes
+    pub reload_interval: Duration,
+}
+
+/// Payment provider and the rules tying payments to order fulfilment
+#[derive(Debug, Clone)]
+pub struct PaymentConfig {
+    /// Provider used to authorize, capture, void and refund payments
+    pub gateway: Arc<dyn PaymentGateway>,
+    /// Orders can only move to `shipped` once a payment for them has been captured
+    pub require_capture_to_ship: bool,
+}
+
+impl Default for PaymentConfig {
+    fn default() -> Self {
+        PaymentConfig {
+            gateway: Arc::new(FakePaymentGateway::default()),
+            require_capture_to_ship: false,
+        }
+    }
+}
+
+/// Delivery settings for outgoing webhooks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WebhookConfig {
+    /// How often the outbox is checked for events that are due
+    pub poll_interval: Duration,
+    /// Delay before the first retry; each further retry waits twice as long
+    pub retry_base_delay: Duration,
+    /// Longest delay between two attempts
+    pub max_retry_delay: Duration,
+    /// Attempts after which an event is given up on
+    pub max_attempts: u32,
+    /// Time a receiver has to answer a delivery
+    pub request_timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            poll_interval: Duration::from_secs(1),
+            retry_base_delay: Duration::from_secs(10),
+            max_retry_delay: Duration::from_secs(60 * 60),
+            max_attempts: 10,
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Default interval between checks for renewed certificate files
+pub const DEFAULT_TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default maximum request body size (64 KiB)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Default time a request may take before it is aborted
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default port of the gRPC order service
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+
+/// Where the API keeps orders and every other resource, picked by the scheme of `DATABASE_URL`
+#[derive(Clone)]
+pub enum Database {
+    /// The SQLite database at this `sqlite:` URL
+    Sqlite(String),
+    /// Process memory, lost on restart (`memory:`)
+    Memory,
+    /// The PostgreSQL database at a `postgres://` or `postgresql://` URL
+    #[cfg(feature = "postgres")]
+    Postgres(Box<PgConnectOptions>),
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Database::Sqlite(DEFAULT_DATABASE_URL.to_string())
+    }
+}
+
+impl fmt::Debug for Database {
+    /// Only SQLite URLs are shown; PostgreSQL URLs may carry a password
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Database::Sqlite(url) => f.debug_tuple("Sqlite").field(url).finish(),
+            Database::Memory => f.write_str("Memory"),
+            #[cfg(feature = "postgres")]
+            Database::Postgres(_) => f.write_str("Postgres(..)"),
+        }
+    }
+}
+
+impl FromStr for Database {
+    type Err = String;
+
+    /// A `sqlite:`, `memory:`, `postgres://` or `postgresql://` URL.
+    /// Errors never include the URL, which may carry a password.
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let scheme = url.split_once(':').map(|(scheme, _)| scheme).unwrap_or_default();
+        match scheme {
+            "sqlite" => {
+                SqliteConnectOptions::from_str(url).map_err(|e| format!("Invalid SQLite URL: {}", e))?;
+                Ok(Database::Sqlite(url.to_string()))
+            }
+            "memory" => Ok(Database::Memory),
+            #[cfg(feature = "postgres")]
+            "postgres" | "postgresql" => PgConnectOptions::from_str(url)
+                .map(|options| Database::Postgres(Box::new(options)))
+                .map_err(|e| format!("Invalid PostgreSQL URL: {}", e)),
+            #[cfg(not(feature = "postgres"))]
+            "postgres" | "postgresql" => Err(
+                "PostgreSQL URLs need a build with the postgres feature (cargo build --features postgres)".to_string()
+            ),
+            _ => Err("Unsupported database URL scheme; expected sqlite:, memory:, postgres:// or postgresql://".to_string()),
+        }
+    }
+}
+
+/// Runtime configuration for the API
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub rate_limit: RateLimitConfig,
+    pub cors: CorsConfig,
+    /// Requests with larger bodies are rejected with 413
+    pub max_body_bytes: usize,
+    /// Requests taking longer are aborted with 504
+    pub request_timeout: Duration,
+    pub tls: Option<TlsConfig>,
+    pub payments: PaymentConfig,
+    pub webhooks: WebhookConfig,
+    /// Port the gRPC order service listens on, next to the HTTP API
+    pub grpc_port: u16,
+    /// Storage `serve` keeps orders and every other resource in
+    pub database: Database,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            rate_limit: RateLimitConfig::default(),
+            cors: CorsConfig::default(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            tls: None,
+            payments: PaymentConfig::default(),
+            webhooks: WebhookConfig::default(),
+            grpc_port: DEFAULT_GRPC_PORT,
+            database: Database::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Build the configuration from environment variables, falling back to defaults.
+    /// Fails if `DATABASE_URL` is set but cannot be used, rather than silently keeping data elsewhere:
+    ///
+    /// - `RATE_LIMIT_READ_BURST`, `RATE_LIMIT_READ_PER_SECOND`
+    /// - `RATE_LIMIT_WRITE_BURST`, `RATE_LIMIT_WRITE_PER_SECOND`
+    /// - `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS`, `CORS_ALLOWED_HEADERS` (comma separated)
+    /// - `MAX_BODY_BYTES`, `REQUEST_TIMEOUT_MS`
+    /// - `TLS_CERT_PATH`, `TLS_KEY_PATH`, `TLS_CLIENT_CA_PATH`, `TLS_RELOAD_INTERVAL_SECS`
+    /// - `TLS_CLIENT_IDENTITIES` (comma separated `common_name:role` pairs)
+    /// - `REQUIRE_CAPTURE_TO_SHIP` (`true` or `false`)
+    /// - `FAKE_PAYMENT_DECLINE_ABOVE_MINOR` (the fake gateway declines larger authorizations)
+    /// - `WEBHOOK_POLL_INTERVAL_MS`, `WEBHOOK_RETRY_BASE_MS`, `WEBHOOK_MAX_RETRY_DELAY_MS`,
+    ///   `WEBHOOK_MAX_ATTEMPTS`, `WEBHOOK_TIMEOUT_MS`
+    /// - `GRPC_PORT`
+    /// - `DATABASE_URL` (`sqlite:`, `memory:`, `postgres://` or `postgresql://`)
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = RateLimitConfig::default();
+        let cors = CorsConfig::default();
+        let webhooks = WebhookConfig::default();
+
+        Ok(AppConfig {
+            rate_limit: RateLimitConfig {
+                read: RateLimit {
+                    burst: env_or("RATE_LIMIT_READ_BURST", defaults.read.burst),
+                    per_second: env_or("RATE_LIMIT_READ_PER_SECOND", defaults.read.per_second),
+                },
+                write: RateLimit {
+                    burst: env_or("RATE_LIMIT_WRITE_BURST", defaults.write.burst),
+                    per_second: env_or("RATE_LIMIT_WRITE_PER_SECOND", defaults.write.per_second),
+                },
+            },
+            cors: CorsConfig {
+                allowed_origins: env_list_or("CORS_ALLOWED_ORIGINS", cors.allowed_origins),
+                allowed_methods: env_list_or("CORS_ALLOWED_METHODS", cors.allowed_methods),
+                allowed_headers: env_list_or("CORS_ALLOWED_HEADERS", cors.allowed_headers),
+            },
+            max_body_bytes: env_or("MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES),
+            request_timeout: Duration::from_millis(
+                env_or("REQUEST_TIMEOUT_MS", DEFAULT_REQUEST_TIMEOUT.as_millis() as u64)
+            ),
+            tls: tls_from_env(),
+            payments: PaymentConfig {
+                gateway: payment_gateway_from_env(),
+                require_capture_to_ship: env_or("REQUIRE_CAPTURE_TO_SHIP", false),
+            },
+            webhooks: WebhookConfig {
+                poll_interval: env_duration_ms_or("WEBHOOK_POLL_INTERVAL_MS", webhooks.poll_interval),
+                retry_base_delay: env_duration_ms_or("WEBHOOK_RETRY_BASE_MS", webhooks.retry_base_delay),
+                max_retry_delay: env_duration_ms_or("WEBHOOK_MAX_RETRY_DELAY_MS", webhooks.max_retry_delay),
+                max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", webhooks.max_attempts),
+                request_timeout: env_duration_ms_or("WEBHOOK_TIMEOUT_MS", webhooks.request_timeout),
+            },
+            grpc_port: env_or("GRPC_PORT", DEFAULT_GRPC_PORT),
+            database: database_from_env()?,
+        })
+    }
+}
+
+/// HTTPS is enabled when both `TLS_CERT_PATH` and `TLS_KEY_PATH` are set
+fn tls_from_env() -> Option<TlsConfig> {
+    let cert_path = std::env::var("TLS_CERT_PATH").ok()?;
+    let key_path = std::env::var("TLS_KEY_PATH").ok()?;
+
+    let client_identities = env_list_or("TLS_CLIENT_IDENTITIES", Vec::new())
+        .into_iter()
+        .filter_map(|entry| {
+            let parsed = entry.rsplit_once(':')
+                .and_then(|(name, role)| Some((name.trim().to_string(), role.trim().parse().ok()?)));
+            if parsed.is_none() {
+                eprintln!("Ignoring invalid TLS client identity: {}", entry);
+            }
+            parsed
+        })
+        .collect();
+
+    Some(TlsConfig {
+        cert_path: PathBuf::from(cert_path),
+        key_path: PathBuf::from(key_path),
+        client_ca_path: std::env::var("TLS_CLIENT_CA_PATH").ok().map(PathBuf::from),
+        client_identities,
+        reload_interval: Duration::from_secs(
+            env_or("TLS_RELOAD_INTERVAL_SECS", DEFAULT_TLS_RELOAD_INTERVAL.as_secs())
+        ),
+    })
+}
+
+/// Unlike `env_or`, an invalid value is an error, and it is never logged
+fn database_from_env() -> Result<Database, String> {
+    match std::env::var("DATABASE_URL") {
+        Ok(url) => url.parse().map_err(|e| format!("Invalid DATABASE_URL: {}", e)),
+        Err(_) => Ok(Database::default()),
+    }
+}
+
+/// No real payment provider is integrated yet, so the fake gateway is always used
+fn payment_gateway_from_env() -> Arc<dyn PaymentGateway> {
+    match std::env::var("FAKE_PAYMENT_DECLINE_ABOVE_MINOR") {
+        Ok(_) => Arc::new(FakePaymentGateway::declining_above(env_or("FAKE_PAYMENT_DECLINE_ABOVE_MINOR", i64::MAX))),
+        Err(_) => Arc::new(FakePaymentGateway::default()),
+    }
+}
+
+/// Read and parse an environment variable, using `default` if it is unset or invalid
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("Ignoring invalid value for {}: {}", name, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Read a duration in milliseconds from an environment variable, using `default` if it is unset or invalid
+fn env_duration_ms_or(name: &str, default: Duration) -> Duration {
+    Duration::from_millis(env_or(name, default.as_millis() as u64))
+}
+
+/// Read a comma separated environment variable, using `default` if it is unset
+fn env_list_or(name: &str, default: Vec<String>) -> Vec<String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_url_scheme_picks_the_store() {
+        assert!(matches!("sqlite::memory:".parse(), Ok(Database::Sqlite(url)) if url == "sqlite::memory:"));
+        assert!(matches!("sqlite://orders.db".parse(), Ok(Database::Sqlite(_))));
+        assert!(matches!("memory:".parse(), Ok(Database::Memory)));
+        assert!(matches!(Database::default(), Database::Sqlite(url) if url == DEFAULT_DATABASE_URL));
+    }
+
+    #[test]
+    fn test_unusable_database_url_is_an_error_without_the_url() {
+        for url in ["mysql://admin:hunter2@db/orders", "orders.db", ""] {
+            let err = url.parse::<Database>().unwrap_err();
+            assert!(err.starts_with("Unsupported database URL scheme"), "{}", err);
+            assert!(!err.contains("hunter2"));
+        }
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[test]
+    fn test_postgres_url_needs_the_postgres_feature() {
+        let err = "postgres://admin:hunter2@db/orders".parse::<Database>().unwrap_err();
+        assert!(err.contains("postgres feature"));
+        assert!(!err.contains("hunter2"));
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_postgres_url_keeps_its_password_out_of_debug_output() {
+        let database: Database = "postgresql://admin:hunter2@db/orders".parse().unwrap();
+        assert!(matches!(database, Database::Postgres(_)));
+        assert!(!format!("{:?}", database).contains("hunter2"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use async_graphql::{
+    ComplexObject, Context, EmptySubscription, ErrorExtensions, InputObject, Object, Schema, SimpleObject
+};
+use axum::response::IntoResponse;
+use crate::config::PaymentConfig;
+use crate::middleware::Caller;
+use crate::utils::{Customer, Order, OrderFilter, Payment, Scope, Shipment, ShippingAddress, Store, DEFAULT_CURRENCY};
+use crate::validators::{validate_order, validate_status, ApiError, ValidationError};
+
+/// Schema served at `/v1/graphql`; resolvers read the store, caller and payment settings from the request data
+pub type OrdersSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Orders returned by `orders` when `first` is not given
+const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// Most orders `orders` returns at once
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Deepest selection accepted, so nested relations cannot fan out without bound
+const MAX_QUERY_DEPTH: usize = 10;
+
+pub fn build_schema() -> OrdersSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .finish()
+}
+
+/// Describe a failed call like the REST API does: the message, plus the HTTP status and the
+/// offending field as error extensions
+fn graphql_error(err: impl Into<ApiError>) -> async_graphql::Error {
+    let err = err.into();
+    let (message, field) = err.describe();
+    let (message, field) = (message.to_string(), field.map(str::to_string));
+    let status = err.into_response().status();
+
+    async_graphql::Error::new(message).extend_with(|_, extensions| {
+        extensions.set("status", status.as_u16());
+        if let Some(field) = &field {
+            extensions.set("field", field.as_str());
+        }
+    })
+}
+
+/// Store the request reads and writes
+fn store<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a dyn Store> {
+    Ok(ctx.data::<Arc<dyn Store>>()?.as_ref())
+}
+
+/// Caller of the request, checked for `scope` like a REST handler would
+fn authorize<'a>(ctx: &Context<'a>, scope: Scope) -> async_graphql::Result<&'a Caller> {
+    let caller = ctx.data::<Caller>()?;
+    caller.require_scope(scope).map_err(graphql_error)?;
+    Ok(caller)
+}
+
+#[derive(Debug, InputObject)]
+/// Fields of an order a client sets; the totals are computed by the server
+pub struct OrderInput {
+    pub id: u32,
+    pub item: String,
+    /// pending, processing, shipped, delivered or cancelled
+    pub status: String,
+    pub quantity: u32,
+    pub customer_id: Option<u32>,
+    pub sku: Option<String>,
+    /// Price of one unit in minor units of `currency`
+    #[graphql(default)]
+    pub unit_price_minor: i64,
+    /// ISO 4217 currency code
+    #[graphql(default_with = "DEFAULT_CURRENCY.to_string()")]
+    pub currency: String,
+    pub promo_code: Option<String>,
+    pub shipping_address: Option<ShippingAddress>,
+}
+
+impl From<OrderInput> for Order {
+    fn from(input: OrderInput) -> Self {
+        Order {
+            id: input.id,
+            item: input.item,
+            status: input.status,
+            quantity: input.quantity,
+            customer_id: input.customer_id,
+            sku: input.sku,
+            unit_price_minor: input.unit_price_minor,
+            currency: input.currency,
+            promo_code: input.promo_code,
+            shipping_address: input.shipping_address,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, SimpleObject)]
+/// One page of orders, in ID order
+pub struct OrderPage {
+    pub nodes: Vec<Order>,
+    /// ID of the last order on the page, passed as `after` to get the next page
+    pub end_cursor: Option<u32>,
+    pub has_next_page: bool,
+}
+
+#[ComplexObject]
+impl Order {
+    /// Customer who placed the order; requires `customers:read`
+    async fn customer(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Customer>> {
+        let Some(customer_id) = self.customer_id else {
+            return Ok(None);
+        };
+        authorize(ctx, Scope::ReadCustomers)?;
+        store(ctx)?.get_customer(customer_id).await.map_err(graphql_error)
+    }
+
+    /// Shipments recorded for the order, oldest first
+    async fn shipments(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Shipment>> {
+        authorize(ctx, Scope::ReadOrders)?;
+        store(ctx)?.list_shipments(self.id).await.map_err(graphql_error)
+    }
+
+    /// Payments taken for the order, oldest first; requires `payments:read`
+    async fn payments(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Payment>> {
+        authorize(ctx, Scope::ReadPayments)?;
+        store(ctx)?.list_payments(self.id).await.map_err(graphql_error)
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Order with this ID, or null if there is none
+    async fn order(&self, ctx: &Context<'_>, id: u32) -> async_graphql::Result<Option<Order>> {
+        authorize(ctx, Scope::ReadOrders)?;
+        store(ctx)?.get_order(id).await.map_err(graphql_error)
+    }
+
+    /// Orders matching the filters, in ID order, `first` at a time (at most 100) after the order with ID `after`
+    async fn orders(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        customer_id: Option<u32>,
+        #[graphql(default_with = "DEFAULT_PAGE_SIZE")] first: u32,
+        after: Option<u32>,
+    ) -> async_graphql::Result<OrderPage> {
+        authorize(ctx, Scope::ReadOrders)?;
+        if !(1..=MAX_PAGE_SIZE).contains(&first) {
+            return Err(graphql_error(ValidationError {
+                error: format!("first must be between 1 and {}", MAX_PAGE_SIZE),
+                field: Some("first".to_string()),
+            }));
+        }
+
+        let filter = OrderFilter { status, customer_id };
+        // One extra order tells whether another page follows
+        let mut nodes = store(ctx)?.orders_page(&filter, after, first + 1).await
+            .map_err(graphql_error)?;
+        let has_next_page = nodes.len() > first as usize;
+        nodes.truncate(first as usize);
+
+        Ok(OrderPage {
+            end_cursor: nodes.last().map(|order| order.id),
+            has_next_page,
+            nodes,
+        })
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Create an order, exactly like `POST /orders`
+    async fn create_order(&self, ctx: &Context<'_>, input: OrderInput) -> async_graphql::Result<Order> {
+        authorize(ctx, Scope::CreateOrders)?;
+        let order = Order::from(input);
+        validate_order(&order).map_err(graphql_error)?;
+
+        store(ctx)?.create_order(&order, ctx.data::<PaymentConfig>()?).await.map_err(graphql_error)
+    }
+
+    /// Replace an order, exactly like `PUT /orders/{id}`
+    async fn update_order(&self, ctx: &Context<'_>, id: u32, input: OrderInput) -> async_graphql::Result<Order> {
+        authorize(ctx, Scope::UpdateOrders)?;
+        let order = Order::from(input);
+        validate_order(&order).map_err(graphql_error)?;
+
+        store(ctx)?.update_order(id, &order, ctx.data::<PaymentConfig>()?).await.map_err(graphql_error)
+    }
+
+    /// Change an order's status, exactly like `PATCH /orders/{id}/status`
+    async fn update_order_status(&self, ctx: &Context<'_>, id: u32, status: String) -> async_graphql::Result<Order> {
+        authorize(ctx, Scope::UpdateOrderStatus)?;
+        validate_status(&status).map_err(graphql_error)?;
+
+        store(ctx)?.update_order_status(id, &status, ctx.data::<PaymentConfig>()?).await
+            .map_err(graphql_error)
+    }
+
+    /// Delete an order, exactly like `DELETE /orders/{id}`, returning it as it was
+    async fn delete_order(&self, ctx: &Context<'_>, id: u32) -> async_graphql::Result<Order> {
+        authorize(ctx, Scope::DeleteOrders)?;
+        store(ctx)?.delete_order(id).await.map_err(graphql_error)
+    }
+}
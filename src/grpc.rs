@@ -0,0 +1,482 @@
+use std::{error::Error, pin::Pin};
+use axum::{body::Body, extract::ConnectInfo, response::IntoResponse};
+use futures_util::{Stream, StreamExt};
+use prost::Message;
+use tokio::net::TcpListener;
+use tonic::{
+    body::{boxed, BoxBody},
+    transport::{
+        server::{TcpConnectInfo, TcpIncoming, TlsConnectInfo},
+        Certificate, Identity, Server, ServerTlsConfig
+    },
+    Code, Request, Response, Status
+};
+use tower::ServiceBuilder;
+use crate::config::{AppConfig, PaymentConfig, TlsConfig};
+use crate::middleware::{authenticate, Caller, CredentialVerification, RateLimitLayer, RouteGroup};
+use crate::tls::ClientCertificate;
+use crate::utils::{Order, OrderFilter, Scope, ShippingAddress, Store, DEFAULT_CURRENCY};
+use crate::validators::{validate_order, validate_status, ApiError, ValidationError};
+
+/// Types generated from `proto/orders.proto`
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("orders.v1");
+}
+
+use proto::order_service_server::{OrderService, OrderServiceServer};
+
+/// Describe a failed call with the gRPC code closest to the REST status, carrying the REST error body
+/// as an encoded `ErrorDetail`
+fn grpc_status(err: impl Into<ApiError>) -> Status {
+    let err = err.into();
+    let code = match &err {
+        ApiError::Validation(_) => Code::InvalidArgument,
+        ApiError::NotFound(_) => Code::NotFound,
+        ApiError::Unauthorized(_) => Code::Unauthenticated,
+        ApiError::Forbidden(_) => Code::PermissionDenied,
+        ApiError::TooManyRequests(_) | ApiError::PayloadTooLarge(_) => Code::ResourceExhausted,
+        ApiError::Timeout(_) => Code::DeadlineExceeded,
+        ApiError::Conflict(_) | ApiError::PaymentDeclined(_) => Code::FailedPrecondition,
+        ApiError::BadGateway(_) => Code::Unavailable,
+        ApiError::Server(_) => Code::Internal,
+    };
+    let (message, field) = err.describe();
+    let (message, field) = (message.to_string(), field.map(str::to_string));
+    let detail = proto::ErrorDetail {
+        error: message.clone(),
+        field,
+        http_status: err.into_response().status().as_u16().into(),
+    };
+
+    Status::with_details(code, message, detail.encode_to_vec().into())
+}
+
+impl From<Order> for proto::Order {
+    fn from(order: Order) -> Self {
+        proto::Order {
+            id: order.id,
+            item: order.item,
+            status: order.status,
+            quantity: order.quantity,
+            customer_id: order.customer_id,
+            sku: order.sku,
+            unit_price_minor: order.unit_price_minor,
+            currency: order.currency,
+            promo_code: order.promo_code,
+            shipping_address: order.shipping_address.map(|address| proto::ShippingAddress {
+                recipient: address.recipient,
+                line1: address.line1,
+                line2: address.line2,
+                city: address.city,
+                region: address.region,
+                postal_code: address.postal_code,
+                country: address.country,
+            }),
+            subtotal_minor: order.subtotal_minor,
+            discount_minor: order.discount_minor,
+            total_minor: order.total_minor,
+        }
+    }
+}
+
+impl From<proto::Order> for Order {
+    /// Fields a client sets; the computed totals are dropped
+    fn from(order: proto::Order) -> Self {
+        Order {
+            id: order.id,
+            item: order.item,
+            status: order.status,
+            quantity: order.quantity,
+            customer_id: order.customer_id,
+            sku: order.sku,
+            unit_price_minor: order.unit_price_minor,
+            currency: if order.currency.is_empty() { DEFAULT_CURRENCY.to_string() } else { order.currency },
+            promo_code: order.promo_code,
+            shipping_address: order.shipping_address.map(|address| ShippingAddress {
+                recipient: address.recipient,
+                line1: address.line1,
+                line2: address.line2,
+                city: address.city,
+                region: address.region,
+                postal_code: address.postal_code,
+                country: address.country,
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Order in a request, which protobuf cannot make required
+fn required_order(order: Option<proto::Order>) -> Result<Order, ApiError> {
+    order.map(Order::from).ok_or_else(|| ApiError::Validation(ValidationError {
+        error: "Order is required".to_string(),
+        field: Some("order".to_string()),
+    }))
+}
+
+/// `OrderService` backed by the same store and validators as the REST handlers
+pub struct GrpcOrderService<S> {
+    store: S,
+    payments: PaymentConfig,
+}
+
+impl<S: Store> GrpcOrderService<S> {
+    /// Identify the caller by the API key or bearer token in the metadata, or else by a mapped client
+    /// certificate, and check it has `scope`
+    async fn authorize<T>(&self, request: &Request<T>, scope: Scope) -> Result<Caller, Status> {
+        let extensions = request.extensions();
+        let caller = authenticate(
+            &self.store,
+            &request.metadata().clone().into_headers(),
+            extensions.get::<Option<ClientCertificate>>().and_then(Option::as_ref),
+            extensions.get::<CredentialVerification>(),
+        ).await.map_err(grpc_status)?;
+        caller.require_scope(scope).map_err(grpc_status)?;
+        Ok(caller)
+    }
+}
+
+type OrderStream = Pin<Box<dyn Stream<Item = Result<proto::Order, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl<S: Store> OrderService for GrpcOrderService<S> {
+    async fn get_order(&self, request: Request<proto::GetOrderRequest>) -> Result<Response<proto::Order>, Status> {
+        self.authorize(&request, Scope::ReadOrders).await?;
+
+        let order = self.store.get_order(request.get_ref().id).await
+            .map_err(grpc_status)?
+            .ok_or_else(|| grpc_status(ApiError::NotFound("Order not found".to_string())))?;
+        Ok(Response::new(order.into()))
+    }
+
+    type ListOrdersStream = OrderStream;
+
+    async fn list_orders(
+        &self,
+        request: Request<proto::ListOrdersRequest>,
+    ) -> Result<Response<Self::ListOrdersStream>, Status> {
+        self.authorize(&request, Scope::ReadOrders).await?;
+
+        let request = request.into_inner();
+        let filter = OrderFilter { status: request.status, customer_id: request.customer_id };
+        // The stream item type is fixed by tonic
+        #[allow(clippy::result_large_err)]
+        let orders = self.store.stream_orders(filter)
+            .map(|order| order.map(proto::Order::from).map_err(grpc_status));
+        Ok(Response::new(Box::pin(orders)))
+    }
+
+    async fn create_order(
+        &self,
+        request: Request<proto::CreateOrderRequest>,
+    ) -> Result<Response<proto::Order>, Status> {
+        self.authorize(&request, Scope::CreateOrders).await?;
+
+        let order = required_order(request.into_inner().order).map_err(grpc_status)?;
+        validate_order(&order).map_err(grpc_status)?;
+
+        let created = self.store.create_order(&order, &self.payments).await.map_err(grpc_status)?;
+        Ok(Response::new(created.into()))
+    }
+
+    async fn update_order(
+        &self,
+        request: Request<proto::UpdateOrderRequest>,
+    ) -> Result<Response<proto::Order>, Status> {
+        self.authorize(&request, Scope::UpdateOrders).await?;
+
+        let request = request.into_inner();
+        let order = required_order(request.order).map_err(grpc_status)?;
+        validate_order(&order).map_err(grpc_status)?;
+
+        let updated = self.store.update_order(request.id, &order, &self.payments).await.map_err(grpc_status)?;
+        Ok(Response::new(updated.into()))
+    }
+
+    async fn update_order_status(
+        &self,
+        request: Request<proto::UpdateOrderStatusRequest>,
+    ) -> Result<Response<proto::Order>, Status> {
+        self.authorize(&request, Scope::UpdateOrderStatus).await?;
+
+        let request = request.into_inner();
+        let status = request.update.map(|update| update.status).unwrap_or_default();
+        validate_status(&status).map_err(grpc_status)?;
+
+        let updated = self.store.update_order_status(request.id, &status, &self.payments).await
+            .map_err(grpc_status)?;
+        Ok(Response::new(updated.into()))
+    }
+
+    async fn delete_order(
+        &self,
+        request: Request<proto::DeleteOrderRequest>,
+    ) -> Result<Response<proto::Order>, Status> {
+        self.authorize(&request, Scope::DeleteOrders).await?;
+
+        let deleted = self.store.delete_order(request.get_ref().id).await.map_err(grpc_status)?;
+        Ok(Response::new(deleted.into()))
+    }
+}
+
+/// Server identity and, when client certificates are accepted, their CA, read from the HTTP API's
+/// PEM files. Certificates stay optional so API key clients can still connect.
+fn server_tls_config(tls: &TlsConfig) -> std::io::Result<ServerTlsConfig> {
+    let identity = Identity::from_pem(std::fs::read(&tls.cert_path)?, std::fs::read(&tls.key_path)?);
+    let mut config = ServerTlsConfig::new().identity(identity);
+    if let Some(client_ca_path) = &tls.client_ca_path {
+        config = config
+            .client_ca_root(Certificate::from_pem(std::fs::read(client_ca_path)?))
+            .client_auth_optional(true);
+    }
+    Ok(config)
+}
+
+/// Paths of the calls that only read orders
+const READ_METHODS: &[&str] = &["/orders.v1.OrderService/GetOrder", "/orders.v1.OrderService/ListOrders"];
+
+/// Expose the peer address and client certificate the way the HTTP server does,
+/// so rate limiting and authentication treat calls like REST requests
+fn with_connection_info(mut request: axum::http::Request<BoxBody>) -> axum::extract::Request {
+    let extensions = request.extensions();
+    let (remote_addr, certificate) = match extensions.get::<TlsConnectInfo<TcpConnectInfo>>() {
+        Some(info) => (
+            info.get_ref().remote_addr(),
+            info.peer_certs().and_then(|certs| certs.first().and_then(ClientCertificate::from_der)),
+        ),
+        None => (extensions.get::<TcpConnectInfo>().and_then(TcpConnectInfo::remote_addr), None),
+    };
+    if let Some(remote_addr) = remote_addr {
+        request.extensions_mut().insert(ConnectInfo(remote_addr));
+    }
+    request.extensions_mut().insert(certificate);
+    // Every call is a POST; reads still draw on the read budget
+    if READ_METHODS.contains(&request.uri().path()) {
+        request.extensions_mut().insert(RouteGroup::Read);
+    }
+    request.map(Body::new)
+}
+
+/// Serve the gRPC order service on `listener` until the process exits, with the HTTP API's
+/// request timeout, body size limit and rate limits. Calls are encrypted when `config.tls` is set.
+pub async fn serve_grpc<S: Store>(
+    listener: TcpListener,
+    store: S,
+    config: &AppConfig,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let service = OrderServiceServer::new(GrpcOrderService { store, payments: config.payments.clone() })
+        .max_decoding_message_size(config.max_body_bytes);
+    let incoming = TcpIncoming::from_listener(listener, true, None)
+        .expect("listener is bound");
+    let rate_limit = ServiceBuilder::new()
+        .map_request(with_connection_info)
+        .layer(RateLimitLayer::new(config.rate_limit)
+            .rejecting_with(|err| grpc_status(err).into_http().map(Body::new)))
+        .map_request(|request: axum::extract::Request| request.map(boxed))
+        .map_response(|response: axum::http::Response<BoxBody>| response.map(Body::new))
+        .into_inner();
+
+    let mut server = Server::builder().timeout(config.request_timeout);
+    if let Some(tls) = &config.tls {
+        server = server.tls_config(server_tls_config(tls)?)?;
+    }
+    server
+        .layer(rate_limit)
+        .add_service(service)
+        .serve_with_incoming(incoming)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::Channel;
+    use crate::config::{RateLimit, RateLimitConfig};
+    use crate::utils::{create_api_key, create_order, create_shipment, init_db, DbPool, Role, Shipment, SqliteStore};
+    use proto::order_service_client::OrderServiceClient;
+
+    /// Serve the service on a free local port and connect a client to it
+    async fn start_service() -> (OrderServiceClient<Channel>, DbPool) {
+        start_service_with(AppConfig::default()).await
+    }
+
+    async fn start_service_with(config: AppConfig) -> (OrderServiceClient<Channel>, DbPool) {
+        let db_pool = init_db().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = db_pool.clone();
+        tokio::spawn(async move { serve_grpc(listener, SqliteStore::new(pool), &config).await });
+
+        let client = OrderServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+        (client, db_pool)
+    }
+
+    fn with_key<T>(message: T, secret: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request.metadata_mut().insert("x-api-key", secret.parse().unwrap());
+        request
+    }
+
+    fn order(id: u32, item: &str) -> proto::Order {
+        proto::Order {
+            id,
+            item: item.to_string(),
+            status: "pending".to_string(),
+            quantity: 2,
+            unit_price_minor: 250,
+            ..Default::default()
+        }
+    }
+
+    fn error_detail(status: &Status) -> proto::ErrorDetail {
+        proto::ErrorDetail::decode(status.details()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_order_lifecycle() {
+        let (mut client, db_pool) = start_service().await;
+        let (_, key) = create_api_key(&db_pool, "billing", Role::Admin).await.unwrap();
+
+        let created = client.create_order(with_key(proto::CreateOrderRequest { order: Some(order(1, "Widget")) }, &key))
+            .await.unwrap().into_inner();
+        assert_eq!((created.currency.as_str(), created.total_minor), ("USD", 500));
+        client.create_order(with_key(proto::CreateOrderRequest { order: Some(order(2, "Gadget")) }, &key))
+            .await.unwrap();
+
+        let update = proto::UpdateOrderStatusRequest {
+            id: 1,
+            update: Some(proto::StatusUpdate { status: "shipped".to_string() }),
+        };
+        let shipped = client.update_order_status(with_key(update, &key)).await.unwrap().into_inner();
+        assert_eq!(shipped.status, "shipped");
+
+        let fetched = client.get_order(with_key(proto::GetOrderRequest { id: 1 }, &key)).await.unwrap().into_inner();
+        assert_eq!(fetched, shipped);
+
+        let listing = proto::ListOrdersRequest { status: Some("pending".to_string()), customer_id: None };
+        let mut orders = client.list_orders(with_key(listing, &key)).await.unwrap().into_inner();
+        let mut ids = Vec::new();
+        while let Some(order) = orders.message().await.unwrap() {
+            ids.push(order.id);
+        }
+        assert_eq!(ids, [2]);
+
+        let deleted = client.delete_order(with_key(proto::DeleteOrderRequest { id: 2 }, &key)).await.unwrap().into_inner();
+        assert_eq!(deleted.item, "Gadget");
+        let status = client.get_order(with_key(proto::GetOrderRequest { id: 2 }, &key)).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(error_detail(&status).http_status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_order_with_shipments_cannot_be_deleted() {
+        let (mut client, db_pool) = start_service().await;
+        let (_, key) = create_api_key(&db_pool, "billing", Role::Admin).await.unwrap();
+
+        let address = ShippingAddress {
+            recipient: "Grace Hopper".to_string(),
+            line1: "1 Navy Way".to_string(),
+            line2: None,
+            city: "Arlington".to_string(),
+            region: Some("VA".to_string()),
+            postal_code: "22202".to_string(),
+            country: "US".to_string(),
+        };
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "processing".to_string(),
+            quantity: 1,
+            shipping_address: Some(address),
+            ..Default::default()
+        };
+        create_order(&db_pool, &order, &PaymentConfig::default()).await.unwrap();
+        let shipment = Shipment {
+            id: 0,
+            order_id: 0,
+            carrier: "UPS".to_string(),
+            tracking_number: "1Z999AA10123456784".to_string(),
+            shipped_at: chrono::Utc::now(),
+            delivered_at: None,
+        };
+        create_shipment(&db_pool, 1, &shipment, &PaymentConfig::default()).await.unwrap();
+
+        let status = client.delete_order(with_key(proto::DeleteOrderRequest { id: 1 }, &key)).await.unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(error_detail(&status).http_status, 409);
+        client.get_order(with_key(proto::GetOrderRequest { id: 1 }, &key)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shipping_requires_captured_payment_when_configured() {
+        let payments = PaymentConfig { require_capture_to_ship: true, ..PaymentConfig::default() };
+        let (mut client, db_pool) = start_service_with(AppConfig { payments, ..AppConfig::default() }).await;
+        let (_, key) = create_api_key(&db_pool, "billing", Role::Admin).await.unwrap();
+
+        let shipped = proto::Order { status: "shipped".to_string(), ..order(1, "Widget") };
+        let status = client.create_order(with_key(proto::CreateOrderRequest { order: Some(shipped) }, &key))
+            .await.unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(error_detail(&status).http_status, 409);
+
+        client.create_order(with_key(proto::CreateOrderRequest { order: Some(order(1, "Widget")) }, &key))
+            .await.unwrap();
+        let update = proto::UpdateOrderStatusRequest {
+            id: 1,
+            update: Some(proto::StatusUpdate { status: "delivered".to_string() }),
+        };
+        let status = client.update_order_status(with_key(update, &key)).await.unwrap_err();
+        assert_eq!(error_detail(&status).http_status, 409);
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_rate_limited() {
+        let rate_limit = RateLimitConfig {
+            read: RateLimit { burst: 2, per_second: 0.5 },
+            write: RateLimit { burst: 1, per_second: 0.5 },
+        };
+        let (mut client, db_pool) = start_service_with(AppConfig { rate_limit, ..AppConfig::default() }).await;
+        let (_, key) = create_api_key(&db_pool, "billing", Role::Admin).await.unwrap();
+        client.get_order(with_key(proto::GetOrderRequest { id: 1 }, &key)).await.unwrap_err();
+
+        // Made-up keys share the client address's budget
+        let status = client.get_order(with_key(proto::GetOrderRequest { id: 1 }, "made-up-1")).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        let status = client.get_order(with_key(proto::GetOrderRequest { id: 1 }, "made-up-2")).await.unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(error_detail(&status).http_status, 429);
+        assert!(status.metadata().get("retry-after").is_some());
+
+        // The verified key has a budget of its own
+        let status = client.get_order(with_key(proto::GetOrderRequest { id: 1 }, &key)).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_from_api_errors() {
+        let (mut client, db_pool) = start_service().await;
+        let (_, admin_key) = create_api_key(&db_pool, "billing", Role::Admin).await.unwrap();
+        let (_, reader_key) = create_api_key(&db_pool, "reporting", Role::ReadOnly).await.unwrap();
+
+        let status = client.get_order(proto::GetOrderRequest { id: 1 }).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        let status = client.create_order(with_key(proto::CreateOrderRequest { order: Some(order(1, "")) }, &admin_key))
+            .await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let detail = error_detail(&status);
+        assert_eq!((detail.field.as_deref(), detail.http_status), (Some("item"), 400));
+        assert_eq!(status.message(), detail.error);
+
+        let status = client.create_order(with_key(proto::CreateOrderRequest { order: None }, &admin_key))
+            .await.unwrap_err();
+        assert_eq!(error_detail(&status).field.as_deref(), Some("order"));
+
+        let status = client.delete_order(with_key(proto::DeleteOrderRequest { id: 1 }, &reader_key))
+            .await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("orders:delete"));
+    }
+}
@@ -0,0 +1,201 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use crate::middleware::Caller;
+use crate::validators::{validate_customer, ApiError};
+use crate::utils::{Customer, CustomerRepository, Order, OrderFilter, OrderRepository, Scope};
+
+#[utoipa::path(
+    get,
+    path = "/customers",
+    responses(
+        (status = 200, description = "List of all customers", body = [Customer]),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:read"]),
+        ("bearer_auth" = ["customers:read"])
+    ),
+    tag = "customers"
+)]
+pub async fn get_customers<C: CustomerRepository + Clone>(
+    State(customers): State<C>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<Customer>>, ApiError> {
+    caller.require_scope(Scope::ReadCustomers)?;
+
+    let customers = customers.list_customers().await?;
+    Ok(Json(customers))
+}
+
+#[utoipa::path(
+    post,
+    path = "/customers",
+    request_body = Customer,
+    responses(
+        (status = 200, description = "Customer created successfully", body = Customer),
+        (status = 400, description = "Invalid input, duplicate ID or email"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:write"]),
+        ("bearer_auth" = ["customers:write"])
+    ),
+    tag = "customers"
+)]
+pub async fn add_customer<C: CustomerRepository + Clone>(
+    State(customers): State<C>,
+    Extension(caller): Extension<Caller>,
+    Json(new_customer): Json<Customer>,
+) -> Result<Json<Customer>, ApiError> {
+    caller.require_scope(Scope::ManageCustomers)?;
+
+    validate_customer(&new_customer)?;
+
+    let created_customer = customers.create_customer(&new_customer).await?;
+    Ok(Json(created_customer))
+}
+
+#[utoipa::path(
+    get,
+    path = "/customers/{id}",
+    params(
+        ("id" = u32, Path, description = "Customer ID")
+    ),
+    responses(
+        (status = 200, description = "Customer found", body = Customer),
+        (status = 404, description = "Customer not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:read"]),
+        ("bearer_auth" = ["customers:read"])
+    ),
+    tag = "customers"
+)]
+pub async fn get_customer_by_id<C: CustomerRepository + Clone>(
+    State(customers): State<C>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Customer>, ApiError> {
+    caller.require_scope(Scope::ReadCustomers)?;
+
+    let customer = customers.get_customer(id).await?
+        .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+    Ok(Json(customer))
+}
+
+#[utoipa::path(
+    put,
+    path = "/customers/{id}",
+    params(
+        ("id" = u32, Path, description = "Customer ID")
+    ),
+    request_body = Customer,
+    responses(
+        (status = 200, description = "Customer updated successfully", body = Customer),
+        (status = 400, description = "Invalid input or duplicate email"),
+        (status = 404, description = "Customer not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:write"]),
+        ("bearer_auth" = ["customers:write"])
+    ),
+    tag = "customers"
+)]
+pub async fn update_customer_by_id<C: CustomerRepository + Clone>(
+    State(customers): State<C>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+    Json(updated_customer): Json<Customer>,
+) -> Result<Json<Customer>, ApiError> {
+    caller.require_scope(Scope::ManageCustomers)?;
+
+    validate_customer(&updated_customer)?;
+
+    let updated = customers.update_customer(id, &updated_customer).await?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/customers/{id}",
+    params(
+        ("id" = u32, Path, description = "Customer ID")
+    ),
+    responses(
+        (status = 200, description = "Customer deleted successfully", body = Customer),
+        (status = 404, description = "Customer not found"),
+        (status = 409, description = "Customer still has orders"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:write"]),
+        ("bearer_auth" = ["customers:write"])
+    ),
+    tag = "customers"
+)]
+pub async fn delete_customer_by_id<C: CustomerRepository + Clone>(
+    State(customers): State<C>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Customer>, ApiError> {
+    caller.require_scope(Scope::ManageCustomers)?;
+
+    let deleted_customer = customers.delete_customer(id).await?;
+    Ok(Json(deleted_customer))
+}
+
+#[utoipa::path(
+    get,
+    path = "/customers/{id}/orders",
+    params(
+        ("id" = u32, Path, description = "Customer ID")
+    ),
+    responses(
+        (status = 200, description = "Orders placed by the customer", body = [Order]),
+        (status = 404, description = "Customer not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `customers:read` or `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["customers:read", "orders:read"]),
+        ("bearer_auth" = ["customers:read", "orders:read"])
+    ),
+    tag = "customers"
+)]
+pub async fn get_customer_orders<S: CustomerRepository + OrderRepository + Clone>(
+    State(store): State<S>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Vec<Order>>, ApiError> {
+    caller.require_scope(Scope::ReadCustomers)?;
+    caller.require_scope(Scope::ReadOrders)?;
+
+    store.get_customer(id).await?
+        .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+
+    let filter = OrderFilter { customer_id: Some(id), ..Default::default() };
+    let orders = store.list_orders(&filter).await?;
+    Ok(Json(orders))
+}
@@ -0,0 +1,143 @@
+use std::io;
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Extension
+};
+use futures_util::{stream, Stream, StreamExt};
+use crate::middleware::Caller;
+use crate::validators::ApiError;
+use crate::utils::{Order, OrderFilter, OrderRepository, Scope, order_csv_header, order_csv_line};
+
+/// Media type of CSV responses
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// Media type of newline-delimited JSON responses
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Representation of the order listing, negotiated with the `Accept` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListingFormat {
+    Json,
+    Csv,
+    /// One JSON order per line, streamed from the database as it is read
+    Ndjson,
+}
+
+impl ListingFormat {
+    /// Pick the format the client prefers by `q` value, falling back to JSON when it accepts
+    /// neither or sends no `Accept` header
+    pub(crate) fn from_accept(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return ListingFormat::Json;
+        };
+
+        let mut ranges: Vec<(&str, f32)> = accept.split(',')
+            .map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let media_type = parts.next().unwrap_or_default();
+                let quality = parts
+                    .filter_map(|param| param.strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (media_type, quality)
+            })
+            .collect();
+        // Stable, so equally preferred types keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        ranges.into_iter()
+            .filter(|(_, quality)| *quality > 0.0)
+            .find_map(|(media_type, _)| match media_type.to_ascii_lowercase().as_str() {
+                "text/csv" => Some(ListingFormat::Csv),
+                "application/x-ndjson" => Some(ListingFormat::Ndjson),
+                "application/json" | "application/*" | "*/*" => Some(ListingFormat::Json),
+                _ => None,
+            })
+            .unwrap_or(ListingFormat::Json)
+    }
+}
+
+/// CSV download written as `orders` yields: the header line first, then one line per order.
+/// Like `ndjson_response`, a database error part way aborts the body.
+pub(crate) fn csv_response(orders: impl Stream<Item = Result<Order, ApiError>> + Send + 'static) -> Result<Response, ApiError> {
+    let header = order_csv_header()?;
+    let lines = orders.map(|order| {
+        let order = order.map_err(|_| io::Error::other("failed to read orders"))?;
+        order_csv_line(&order).map_err(|_| io::Error::other("failed to write orders as CSV"))
+    });
+    let body = stream::once(async { Ok::<_, io::Error>(header) }).chain(lines);
+    Ok((
+        [
+            (header::CONTENT_TYPE, CSV_CONTENT_TYPE),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"orders.csv\""),
+        ],
+        Body::from_stream(body),
+    ).into_response())
+}
+
+/// Newline-delimited JSON body written as `orders` yields, so it is never held in memory whole.
+/// The status is sent before the first order is read, so a database error part way aborts the body instead.
+pub(crate) fn ndjson_response(orders: impl Stream<Item = Result<Order, ApiError>> + Send + 'static) -> Response {
+    let lines = orders.map(|order| {
+        let order = order.map_err(|_| io::Error::other("failed to read orders"))?;
+        let mut line = serde_json::to_vec(&order)?;
+        line.push(b'\n');
+        Ok::<_, io::Error>(line)
+    });
+    ([(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)], Body::from_stream(lines)).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/export.csv",
+    params(OrderFilter),
+    responses(
+        (status = 200, description = "Orders matching the filters as CSV with a header line", content_type = "text/csv", body = String),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
+    tag = "orders"
+)]
+pub async fn export_orders_csv<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Query(filter): Query<OrderFilter>,
+) -> Result<Response, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+
+    csv_response(orders.stream_orders(filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn format_for(accept: &str) -> ListingFormat {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(accept).unwrap());
+        ListingFormat::from_accept(&headers)
+    }
+
+    #[test]
+    fn test_listing_format_from_accept() {
+        assert_eq!(ListingFormat::from_accept(&HeaderMap::new()), ListingFormat::Json);
+        assert_eq!(format_for("text/csv"), ListingFormat::Csv);
+        assert_eq!(format_for("application/x-ndjson"), ListingFormat::Ndjson);
+        assert_eq!(format_for("application/x-ndjson;q=0.9, text/csv;q=0.8"), ListingFormat::Ndjson);
+        assert_eq!(format_for("Text/CSV; charset=utf-8"), ListingFormat::Csv);
+        assert_eq!(format_for("application/json, text/csv"), ListingFormat::Json);
+        assert_eq!(format_for("application/json;q=0.5, text/csv"), ListingFormat::Csv);
+        assert_eq!(format_for("text/csv;q=0, */*"), ListingFormat::Json);
+        assert_eq!(format_for("text/html"), ListingFormat::Json);
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use async_graphql::http::GraphiQLSource;
+use axum::{
+    extract::State,
+    response::Html,
+    Extension, Json
+};
+use crate::config::PaymentConfig;
+use crate::graphql::OrdersSchema;
+use crate::middleware::Caller;
+use crate::utils::Store;
+
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    request_body(content = serde_json::Value, description = "GraphQL request with `query`, and optionally `variables` and `operationName`"),
+    responses(
+        (status = 200, description = "GraphQL response with `data` and any `errors`; each error carries the REST status code in `extensions.status`", body = serde_json::Value),
+        (status = 400, description = "Body is not a GraphQL request"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    tag = "orders"
+)]
+pub async fn graphql<S: Store + Clone>(
+    State(store): State<S>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Extension(schema): Extension<OrdersSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    // Scopes are checked per field, so a credential only sees the fields its role may read
+    let store: Arc<dyn Store> = Arc::new(store);
+    let request = request.data(store).data(caller).data(payments);
+    Json(schema.execute(request).await)
+}
+
+/// GraphiQL playground for `/v1/graphql`; credentials go in its headers tab
+pub async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/v1/graphql").finish())
+}
@@ -1,13 +1,17 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Extension,
     Json
 };
 use serde::{Deserialize, Serialize};
 use utoipa;
+use crate::config::PaymentConfig;
+use crate::handlers::export_handlers::{csv_response, ndjson_response, ListingFormat};
+use crate::middleware::Caller;
 use crate::validators::{validate_order, validate_status, ApiError};
-use crate::utils::{DbPool, Order, get_all_orders, get_order_by_id as db_get_order_by_id, 
-                   create_order, update_order, update_order_status as db_update_order_status, 
-                   delete_order};
+use crate::utils::{Order, OrderFilter, OrderRepository, Scope};
 
 #[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
 /// Status update request body
@@ -19,16 +23,41 @@ pub struct StatusUpdate {
 #[utoipa::path(
     get,
     path = "/orders",
+    params(
+        OrderFilter,
+        ("Accept" = Option<String>, Header, description = "`text/csv` returns the orders as CSV instead of JSON; `application/x-ndjson` streams one JSON order per line")
+    ),
     responses(
-        (status = 200, description = "List of all orders", body = [Order]),
+        (status = 200, description = "List of orders matching the filters", content(
+            ([Order] = "application/json"),
+            (String = "text/csv"),
+            (Order = "application/x-ndjson")
+        )),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
     tag = "orders"
 )]
-#[axum::debug_handler]
-pub async fn get_orders(State(db_pool): State<DbPool>) -> Result<Json<Vec<Order>>, ApiError> {
-    let orders = get_all_orders(&db_pool).await?;
-    Ok(Json(orders))
+pub async fn get_orders<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+    Query(filter): Query<OrderFilter>,
+) -> Result<Response, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+    
+    match ListingFormat::from_accept(&headers) {
+        ListingFormat::Json => Ok(Json(orders.list_orders(&filter).await?).into_response()),
+        // Streamed so large listings never sit in memory whole
+        ListingFormat::Csv => csv_response(orders.stream_orders(filter)),
+        ListingFormat::Ndjson => Ok(ndjson_response(orders.stream_orders(filter))),
+    }
 }
 
 #[utoipa::path(
@@ -37,19 +66,32 @@ pub async fn get_orders(State(db_pool): State<DbPool>) -> Result<Json<Vec<Order>
     request_body = Order,
     responses(
         (status = 201, description = "Order created successfully", body = Order),
-        (status = 400, description = "Invalid input"),
-        (status = 409, description = "Order with ID already exists"),
+        (status = 400, description = "Invalid input, unknown customer or SKU, or a promo code that is unknown, expired or exhausted"),
+        (status = 409, description = "Order with ID already exists, insufficient stock, or created shipped before its payment is captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:create` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:create"]),
+        ("bearer_auth" = ["orders:create"])
+    ),
     tag = "orders"
 )]
-#[axum::debug_handler]
-pub async fn add_order(State(db_pool): State<DbPool>, Json(new_order): Json<Order>) -> Result<Json<Order>, ApiError> {
+pub async fn add_order<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Json(new_order): Json<Order>,
+) -> Result<Json<Order>, ApiError> {
+    caller.require_scope(Scope::CreateOrders)?;
+    
     // Validate the order first
     validate_order(&new_order)?;
     
     // Create the order in the database (includes duplicate ID check)
-    let created_order = create_order(&db_pool, &new_order).await?;
+    let created_order = orders.create_order(&new_order, &payments).await?;
     Ok(Json(created_order))
 }
 
@@ -62,16 +104,25 @@ pub async fn add_order(State(db_pool): State<DbPool>, Json(new_order): Json<Orde
     responses(
         (status = 200, description = "Order found", body = Order),
         (status = 404, description = "Order not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
     tag = "orders"
 )]
-#[axum::debug_handler]
-pub async fn get_order_by_id(
-    State(db_pool): State<DbPool>,
+pub async fn get_order_by_id<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
     Path(id): Path<u32>,
 ) -> Result<Json<Order>, ApiError> {
-    let order = db_get_order_by_id(&db_pool, id).await?
+    caller.require_scope(Scope::ReadOrders)?;
+    
+    let order = orders.get_order(id).await?
         .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
     Ok(Json(order))
 }
@@ -85,22 +136,33 @@ pub async fn get_order_by_id(
     request_body = Order,
     responses(
         (status = 200, description = "Order updated successfully", body = Order),
-        (status = 400, description = "Invalid input"),
+        (status = 400, description = "Invalid input, unknown customer or SKU, or a changed promo code"),
         (status = 404, description = "Order not found"),
+        (status = 409, description = "Insufficient stock, or shipping before the payment is captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:update` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:update"]),
+        ("bearer_auth" = ["orders:update"])
+    ),
     tag = "orders"
 )]
-#[axum::debug_handler]
-pub async fn update_order_by_id(
-    State(db_pool): State<DbPool>,
+pub async fn update_order_by_id<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
     Path(id): Path<u32>,
     Json(updated_order): Json<Order>,
 ) -> Result<Json<Order>, ApiError> {
+    caller.require_scope(Scope::UpdateOrders)?;
+    
     // Validate the updated order
     validate_order(&updated_order)?;
     
-    let updated = update_order(&db_pool, id, &updated_order).await?;
+    let updated = orders.update_order(id, &updated_order, &payments).await?;
     Ok(Json(updated))
 }
 
@@ -115,20 +177,31 @@ pub async fn update_order_by_id(
         (status = 200, description = "Order status updated successfully", body = Order),
         (status = 400, description = "Invalid status"),
         (status = 404, description = "Order not found"),
+        (status = 409, description = "Insufficient stock to reopen a cancelled order, or shipping before the payment is captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:status` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:status"]),
+        ("bearer_auth" = ["orders:status"])
+    ),
     tag = "orders"
 )]
-#[axum::debug_handler]
-pub async fn update_order_status(
-    State(db_pool): State<DbPool>,
+pub async fn update_order_status<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
     Path(id): Path<u32>,
     Json(status_update): Json<StatusUpdate>,
 ) -> Result<Json<Order>, ApiError> {
+    caller.require_scope(Scope::UpdateOrderStatus)?;
+    
     // Validate the status
     validate_status(&status_update.status)?;
     
-    let updated = db_update_order_status(&db_pool, id, &status_update.status).await?;
+    let updated = orders.update_order_status(id, &status_update.status, &payments).await?;
     Ok(Json(updated))
 }
 
@@ -141,14 +214,25 @@ pub async fn update_order_status(
     responses(
         (status = 200, description = "Order deleted successfully", body = Order),
         (status = 404, description = "Order not found"),
+        (status = 409, description = "Order still has shipments or payments"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:delete` scope"),
+        (status = 429, description = "Rate limit exceeded"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("api_key" = ["orders:delete"]),
+        ("bearer_auth" = ["orders:delete"])
+    ),
     tag = "orders"
 )]
-pub async fn delete_order_by_id(
-    State(db_pool): State<DbPool>,
+pub async fn delete_order_by_id<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
     Path(id): Path<u32>,
 ) -> Result<Json<Order>, ApiError> {
-    let deleted_order = delete_order(&db_pool, id).await?;
+    caller.require_scope(Scope::DeleteOrders)?;
+    
+    let deleted_order = orders.delete_order(id).await?;
     Ok(Json(deleted_order))
 }
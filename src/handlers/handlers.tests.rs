@@ -1,74 +1,92 @@
 #[cfg(test)]
 mod tests {
-    use crate::utils::{init_db, Order, DbPool};
+    use crate::utils::{init_db, InMemoryStore, Order, OrderFilter, OrderRepository, Role, SqliteStore};
     use crate::handlers::handlers::*;
+    use crate::config::PaymentConfig;
+    use crate::middleware::Caller;
     use crate::validators::ApiError;
     use axum::{
-        extract::{Path, State},
+        extract::{Path, Query, State},
+        http::HeaderMap,
+        response::Response,
+        Extension,
         Json
     };
-    use tokio;
 
-    async fn setup_test_db() -> DbPool {
+    fn caller(role: Role) -> Extension<Caller> {
+        Extension(Caller {
+            name: "test-client".to_string(),
+            role,
+        })
+    }
+
+    fn admin() -> Extension<Caller> {
+        caller(Role::Admin)
+    }
+
+    fn payments() -> Extension<PaymentConfig> {
+        Extension(PaymentConfig::default())
+    }
+
+    /// Decode the JSON body of an order listing
+    async fn orders_from(response: Response) -> Vec<Order> {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn setup_test_db() -> SqliteStore {
         // Use in-memory database for tests to ensure isolation
-        let db_pool = init_db().await.expect("Failed to initialize test database");
-        db_pool
+        SqliteStore::new(init_db().await.expect("Failed to initialize test database"))
     }
 
-    async fn create_test_order(db_pool: &DbPool) -> Order {
+    async fn create_test_order<R: OrderRepository + Clone>(orders: &R) -> Order {
         let order = Order {
             id: 1,
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
-        let _result = add_order(State(db_pool.clone()), Json(order.clone())).await.unwrap();
+        let _result = add_order(State(orders.clone()), admin(), payments(), Json(order.clone())).await.unwrap();
         order
     }
 
-    #[tokio::test]
-    async fn test_get_orders_empty() {
-        let db_pool = setup_test_db().await;
-        
-        let result = get_orders(State(db_pool)).await;
+    async fn test_get_orders_empty<R: OrderRepository + Clone>(orders: R) {
+        let result = get_orders(State(orders), admin(), HeaderMap::new(), Query(OrderFilter::default())).await;
         assert!(result.is_ok());
-        let orders = result.unwrap().0;
+        let orders = orders_from(result.unwrap()).await;
         assert_eq!(orders.len(), 0);
     }
 
-    #[tokio::test]
-    async fn test_get_orders_with_data() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_get_orders_with_data<R: OrderRepository + Clone>(orders: R) {
         // Add some test orders
-        let _order1 = create_test_order(&db_pool).await;
+        let _order1 = create_test_order(&orders).await;
         
         let order2 = Order {
             id: 2,
             item: "Another Item".to_string(),
             status: "shipped".to_string(),
             quantity: 10,
+            ..Default::default()
         };
-        let _result2 = add_order(State(db_pool.clone()), Json(order2)).await.unwrap();
+        let _result2 = add_order(State(orders.clone()), admin(), payments(), Json(order2)).await.unwrap();
         
-        let result = get_orders(State(db_pool)).await;
+        let result = get_orders(State(orders), admin(), HeaderMap::new(), Query(OrderFilter::default())).await;
         assert!(result.is_ok());
-        let orders = result.unwrap().0;
+        let orders = orders_from(result.unwrap()).await;
         assert_eq!(orders.len(), 2);
     }
 
-    #[tokio::test]
-    async fn test_add_order_success() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_add_order_success<R: OrderRepository + Clone>(orders: R) {
         let new_order = Order {
             id: 1,
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
 
-        let result = add_order(State(db_pool.clone()), Json(new_order.clone())).await;
+        let result = add_order(State(orders.clone()), admin(), payments(), Json(new_order.clone())).await;
         assert!(result.is_ok());
         let created_order = result.unwrap().0;
         assert_eq!(created_order.id, new_order.id);
@@ -77,22 +95,20 @@ mod tests {
         assert_eq!(created_order.quantity, new_order.quantity);
         
         // Verify it was actually added to the database
-        let orders_result = get_orders(State(db_pool)).await;
+        let orders_result = get_orders(State(orders), admin(), HeaderMap::new(), Query(OrderFilter::default())).await;
         assert!(orders_result.is_ok());
-        let orders = orders_result.unwrap().0;
+        let orders = orders_from(orders_result.unwrap()).await;
         assert_eq!(orders.len(), 1);
         assert_eq!(orders[0].id, new_order.id);
     }
 
-    #[tokio::test]
-    async fn test_add_order_duplicate_id() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_add_order_duplicate_id<R: OrderRepository + Clone>(orders: R) {
         let order1 = Order {
             id: 1,
             item: "First Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
 
         let order2 = Order {
@@ -100,29 +116,28 @@ mod tests {
             item: "Second Item".to_string(),
             status: "processing".to_string(),
             quantity: 3,
+            ..Default::default()
         };
 
         // Add first order - should succeed
-        let result1 = add_order(State(db_pool.clone()), Json(order1)).await;
+        let result1 = add_order(State(orders.clone()), admin(), payments(), Json(order1)).await;
         assert!(result1.is_ok());
 
         // Add second order with same ID - should fail
-        let result2 = add_order(State(db_pool), Json(order2)).await;
+        let result2 = add_order(State(orders), admin(), payments(), Json(order2)).await;
         assert!(result2.is_err());
     }
 
-    #[tokio::test]
-    async fn test_add_order_validation_empty_item() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_add_order_validation_empty_item<R: OrderRepository + Clone>(orders: R) {
         let invalid_order = Order {
             id: 1,
             item: "".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
 
-        let result = add_order(State(db_pool), Json(invalid_order)).await;
+        let result = add_order(State(orders), admin(), payments(), Json(invalid_order)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::Validation(error)) = result {
@@ -133,18 +148,16 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_add_order_validation_invalid_status() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_add_order_validation_invalid_status<R: OrderRepository + Clone>(orders: R) {
         let invalid_order = Order {
             id: 1,
             item: "Test Item".to_string(),
             status: "invalid_status".to_string(),
             quantity: 5,
+            ..Default::default()
         };
 
-        let result = add_order(State(db_pool), Json(invalid_order)).await;
+        let result = add_order(State(orders), admin(), payments(), Json(invalid_order)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::Validation(error)) = result {
@@ -155,18 +168,16 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_add_order_validation_zero_quantity() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_add_order_validation_zero_quantity<R: OrderRepository + Clone>(orders: R) {
         let invalid_order = Order {
             id: 1,
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 0,
+            ..Default::default()
         };
 
-        let result = add_order(State(db_pool), Json(invalid_order)).await;
+        let result = add_order(State(orders), admin(), payments(), Json(invalid_order)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::Validation(error)) = result {
@@ -177,13 +188,10 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_get_order_by_id_success() {
-        let db_pool = setup_test_db().await;
+    async fn test_get_order_by_id_success<R: OrderRepository + Clone>(orders: R) {
+        let created_order = create_test_order(&orders).await;
         
-        let created_order = create_test_order(&db_pool).await;
-        
-        let result = get_order_by_id(State(db_pool), Path(1)).await;
+        let result = get_order_by_id(State(orders), admin(), Path(1)).await;
         assert!(result.is_ok());
         let order = result.unwrap().0;
         assert_eq!(order.id, created_order.id);
@@ -192,11 +200,8 @@ mod tests {
         assert_eq!(order.quantity, created_order.quantity);
     }
 
-    #[tokio::test]
-    async fn test_get_order_by_id_not_found() {
-        let db_pool = setup_test_db().await;
-        
-        let result = get_order_by_id(State(db_pool), Path(999)).await;
+    async fn test_get_order_by_id_not_found<R: OrderRepository + Clone>(orders: R) {
+        let result = get_order_by_id(State(orders), admin(), Path(999)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::NotFound(message)) = result {
@@ -206,20 +211,18 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_update_order_by_id_success() {
-        let db_pool = setup_test_db().await;
-        
-        let _created_order = create_test_order(&db_pool).await;
+    async fn test_update_order_by_id_success<R: OrderRepository + Clone>(orders: R) {
+        let _created_order = create_test_order(&orders).await;
         
         let updated_order = Order {
             id: 1,
             item: "Updated Item".to_string(),
             status: "shipped".to_string(),
             quantity: 10,
+            ..Default::default()
         };
 
-        let result = update_order_by_id(State(db_pool), Path(1), Json(updated_order.clone())).await;
+        let result = update_order_by_id(State(orders), admin(), payments(), Path(1), Json(updated_order.clone())).await;
         assert!(result.is_ok());
         let order = result.unwrap().0;
         assert_eq!(order.item, updated_order.item);
@@ -227,18 +230,16 @@ mod tests {
         assert_eq!(order.quantity, updated_order.quantity);
     }
 
-    #[tokio::test]
-    async fn test_update_order_by_id_not_found() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_update_order_by_id_not_found<R: OrderRepository + Clone>(orders: R) {
         let updated_order = Order {
             id: 999,
             item: "Updated Item".to_string(),
             status: "shipped".to_string(),
             quantity: 10,
+            ..Default::default()
         };
 
-        let result = update_order_by_id(State(db_pool), Path(999), Json(updated_order)).await;
+        let result = update_order_by_id(State(orders), admin(), payments(), Path(999), Json(updated_order)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::NotFound(message)) = result {
@@ -248,20 +249,18 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_update_order_by_id_validation_error() {
-        let db_pool = setup_test_db().await;
-        
-        let _created_order = create_test_order(&db_pool).await;
+    async fn test_update_order_by_id_validation_error<R: OrderRepository + Clone>(orders: R) {
+        let _created_order = create_test_order(&orders).await;
         
         let invalid_updated_order = Order {
             id: 1,
             item: "".to_string(), // Invalid empty item
             status: "shipped".to_string(),
             quantity: 10,
+            ..Default::default()
         };
 
-        let result = update_order_by_id(State(db_pool), Path(1), Json(invalid_updated_order)).await;
+        let result = update_order_by_id(State(orders), admin(), payments(), Path(1), Json(invalid_updated_order)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::Validation(error)) = result {
@@ -271,17 +270,14 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_success() {
-        let db_pool = setup_test_db().await;
-        
-        let _created_order = create_test_order(&db_pool).await;
+    async fn test_update_order_status_success<R: OrderRepository + Clone>(orders: R) {
+        let _created_order = create_test_order(&orders).await;
         
         let status_update = StatusUpdate {
             status: "shipped".to_string(),
         };
 
-        let result = update_order_status(State(db_pool), Path(1), Json(status_update)).await;
+        let result = update_order_status(State(orders), admin(), payments(), Path(1), Json(status_update)).await;
         assert!(result.is_ok());
         let order = result.unwrap().0;
         assert_eq!(order.status, "shipped");
@@ -290,15 +286,12 @@ mod tests {
         assert_eq!(order.quantity, 5);
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_not_found() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_update_order_status_not_found<R: OrderRepository + Clone>(orders: R) {
         let status_update = StatusUpdate {
             status: "shipped".to_string(),
         };
 
-        let result = update_order_status(State(db_pool), Path(999), Json(status_update)).await;
+        let result = update_order_status(State(orders), admin(), payments(), Path(999), Json(status_update)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::NotFound(message)) = result {
@@ -308,17 +301,14 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_validation_error() {
-        let db_pool = setup_test_db().await;
-        
-        let _created_order = create_test_order(&db_pool).await;
+    async fn test_update_order_status_validation_error<R: OrderRepository + Clone>(orders: R) {
+        let _created_order = create_test_order(&orders).await;
         
         let invalid_status_update = StatusUpdate {
             status: "invalid_status".to_string(),
         };
 
-        let result = update_order_status(State(db_pool), Path(1), Json(invalid_status_update)).await;
+        let result = update_order_status(State(orders), admin(), payments(), Path(1), Json(invalid_status_update)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::Validation(error)) = result {
@@ -328,30 +318,24 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_delete_order_by_id_success() {
-        let db_pool = setup_test_db().await;
-        
-        let created_order = create_test_order(&db_pool).await;
+    async fn test_delete_order_by_id_success<R: OrderRepository + Clone>(orders: R) {
+        let created_order = create_test_order(&orders).await;
         
-        let result = delete_order_by_id(State(db_pool.clone()), Path(1)).await;
+        let result = delete_order_by_id(State(orders.clone()), admin(), Path(1)).await;
         assert!(result.is_ok());
         let deleted_order = result.unwrap().0;
         assert_eq!(deleted_order.id, created_order.id);
         assert_eq!(deleted_order.item, created_order.item);
         
         // Verify it was deleted from the database
-        let orders_result = get_orders(State(db_pool)).await;
+        let orders_result = get_orders(State(orders), admin(), HeaderMap::new(), Query(OrderFilter::default())).await;
         assert!(orders_result.is_ok());
-        let orders = orders_result.unwrap().0;
+        let orders = orders_from(orders_result.unwrap()).await;
         assert_eq!(orders.len(), 0);
     }
 
-    #[tokio::test]
-    async fn test_delete_order_by_id_not_found() {
-        let db_pool = setup_test_db().await;
-        
-        let result = delete_order_by_id(State(db_pool), Path(999)).await;
+    async fn test_delete_order_by_id_not_found<R: OrderRepository + Clone>(orders: R) {
+        let result = delete_order_by_id(State(orders), admin(), Path(999)).await;
         assert!(result.is_err());
         
         if let Err(ApiError::NotFound(message)) = result {
@@ -377,22 +361,20 @@ mod tests {
         assert_eq!(deserialized.status, "delivered");
     }
 
-    #[tokio::test]
-    async fn test_multiple_operations_sequence() {
-        let db_pool = setup_test_db().await;
-        
+    async fn test_multiple_operations_sequence<R: OrderRepository + Clone>(orders: R) {
         // 1. Add an order
         let new_order = Order {
             id: 1,
             item: "Sequential Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
-        let add_result = add_order(State(db_pool.clone()), Json(new_order.clone())).await;
+        let add_result = add_order(State(orders.clone()), admin(), payments(), Json(new_order.clone())).await;
         assert!(add_result.is_ok());
         
         // 2. Get the order
-        let get_result = get_order_by_id(State(db_pool.clone()), Path(1)).await;
+        let get_result = get_order_by_id(State(orders.clone()), admin(), Path(1)).await;
         assert!(get_result.is_ok());
         let retrieved_order = get_result.unwrap().0;
         assert_eq!(retrieved_order.item, new_order.item);
@@ -401,7 +383,7 @@ mod tests {
         let status_update = StatusUpdate {
             status: "processing".to_string(),
         };
-        let status_result = update_order_status(State(db_pool.clone()), Path(1), Json(status_update)).await;
+        let status_result = update_order_status(State(orders.clone()), admin(), payments(), Path(1), Json(status_update)).await;
         assert!(status_result.is_ok());
         let updated_order = status_result.unwrap().0;
         assert_eq!(updated_order.status, "processing");
@@ -412,8 +394,9 @@ mod tests {
             item: "Fully Updated Item".to_string(),
             status: "shipped".to_string(),
             quantity: 15,
+            ..Default::default()
         };
-        let full_update_result = update_order_by_id(State(db_pool.clone()), Path(1), Json(full_update.clone())).await;
+        let full_update_result = update_order_by_id(State(orders.clone()), admin(), payments(), Path(1), Json(full_update.clone())).await;
         assert!(full_update_result.is_ok());
         let final_order = full_update_result.unwrap().0;
         assert_eq!(final_order.item, full_update.item);
@@ -421,11 +404,103 @@ mod tests {
         assert_eq!(final_order.quantity, full_update.quantity);
         
         // 5. Delete the order
-        let delete_result = delete_order_by_id(State(db_pool.clone()), Path(1)).await;
+        let delete_result = delete_order_by_id(State(orders.clone()), admin(), Path(1)).await;
         assert!(delete_result.is_ok());
         
         // 6. Verify it's gone
-        let final_get_result = get_order_by_id(State(db_pool), Path(1)).await;
+        let final_get_result = get_order_by_id(State(orders), admin(), Path(1)).await;
         assert!(final_get_result.is_err());
     }
+
+    async fn test_read_only_caller_can_only_read<R: OrderRepository + Clone>(orders: R) {
+        let order = create_test_order(&orders).await;
+        
+        assert!(get_orders(State(orders.clone()), caller(Role::ReadOnly), HeaderMap::new(), Query(OrderFilter::default())).await.is_ok());
+        assert!(get_order_by_id(State(orders.clone()), caller(Role::ReadOnly), Path(1)).await.is_ok());
+        
+        let result = add_order(State(orders.clone()), caller(Role::ReadOnly), payments(), Json(order.clone())).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        
+        let status_update = StatusUpdate { status: "shipped".to_string() };
+        let result = update_order_status(State(orders.clone()), caller(Role::ReadOnly), payments(), Path(1), Json(status_update)).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        
+        let result = delete_order_by_id(State(orders), caller(Role::ReadOnly), Path(1)).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+    }
+
+    async fn test_warehouse_caller_can_change_status_only<R: OrderRepository + Clone>(orders: R) {
+        let order = create_test_order(&orders).await;
+        
+        let status_update = StatusUpdate { status: "shipped".to_string() };
+        let result = update_order_status(State(orders.clone()), caller(Role::Warehouse), payments(), Path(1), Json(status_update)).await;
+        assert_eq!(result.unwrap().0.status, "shipped");
+        
+        let result = update_order_by_id(State(orders.clone()), caller(Role::Warehouse), payments(), Path(1), Json(order)).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        
+        let result = delete_order_by_id(State(orders.clone()), caller(Role::Warehouse), Path(1)).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        
+        // Forbidden operations must not have touched the order
+        let order = get_order_by_id(State(orders), caller(Role::Warehouse), Path(1)).await.unwrap().0;
+        assert_eq!(order.item, "Test Item");
+    }
+
+    /// Run each test body once per store
+    macro_rules! for_each_repository {
+        ($($test:ident),* $(,)?) => {
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::setup_test_db().await).await;
+                    }
+                )*
+            }
+
+            mod in_memory {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::InMemoryStore::default()).await;
+                    }
+                )*
+            }
+
+            #[cfg(feature = "postgres")]
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        let options = crate::utils::test_connect_options().await;
+                        super::$test(crate::utils::PostgresStore::connect_lazy(options)).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    for_each_repository!(
+        test_get_orders_empty,
+        test_get_orders_with_data,
+        test_add_order_success,
+        test_add_order_duplicate_id,
+        test_add_order_validation_empty_item,
+        test_add_order_validation_invalid_status,
+        test_add_order_validation_zero_quantity,
+        test_get_order_by_id_success,
+        test_get_order_by_id_not_found,
+        test_update_order_by_id_success,
+        test_update_order_by_id_not_found,
+        test_update_order_by_id_validation_error,
+        test_update_order_status_success,
+        test_update_order_status_not_found,
+        test_update_order_status_validation_error,
+        test_delete_order_by_id_success,
+        test_delete_order_by_id_not_found,
+        test_multiple_operations_sequence,
+        test_read_only_caller_can_only_read,
+        test_warehouse_caller_can_change_status_only,
+    );
 }
@@ -0,0 +1,77 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json
+};
+use serde::{Deserialize, Serialize};
+use crate::utils::{PoolStatus, Store};
+
+#[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
+/// Liveness probe response
+pub struct HealthStatus {
+    /// Always "ok" while the process is able to serve requests
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
+/// Readiness probe response
+pub struct ReadinessStatus {
+    /// "ready" when the database is reachable, "unavailable" otherwise
+    pub status: String,
+    /// Result of the database round trip ("ok" or "unreachable")
+    pub database: String,
+    /// Schema migration version the database is at (absent if it could not be read or the store has no schema)
+    pub migration_version: Option<i64>,
+    /// Connection pool state
+    pub pool: PoolStatus,
+}
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "Process is alive", body = HealthStatus)
+    ),
+    tag = "health"
+)]
+pub async fn healthz() -> Json<HealthStatus> {
+    Json(HealthStatus {
+        status: "ok".to_string(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Service is ready to accept traffic", body = ReadinessStatus),
+        (status = 503, description = "Database is unreachable", body = ReadinessStatus)
+    ),
+    tag = "health"
+)]
+pub async fn readyz<S: Store + Clone>(State(store): State<S>) -> (StatusCode, Json<ReadinessStatus>) {
+    let reachable = store.ping().await.is_ok();
+    let migration_version = if reachable {
+        store.migration_version().await.ok().flatten()
+    } else {
+        None
+    };
+
+    let pool = store.pool_status();
+
+    let (status_code, status, database) = if reachable {
+        (StatusCode::OK, "ready", "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable", "unreachable")
+    };
+
+    (
+        status_code,
+        Json(ReadinessStatus {
+            status: status.to_string(),
+            database: database.to_string(),
+            migration_version,
+            pool,
+        })
+    )
+}
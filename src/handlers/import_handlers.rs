@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json
+};
+use serde::{Deserialize, Serialize};
+use crate::config::PaymentConfig;
+use crate::middleware::Caller;
+use crate::validators::{validate_order, ApiError, ValidationError};
+use crate::utils::{OrderRepository, Scope, parse_orders_csv};
+
+#[derive(Debug, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Options of an order import
+pub struct ImportOptions {
+    /// Check every row, including stock, promotions and duplicate IDs, without saving anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+/// Outcome of an order import
+pub struct ImportReport {
+    /// Whether the import was only a check
+    pub dry_run: bool,
+    /// Number of orders in the file
+    pub rows: usize,
+    /// Number of orders saved, or that would be saved by a dry run; 0 when any row failed
+    pub imported: usize,
+    /// Rows that failed, in file order
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+/// Reason a row of an imported file was rejected
+pub struct ImportRowError {
+    /// Line of the file the row starts on; the header is line 1
+    pub row: u64,
+    /// ID of the order on the row, if it could be read
+    pub order_id: Option<u32>,
+    /// Column that caused the error (if applicable)
+    pub field: Option<String>,
+    /// Error message describing what went wrong
+    pub error: String,
+}
+
+impl ImportRowError {
+    fn new(row: u64, order_id: Option<u32>, err: ValidationError) -> Self {
+        ImportRowError { row, order_id, field: err.field, error: err.error }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/import",
+    params(ImportOptions),
+    request_body(content = String, content_type = "text/csv", description = "Orders in the CSV layout of `GET /orders/export.csv`; only `id`, `item`, `status` and `quantity` are required and the total columns are ignored"),
+    responses(
+        (status = 200, description = "Every row was valid and all orders were imported, or would be for a dry run", body = ImportReport),
+        (status = 400, description = "Empty file or a header missing required columns"),
+        (status = 413, description = "File larger than the request body limit"),
+        (status = 422, description = "Some rows were invalid and nothing was imported", body = ImportReport),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:create` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:create"]),
+        ("bearer_auth" = ["orders:create"])
+    ),
+    tag = "orders"
+)]
+pub async fn import_orders<R: OrderRepository + Clone>(
+    State(store): State<R>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Query(options): Query<ImportOptions>,
+    body: String,
+) -> Result<Response, ApiError> {
+    caller.require_scope(Scope::CreateOrders)?;
+
+    let rows = parse_orders_csv(&body)?;
+    if rows.is_empty() {
+        return Err(ApiError::Validation(ValidationError {
+            error: "CSV file contains no orders".to_string(),
+            field: None,
+        }));
+    }
+
+    // Check every row on its own first so the report lists all of them
+    let row_count = rows.len();
+    let mut lines = Vec::with_capacity(rows.len());
+    let mut orders = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    let mut first_line_of_id = HashMap::new();
+    for row in rows {
+        let order = match row.order {
+            Ok(order) => order,
+            Err(err) => {
+                errors.push(ImportRowError::new(row.line, None, err));
+                continue;
+            }
+        };
+        if let Err(err) = validate_order(&order) {
+            errors.push(ImportRowError::new(row.line, Some(order.id), err));
+        } else if let Some(&first_line) = first_line_of_id.get(&order.id) {
+            errors.push(ImportRowError::new(row.line, Some(order.id), ValidationError {
+                error: format!("Order ID {} is already used on row {}", order.id, first_line),
+                field: Some("id".to_string()),
+            }));
+        }
+        first_line_of_id.entry(order.id).or_insert(row.line);
+        lines.push(row.line);
+        orders.push(order);
+    }
+
+    if errors.is_empty() {
+        // Rows can still clash with saved orders, stock or promotions; the first such row stops the import
+        match store.create_orders(&orders, options.dry_run, &payments).await? {
+            Ok(created) => {
+                let report = ImportReport {
+                    dry_run: options.dry_run,
+                    rows: row_count,
+                    imported: created.len(),
+                    errors,
+                };
+                return Ok(Json(report).into_response());
+            }
+            Err((index, err)) => {
+                let err = match err {
+                    ApiError::Validation(err) => err,
+                    ApiError::NotFound(message) | ApiError::Conflict(message) => ValidationError {
+                        error: message,
+                        field: None,
+                    },
+                    err => return Err(err),
+                };
+                errors.push(ImportRowError::new(lines[index], Some(orders[index].id), err));
+            }
+        }
+    }
+
+    let report = ImportReport {
+        dry_run: options.dry_run,
+        rows: row_count,
+        imported: 0,
+        errors,
+    };
+    Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(report)).into_response())
+}
@@ -1,4 +1,18 @@
+#[allow(clippy::module_inception)]
 pub mod handlers;
+pub mod health;
+pub mod customer_handlers;
+pub mod product_handlers;
+pub mod promotion_handlers;
+pub mod shipment_handlers;
+pub mod payment_handlers;
+pub mod webhook_handlers;
+pub mod order_event_handlers;
+pub mod ws_handlers;
+pub mod export_handlers;
+pub mod import_handlers;
+pub mod stats_handlers;
+pub mod graphql_handlers;
 pub use handlers::{
     get_orders, 
     add_order, 
@@ -8,7 +22,57 @@ pub use handlers::{
     delete_order_by_id,
     StatusUpdate
 };
+pub use customer_handlers::{
+    get_customers,
+    add_customer,
+    get_customer_by_id,
+    update_customer_by_id,
+    delete_customer_by_id,
+    get_customer_orders
+};
+pub use product_handlers::{
+    get_products,
+    add_product,
+    get_product_by_sku,
+    update_product_by_sku,
+    delete_product_by_sku
+};
+pub use promotion_handlers::{
+    get_promotions,
+    add_promotion,
+    get_promotion_by_code,
+    delete_promotion_by_code
+};
+pub use shipment_handlers::{
+    get_order_shipments,
+    add_shipment,
+    update_shipment,
+    ShipmentDelivery
+};
+pub use payment_handlers::{
+    get_order_payments,
+    add_payment,
+    capture_order_payment,
+    void_order_payment,
+    refund_order_payment,
+    RefundRequest
+};
+pub use webhook_handlers::{
+    get_webhooks,
+    add_webhook,
+    get_webhook,
+    delete_webhook,
+    get_webhook_delivery_log
+};
+pub use order_event_handlers::get_order_events;
+pub use ws_handlers::order_updates_socket;
+pub use export_handlers::export_orders_csv;
+pub use import_handlers::{import_orders, ImportReport, ImportRowError};
+pub use stats_handlers::get_orders_stats;
+pub use graphql_handlers::{graphql, graphiql};
+pub use health::{healthz, readyz, HealthStatus, ReadinessStatus};
 
 #[cfg(test)]
 #[path = "handlers.tests.rs"]
+#[allow(clippy::module_inception)]
 mod tests;
@@ -0,0 +1,70 @@
+use std::{convert::Infallible, time::Duration};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    Extension
+};
+use futures_util::{Stream, StreamExt};
+use crate::middleware::Caller;
+use crate::validators::{validate_status, ApiError, ValidationError};
+use crate::utils::{OrderChange, OrderChangeFilter, OrderRepository, Scope, order_change_stream};
+
+/// Interval between comments keeping idle streams open through proxies
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[utoipa::path(
+    get,
+    path = "/orders/events",
+    params(
+        OrderChangeFilter,
+        ("Last-Event-ID" = Option<i64>, Header, description = "Sequence number of the last change received; changes after it are replayed first")
+    ),
+    responses(
+        (status = 200, description = "Server-Sent Events stream of order changes, one `OrderChange` per event", content_type = "text/event-stream", body = OrderChange),
+        (status = 400, description = "Invalid status filter or Last-Event-ID"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
+    tag = "orders"
+)]
+pub async fn get_order_events<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    headers: HeaderMap,
+    Query(filter): Query<OrderChangeFilter>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+
+    if let Some(status) = &filter.status {
+        validate_status(status)?;
+    }
+
+    // Without Last-Event-ID the stream starts with the next change
+    let after_seq = match headers.get("last-event-id") {
+        Some(value) => value.to_str().ok()
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .ok_or_else(|| ApiError::Validation(ValidationError {
+                error: "Last-Event-ID must be a change sequence number".to_string(),
+                field: Some("Last-Event-ID".to_string()),
+            }))?,
+        None => orders.latest_order_change_seq().await?,
+    };
+
+    let events = order_change_stream(orders, after_seq, filter).map(|change| {
+        let event = Event::default()
+            .id(change.seq.to_string())
+            .event(change.event.clone())
+            .json_data(&change)
+            .expect("order changes serialize to JSON");
+        Ok(event)
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
@@ -0,0 +1,199 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use serde::{Deserialize, Serialize};
+use crate::config::PaymentConfig;
+use crate::middleware::Caller;
+use crate::validators::ApiError;
+use crate::utils::{OrderRepository, Payment, PaymentRepository, Scope};
+
+#[derive(Debug, Default, Deserialize, Serialize, utoipa::ToSchema)]
+/// Refund request body
+pub struct RefundRequest {
+    /// Amount to return in minor units; defaults to everything not yet refunded
+    #[serde(default)]
+    pub amount_minor: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}/payments",
+    params(
+        ("id" = u32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Payments taken for the order", body = [Payment]),
+        (status = 404, description = "Order not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `payments:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["payments:read"]),
+        ("bearer_auth" = ["payments:read"])
+    ),
+    tag = "payments"
+)]
+pub async fn get_order_payments<S: OrderRepository + PaymentRepository + Clone>(
+    State(store): State<S>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Vec<Payment>>, ApiError> {
+    caller.require_scope(Scope::ReadPayments)?;
+
+    store.get_order(id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+
+    let payments = store.list_payments(id).await?;
+    Ok(Json(payments))
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/payments",
+    params(
+        ("id" = u32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Order total authorized", body = Payment),
+        (status = 400, description = "Order has no amount to pay"),
+        (status = 402, description = "Authorization declined by the payment gateway"),
+        (status = 404, description = "Order not found"),
+        (status = 409, description = "Order is cancelled or already has an active payment"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `payments:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error"),
+        (status = 502, description = "Payment gateway unavailable")
+    ),
+    security(
+        ("api_key" = ["payments:write"]),
+        ("bearer_auth" = ["payments:write"])
+    ),
+    tag = "payments"
+)]
+pub async fn add_payment<P: PaymentRepository + Clone>(
+    State(store): State<P>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path(id): Path<u32>,
+) -> Result<Json<Payment>, ApiError> {
+    caller.require_scope(Scope::ManagePayments)?;
+
+    let payment = store.authorize_payment( payments.gateway.as_ref(), id).await?;
+    Ok(Json(payment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/payments/{payment_id}/capture",
+    params(
+        ("id" = u32, Path, description = "Order ID"),
+        ("payment_id" = u32, Path, description = "Payment ID")
+    ),
+    responses(
+        (status = 200, description = "Payment captured", body = Payment),
+        (status = 402, description = "Capture declined by the payment gateway"),
+        (status = 404, description = "Payment not found"),
+        (status = 409, description = "Payment is not authorized"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `payments:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error"),
+        (status = 502, description = "Payment gateway unavailable")
+    ),
+    security(
+        ("api_key" = ["payments:write"]),
+        ("bearer_auth" = ["payments:write"])
+    ),
+    tag = "payments"
+)]
+pub async fn capture_order_payment<P: PaymentRepository + Clone>(
+    State(store): State<P>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path((id, payment_id)): Path<(u32, u32)>,
+) -> Result<Json<Payment>, ApiError> {
+    caller.require_scope(Scope::ManagePayments)?;
+
+    let payment = store.capture_payment( payments.gateway.as_ref(), id, payment_id).await?;
+    Ok(Json(payment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/payments/{payment_id}/void",
+    params(
+        ("id" = u32, Path, description = "Order ID"),
+        ("payment_id" = u32, Path, description = "Payment ID")
+    ),
+    responses(
+        (status = 200, description = "Authorization released", body = Payment),
+        (status = 402, description = "Void declined by the payment gateway"),
+        (status = 404, description = "Payment not found"),
+        (status = 409, description = "Payment is not authorized"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `payments:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error"),
+        (status = 502, description = "Payment gateway unavailable")
+    ),
+    security(
+        ("api_key" = ["payments:write"]),
+        ("bearer_auth" = ["payments:write"])
+    ),
+    tag = "payments"
+)]
+pub async fn void_order_payment<P: PaymentRepository + Clone>(
+    State(store): State<P>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path((id, payment_id)): Path<(u32, u32)>,
+) -> Result<Json<Payment>, ApiError> {
+    caller.require_scope(Scope::ManagePayments)?;
+
+    let payment = store.void_payment( payments.gateway.as_ref(), id, payment_id).await?;
+    Ok(Json(payment))
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/payments/{payment_id}/refund",
+    params(
+        ("id" = u32, Path, description = "Order ID"),
+        ("payment_id" = u32, Path, description = "Payment ID")
+    ),
+    request_body = RefundRequest,
+    responses(
+        (status = 200, description = "Refund recorded", body = Payment),
+        (status = 400, description = "Refund amount exceeds what is left to refund"),
+        (status = 402, description = "Refund declined by the payment gateway"),
+        (status = 404, description = "Payment not found"),
+        (status = 409, description = "Payment has not been captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `payments:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error"),
+        (status = 502, description = "Payment gateway unavailable")
+    ),
+    security(
+        ("api_key" = ["payments:write"]),
+        ("bearer_auth" = ["payments:write"])
+    ),
+    tag = "payments"
+)]
+pub async fn refund_order_payment<P: PaymentRepository + Clone>(
+    State(store): State<P>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path((id, payment_id)): Path<(u32, u32)>,
+    Json(refund): Json<RefundRequest>,
+) -> Result<Json<Payment>, ApiError> {
+    caller.require_scope(Scope::ManagePayments)?;
+
+    let payment = store.refund_payment( payments.gateway.as_ref(), id, payment_id, refund.amount_minor).await?;
+    Ok(Json(payment))
+}
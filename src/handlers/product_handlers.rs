@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use crate::middleware::Caller;
+use crate::validators::{validate_product, ApiError};
+use crate::utils::{Product, ProductRepository, Scope};
+
+#[utoipa::path(
+    get,
+    path = "/products",
+    responses(
+        (status = 200, description = "List of all products with their stock on hand", body = [Product]),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `products:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["products:read"]),
+        ("bearer_auth" = ["products:read"])
+    ),
+    tag = "products"
+)]
+pub async fn get_products<P: ProductRepository + Clone>(
+    State(products): State<P>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<Product>>, ApiError> {
+    caller.require_scope(Scope::ReadProducts)?;
+
+    let products = products.list_products().await?;
+    Ok(Json(products))
+}
+
+#[utoipa::path(
+    post,
+    path = "/products",
+    request_body = Product,
+    responses(
+        (status = 200, description = "Product created successfully", body = Product),
+        (status = 400, description = "Invalid input or duplicate SKU"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `products:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["products:write"]),
+        ("bearer_auth" = ["products:write"])
+    ),
+    tag = "products"
+)]
+pub async fn add_product<P: ProductRepository + Clone>(
+    State(products): State<P>,
+    Extension(caller): Extension<Caller>,
+    Json(new_product): Json<Product>,
+) -> Result<Json<Product>, ApiError> {
+    caller.require_scope(Scope::ManageProducts)?;
+
+    validate_product(&new_product)?;
+
+    let created_product = products.create_product(&new_product).await?;
+    Ok(Json(created_product))
+}
+
+#[utoipa::path(
+    get,
+    path = "/products/{sku}",
+    params(
+        ("sku" = String, Path, description = "Product SKU")
+    ),
+    responses(
+        (status = 200, description = "Product found", body = Product),
+        (status = 404, description = "Product not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `products:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["products:read"]),
+        ("bearer_auth" = ["products:read"])
+    ),
+    tag = "products"
+)]
+pub async fn get_product_by_sku<P: ProductRepository + Clone>(
+    State(products): State<P>,
+    Extension(caller): Extension<Caller>,
+    Path(sku): Path<String>,
+) -> Result<Json<Product>, ApiError> {
+    caller.require_scope(Scope::ReadProducts)?;
+
+    let product = products.get_product(&sku).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+    Ok(Json(product))
+}
+
+#[utoipa::path(
+    put,
+    path = "/products/{sku}",
+    params(
+        ("sku" = String, Path, description = "Product SKU")
+    ),
+    request_body = Product,
+    responses(
+        (status = 200, description = "Product name and stock on hand updated", body = Product),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Product not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `products:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["products:write"]),
+        ("bearer_auth" = ["products:write"])
+    ),
+    tag = "products"
+)]
+pub async fn update_product_by_sku<P: ProductRepository + Clone>(
+    State(products): State<P>,
+    Extension(caller): Extension<Caller>,
+    Path(sku): Path<String>,
+    Json(mut updated_product): Json<Product>,
+) -> Result<Json<Product>, ApiError> {
+    caller.require_scope(Scope::ManageProducts)?;
+
+    // The SKU in the path identifies the product; it cannot be changed
+    updated_product.sku = sku;
+    validate_product(&updated_product)?;
+
+    let updated = products.update_product(&updated_product.sku, &updated_product).await?;
+    Ok(Json(updated))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/products/{sku}",
+    params(
+        ("sku" = String, Path, description = "Product SKU")
+    ),
+    responses(
+        (status = 200, description = "Product deleted successfully", body = Product),
+        (status = 404, description = "Product not found"),
+        (status = 409, description = "Product is referenced by orders"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `products:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["products:write"]),
+        ("bearer_auth" = ["products:write"])
+    ),
+    tag = "products"
+)]
+pub async fn delete_product_by_sku<P: ProductRepository + Clone>(
+    State(products): State<P>,
+    Extension(caller): Extension<Caller>,
+    Path(sku): Path<String>,
+) -> Result<Json<Product>, ApiError> {
+    caller.require_scope(Scope::ManageProducts)?;
+
+    let deleted_product = products.delete_product(&sku).await?;
+    Ok(Json(deleted_product))
+}
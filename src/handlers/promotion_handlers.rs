@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use crate::middleware::Caller;
+use crate::validators::{validate_promotion, ApiError};
+use crate::utils::{Promotion, PromotionRepository, Scope};
+
+#[utoipa::path(
+    get,
+    path = "/promotions",
+    responses(
+        (status = 200, description = "List of all promotions with their redemption counts", body = [Promotion]),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `promotions:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["promotions:read"]),
+        ("bearer_auth" = ["promotions:read"])
+    ),
+    tag = "promotions"
+)]
+pub async fn get_promotions<P: PromotionRepository + Clone>(
+    State(promotions): State<P>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<Promotion>>, ApiError> {
+    caller.require_scope(Scope::ReadPromotions)?;
+
+    let promotions = promotions.list_promotions().await?;
+    Ok(Json(promotions))
+}
+
+#[utoipa::path(
+    post,
+    path = "/promotions",
+    request_body = Promotion,
+    responses(
+        (status = 200, description = "Promotion created successfully", body = Promotion),
+        (status = 400, description = "Invalid input or duplicate code"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `promotions:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["promotions:write"]),
+        ("bearer_auth" = ["promotions:write"])
+    ),
+    tag = "promotions"
+)]
+pub async fn add_promotion<P: PromotionRepository + Clone>(
+    State(promotions): State<P>,
+    Extension(caller): Extension<Caller>,
+    Json(new_promotion): Json<Promotion>,
+) -> Result<Json<Promotion>, ApiError> {
+    caller.require_scope(Scope::ManagePromotions)?;
+
+    validate_promotion(&new_promotion)?;
+
+    let created_promotion = promotions.create_promotion(&new_promotion).await?;
+    Ok(Json(created_promotion))
+}
+
+#[utoipa::path(
+    get,
+    path = "/promotions/{code}",
+    params(
+        ("code" = String, Path, description = "Promo code")
+    ),
+    responses(
+        (status = 200, description = "Promotion found", body = Promotion),
+        (status = 404, description = "Promotion not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `promotions:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["promotions:read"]),
+        ("bearer_auth" = ["promotions:read"])
+    ),
+    tag = "promotions"
+)]
+pub async fn get_promotion_by_code<P: PromotionRepository + Clone>(
+    State(promotions): State<P>,
+    Extension(caller): Extension<Caller>,
+    Path(code): Path<String>,
+) -> Result<Json<Promotion>, ApiError> {
+    caller.require_scope(Scope::ReadPromotions)?;
+
+    let promotion = promotions.get_promotion(&code).await?
+        .ok_or_else(|| ApiError::NotFound("Promotion not found".to_string()))?;
+    Ok(Json(promotion))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/promotions/{code}",
+    params(
+        ("code" = String, Path, description = "Promo code")
+    ),
+    responses(
+        (status = 200, description = "Promotion deleted successfully", body = Promotion),
+        (status = 404, description = "Promotion not found"),
+        (status = 409, description = "Promotion has already been redeemed"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `promotions:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["promotions:write"]),
+        ("bearer_auth" = ["promotions:write"])
+    ),
+    tag = "promotions"
+)]
+pub async fn delete_promotion_by_code<P: PromotionRepository + Clone>(
+    State(promotions): State<P>,
+    Extension(caller): Extension<Caller>,
+    Path(code): Path<String>,
+) -> Result<Json<Promotion>, ApiError> {
+    caller.require_scope(Scope::ManagePromotions)?;
+
+    let deleted_promotion = promotions.delete_promotion(&code).await?;
+    Ok(Json(deleted_promotion))
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::config::PaymentConfig;
+use crate::middleware::Caller;
+use crate::validators::{validate_shipment, ApiError};
+use crate::utils::{OrderRepository, Scope, Shipment, ShipmentRepository};
+
+#[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
+/// Delivery confirmation request body
+pub struct ShipmentDelivery {
+    /// When the parcel was delivered; defaults to now
+    #[serde(default = "Utc::now")]
+    pub delivered_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}/shipments",
+    params(
+        ("id" = u32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Shipments recorded for the order", body = [Shipment]),
+        (status = 404, description = "Order not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
+    tag = "shipments"
+)]
+pub async fn get_order_shipments<S: OrderRepository + ShipmentRepository + Clone>(
+    State(store): State<S>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Vec<Shipment>>, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+
+    store.get_order(id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+
+    let shipments = store.list_shipments(id).await?;
+    Ok(Json(shipments))
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/shipments",
+    params(
+        ("id" = u32, Path, description = "Order ID")
+    ),
+    request_body = Shipment,
+    responses(
+        (status = 200, description = "Shipment recorded and order status advanced", body = Shipment),
+        (status = 400, description = "Invalid input or order has no shipping address"),
+        (status = 404, description = "Order not found"),
+        (status = 409, description = "Order is cancelled, already delivered or its payment is not captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:status` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:status"]),
+        ("bearer_auth" = ["orders:status"])
+    ),
+    tag = "shipments"
+)]
+pub async fn add_shipment<S: ShipmentRepository + Clone>(
+    State(shipments): State<S>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path(id): Path<u32>,
+    Json(new_shipment): Json<Shipment>,
+) -> Result<Json<Shipment>, ApiError> {
+    caller.require_scope(Scope::UpdateOrderStatus)?;
+
+    validate_shipment(&new_shipment)?;
+
+    let created_shipment = shipments.create_shipment(id, &new_shipment, &payments).await?;
+    Ok(Json(created_shipment))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/orders/{id}/shipments/{shipment_id}",
+    params(
+        ("id" = u32, Path, description = "Order ID"),
+        ("shipment_id" = u32, Path, description = "Shipment ID")
+    ),
+    request_body = ShipmentDelivery,
+    responses(
+        (status = 200, description = "Delivery recorded and order status advanced", body = Shipment),
+        (status = 400, description = "Delivery time before shipping time"),
+        (status = 404, description = "Shipment not found"),
+        (status = 409, description = "Delivering would ship the order before its payment is captured"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:status` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:status"]),
+        ("bearer_auth" = ["orders:status"])
+    ),
+    tag = "shipments"
+)]
+pub async fn update_shipment<S: ShipmentRepository + Clone>(
+    State(shipments): State<S>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    Path((id, shipment_id)): Path<(u32, u32)>,
+    Json(delivery): Json<ShipmentDelivery>,
+) -> Result<Json<Shipment>, ApiError> {
+    caller.require_scope(Scope::UpdateOrderStatus)?;
+
+    let delivered = shipments.mark_shipment_delivered(id, shipment_id, delivery.delivered_at, &payments).await?;
+    Ok(Json(delivered))
+}
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json
+};
+use serde::Deserialize;
+use crate::middleware::Caller;
+use crate::validators::{ApiError, ValidationError};
+use crate::utils::{OrderRepository, OrderStats, Scope};
+
+/// Most items that can be ranked in one request
+const MAX_TOP_ITEMS: u32 = 100;
+
+/// Longest daily series that can be requested
+const MAX_DAYS: u32 = 366;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Size of the rankings and series in the order statistics
+pub struct StatsOptions {
+    /// Number of items to rank by quantity ordered, 1 to 100
+    #[serde(default = "default_top_items")]
+    #[param(default = 10, minimum = 1, maximum = 100)]
+    pub top_items: u32,
+    /// Number of days in the daily series, ending today (UTC), 1 to 366
+    #[serde(default = "default_days")]
+    #[param(default = 30, minimum = 1, maximum = 366)]
+    pub days: u32,
+}
+
+fn default_top_items() -> u32 {
+    10
+}
+
+fn default_days() -> u32 {
+    30
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/stats",
+    params(StatsOptions),
+    responses(
+        (status = 200, description = "Order counts and quantities by status and item, with daily creations and deliveries", body = OrderStats),
+        (status = 400, description = "`top_items` or `days` out of range"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
+    tag = "orders"
+)]
+pub async fn get_orders_stats<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Query(options): Query<StatsOptions>,
+) -> Result<Json<OrderStats>, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+
+    if !(1..=MAX_TOP_ITEMS).contains(&options.top_items) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("top_items must be between 1 and {}", MAX_TOP_ITEMS),
+            field: Some("top_items".to_string()),
+        }));
+    }
+    if !(1..=MAX_DAYS).contains(&options.days) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("days must be between 1 and {}", MAX_DAYS),
+            field: Some("days".to_string()),
+        }));
+    }
+
+    let stats = orders.order_stats(options.top_items, options.days).await?;
+    Ok(Json(stats))
+}
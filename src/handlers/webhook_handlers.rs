@@ -0,0 +1,162 @@
+use axum::{
+    extract::{Path, State},
+    Extension,
+    Json
+};
+use crate::middleware::Caller;
+use crate::validators::{validate_webhook_subscription, ApiError};
+use crate::utils::{Scope, WebhookDelivery, WebhookRepository, WebhookSubscription};
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "List of all webhook subscriptions", body = [WebhookSubscription]),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `webhooks:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["webhooks:read"]),
+        ("bearer_auth" = ["webhooks:read"])
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhooks<W: WebhookRepository + Clone>(
+    State(webhooks): State<W>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<Vec<WebhookSubscription>>, ApiError> {
+    caller.require_scope(Scope::ReadWebhooks)?;
+
+    let subscriptions = webhooks.list_webhook_subscriptions().await?;
+    Ok(Json(subscriptions))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    request_body = WebhookSubscription,
+    responses(
+        (status = 200, description = "Webhook subscription created successfully", body = WebhookSubscription),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `webhooks:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["webhooks:write"]),
+        ("bearer_auth" = ["webhooks:write"])
+    ),
+    tag = "webhooks"
+)]
+pub async fn add_webhook<W: WebhookRepository + Clone>(
+    State(webhooks): State<W>,
+    Extension(caller): Extension<Caller>,
+    Json(new_subscription): Json<WebhookSubscription>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+    caller.require_scope(Scope::ManageWebhooks)?;
+
+    validate_webhook_subscription(&new_subscription)?;
+
+    let created_subscription = webhooks.create_webhook_subscription(&new_subscription).await?;
+    Ok(Json(created_subscription))
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}",
+    params(
+        ("id" = u32, Path, description = "Webhook subscription ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook subscription found", body = WebhookSubscription),
+        (status = 404, description = "Webhook subscription not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `webhooks:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["webhooks:read"]),
+        ("bearer_auth" = ["webhooks:read"])
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhook<W: WebhookRepository + Clone>(
+    State(webhooks): State<W>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+    caller.require_scope(Scope::ReadWebhooks)?;
+
+    let subscription = webhooks.get_webhook_subscription(id).await?
+        .ok_or_else(|| ApiError::NotFound("Webhook subscription not found".to_string()))?;
+    Ok(Json(subscription))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    params(
+        ("id" = u32, Path, description = "Webhook subscription ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook subscription deleted with its pending events and delivery log", body = WebhookSubscription),
+        (status = 404, description = "Webhook subscription not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `webhooks:write` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["webhooks:write"]),
+        ("bearer_auth" = ["webhooks:write"])
+    ),
+    tag = "webhooks"
+)]
+pub async fn delete_webhook<W: WebhookRepository + Clone>(
+    State(webhooks): State<W>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+    caller.require_scope(Scope::ManageWebhooks)?;
+
+    let deleted_subscription = webhooks.delete_webhook_subscription(id).await?;
+    Ok(Json(deleted_subscription))
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    params(
+        ("id" = u32, Path, description = "Webhook subscription ID")
+    ),
+    responses(
+        (status = 200, description = "Delivery attempts for the subscription, most recent first", body = [WebhookDelivery]),
+        (status = 404, description = "Webhook subscription not found"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `webhooks:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["webhooks:read"]),
+        ("bearer_auth" = ["webhooks:read"])
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhook_delivery_log<W: WebhookRepository + Clone>(
+    State(webhooks): State<W>,
+    Extension(caller): Extension<Caller>,
+    Path(id): Path<u32>,
+) -> Result<Json<Vec<WebhookDelivery>>, ApiError> {
+    caller.require_scope(Scope::ReadWebhooks)?;
+
+    webhooks.get_webhook_subscription(id).await?
+        .ok_or_else(|| ApiError::NotFound("Webhook subscription not found".to_string()))?;
+
+    let deliveries = webhooks.webhook_deliveries(id).await?;
+    Ok(Json(deliveries))
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+use axum::{
+    body::to_bytes,
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, State},
+    response::{IntoResponse, Response},
+    Extension
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use crate::config::PaymentConfig;
+use crate::middleware::Caller;
+use crate::validators::{validate_status, ApiError, ValidationError};
+use crate::utils::{Order, OrderChangeFilter, OrderRepository, Scope, subscribe_order_changes, WebhookEvent};
+
+/// Orders a single connection can follow at once
+const MAX_SUBSCRIPTIONS: usize = 100;
+
+/// Changes read from the log per query when pushing updates
+const PUSH_BATCH_SIZE: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+/// Message sent by the client; `id` is echoed in the reply so requests can be matched to answers
+struct ClientEnvelope {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(flatten)]
+    message: ClientMessage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Start pushing status changes of these orders
+    Subscribe { order_ids: Vec<u32> },
+    /// Stop pushing status changes of these orders
+    Unsubscribe { order_ids: Vec<u32> },
+    /// Change an order's status, exactly like `PATCH /orders/{id}/status`
+    UpdateStatus { order_id: u32, status: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    /// Request succeeded; carries the current subscriptions or the updated order
+    Ack {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        order_ids: Option<Vec<u32>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        order: Option<Order>,
+    },
+    /// Request failed; carries the HTTP status and error body the REST API would answer with
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<Value>,
+        status: u16,
+        #[serde(flatten)]
+        body: Value,
+    },
+    /// A subscribed order changed status
+    StatusChanged {
+        seq: i64,
+        order_id: u32,
+        status: String,
+        previous_status: Option<String>,
+        order: Order,
+    },
+}
+
+impl ServerMessage {
+    /// Describe a failed request with the same status and body as the REST API
+    async fn error(id: Option<Value>, err: ApiError) -> Self {
+        let response = err.into_response();
+        let status = response.status().as_u16();
+        let body = to_bytes(response.into_body(), usize::MAX).await.ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or(Value::Null);
+        ServerMessage::Error { id, status, body }
+    }
+}
+
+/// State of one WebSocket connection
+struct Session<R> {
+    orders: R,
+    caller: Caller,
+    payments: PaymentConfig,
+    subscriptions: BTreeSet<u32>,
+    /// Sequence number of the last order change looked at
+    last_seq: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ws",
+    responses(
+        (status = 101, description = "Switched to the WebSocket order update protocol"),
+        (status = 400, description = "Not a WebSocket upgrade request"),
+        (status = 401, description = "Missing or invalid credentials"),
+        (status = 403, description = "Credential lacks the `orders:read` scope"),
+        (status = 429, description = "Rate limit exceeded"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("api_key" = ["orders:read"]),
+        ("bearer_auth" = ["orders:read"])
+    ),
+    tag = "orders"
+)]
+pub async fn order_updates_socket<R: OrderRepository + Clone>(
+    State(orders): State<R>,
+    Extension(caller): Extension<Caller>,
+    Extension(payments): Extension<PaymentConfig>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    caller.require_scope(Scope::ReadOrders)?;
+
+    let last_seq = orders.latest_order_change_seq().await?;
+    let session = Session {
+        orders,
+        caller,
+        payments,
+        subscriptions: BTreeSet::new(),
+        last_seq,
+    };
+    Ok(ws.on_upgrade(move |socket| serve_socket(socket, session)))
+}
+
+/// Answer client requests and push status changes of subscribed orders until either side closes
+async fn serve_socket<R: OrderRepository>(mut socket: WebSocket, mut session: Session<R>) {
+    // Published changes only wake the session up; changes are read back from the log so none is
+    // missed when the connection falls behind the channel
+    let mut changes = subscribe_order_changes();
+
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => vec![session.handle(&text).await],
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames are not part of the protocol
+                Some(Ok(_)) => continue,
+            },
+            notice = changes.recv() => match notice {
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => match session.pending_status_changes().await {
+                    Ok(messages) => messages,
+                    Err(_) => break,
+                },
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        for message in outgoing {
+            let text = serde_json::to_string(&message).expect("server messages serialize to JSON");
+            if socket.send(Message::Text(text)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl<R: OrderRepository> Session<R> {
+    /// Handle one client message, answering with an ack or an error
+    async fn handle(&mut self, text: &str) -> ServerMessage {
+        let envelope = match serde_json::from_str::<ClientEnvelope>(text) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                let err = ApiError::Validation(ValidationError {
+                    error: format!("Invalid message: {}", e),
+                    field: None,
+                });
+                return ServerMessage::error(None, err).await;
+            }
+        };
+
+        let id = envelope.id;
+        match self.apply(envelope.message).await {
+            Ok((order_ids, order)) => ServerMessage::Ack { id, order_ids, order },
+            Err(err) => ServerMessage::error(id, err).await,
+        }
+    }
+
+    async fn apply(&mut self, message: ClientMessage) -> Result<(Option<Vec<u32>>, Option<Order>), ApiError> {
+        match message {
+            ClientMessage::Subscribe { order_ids } => {
+                for &order_id in &order_ids {
+                    self.orders.get_order(order_id).await?
+                        .ok_or_else(|| ApiError::NotFound(format!("Order {} not found", order_id)))?;
+                }
+                let mut subscriptions = self.subscriptions.clone();
+                subscriptions.extend(order_ids);
+                if subscriptions.len() > MAX_SUBSCRIPTIONS {
+                    return Err(ApiError::Validation(ValidationError {
+                        error: format!("A connection can follow at most {} orders", MAX_SUBSCRIPTIONS),
+                        field: Some("order_ids".to_string()),
+                    }));
+                }
+                self.subscriptions = subscriptions;
+                Ok((Some(self.subscriptions.iter().copied().collect()), None))
+            }
+            ClientMessage::Unsubscribe { order_ids } => {
+                for order_id in order_ids {
+                    self.subscriptions.remove(&order_id);
+                }
+                Ok((Some(self.subscriptions.iter().copied().collect()), None))
+            }
+            ClientMessage::UpdateStatus { order_id, status } => {
+                self.caller.require_scope(Scope::UpdateOrderStatus)?;
+
+                validate_status(&status)?;
+
+                let updated = self.orders.update_order_status(order_id, &status, &self.payments).await?;
+                Ok((None, Some(updated)))
+            }
+        }
+    }
+
+    /// Status changes of subscribed orders committed since the last look
+    async fn pending_status_changes(&mut self) -> Result<Vec<ServerMessage>, ApiError> {
+        let mut messages = Vec::new();
+        loop {
+            let changes = self.orders.order_changes_since(
+                self.last_seq,
+                &OrderChangeFilter::default(),
+                PUSH_BATCH_SIZE,
+            ).await?;
+            let Some(last) = changes.last() else {
+                return Ok(messages);
+            };
+            self.last_seq = last.seq;
+
+            messages.extend(changes.into_iter()
+                .filter(|change| change.event == WebhookEvent::StatusChanged.as_str())
+                .filter(|change| self.subscriptions.contains(&change.order_id))
+                .map(|change| ServerMessage::StatusChanged {
+                    seq: change.seq,
+                    order_id: change.order_id,
+                    status: change.order.status.clone(),
+                    previous_status: change.previous_status,
+                    order: change.order,
+                }));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Order management API: REST under `/v1`, GraphQL, gRPC, webhooks and WebSocket updates over SQLite, PostgreSQL or process memory.
+//!
+//! Embed the API in another axum application with `AppBuilder`, or run it on its own with `serve`.
+
+mod app;
+mod graphql;
+mod grpc;
+mod handlers;
+mod middleware;
+mod openapi;
+mod payments;
+mod routes;
+mod tls;
+mod utils;
+mod webhooks;
+pub mod config;
+pub mod validators;
+
+pub use app::{serve, AppBuilder};
+pub use config::{AppConfig, CorsConfig, Database, PaymentConfig, RateLimit, RateLimitConfig};
+pub use payments::{FakePaymentGateway, GatewayError, PaymentGateway};
+pub use routes::create_router;
+pub use utils::{
+    connect_db, init_db, CredentialRepository, CustomerRepository, DbPool, InMemoryStore, Order, OrderFilter, OrderRepository,
+    PaymentRepository, ProductRepository, PromotionRepository, Role, ShipmentRepository, ShippingAddress, SqliteStore,
+    Store, WebhookRepository, DEFAULT_CURRENCY, DEFAULT_DATABASE_URL
+};
+#[cfg(feature = "postgres")]
+pub use utils::PostgresStore;
+pub use validators::{validate_order, validate_status, ApiError, ServerError, ValidationError};
@@ -1,25 +1,10 @@
-mod handlers;
-mod routes;
-mod validators;
-mod utils;
-mod openapi;
-
-use axum::serve;
-use routes::create_router;
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use utils::init_db;
+use rustapi::AppConfig;
 
 #[tokio::main]
 async fn main() {
-    // Initialize the database
-    let db_pool = init_db().await.expect("Failed to initialize database");
-    
-    let app = create_router(db_pool);
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    println!("Server running at http://{}", addr);
-
-    let listener = TcpListener::bind(addr).await.unwrap();
-    serve(listener, app.into_make_service()).await.unwrap();
+    let config = AppConfig::from_env().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    rustapi::serve(config).await;
 }
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response
+};
+use crate::middleware::rate_limit::CredentialVerification;
+use crate::tls::ClientCertificate;
+use crate::utils::{CredentialRepository, Role, Scope};
+use crate::validators::ApiError;
+
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Identity of an authenticated caller, available to handlers as a request extension
+#[derive(Debug, Clone)]
+pub struct Caller {
+    /// Name of the client the key was issued to
+    pub name: String,
+    /// Role attached to the credential
+    pub role: Role,
+}
+
+impl Caller {
+    /// Ensure the caller has been granted `scope`, returning 403 otherwise
+    pub fn require_scope(&self, scope: Scope) -> Result<(), ApiError> {
+        if self.role.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "API key '{}' lacks the required scope: {}",
+                self.name,
+                scope.as_str()
+            )))
+        }
+    }
+}
+
+/// Extract the presented secret from either `X-API-Key` or `Authorization: Bearer`
+fn extract_credential(headers: &HeaderMap) -> Result<Option<&str>, ApiError> {
+    if let Some(value) = headers.get(API_KEY_HEADER) {
+        let key = value.to_str()
+            .map_err(|_| ApiError::Unauthorized("Malformed API key header".to_string()))?;
+        return Ok(Some(key.trim()));
+    }
+
+    if let Some(value) = headers.get(header::AUTHORIZATION) {
+        let value = value.to_str()
+            .map_err(|_| ApiError::Unauthorized("Malformed Authorization header".to_string()))?;
+        let (scheme, token) = value.split_once(' ')
+            .ok_or_else(|| ApiError::Unauthorized("Malformed Authorization header".to_string()))?;
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return Err(ApiError::Unauthorized("Unsupported authorization scheme".to_string()));
+        }
+        return Ok(Some(token.trim()));
+    }
+
+    Ok(None)
+}
+
+/// Identify the caller by the API key or bearer token in `headers`, if one is presented
+pub async fn authenticate_credential<C: CredentialRepository + ?Sized>(
+    credentials: &C,
+    headers: &HeaderMap,
+) -> Result<Option<Caller>, ApiError> {
+    let Some(secret) = extract_credential(headers)?.filter(|secret| !secret.is_empty()) else {
+        return Ok(None);
+    };
+
+    let key = credentials.find_api_key(secret).await?
+        .ok_or_else(|| ApiError::Unauthorized("Invalid API key or bearer token".to_string()))?;
+    Ok(Some(Caller { name: key.name, role: key.role }))
+}
+
+/// Client certificate presented on the connection, when served over mutual TLS
+pub fn client_certificate<B>(request: &axum::http::Request<B>) -> Option<&ClientCertificate> {
+    request.extensions().get::<Option<ClientCertificate>>()?.as_ref()
+}
+
+/// Identify the caller by the API key or bearer token in `headers`, or else by a mapped client certificate.
+/// An explicit API key or bearer token takes precedence over the client certificate;
+/// once verified it is reported through `verification` so the rate limiter can give it its own budget.
+pub async fn authenticate<C: CredentialRepository + ?Sized>(
+    credentials: &C,
+    headers: &HeaderMap,
+    certificate: Option<&ClientCertificate>,
+    verification: Option<&CredentialVerification>,
+) -> Result<Caller, ApiError> {
+    if let Some(caller) = authenticate_credential(credentials, headers).await? {
+        if let Some(verification) = verification {
+            verification.mark_verified();
+        }
+        return Ok(caller);
+    }
+
+    let Some(certificate) = certificate else {
+        return Err(ApiError::Unauthorized("Missing API key or bearer token".to_string()));
+    };
+    let identity = credentials.find_certificate_identity(&certificate.subject).await?
+        .ok_or_else(|| ApiError::Unauthorized(format!(
+            "Client certificate '{}' is not mapped to an identity",
+            certificate.subject
+        )))?;
+    Ok(Caller { name: identity.name, role: identity.role })
+}
+
+/// Reject requests that do not carry a valid API key, bearer token or mapped client certificate
+pub async fn require_auth<C: CredentialRepository + Clone>(
+    State(credentials): State<C>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let caller = authenticate(
+        &credentials,
+        request.headers(),
+        client_certificate(&request),
+        request.extensions().get::<CredentialVerification>(),
+    ).await?;
+    request.extensions_mut().insert(caller);
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,30 @@
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response
+};
+
+/// Date from which the unversioned aliases are deprecated (RFC 9745 `Deprecation`, a structured-field date:
+/// `@` followed by seconds since the Unix epoch), 18 October 2026
+pub const LEGACY_DEPRECATION: &str = "@1792281600";
+
+/// Date after which the unversioned aliases may be removed (RFC 8594 `Sunset`, HTTP-date format)
+pub const LEGACY_SUNSET: &str = "Thu, 01 Jul 2027 00:00:00 GMT";
+
+/// Prefix under which the current API version is mounted
+pub const CURRENT_VERSION_PREFIX: &str = "/v1";
+
+/// Mark responses from unversioned alias routes as deprecated, pointing at the `/v1` successor
+pub async fn deprecated_alias(request: Request, next: Next) -> Response {
+    let successor = format!("<{}{}>; rel=\"successor-version\"", CURRENT_VERSION_PREFIX, request.uri().path());
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static(LEGACY_DEPRECATION));
+    headers.insert("sunset", HeaderValue::from_static(LEGACY_SUNSET));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.append("link", link);
+    }
+    response
+}
@@ -0,0 +1,160 @@
+use std::time::Duration;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response}
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use crate::config::CorsConfig;
+use crate::validators::ApiError;
+
+/// Build the CORS layer from configuration; with no allowed origins no CORS headers are sent
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.allowed_origins.iter().filter_map(|origin| {
+            HeaderValue::from_str(origin)
+                .inspect_err(|_| eprintln!("Ignoring invalid CORS origin: {}", origin))
+                .ok()
+        }))
+    };
+
+    let methods: Vec<Method> = config.allowed_methods.iter()
+        .filter_map(|method| {
+            Method::from_bytes(method.to_uppercase().as_bytes())
+                .inspect_err(|_| eprintln!("Ignoring invalid CORS method: {}", method))
+                .ok()
+        })
+        .collect();
+
+    let headers: Vec<HeaderName> = config.allowed_headers.iter()
+        .filter_map(|name| {
+            HeaderName::from_bytes(name.as_bytes())
+                .inspect_err(|_| eprintln!("Ignoring invalid CORS header: {}", name))
+                .ok()
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+}
+
+/// Reject bodies larger than the configured maximum with 413 and the standard error body.
+/// Declared lengths are checked up front; chunked bodies are capped by axum's
+/// `DefaultBodyLimit`, whose plain-text rejection is rewritten here.
+pub async fn enforce_body_limit(
+    State(max_body_bytes): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let too_large = || {
+        ApiError::PayloadTooLarge(format!(
+            "Request body exceeds the maximum of {} bytes",
+            max_body_bytes
+        )).into_response()
+    };
+
+    let declared_length = request.headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared_length.is_some_and(|length| length > max_body_bytes) {
+        return too_large();
+    }
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return too_large();
+    }
+    response
+}
+
+/// Abort requests that take longer than the configured timeout with 504
+pub async fn enforce_timeout(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => ApiError::Timeout(format!(
+            "Request did not complete within {} ms",
+            timeout.as_millis()
+        )).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::DefaultBodyLimit, middleware, routing::{get, post}, Router};
+    use axum_test::TestServer;
+    use serde_json::Value;
+
+    fn test_server(max_body_bytes: usize, timeout: Duration) -> TestServer {
+        let app = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route("/slow", get(|| async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                "done"
+            }))
+            .layer(DefaultBodyLimit::max(max_body_bytes))
+            .layer(middleware::from_fn_with_state(max_body_bytes, enforce_body_limit))
+            .layer(middleware::from_fn_with_state(timeout, enforce_timeout));
+        TestServer::new(app).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_is_accepted() {
+        let server = test_server(16, Duration::from_secs(5));
+
+        let response = server.post("/echo").text("small").await;
+        response.assert_status_ok();
+        response.assert_text("small");
+    }
+
+    #[tokio::test]
+    async fn test_body_over_limit_is_rejected() {
+        let server = test_server(16, Duration::from_secs(5));
+
+        let response = server.post("/echo").text("a".repeat(17)).await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Payload too large");
+        assert_eq!(body["message"], "Request body exceeds the maximum of 16 bytes");
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let server = test_server(16, Duration::from_millis(50));
+
+        let response = server.get("/slow").await;
+        response.assert_status(StatusCode::GATEWAY_TIMEOUT);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Gateway timeout");
+        assert_eq!(body["message"], "Request did not complete within 50 ms");
+    }
+
+    #[tokio::test]
+    async fn test_fast_request_completes() {
+        let server = test_server(16, Duration::from_secs(5));
+
+        server.get("/slow").await.assert_text("done");
+    }
+
+    #[test]
+    fn test_cors_layer_skips_invalid_entries() {
+        let config = CorsConfig {
+            allowed_origins: vec!["https://dashboard.example.com".to_string(), "bad\norigin".to_string()],
+            allowed_methods: vec!["get".to_string(), "NOT A METHOD".to_string()],
+            allowed_headers: vec!["x-api-key".to_string(), "bad header".to_string()],
+        };
+
+        // Building must not panic on invalid entries
+        let _layer = cors_layer(&config);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod auth;
+pub mod deprecation;
+pub mod limits;
+pub mod rate_limit;
+pub use auth::{authenticate, require_auth, Caller};
+pub use deprecation::deprecated_alias;
+pub use limits::{cors_layer, enforce_body_limit, enforce_timeout};
+pub use rate_limit::{CredentialVerification, RateLimitLayer, RouteGroup};
@@ -0,0 +1,365 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    task::{Context, Poll},
+    time::Instant
+};
+use axum::{
+    extract::ConnectInfo,
+    http::{header, HeaderMap, HeaderValue, Method, Request},
+    response::{IntoResponse, Response}
+};
+use tower::{Layer, Service};
+use crate::config::{RateLimit, RateLimitConfig};
+use crate::middleware::auth::{client_certificate, API_KEY_HEADER};
+use crate::utils::hash_api_key;
+use crate::validators::ApiError;
+
+/// Number of buckets kept before idle, fully refilled buckets are pruned
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Group of routes sharing a rate limit.
+/// Requests are grouped by HTTP method unless they carry a `RouteGroup` extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    Read,
+    Write,
+}
+
+impl RouteGroup {
+    fn for_method(method: &Method) -> Self {
+        if method == Method::GET || method == Method::HEAD {
+            RouteGroup::Read
+        } else {
+            RouteGroup::Write
+        }
+    }
+
+    fn for_request<B>(request: &Request<B>) -> Self {
+        request.extensions().get::<RouteGroup>()
+            .copied()
+            .unwrap_or_else(|| RouteGroup::for_method(request.method()))
+    }
+}
+
+/// Request extension through which authentication tells the rate limiter that the presented
+/// API key or bearer token is genuine
+#[derive(Debug, Clone, Default)]
+pub struct CredentialVerification(Arc<AtomicBool>);
+
+impl CredentialVerification {
+    pub fn mark_verified(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn is_verified(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst as f64);
+        self.last_refill = now;
+    }
+}
+
+/// Outcome of checking a request against its bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    /// Seconds until the bucket is full again
+    reset: u64,
+    /// Seconds until the next request will be accepted (only meaningful when denied)
+    retry_after: u64,
+}
+
+/// In-memory token buckets keyed by route group and client
+#[derive(Debug)]
+struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<(RouteGroup, String), Bucket>>,
+    /// Hashes of the credentials that passed authentication, the only ones given their own bucket
+    verified: Mutex<HashSet<String>>,
+}
+
+impl RateLimiter {
+    fn limit_for(&self, group: RouteGroup) -> &RateLimit {
+        match group {
+            RouteGroup::Read => &self.config.read,
+            RouteGroup::Write => &self.config.write,
+        }
+    }
+
+    fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            verified: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Bucket for a request presenting `credential` (hashed): its own once verified, otherwise
+    /// the client certificate's or the peer IP address's, so made-up keys share a budget
+    fn client_key<B>(&self, request: &Request<B>, credential: Option<&str>) -> String {
+        if let Some(credential) = credential.filter(|credential| self.verified.lock().unwrap().contains(*credential)) {
+            return format!("key:{}", credential);
+        }
+
+        if let Some(certificate) = client_certificate(request) {
+            return format!("cert:{}", certificate.subject);
+        }
+
+        match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        }
+    }
+
+    /// Remember whether `credential` passed authentication. A newly verified credential is charged
+    /// for the request that verified it, so its budget covers every request made with it.
+    fn record_verification(&self, group: RouteGroup, credential: String, verified: bool, now: Instant) {
+        let mut known = self.verified.lock().unwrap();
+        if !verified {
+            known.remove(&credential);
+        } else if known.len() < MAX_TRACKED_CLIENTS && known.insert(credential.clone()) {
+            drop(known);
+            self.check(group, format!("key:{}", credential), now);
+        }
+    }
+
+    fn check(&self, group: RouteGroup, client: String, now: Instant) -> Decision {
+        let limit = *self.limit_for(group);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|(group, _), bucket| {
+                let limit = self.limit_for(*group);
+                bucket.refill(limit, now);
+                bucket.tokens < limit.burst as f64
+            });
+        }
+
+        let bucket = buckets.entry((group, client)).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            last_refill: now,
+        });
+        bucket.refill(&limit, now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let seconds_until = |tokens: f64| {
+            if limit.per_second > 0.0 {
+                (tokens.max(0.0) / limit.per_second).ceil() as u64
+            } else {
+                u64::MAX
+            }
+        };
+
+        Decision {
+            allowed,
+            limit: limit.burst,
+            remaining: bucket.tokens.floor() as u32,
+            reset: seconds_until(limit.burst as f64 - bucket.tokens),
+            retry_after: seconds_until(1.0 - bucket.tokens),
+        }
+    }
+}
+
+/// Hash of the API key or bearer token presented, if any; raw secrets are never kept in memory
+fn presented_credential<B>(request: &Request<B>) -> Option<String> {
+    let headers = request.headers();
+    headers.get(API_KEY_HEADER)
+        .or_else(|| headers.get(header::AUTHORIZATION))
+        .and_then(|value| value.to_str().ok())
+        .map(hash_api_key)
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &Decision) {
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(decision.reset));
+}
+
+/// Tower layer applying per-client token bucket rate limiting.
+/// Requests are limited by client IP address (or client certificate) until their API key or bearer
+/// token has been verified once, so rotating made-up credentials does not buy a fresh budget.
+/// Responses carry `RateLimit-*` headers; rejected requests get 429 with `Retry-After`.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+    reject: fn(ApiError) -> Response,
+}
+
+impl RateLimitLayer {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimitLayer {
+            limiter: Arc::new(RateLimiter::new(config)),
+            reject: IntoResponse::into_response,
+        }
+    }
+
+    /// Answer rejected requests with `reject` instead of the JSON error body, e.g. as a gRPC status
+    pub fn rejecting_with(mut self, reject: fn(ApiError) -> Response) -> Self {
+        self.reject = reject;
+        self
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+            reject: self.reject,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+    reject: fn(ApiError) -> Response,
+}
+
+impl<S, B> Service<Request<B>> for RateLimitService<S>
+where
+    S: Service<Request<B>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        let group = RouteGroup::for_request(&request);
+        let credential = presented_credential(&request);
+        let client = self.limiter.client_key(&request, credential.as_deref());
+        let decision = self.limiter.check(group, client, Instant::now());
+
+        if !decision.allowed {
+            let mut response = (self.reject)(ApiError::TooManyRequests(format!(
+                "Rate limit exceeded, retry in {} seconds",
+                decision.retry_after
+            )));
+            let headers = response.headers_mut();
+            insert_rate_limit_headers(headers, &decision);
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(decision.retry_after));
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let verification = CredentialVerification::default();
+        request.extensions_mut().insert(verification.clone());
+        let future = self.inner.call(request);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let mut response = future.await?;
+            if let Some(credential) = credential {
+                limiter.record_verification(group, credential, verification.is_verified(), Instant::now());
+            }
+            insert_rate_limit_headers(response.headers_mut(), &decision);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(burst: u32, per_second: f64) -> RateLimiter {
+        let limit = RateLimit { burst, per_second };
+        RateLimiter::new(RateLimitConfig { read: limit, write: limit })
+    }
+
+    #[test]
+    fn test_bucket_allows_burst_then_denies() {
+        let limiter = limiter(2, 1.0);
+        let now = Instant::now();
+
+        let first = limiter.check(RouteGroup::Write, "client".to_string(), now);
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+
+        assert!(limiter.check(RouteGroup::Write, "client".to_string(), now).allowed);
+
+        let denied = limiter.check(RouteGroup::Write, "client".to_string(), now);
+        assert!(!denied.allowed);
+        assert_eq!(denied.remaining, 0);
+        assert_eq!(denied.retry_after, 1);
+        assert_eq!(denied.reset, 2);
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = limiter(1, 2.0);
+        let now = Instant::now();
+
+        assert!(limiter.check(RouteGroup::Read, "client".to_string(), now).allowed);
+        assert!(!limiter.check(RouteGroup::Read, "client".to_string(), now).allowed);
+
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check(RouteGroup::Read, "client".to_string(), later).allowed);
+    }
+
+    #[test]
+    fn test_buckets_are_per_client_and_group() {
+        let limiter = limiter(1, 1.0);
+        let now = Instant::now();
+
+        assert!(limiter.check(RouteGroup::Write, "a".to_string(), now).allowed);
+        assert!(!limiter.check(RouteGroup::Write, "a".to_string(), now).allowed);
+        assert!(limiter.check(RouteGroup::Write, "b".to_string(), now).allowed);
+        assert!(limiter.check(RouteGroup::Read, "a".to_string(), now).allowed);
+    }
+
+    #[test]
+    fn test_credentials_get_own_bucket_once_verified() {
+        let limiter = limiter(2, 1.0);
+        let now = Instant::now();
+        let request = Request::new(());
+
+        assert_eq!(limiter.client_key(&request, Some("hash")), "ip:unknown");
+
+        limiter.record_verification(RouteGroup::Read, "hash".to_string(), true, now);
+        assert_eq!(limiter.client_key(&request, Some("hash")), "key:hash");
+        // The verifying request counts against the credential's budget
+        assert!(limiter.check(RouteGroup::Read, "key:hash".to_string(), now).allowed);
+        assert!(!limiter.check(RouteGroup::Read, "key:hash".to_string(), now).allowed);
+
+        // A credential that stops verifying, such as a revoked key, falls back to the address
+        limiter.record_verification(RouteGroup::Read, "hash".to_string(), false, now);
+        assert_eq!(limiter.client_key(&request, Some("hash")), "ip:unknown");
+    }
+
+    #[test]
+    fn test_route_group_for_method() {
+        assert_eq!(RouteGroup::for_method(&Method::GET), RouteGroup::Read);
+        assert_eq!(RouteGroup::for_method(&Method::HEAD), RouteGroup::Read);
+        assert_eq!(RouteGroup::for_method(&Method::POST), RouteGroup::Write);
+        assert_eq!(RouteGroup::for_method(&Method::DELETE), RouteGroup::Write);
+    }
+}
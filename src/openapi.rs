@@ -1,6 +1,17 @@
-use utoipa::OpenApi;
-use crate::utils::Order;
-use crate::handlers::StatusUpdate;
+use utoipa::{
+    openapi::{
+        path::Operation,
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Deprecated
+    },
+    Modify, OpenApi
+};
+use crate::utils::{
+    Customer, Order, Payment, PaymentStatus, Product, Promotion, PromotionKind, Shipment, ShippingAddress, WebhookDelivery,
+    WebhookEvent, WebhookSubscription, OrderChange, OrderStats, StatusStats, ItemStats, DailyOrderStats, PoolStatus
+};
+use crate::handlers::{StatusUpdate, ShipmentDelivery, RefundRequest, ImportReport, ImportRowError, HealthStatus, ReadinessStatus};
+use crate::middleware::deprecation::{CURRENT_VERSION_PREFIX, LEGACY_SUNSET};
 use crate::validators::{ValidationError, ServerError};
 
 #[derive(OpenApi)]
@@ -12,13 +23,57 @@ use crate::validators::{ValidationError, ServerError};
         crate::handlers::handlers::update_order_by_id,
         crate::handlers::handlers::update_order_status,
         crate::handlers::handlers::delete_order_by_id,
+        crate::handlers::order_event_handlers::get_order_events,
+        crate::handlers::ws_handlers::order_updates_socket,
+        crate::handlers::export_handlers::export_orders_csv,
+        crate::handlers::import_handlers::import_orders,
+        crate::handlers::stats_handlers::get_orders_stats,
+        crate::handlers::graphql_handlers::graphql,
+        crate::handlers::customer_handlers::get_customers,
+        crate::handlers::customer_handlers::add_customer,
+        crate::handlers::customer_handlers::get_customer_by_id,
+        crate::handlers::customer_handlers::update_customer_by_id,
+        crate::handlers::customer_handlers::delete_customer_by_id,
+        crate::handlers::customer_handlers::get_customer_orders,
+        crate::handlers::product_handlers::get_products,
+        crate::handlers::product_handlers::add_product,
+        crate::handlers::product_handlers::get_product_by_sku,
+        crate::handlers::product_handlers::update_product_by_sku,
+        crate::handlers::product_handlers::delete_product_by_sku,
+        crate::handlers::promotion_handlers::get_promotions,
+        crate::handlers::promotion_handlers::add_promotion,
+        crate::handlers::promotion_handlers::get_promotion_by_code,
+        crate::handlers::promotion_handlers::delete_promotion_by_code,
+        crate::handlers::shipment_handlers::get_order_shipments,
+        crate::handlers::shipment_handlers::add_shipment,
+        crate::handlers::shipment_handlers::update_shipment,
+        crate::handlers::payment_handlers::get_order_payments,
+        crate::handlers::payment_handlers::add_payment,
+        crate::handlers::payment_handlers::capture_order_payment,
+        crate::handlers::payment_handlers::void_order_payment,
+        crate::handlers::payment_handlers::refund_order_payment,
+        crate::handlers::webhook_handlers::get_webhooks,
+        crate::handlers::webhook_handlers::add_webhook,
+        crate::handlers::webhook_handlers::get_webhook,
+        crate::handlers::webhook_handlers::delete_webhook,
+        crate::handlers::webhook_handlers::get_webhook_delivery_log,
+        crate::handlers::health::healthz,
+        crate::handlers::health::readyz,
     ),
     components(
-        schemas(Order, StatusUpdate, ValidationError, ServerError)
+        schemas(Order, OrderChange, OrderStats, StatusStats, ItemStats, DailyOrderStats, ShippingAddress, Customer, Product, Promotion, PromotionKind, Shipment, Payment, PaymentStatus, WebhookSubscription, WebhookEvent, WebhookDelivery, StatusUpdate, ShipmentDelivery, RefundRequest, ImportReport, ImportRowError, ValidationError, ServerError, HealthStatus, PoolStatus, ReadinessStatus)
     ),
     tags(
-        (name = "orders", description = "Order management endpoints")
+        (name = "orders", description = "Order management endpoints"),
+        (name = "customers", description = "Customer management and order history"),
+        (name = "products", description = "Product catalog and stock on hand"),
+        (name = "promotions", description = "Discount codes applied at order creation"),
+        (name = "shipments", description = "Parcels shipped for an order and their delivery"),
+        (name = "payments", description = "Authorizing, capturing, voiding and refunding order payments"),
+        (name = "webhooks", description = "Subscriptions to order events and their delivery log"),
+        (name = "health", description = "Liveness and readiness probes")
     ),
+    modifiers(&SecurityAddon),
     info(
         title = "Rust Order Management API",
         description = "A comprehensive REST API for managing orders with SQLite persistence",
@@ -37,3 +92,72 @@ use crate::validators::{ValidationError, ServerError};
     )
 )]
 pub struct ApiDoc;
+
+/// Declares the credentials accepted by the API endpoints so Swagger UI can send them
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Paths served outside any API version
+const UNVERSIONED_PATHS: &[&str] = &["/healthz", "/readyz"];
+
+/// Paths that existed before versioning and are still served as deprecated aliases
+const LEGACY_PATHS: &[&str] = &["/orders", "/orders/{id}", "/orders/{id}/status"];
+
+/// OpenAPI document for version 1, with every API path mounted under `/v1`
+pub fn v1_openapi() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    doc.paths.paths = std::mem::take(&mut doc.paths.paths)
+        .into_iter()
+        .map(|(path, item)| {
+            if UNVERSIONED_PATHS.contains(&path.as_str()) {
+                (path, item)
+            } else {
+                (format!("{}{}", CURRENT_VERSION_PREFIX, path), item)
+            }
+        })
+        .collect();
+    doc
+}
+
+/// OpenAPI document for the unversioned aliases, with every aliased operation marked deprecated
+pub fn legacy_openapi() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    doc.info.title = format!("{} (unversioned, deprecated)", doc.info.title);
+    doc.info.description = Some(format!(
+        "Deprecated aliases of the {} API, removed after {}. Use the {} paths instead.",
+        CURRENT_VERSION_PREFIX, LEGACY_SUNSET, CURRENT_VERSION_PREFIX
+    ));
+    doc.paths.paths.retain(|path, _| {
+        UNVERSIONED_PATHS.contains(&path.as_str()) || LEGACY_PATHS.contains(&path.as_str())
+    });
+
+    for (path, item) in doc.paths.paths.iter_mut() {
+        if UNVERSIONED_PATHS.contains(&path.as_str()) {
+            continue;
+        }
+        let operations: [&mut Option<Operation>; 5] = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.patch,
+            &mut item.delete,
+        ];
+        for operation in operations.into_iter().flatten() {
+            operation.deprecated = Some(Deprecated::True);
+        }
+    }
+    doc
+}
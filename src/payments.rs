@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex
+    }
+};
+use async_trait::async_trait;
+use crate::validators::ApiError;
+
+/// Failure reported by a payment gateway
+#[derive(Debug, Clone, PartialEq)]
+pub enum GatewayError {
+    /// The provider refused the operation, e.g. insufficient funds or an unknown charge
+    Declined(String),
+    /// The provider could not be reached or answered with an error.
+    /// Only real providers fail this way; the fake gateway never does.
+    #[allow(dead_code)]
+    Unavailable(String),
+}
+
+impl From<GatewayError> for ApiError {
+    fn from(err: GatewayError) -> Self {
+        match err {
+            GatewayError::Declined(message) => ApiError::PaymentDeclined(message),
+            GatewayError::Unavailable(message) => {
+                eprintln!("Payment gateway error: {}", message);
+                ApiError::BadGateway("Payment gateway is unavailable".to_string())
+            }
+        }
+    }
+}
+
+/// Payment provider that holds and moves the money for an order.
+/// The API records the outcome of every call in the `payments` table; implementations only
+/// talk to the provider.
+#[async_trait]
+pub trait PaymentGateway: Debug + Send + Sync {
+    /// Place a hold for `amount_minor` and return the provider's reference for it
+    async fn authorize(&self, amount_minor: i64, currency: &str) -> Result<String, GatewayError>;
+    /// Collect an authorized amount
+    async fn capture(&self, reference: &str, amount_minor: i64) -> Result<(), GatewayError>;
+    /// Release an authorization that has not been captured
+    async fn void(&self, reference: &str) -> Result<(), GatewayError>;
+    /// Return part or all of a captured amount
+    async fn refund(&self, reference: &str, amount_minor: i64) -> Result<(), GatewayError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FakeCharge {
+    Authorized(i64),
+    Captured { amount_minor: i64, refunded_minor: i64 },
+    Voided,
+}
+
+/// In-process gateway that approves every operation a real provider would accept.
+/// Used when no provider is configured and in tests; no money moves.
+#[derive(Debug, Default)]
+pub struct FakePaymentGateway {
+    /// Authorizations above this amount are declined
+    decline_above_minor: Option<i64>,
+    next_reference: AtomicU64,
+    charges: Mutex<HashMap<String, FakeCharge>>,
+}
+
+impl FakePaymentGateway {
+    /// Fake gateway declining authorizations larger than `amount_minor`
+    pub fn declining_above(amount_minor: i64) -> Self {
+        FakePaymentGateway {
+            decline_above_minor: Some(amount_minor),
+            ..Default::default()
+        }
+    }
+
+    fn update<T>(
+        &self,
+        reference: &str,
+        change: impl FnOnce(&mut FakeCharge) -> Result<T, GatewayError>,
+    ) -> Result<T, GatewayError> {
+        let mut charges = self.charges.lock().unwrap();
+        let charge = charges.get_mut(reference)
+            .ok_or_else(|| GatewayError::Declined(format!("Unknown charge {}", reference)))?;
+        change(charge)
+    }
+}
+
+#[async_trait]
+impl PaymentGateway for FakePaymentGateway {
+    async fn authorize(&self, amount_minor: i64, currency: &str) -> Result<String, GatewayError> {
+        if self.decline_above_minor.is_some_and(|limit| amount_minor > limit) {
+            return Err(GatewayError::Declined(format!(
+                "Authorization of {} {} minor units declined",
+                amount_minor, currency
+            )));
+        }
+
+        let reference = format!("fake_{}", self.next_reference.fetch_add(1, Ordering::Relaxed) + 1);
+        self.charges.lock().unwrap().insert(reference.clone(), FakeCharge::Authorized(amount_minor));
+        Ok(reference)
+    }
+
+    async fn capture(&self, reference: &str, amount_minor: i64) -> Result<(), GatewayError> {
+        self.update(reference, |charge| match *charge {
+            FakeCharge::Authorized(authorized) if amount_minor <= authorized => {
+                *charge = FakeCharge::Captured { amount_minor, refunded_minor: 0 };
+                Ok(())
+            }
+            _ => Err(GatewayError::Declined(format!("Charge {} cannot be captured", reference))),
+        })
+    }
+
+    async fn void(&self, reference: &str) -> Result<(), GatewayError> {
+        self.update(reference, |charge| match *charge {
+            FakeCharge::Authorized(_) => {
+                *charge = FakeCharge::Voided;
+                Ok(())
+            }
+            _ => Err(GatewayError::Declined(format!("Charge {} cannot be voided", reference))),
+        })
+    }
+
+    async fn refund(&self, reference: &str, amount_minor: i64) -> Result<(), GatewayError> {
+        self.update(reference, |charge| match charge {
+            FakeCharge::Captured { amount_minor: captured, refunded_minor }
+                if *refunded_minor + amount_minor <= *captured =>
+            {
+                *refunded_minor += amount_minor;
+                Ok(())
+            }
+            _ => Err(GatewayError::Declined(format!("Charge {} cannot be refunded", reference))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fake_gateway_lifecycle() {
+        let gateway = FakePaymentGateway::default();
+
+        let reference = gateway.authorize(1000, "USD").await.unwrap();
+        assert!(gateway.refund(&reference, 100).await.is_err());
+        gateway.capture(&reference, 1000).await.unwrap();
+        assert!(gateway.void(&reference).await.is_err());
+
+        gateway.refund(&reference, 400).await.unwrap();
+        gateway.refund(&reference, 600).await.unwrap();
+        assert!(gateway.refund(&reference, 1).await.is_err());
+    }
+
+    #[test]
+    fn test_gateway_errors_map_to_api_errors() {
+        let error: ApiError = GatewayError::Declined("Insufficient funds".to_string()).into();
+        assert!(matches!(error, ApiError::PaymentDeclined(message) if message == "Insufficient funds"));
+
+        let error: ApiError = GatewayError::Unavailable("connection refused".to_string()).into();
+        assert!(matches!(error, ApiError::BadGateway(message) if message == "Payment gateway is unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_fake_gateway_declines() {
+        let gateway = FakePaymentGateway::declining_above(500);
+
+        assert!(matches!(gateway.authorize(501, "USD").await, Err(GatewayError::Declined(_))));
+        let reference = gateway.authorize(500, "USD").await.unwrap();
+        gateway.void(&reference).await.unwrap();
+        assert!(gateway.capture(&reference, 500).await.is_err());
+        assert!(gateway.capture("fake_unknown", 1).await.is_err());
+    }
+}
@@ -1,6 +1,8 @@
+#[allow(clippy::module_inception)]
 pub mod routes;
 pub use routes::create_router;
 
 #[cfg(test)]
 #[path = "routes.tests.rs"]
+#[allow(clippy::module_inception)]
 mod tests;
@@ -1,11 +1,13 @@
 use axum::{
+    extract::DefaultBodyLimit,
     http::StatusCode,
+    middleware,
     response::Json,
-    routing::{get, patch},
+    routing::{get, patch, post},
+    Extension,
     Router,
 };
 use serde_json::json;
-use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::handlers::{ // bring in all handler functions
@@ -15,9 +17,56 @@ use crate::handlers::{ // bring in all handler functions
     update_order_by_id,
     update_order_status,
     delete_order_by_id,
+    get_customers,
+    add_customer,
+    get_customer_by_id,
+    update_customer_by_id,
+    delete_customer_by_id,
+    get_customer_orders,
+    get_products,
+    add_product,
+    get_product_by_sku,
+    update_product_by_sku,
+    delete_product_by_sku,
+    get_promotions,
+    add_promotion,
+    get_promotion_by_code,
+    delete_promotion_by_code,
+    get_order_shipments,
+    add_shipment,
+    update_shipment,
+    get_order_payments,
+    add_payment,
+    capture_order_payment,
+    void_order_payment,
+    refund_order_payment,
+    get_webhooks,
+    add_webhook,
+    get_webhook,
+    delete_webhook,
+    get_webhook_delivery_log,
+    get_order_events,
+    order_updates_socket,
+    export_orders_csv,
+    import_orders,
+    get_orders_stats,
+    graphql,
+    graphiql,
+    healthz,
+    readyz,
 };
-use crate::utils::DbPool;
-use crate::openapi::ApiDoc;
+use crate::config::AppConfig;
+use crate::middleware::{
+    cors_layer,
+    deprecated_alias,
+    enforce_body_limit,
+    enforce_timeout,
+    require_auth,
+    RateLimitLayer,
+};
+use crate::utils::Store;
+use crate::openapi::{legacy_openapi, v1_openapi};
+use crate::graphql::build_schema;
 
 // Fallback handler for unmatched routes
 async fn path_not_found() -> (StatusCode, Json<serde_json::Value>) {
@@ -30,15 +79,78 @@ async fn path_not_found() -> (StatusCode, Json<serde_json::Value>) {
     )
 }
 
-pub fn create_router(db_pool: DbPool) -> Router {
-    Router::new()
-        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .route("/orders", get(get_orders).post(add_order))
+/// Router serving the whole API from `store`, whichever backend it is
+pub fn create_router<S: Store + Clone>(store: S, config: AppConfig) -> Router {
+    // Every API endpoint requires an API key or bearer token and is rate limited per client;
+    // the rate limiter runs first and limits unverified credentials by client IP, so floods of
+    // made-up keys are cut off before the credential lookup
+    let rate_limit = RateLimitLayer::new(config.rate_limit);
+    let order_routes = Router::new()
+        .route("/orders", get(get_orders::<S>).post(add_order::<S>))
         .route(
             "/orders/:id",
-            get(get_order_by_id).put(update_order_by_id).delete(delete_order_by_id)
+            get(get_order_by_id::<S>).put(update_order_by_id::<S>).delete(delete_order_by_id::<S>)
+        )
+        .route("/orders/:id/status", patch(update_order_status::<S>))
+        .route_layer(middleware::from_fn_with_state(store.clone(), require_auth::<S>))
+        .route_layer(rate_limit.clone());
+
+    // Resources added after versioning are only served under /v1
+    let v1_routes = Router::new()
+        .route("/orders/events", get(get_order_events::<S>))
+        .route("/orders/export.csv", get(export_orders_csv::<S>))
+        .route("/orders/import", post(import_orders::<S>))
+        .route("/orders/stats", get(get_orders_stats::<S>))
+        .route("/ws", get(order_updates_socket::<S>))
+        .route("/customers", get(get_customers::<S>).post(add_customer::<S>))
+        .route(
+            "/customers/:id",
+            get(get_customer_by_id::<S>).put(update_customer_by_id::<S>).delete(delete_customer_by_id::<S>)
+        )
+        .route("/customers/:id/orders", get(get_customer_orders::<S>))
+        .route("/products", get(get_products::<S>).post(add_product::<S>))
+        .route(
+            "/products/:sku",
+            get(get_product_by_sku::<S>).put(update_product_by_sku::<S>).delete(delete_product_by_sku::<S>)
+        )
+        .route("/promotions", get(get_promotions::<S>).post(add_promotion::<S>))
+        .route("/promotions/:code", get(get_promotion_by_code::<S>).delete(delete_promotion_by_code::<S>))
+        .route("/orders/:id/shipments", get(get_order_shipments::<S>).post(add_shipment::<S>))
+        .route("/orders/:id/shipments/:shipment_id", patch(update_shipment::<S>))
+        .route("/orders/:id/payments", get(get_order_payments::<S>).post(add_payment::<S>))
+        .route("/orders/:id/payments/:payment_id/capture", post(capture_order_payment::<S>))
+        .route("/orders/:id/payments/:payment_id/void", post(void_order_payment::<S>))
+        .route("/orders/:id/payments/:payment_id/refund", post(refund_order_payment::<S>))
+        .route("/webhooks", get(get_webhooks::<S>).post(add_webhook::<S>))
+        .route("/webhooks/:id", get(get_webhook::<S>).delete(delete_webhook::<S>))
+        .route("/webhooks/:id/deliveries", get(get_webhook_delivery_log::<S>))
+        .route("/graphql", post(graphql::<S>))
+        .route_layer(middleware::from_fn_with_state(store.clone(), require_auth::<S>))
+        .route_layer(rate_limit)
+        .merge(order_routes.clone());
+
+    // The unversioned paths predate /v1 and are kept as deprecated aliases
+    let legacy_routes = order_routes
+        .route_layer(middleware::from_fn(deprecated_alias));
+
+    Router::new()
+        .merge(
+            SwaggerUi::new("/docs")
+                .url("/api-docs/v1/openapi.json", v1_openapi())
+                .url("/api-docs/openapi.json", legacy_openapi())
         )
-        .route("/orders/:id/status", patch(update_order_status))
+        .route("/graphiql", get(graphiql))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz::<S>))
+        .nest("/v1", v1_routes)
+        .merge(legacy_routes)
         .fallback(path_not_found)
-        .with_state(db_pool)
+        // Applied to every route, outermost last: CORS answers preflights before anything else
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .layer(middleware::from_fn_with_state(config.max_body_bytes, enforce_body_limit))
+        .layer(middleware::from_fn_with_state(config.request_timeout, enforce_timeout))
+        .layer(cors_layer(&config.cors))
+        .layer(Extension(config.payments))
+        .layer(Extension(build_schema()))
+        .with_state(store)
 }
@@ -1,16 +1,47 @@
 #[cfg(test)]
 mod tests {
-    use crate::utils::{init_db, Order};
+    use crate::utils::{init_db, create_api_key, InMemoryStore, Order, Role, SqliteStore, Store};
+    use crate::config::{AppConfig, CorsConfig, PaymentConfig, RateLimit, RateLimitConfig};
     use crate::routes::create_router;
     use axum_test::TestServer;
     use axum::http::StatusCode;
     use serde_json::{json, Value};
-    use tokio;
 
-    async fn setup_test_server() -> TestServer {
-        let db_pool = init_db().await.expect("Failed to initialize test database");
-        let app = create_router(db_pool);
-        TestServer::new(app).unwrap()
+    /// Store a test server keeps its data in
+    #[derive(Debug, Clone, Copy)]
+    enum Backend {
+        Sqlite,
+        Memory,
+        #[cfg(feature = "postgres")]
+        Postgres,
+    }
+
+    async fn setup_test_server_for(backend: Backend) -> TestServer {
+        setup_test_server_with(backend, AppConfig::default()).await
+    }
+
+    async fn setup_test_server_with(backend: Backend, config: AppConfig) -> TestServer {
+        match backend {
+            Backend::Sqlite => {
+                let db_pool = init_db().await.expect("Failed to initialize test database");
+                serve_store(SqliteStore::new(db_pool), config).await
+            }
+            Backend::Memory => serve_store(InMemoryStore::default(), config).await,
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => serve_store(postgres_store().await, config).await,
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn postgres_store() -> crate::utils::PostgresStore {
+        crate::utils::PostgresStore::connect_lazy(crate::utils::test_connect_options().await)
+    }
+
+    async fn serve_store<S: Store + Clone>(store: S, config: AppConfig) -> TestServer {
+        let (_, secret) = store.create_api_key("test-client", Role::Admin).await.unwrap();
+        let mut server = TestServer::new(create_router(store, config)).unwrap();
+        server.add_header("x-api-key", secret);
+        server
     }
 
     async fn add_test_order(server: &TestServer, id: u32, item: &str, status: &str, quantity: u32) -> Order {
@@ -26,9 +57,8 @@ mod tests {
         response.json()
     }
 
-    #[tokio::test]
-    async fn test_get_orders_empty() {
-        let server = setup_test_server().await;
+    async fn test_get_orders_empty(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let response = server.get("/orders").await;
         response.assert_status_ok();
@@ -37,9 +67,8 @@ mod tests {
         assert_eq!(orders.len(), 0);
     }
 
-    #[tokio::test]
-    async fn test_get_orders_with_data() {
-        let server = setup_test_server().await;
+    async fn test_get_orders_with_data(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add some test orders
         add_test_order(&server, 1, "First Item", "pending", 5).await;
@@ -59,9 +88,8 @@ mod tests {
         assert!(items.contains(&"Third Item"));
     }
 
-    #[tokio::test]
-    async fn test_add_order_valid() {
-        let server = setup_test_server().await;
+    async fn test_add_order_valid(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let new_order = json!({
             "id": 1,
@@ -80,9 +108,8 @@ mod tests {
         assert_eq!(order.quantity, 5);
     }
 
-    #[tokio::test]
-    async fn test_add_order_all_valid_statuses() {
-        let server = setup_test_server().await;
+    async fn test_add_order_all_valid_statuses(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         let valid_statuses = ["pending", "processing", "shipped", "delivered", "cancelled"];
         
         for (i, status) in valid_statuses.iter().enumerate() {
@@ -101,9 +128,8 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_add_order_invalid_empty_item() {
-        let server = setup_test_server().await;
+    async fn test_add_order_invalid_empty_item(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let invalid_order = json!({
             "id": 1,
@@ -119,9 +145,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Item name cannot be empty"));
     }
 
-    #[tokio::test]
-    async fn test_add_order_invalid_status() {
-        let server = setup_test_server().await;
+    async fn test_add_order_invalid_status(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let invalid_order = json!({
             "id": 1,
@@ -137,9 +162,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Status must be one of:"));
     }
 
-    #[tokio::test]
-    async fn test_add_order_zero_quantity() {
-        let server = setup_test_server().await;
+    async fn test_add_order_zero_quantity(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let invalid_order = json!({
             "id": 1,
@@ -155,9 +179,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Quantity must be greater than 0"));
     }
 
-    #[tokio::test]
-    async fn test_add_order_excessive_quantity() {
-        let server = setup_test_server().await;
+    async fn test_add_order_excessive_quantity(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let invalid_order = json!({
             "id": 1,
@@ -173,9 +196,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Quantity cannot exceed 1000"));
     }
 
-    #[tokio::test]
-    async fn test_add_order_duplicate_id() {
-        let server = setup_test_server().await;
+    async fn test_add_order_duplicate_id(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let order1 = json!({
             "id": 1,
@@ -200,9 +222,8 @@ mod tests {
         response2.assert_status(StatusCode::BAD_REQUEST);
     }
 
-    #[tokio::test]
-    async fn test_get_order_by_id_success() {
-        let server = setup_test_server().await;
+    async fn test_get_order_by_id_success(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         add_test_order(&server, 1, "Test Item", "pending", 5).await;
@@ -218,9 +239,8 @@ mod tests {
         assert_eq!(order.quantity, 5);
     }
 
-    #[tokio::test]
-    async fn test_get_order_by_id_not_found() {
-        let server = setup_test_server().await;
+    async fn test_get_order_by_id_not_found(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let response = server.get("/orders/999").await;
         response.assert_status(StatusCode::NOT_FOUND);
@@ -229,9 +249,8 @@ mod tests {
         assert_eq!(error_body["error"], "Order not found");
     }
 
-    #[tokio::test]
-    async fn test_update_order_success() {
-        let server = setup_test_server().await;
+    async fn test_update_order_success(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         add_test_order(&server, 1, "Original Item", "pending", 5).await;
@@ -254,9 +273,8 @@ mod tests {
         assert_eq!(order.quantity, 10);
     }
 
-    #[tokio::test]
-    async fn test_update_order_not_found() {
-        let server = setup_test_server().await;
+    async fn test_update_order_not_found(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let updated_order = json!({
             "id": 999,
@@ -272,9 +290,8 @@ mod tests {
         assert_eq!(error_body["error"], "Order not found");
     }
 
-    #[tokio::test]
-    async fn test_update_order_validation_error() {
-        let server = setup_test_server().await;
+    async fn test_update_order_validation_error(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         add_test_order(&server, 1, "Original Item", "pending", 5).await;
@@ -294,9 +311,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Item name cannot be empty"));
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_success() {
-        let server = setup_test_server().await;
+    async fn test_update_order_status_success(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         add_test_order(&server, 1, "Test Item", "pending", 5).await;
@@ -316,9 +332,8 @@ mod tests {
         assert_eq!(order.quantity, 5);
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_not_found() {
-        let server = setup_test_server().await;
+    async fn test_update_order_status_not_found(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let status_update = json!({
             "status": "shipped"
@@ -331,9 +346,8 @@ mod tests {
         assert_eq!(error_body["error"], "Order not found");
     }
 
-    #[tokio::test]
-    async fn test_update_order_status_validation_error() {
-        let server = setup_test_server().await;
+    async fn test_update_order_status_validation_error(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         add_test_order(&server, 1, "Test Item", "pending", 5).await;
@@ -350,9 +364,8 @@ mod tests {
         assert!(error_body["error"].as_str().unwrap().contains("Status must be one of:"));
     }
 
-    #[tokio::test]
-    async fn test_delete_order_success() {
-        let server = setup_test_server().await;
+    async fn test_delete_order_success(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Add an order first
         let created_order = add_test_order(&server, 1, "Test Item", "pending", 5).await;
@@ -370,9 +383,8 @@ mod tests {
         get_response.assert_status(StatusCode::NOT_FOUND);
     }
 
-    #[tokio::test]
-    async fn test_delete_order_not_found() {
-        let server = setup_test_server().await;
+    async fn test_delete_order_not_found(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let response = server.delete("/orders/999").await;
         response.assert_status(StatusCode::NOT_FOUND);
@@ -381,9 +393,8 @@ mod tests {
         assert_eq!(error_body["error"], "Order not found");
     }
 
-    #[tokio::test]
-    async fn test_catch_all_route() {
-        let server = setup_test_server().await;
+    async fn test_catch_all_route(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let response = server.get("/nonexistent").await;
         response.assert_status(StatusCode::NOT_FOUND);
@@ -392,9 +403,8 @@ mod tests {
         assert_eq!(error_body["error"], "Path not found");
     }
 
-    #[tokio::test]
-    async fn test_catch_all_route_different_paths() {
-        let server = setup_test_server().await;
+    async fn test_catch_all_route_different_paths(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let invalid_paths = [
             "/invalid",
@@ -412,9 +422,8 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_invalid_id_format() {
-        let server = setup_test_server().await;
+    async fn test_invalid_id_format(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // Test invalid ID formats that return 400 instead of 404
         let invalid_id_paths = [
@@ -430,9 +439,8 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_malformed_json() {
-        let server = setup_test_server().await;
+    async fn test_malformed_json(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let response = server
             .post("/orders")
@@ -442,9 +450,8 @@ mod tests {
         response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
     }
 
-    #[tokio::test]
-    async fn test_missing_fields() {
-        let server = setup_test_server().await;
+    async fn test_missing_fields(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let incomplete_order = json!({
             "id": 1,
@@ -456,9 +463,8 @@ mod tests {
         response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
     }
 
-    #[tokio::test]
-    async fn test_wrong_data_types() {
-        let server = setup_test_server().await;
+    async fn test_wrong_data_types(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let wrong_types_order = json!({
             "id": "not_a_number", // Should be u32
@@ -471,9 +477,8 @@ mod tests {
         response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
     }
 
-    #[tokio::test]
-    async fn test_content_type_headers() {
-        let server = setup_test_server().await;
+    async fn test_content_type_headers(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         let new_order = json!({
             "id": 1,
@@ -490,9 +495,8 @@ mod tests {
         assert!(content_type.to_str().unwrap().contains("application/json"));
     }
 
-    #[tokio::test]
-    async fn test_integration_workflow() {
-        let server = setup_test_server().await;
+    async fn test_integration_workflow(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
         
         // 1. Start with empty orders list
         let response = server.get("/orders").await;
@@ -545,4 +549,1323 @@ mod tests {
         let response = server.get("/orders/3").await;
         response.assert_status(StatusCode::NOT_FOUND);
     }
+
+    async fn test_healthz(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/healthz").await;
+        response.assert_status_ok();
+        
+        let body: Value = response.json();
+        assert_eq!(body["status"], "ok");
+    }
+
+    async fn test_readyz(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/readyz").await;
+        response.assert_status_ok();
+        
+        let body: Value = response.json();
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["database"], "ok");
+        assert_eq!(body["pool"]["closed"], false);
+        // Process memory has neither a schema nor connections
+        match backend {
+            Backend::Memory => assert!(body["migration_version"].is_null()),
+            _ => {
+                assert!(body["migration_version"].as_i64().unwrap() >= 1);
+                assert!(body["pool"]["size"].as_u64().unwrap() >= 1);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readyz_database_unavailable() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool.clone()), AppConfig::default())).unwrap();
+        db_pool.close().await;
+        
+        let response = server.get("/readyz").await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        
+        let body: Value = response.json();
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["database"], "unreachable");
+        assert!(body["migration_version"].is_null());
+        assert_eq!(body["pool"]["closed"], true);
+    }
+
+    async fn test_openapi_documents_health_endpoints(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/api-docs/openapi.json").await;
+        response.assert_status_ok();
+        
+        let doc: Value = response.json();
+        assert!(doc["paths"]["/healthz"]["get"].is_object());
+        assert!(doc["paths"]["/readyz"]["get"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_orders_require_credentials() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        let response = server.get("/orders").await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        assert_eq!(response.header("www-authenticate"), "Bearer");
+        
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Unauthorized");
+        assert_eq!(body["message"], "Missing API key or bearer token");
+        
+        let response = server.delete("/orders/1").await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_orders_reject_invalid_credentials() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        let response = server.get("/orders").add_header("x-api-key", "not-a-key").await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Invalid API key or bearer token");
+        
+        let response = server.get("/orders").authorization("Basic dXNlcjpwYXNz").await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Unsupported authorization scheme");
+    }
+
+    #[tokio::test]
+    async fn test_orders_accept_bearer_token() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "bearer-client", Role::Admin).await.unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        let response = server.get("/orders").authorization_bearer(&secret).await;
+        response.assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_is_rejected() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (key, secret) = create_api_key(&db_pool, "revoked-client", Role::Admin).await.unwrap();
+        sqlx::query("UPDATE api_keys SET revoked = 1 WHERE id = ?")
+            .bind(key.id)
+            .execute(&db_pool)
+            .await
+            .unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        let response = server.get("/orders").add_header("x-api-key", secret).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_health_and_docs_do_not_require_credentials() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        server.get("/healthz").await.assert_status_ok();
+        server.get("/readyz").await.assert_status_ok();
+        server.get("/api-docs/openapi.json").await.assert_status_ok();
+        server.get("/nonexistent").await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_openapi_declares_security_schemes(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let doc: Value = server.get("/api-docs/openapi.json").await.json();
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(schemes["api_key"]["type"], "apiKey");
+        assert_eq!(schemes["api_key"]["name"], "X-API-Key");
+        assert_eq!(schemes["bearer_auth"]["scheme"], "bearer");
+        assert!(doc["paths"]["/orders"]["get"]["security"].is_array());
+        assert!(doc["paths"]["/healthz"]["get"]["security"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_role_permissions_enforced() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, admin_key) = create_api_key(&db_pool, "admin", Role::Admin).await.unwrap();
+        let (_, reader_key) = create_api_key(&db_pool, "reporting", Role::ReadOnly).await.unwrap();
+        let (_, warehouse_key) = create_api_key(&db_pool, "scanner", Role::Warehouse).await.unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2});
+        server.post("/orders").add_header("x-api-key", &admin_key).json(&order).await.assert_status_ok();
+        
+        // Read-only integrations can only GET
+        server.get("/orders").add_header("x-api-key", &reader_key).await.assert_status_ok();
+        server.get("/orders/1").add_header("x-api-key", &reader_key).await.assert_status_ok();
+        let response = server.post("/orders").add_header("x-api-key", &reader_key).json(&order).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Forbidden");
+        assert!(body["message"].as_str().unwrap().contains("orders:create"));
+        server.patch("/orders/1/status").add_header("x-api-key", &reader_key)
+            .json(&json!({"status": "shipped"})).await.assert_status(StatusCode::FORBIDDEN);
+        
+        // Warehouse clients can change status but not replace or delete
+        server.patch("/orders/1/status").add_header("x-api-key", &warehouse_key)
+            .json(&json!({"status": "shipped"})).await.assert_status_ok();
+        server.put("/orders/1").add_header("x-api-key", &warehouse_key)
+            .json(&order).await.assert_status(StatusCode::FORBIDDEN);
+        server.delete("/orders/1").add_header("x-api-key", &warehouse_key)
+            .await.assert_status(StatusCode::FORBIDDEN);
+        
+        // Only admins can replace and delete
+        server.put("/orders/1").add_header("x-api-key", &admin_key).json(&order).await.assert_status_ok();
+        server.delete("/orders/1").add_header("x-api-key", &admin_key).await.assert_status_ok();
+    }
+
+    async fn test_openapi_documents_required_scopes(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let doc: Value = server.get("/api-docs/openapi.json").await.json();
+        let scopes = |path: &str, method: &str| doc["paths"][path][method]["security"][0]["api_key"][0].clone();
+        assert_eq!(scopes("/orders", "get"), "orders:read");
+        assert_eq!(scopes("/orders", "post"), "orders:create");
+        assert_eq!(scopes("/orders/{id}", "put"), "orders:update");
+        assert_eq!(scopes("/orders/{id}/status", "patch"), "orders:status");
+        assert_eq!(scopes("/orders/{id}", "delete"), "orders:delete");
+    }
+
+    fn strict_rate_limit_config() -> AppConfig {
+        AppConfig {
+            rate_limit: RateLimitConfig {
+                read: RateLimit { burst: 3, per_second: 0.5 },
+                write: RateLimit { burst: 1, per_second: 0.5 },
+            },
+            ..AppConfig::default()
+        }
+    }
+
+    async fn test_rate_limit_headers_on_success(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/orders").await;
+        response.assert_status_ok();
+        assert_eq!(response.header("ratelimit-limit"), "120");
+        assert_eq!(response.header("ratelimit-remaining"), "119");
+        assert!(response.maybe_header("ratelimit-reset").is_some());
+        assert!(response.maybe_header("retry-after").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_rejects_with_429() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "noisy-script", Role::Admin).await.unwrap();
+        let mut server = TestServer::new(create_router(SqliteStore::new(db_pool), strict_rate_limit_config())).unwrap();
+        server.add_header("x-api-key", secret);
+        
+        let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2});
+        server.post("/orders").json(&order).await.assert_status_ok();
+        
+        let response = server.post("/orders").json(&order).await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.header("retry-after"), "2");
+        assert_eq!(response.header("ratelimit-limit"), "1");
+        assert_eq!(response.header("ratelimit-remaining"), "0");
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Too many requests");
+        
+        // Reads have their own budget
+        server.get("/orders").await.assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_per_client() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, first) = create_api_key(&db_pool, "first", Role::Admin).await.unwrap();
+        let (_, second) = create_api_key(&db_pool, "second", Role::Admin).await.unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), strict_rate_limit_config())).unwrap();
+        
+        for _ in 0..3 {
+            server.get("/orders").add_header("x-api-key", &first).await.assert_status_ok();
+        }
+        server.get("/orders").add_header("x-api-key", &first).await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+        server.get("/orders").add_header("x-api-key", &second).await.assert_status_ok();
+        
+        // Health probes are never rate limited
+        for _ in 0..5 {
+            server.get("/healthz").await.assert_status_ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotating_unknown_keys_are_rate_limited() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "known", Role::Admin).await.unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), strict_rate_limit_config())).unwrap();
+        server.get("/orders").add_header("x-api-key", &secret).await.assert_status_ok();
+        
+        // Keys that never verified share the client's address budget, whatever their value
+        for attempt in 0..2 {
+            server.get("/orders").add_header("x-api-key", format!("made-up-{}", attempt)).await
+                .assert_status(StatusCode::UNAUTHORIZED);
+        }
+        let response = server.get("/orders").add_header("x-api-key", "made-up-2").await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        server.get("/orders").add_header("authorization", "Bearer made-up-3").await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+        
+        // A verified key keeps its own budget
+        server.get("/orders").add_header("x-api-key", &secret).await.assert_status_ok();
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_for_allowed_origin() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let config = AppConfig {
+            cors: CorsConfig {
+                allowed_origins: vec!["https://dashboard.example.com".to_string()],
+                ..CorsConfig::default()
+            },
+            ..AppConfig::default()
+        };
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), config)).unwrap();
+        
+        let response = server.method(axum::http::Method::OPTIONS, "/orders")
+            .add_header("origin", "https://dashboard.example.com")
+            .add_header("access-control-request-method", "DELETE")
+            .add_header("access-control-request-headers", "x-api-key")
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.header("access-control-allow-origin"), "https://dashboard.example.com");
+        assert!(response.header("access-control-allow-methods").to_str().unwrap().contains("DELETE"));
+        assert!(response.header("access-control-allow-headers").to_str().unwrap().contains("x-api-key"));
+        
+        // Other origins get no CORS grant
+        let response = server.get("/healthz").add_header("origin", "https://evil.example.com").await;
+        assert!(response.maybe_header("access-control-allow-origin").is_none());
+    }
+
+    async fn test_cors_disabled_by_default(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/orders").add_header("origin", "https://dashboard.example.com").await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("access-control-allow-origin").is_none());
+    }
+
+    async fn test_oversized_body_returns_413(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let oversized_order = json!({
+            "id": 1,
+            "item": "a".repeat(crate::config::DEFAULT_MAX_BODY_BYTES),
+            "status": "pending",
+            "quantity": 1
+        });
+        let response = server.post("/orders").json(&oversized_order).await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Payload too large");
+    }
+
+    async fn test_v1_routes(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2});
+        let response = server.post("/v1/orders").json(&order).await;
+        response.assert_status_ok();
+        assert!(response.maybe_header("deprecation").is_none());
+        
+        server.get("/v1/orders/1").await.assert_status_ok();
+        server.patch("/v1/orders/1/status").json(&json!({"status": "shipped"})).await.assert_status_ok();
+        server.put("/v1/orders/1").json(&order).await.assert_status_ok();
+        
+        // Both mounts share the same data
+        let orders: Vec<Order> = server.get("/orders").await.json();
+        assert_eq!(orders.len(), 1);
+        
+        server.delete("/v1/orders/1").await.assert_status_ok();
+        server.get("/v1/orders/1").await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_unversioned_routes_are_deprecated_aliases(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.get("/orders").await;
+        response.assert_status_ok();
+        assert_eq!(response.header("deprecation"), "@1792281600");
+        assert_eq!(response.header("sunset"), "Thu, 01 Jul 2027 00:00:00 GMT");
+        assert_eq!(response.header("link"), "</v1/orders>; rel=\"successor-version\"");
+        
+        let response = server.get("/orders/42").await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(response.header("link"), "</v1/orders/42>; rel=\"successor-version\"");
+        
+        // Health probes are not versioned
+        let response = server.get("/healthz").await;
+        assert!(response.maybe_header("deprecation").is_none());
+        server.get("/v1/healthz").await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_openapi_document_per_version(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let v1: Value = server.get("/api-docs/v1/openapi.json").await.json();
+        assert!(v1["paths"]["/v1/orders"]["get"].is_object());
+        assert!(v1["paths"]["/v1/orders/{id}/status"]["patch"].is_object());
+        assert!(v1["paths"]["/orders"].is_null());
+        assert!(v1["paths"]["/healthz"]["get"].is_object());
+        assert!(v1["paths"]["/v1/orders"]["get"]["deprecated"].is_null());
+        assert!(v1["paths"]["/v1/customers/{id}/orders"]["get"].is_object());
+        
+        let legacy: Value = server.get("/api-docs/openapi.json").await.json();
+        assert_eq!(legacy["paths"]["/orders"]["get"]["deprecated"], true);
+        assert_eq!(legacy["paths"]["/orders/{id}"]["delete"]["deprecated"], true);
+        assert!(legacy["paths"]["/healthz"]["get"]["deprecated"].is_null());
+        assert!(legacy["paths"]["/v1/orders"].is_null());
+        assert!(legacy["paths"]["/customers"].is_null());
+    }
+
+    async fn test_customer_crud(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let customer = json!({"id": 1, "name": "Ada Lovelace", "email": "ada@example.com"});
+        server.post("/v1/customers").json(&customer).await.assert_status_ok();
+        
+        let customers: Vec<Value> = server.get("/v1/customers").await.json();
+        assert_eq!(customers.len(), 1);
+        
+        let updated = json!({"id": 1, "name": "Ada Lovelace", "email": "ada@example.org"});
+        server.put("/v1/customers/1").json(&updated).await.assert_status_ok();
+        let fetched: Value = server.get("/v1/customers/1").await.json();
+        assert_eq!(fetched["email"], "ada@example.org");
+        
+        server.delete("/v1/customers/1").await.assert_status_ok();
+        server.get("/v1/customers/1").await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_customer_validation(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.post("/v1/customers")
+            .json(&json!({"id": 1, "name": "Ada Lovelace", "email": "not-an-email"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "email");
+    }
+
+    async fn test_customer_orders(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/customers")
+            .json(&json!({"id": 1, "name": "Ada Lovelace", "email": "ada@example.com"}))
+            .await
+            .assert_status_ok();
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2, "customer_id": 1}))
+            .await
+            .assert_status_ok();
+        add_test_order(&server, 2, "Gadget", "pending", 1).await;
+        
+        let orders: Vec<Order> = server.get("/v1/customers/1/orders").await.json();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].id, 1);
+        assert_eq!(orders[0].customer_id, Some(1));
+        
+        // The same listing is available as a filter on /orders
+        let orders: Vec<Order> = server.get("/v1/orders").add_query_param("customer_id", 1).await.json();
+        assert_eq!(orders.len(), 1);
+        
+        server.get("/v1/customers/99/orders").await.assert_status(StatusCode::NOT_FOUND);
+        
+        // Customers with orders cannot be removed
+        let response = server.delete("/v1/customers/1").await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Conflict");
+    }
+
+    async fn test_order_with_unknown_customer_is_rejected(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2, "customer_id": 5}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Customer with ID 5 does not exist");
+        assert_eq!(body["field"], "customer_id");
+    }
+
+    async fn test_customers_require_scopes(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        let db_pool = init_db().await.unwrap();
+        let (_, secret) = create_api_key(&db_pool, "reader", Role::ReadOnly).await.unwrap();
+        let server_read_only = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        server_read_only.get("/v1/customers").add_header("x-api-key", &secret).await.assert_status_ok();
+        server_read_only.post("/v1/customers")
+            .add_header("x-api-key", &secret)
+            .json(&json!({"id": 1, "name": "Ada Lovelace", "email": "ada@example.com"}))
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        
+        // Customers are not served on the deprecated unversioned paths
+        server.get("/customers").await.assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_orders_reserve_product_stock(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/products")
+            .json(&json!({"sku": "WID-1", "name": "Widget", "stock": 5}))
+            .await
+            .assert_status_ok();
+        
+        let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 3, "sku": "WID-1"});
+        server.post("/v1/orders").json(&order).await.assert_status_ok();
+        let product: Value = server.get("/v1/products/WID-1").await.json();
+        assert_eq!(product["stock"], 2);
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 2, "item": "Widget", "status": "pending", "quantity": 3, "sku": "WID-1"}))
+            .await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Insufficient stock for SKU WID-1: requested 3, available 2");
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 3, "item": "Gadget", "status": "pending", "quantity": 1, "sku": "GAD-1"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "sku");
+        
+        server.patch("/v1/orders/1/status").json(&json!({"status": "cancelled"})).await.assert_status_ok();
+        let product: Value = server.get("/v1/products/WID-1").await.json();
+        assert_eq!(product["stock"], 5);
+        
+        // Referenced products cannot be removed
+        server.delete("/v1/products/WID-1").await.assert_status(StatusCode::CONFLICT);
+    }
+
+    async fn test_product_restock(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/products")
+            .json(&json!({"sku": "WID-1", "name": "Widget", "stock": 0}))
+            .await
+            .assert_status_ok();
+        
+        let restocked: Value = server.put("/v1/products/WID-1")
+            .json(&json!({"sku": "WID-1", "name": "Widget", "stock": 40}))
+            .await
+            .json();
+        assert_eq!(restocked["stock"], 40);
+        
+        let products: Vec<Value> = server.get("/v1/products").await.json();
+        assert_eq!(products.len(), 1);
+        
+        server.put("/v1/products/NOPE")
+            .json(&json!({"sku": "NOPE", "name": "Nope", "stock": 1}))
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    async fn test_order_pricing_and_totals(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        // Client supplied totals are ignored; the server computes them
+        let order = json!({
+            "id": 1, "item": "Widget", "status": "pending", "quantity": 3,
+            "unit_price_minor": 1250, "currency": "GBP", "total_minor": 1
+        });
+        let created: Value = server.post("/v1/orders").json(&order).await.json();
+        assert_eq!(created["subtotal_minor"], 3750);
+        assert_eq!(created["total_minor"], 3750);
+        
+        let fetched: Value = server.get("/v1/orders/1").await.json();
+        assert_eq!(fetched["currency"], "GBP");
+        assert_eq!(fetched["total_minor"], 3750);
+        
+        // Orders without pricing default to zero in the default currency
+        let unpriced = add_test_order(&server, 2, "Gadget", "pending", 1).await;
+        assert_eq!(unpriced.unit_price_minor, 0);
+        assert_eq!(unpriced.currency, "USD");
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 3, "item": "Widget", "status": "pending", "quantity": 1, "unit_price_minor": 100, "currency": "BTC"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "currency");
+        
+        // Fractional prices are rejected outright rather than rounded
+        server.post("/v1/orders")
+            .json(&json!({"id": 4, "item": "Widget", "status": "pending", "quantity": 1, "unit_price_minor": 12.5}))
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    async fn test_promo_code_applied_at_order_creation(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/promotions")
+            .json(&json!({
+                "code": "3FOR2", "kind": "buy_x_get_y", "buy_quantity": 2, "free_quantity": 1,
+                "max_redemptions": 1
+            }))
+            .await
+            .assert_status_ok();
+        server.post("/v1/promotions")
+            .json(&json!({
+                "code": "SUMMER", "kind": "percent_off", "percent_off": 20,
+                "starts_at": "2020-06-01T00:00:00Z", "ends_at": "2020-09-01T00:00:00Z"
+            }))
+            .await
+            .assert_status_ok();
+        
+        let order = json!({
+            "id": 1, "item": "Widget", "status": "pending", "quantity": 3,
+            "unit_price_minor": 500, "promo_code": "3FOR2"
+        });
+        let created: Value = server.post("/v1/orders").json(&order).await.json();
+        assert_eq!(created["subtotal_minor"], 1500);
+        assert_eq!(created["discount_minor"], 500);
+        assert_eq!(created["total_minor"], 1000);
+        
+        let promotion: Value = server.get("/v1/promotions/3FOR2").await.json();
+        assert_eq!(promotion["redemptions"], 1);
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 2, "item": "Widget", "status": "pending", "quantity": 3, "promo_code": "3FOR2"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Promo code 3FOR2 has reached its redemption limit");
+        assert_eq!(body["field"], "promo_code");
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({"id": 3, "item": "Widget", "status": "pending", "quantity": 1, "promo_code": "SUMMER"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Promo code SUMMER expired on 2020-09-01T00:00:00+00:00");
+        
+        // Redeemed promotions are kept for the orders that reference them
+        server.delete("/v1/promotions/3FOR2").await.assert_status(StatusCode::CONFLICT);
+        server.delete("/v1/promotions/SUMMER").await.assert_status_ok();
+    }
+
+    async fn test_shipments_advance_order_to_delivered(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let order = json!({
+            "id": 1, "item": "Widget", "status": "processing", "quantity": 1,
+            "shipping_address": {
+                "recipient": "Grace Hopper", "line1": "1 Navy Way", "city": "Arlington",
+                "region": "VA", "postal_code": "22202", "country": "US"
+            }
+        });
+        server.post("/v1/orders").json(&order).await.assert_status_ok();
+        
+        let response = server.post("/v1/orders/1/shipments")
+            .json(&json!({"carrier": "UPS", "tracking_number": "1Z999AA10123456784"}))
+            .await;
+        response.assert_status_ok();
+        let shipment: Value = response.json();
+        assert_eq!(shipment["order_id"], 1);
+        assert!(shipment["delivered_at"].is_null());
+        
+        let stored: Order = server.get("/v1/orders/1").await.json();
+        assert_eq!(stored.status, "shipped");
+        assert_eq!(stored.shipping_address.unwrap().postal_code, "22202");
+        
+        let shipment_id = shipment["id"].as_u64().unwrap();
+        server.patch(&format!("/v1/orders/1/shipments/{}", shipment_id))
+            .json(&json!({"delivered_at": "2099-01-01T12:00:00Z"}))
+            .await
+            .assert_status_ok();
+        
+        let stored: Order = server.get("/v1/orders/1").await.json();
+        assert_eq!(stored.status, "delivered");
+        let shipments: Vec<Value> = server.get("/v1/orders/1/shipments").await.json();
+        assert_eq!(shipments.len(), 1);
+        assert_eq!(shipments[0]["delivered_at"], "2099-01-01T12:00:00Z");
+        
+        // Shipments are only served under /v1
+        server.get("/orders/1/shipments").await.assert_status_not_found();
+    }
+
+    async fn add_shipped_order(server: &TestServer, id: u32) {
+        let order = json!({
+            "id": id, "item": "Widget", "status": "processing", "quantity": 1,
+            "shipping_address": {
+                "recipient": "Grace Hopper", "line1": "1 Navy Way", "city": "Arlington",
+                "region": "VA", "postal_code": "22202", "country": "US"
+            }
+        });
+        server.post("/v1/orders").json(&order).await.assert_status_ok();
+        server.post(&format!("/v1/orders/{}/shipments", id))
+            .json(&json!({"carrier": "UPS", "tracking_number": "1Z999AA10123456784"}))
+            .await
+            .assert_status_ok();
+    }
+
+    async fn test_order_with_shipments_cannot_be_deleted(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        add_shipped_order(&server, 1).await;
+        
+        let response = server.delete("/v1/orders/1").await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Order 1 still has 1 shipment(s)");
+        
+        server.get("/v1/orders/1").await.assert_status_ok();
+        let gone = graphql(&server, "mutation { deleteOrder(id: 1) { id } }", json!({})).await;
+        assert_eq!(gone["errors"][0]["extensions"]["status"], 409);
+    }
+
+    async fn test_shipping_address_and_shipment_validation(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        let response = server.post("/v1/orders")
+            .json(&json!({
+                "id": 1, "item": "Widget", "status": "pending", "quantity": 1,
+                "shipping_address": {
+                    "recipient": "Ada Lovelace", "line1": "12 St James's Square", "city": "London",
+                    "postal_code": "94103", "country": "GB"
+                }
+            }))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "shipping_address.postal_code");
+        
+        add_test_order(&server, 2, "Widget", "pending", 1).await;
+        let response = server.post("/v1/orders/2/shipments")
+            .json(&json!({"carrier": "UPS", "tracking_number": "1Z999AA10123456784"}))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "shipping_address");
+        
+        server.post("/v1/orders/99/shipments")
+            .json(&json!({"carrier": "UPS", "tracking_number": "1Z999AA10123456784"}))
+            .await
+            .assert_status_not_found();
+    }
+
+    async fn test_payment_lifecycle_endpoints(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2, "unit_price_minor": 750}))
+            .await
+            .assert_status_ok();
+        
+        let response = server.post("/v1/orders/1/payments").await;
+        response.assert_status_ok();
+        let payment: Value = response.json();
+        assert_eq!(payment["status"], "authorized");
+        assert_eq!(payment["amount_minor"], 1500);
+        assert_eq!(payment["currency"], "USD");
+        server.post("/v1/orders/1/payments").await.assert_status(StatusCode::CONFLICT);
+        
+        let id = payment["id"].as_u64().unwrap();
+        let captured: Value = server.post(&format!("/v1/orders/1/payments/{}/capture", id)).await.json();
+        assert_eq!(captured["status"], "captured");
+        
+        let response = server.post(&format!("/v1/orders/1/payments/{}/void", id)).await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], format!("Payment {} is captured and cannot be voided", id));
+        
+        let refunded: Value = server.post(&format!("/v1/orders/1/payments/{}/refund", id))
+            .json(&json!({"amount_minor": 500}))
+            .await
+            .json();
+        assert_eq!(refunded["status"], "partially_refunded");
+        assert_eq!(refunded["refunded_minor"], 500);
+        
+        let payments: Vec<Value> = server.get("/v1/orders/1/payments").await.json();
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0]["refunded_minor"], 500);
+        
+        server.post("/v1/orders/1/payments/99/capture").await.assert_status_not_found();
+        server.get("/orders/1/payments").await.assert_status_not_found();
+    }
+
+    async fn test_order_with_payments_cannot_be_deleted(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 1, "unit_price_minor": 750}))
+            .await
+            .assert_status_ok();
+        let payment: Value = server.post("/v1/orders/1/payments").await.json();
+        
+        let response = server.delete("/v1/orders/1").await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Order 1 has an active payment; void or refund it first");
+        
+        // A voided payment is still kept on record with its order
+        server.post(&format!("/v1/orders/1/payments/{}/void", payment["id"]))
+            .await
+            .assert_status_ok();
+        let response = server.delete("/v1/orders/1").await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Order 1 still has 1 payment(s)");
+        
+        server.get("/v1/orders/1").await.assert_status_ok();
+    }
+
+    async fn test_shipping_requires_captured_payment_when_configured(backend: Backend) {
+        let config = AppConfig {
+            payments: PaymentConfig { require_capture_to_ship: true, ..PaymentConfig::default() },
+            ..AppConfig::default()
+        };
+        let server = setup_test_server_with(backend, config).await;
+        
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 1, "unit_price_minor": 999}))
+            .await
+            .assert_status_ok();
+        
+        let response = server.patch("/v1/orders/1/status").json(&json!({"status": "shipped"})).await;
+        response.assert_status(StatusCode::CONFLICT);
+        let body: Value = response.json();
+        assert_eq!(body["message"], "Order 1 cannot be shipped before its payment is captured");
+        let shipped = json!({"id": 1, "item": "Widget", "status": "delivered", "quantity": 1, "unit_price_minor": 999});
+        server.put("/v1/orders/1").json(&shipped).await.assert_status(StatusCode::CONFLICT);
+        
+        // Orders cannot be created already shipped, since they have no payment yet
+        let shipped = json!({"id": 2, "item": "Widget", "status": "shipped", "quantity": 1, "unit_price_minor": 999});
+        server.post("/v1/orders").json(&shipped).await.assert_status(StatusCode::CONFLICT);
+        let create = "mutation($input: OrderInput!) { createOrder(input: $input) { id } }";
+        let input = json!({"id": 2, "item": "Widget", "status": "delivered", "quantity": 1});
+        let created = graphql(&server, create, json!({"input": input})).await;
+        assert_eq!(created["errors"][0]["extensions"]["status"], 409);
+        let response = server.post("/v1/orders/import").text("id,item,status,quantity\n2,Widget,shipped,1\n").await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.json::<Value>()["errors"][0]["error"], "Order 2 cannot be shipped before its payment is captured");
+        assert_eq!(server.get("/v1/orders").await.json::<Vec<Order>>().len(), 1);
+        
+        let payment: Value = server.post("/v1/orders/1/payments").await.json();
+        server.post(&format!("/v1/orders/1/payments/{}/capture", payment["id"]))
+            .await
+            .assert_status_ok();
+        
+        let order: Order = server.patch("/v1/orders/1/status").json(&json!({"status": "shipped"})).await.json();
+        assert_eq!(order.status, "shipped");
+    }
+
+    #[tokio::test]
+    async fn test_payment_declined_by_gateway() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "test-client", Role::Admin).await.unwrap();
+        let config = AppConfig {
+            payments: PaymentConfig {
+                gateway: std::sync::Arc::new(crate::payments::FakePaymentGateway::declining_above(100)),
+                ..PaymentConfig::default()
+            },
+            ..AppConfig::default()
+        };
+        let mut server = TestServer::new(create_router(SqliteStore::new(db_pool), config)).unwrap();
+        server.add_header("x-api-key", secret);
+        
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 1, "unit_price_minor": 101}))
+            .await
+            .assert_status_ok();
+        
+        let response = server.post("/v1/orders/1/payments").await;
+        response.assert_status(StatusCode::PAYMENT_REQUIRED);
+        let body: Value = response.json();
+        assert_eq!(body["error"], "Payment declined");
+        
+        let payments: Vec<Value> = server.get("/v1/orders/1/payments").await.json();
+        assert!(payments.is_empty());
+    }
+
+    async fn test_webhook_subscription_and_delivery_log(backend: Backend) {
+        match backend {
+            Backend::Sqlite => {
+                let db_pool = init_db().await.expect("Failed to initialize test database");
+                check_webhook_delivery_log(SqliteStore::new(db_pool)).await;
+            }
+            Backend::Memory => check_webhook_delivery_log(InMemoryStore::default()).await,
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => check_webhook_delivery_log(postgres_store().await).await,
+        }
+    }
+
+    async fn check_webhook_delivery_log<S: Store + Clone>(store: S) {
+        let server = serve_store(store.clone(), AppConfig::default()).await;
+        
+        server.post("/v1/webhooks")
+            .json(&json!({"url": "ftp://example.com", "event_types": ["order.created"], "secret": "0123456789abcdef"}))
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+        
+        // Nothing listens on the discard port, so the delivery fails
+        let subscription: Value = server.post("/v1/webhooks")
+            .json(&json!({
+                "url": "http://127.0.0.1:9/hooks",
+                "event_types": ["order.created", "order.deleted"],
+                "secret": "0123456789abcdef"
+            }))
+            .await
+            .json();
+        assert!(subscription.get("secret").is_none());
+        let id = subscription["id"].as_u64().unwrap();
+        
+        server.post("/v1/orders")
+            .json(&json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 1}))
+            .await
+            .assert_status_ok();
+        let config = crate::config::WebhookConfig::default();
+        crate::webhooks::deliver_due_webhooks(&store, &reqwest::Client::new(), &config).await.unwrap();
+        
+        let deliveries: Vec<Value> = server.get(&format!("/v1/webhooks/{}/deliveries", id)).await.json();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0]["event_type"], "order.created");
+        assert_eq!(deliveries[0]["succeeded"], false);
+        
+        server.delete(&format!("/v1/webhooks/{}", id)).await.assert_status_ok();
+        server.get(&format!("/v1/webhooks/{}/deliveries", id)).await.assert_status_not_found();
+    }
+
+    async fn test_order_events_rejects_invalid_filters(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        
+        server.get("/v1/orders/events?status=lost").await.assert_status(StatusCode::BAD_REQUEST);
+        
+        let response = server.get("/v1/orders/events").add_header("last-event-id", "yesterday").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let body: Value = response.json();
+        assert_eq!(body["field"], "Last-Event-ID");
+    }
+
+    #[tokio::test]
+    async fn test_order_events_stream_resumes_from_last_event_id() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "test-client", Role::Admin).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = create_router(SqliteStore::new(db_pool), AppConfig::default());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await.unwrap()
+        });
+        
+        let client = reqwest::Client::new();
+        client.post(format!("http://{}/v1/orders", addr))
+            .header("x-api-key", &secret)
+            .json(&json!({"id": 7, "item": "Widget", "status": "pending", "quantity": 1}))
+            .send()
+            .await
+            .unwrap();
+        
+        let mut response = client.get(format!("http://{}/v1/orders/events?id=7", addr))
+            .header("x-api-key", &secret)
+            .header("last-event-id", "0")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        
+        let mut received = String::new();
+        while !received.contains("\n\n") {
+            let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), response.chunk())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(received.contains("event: order.created\n"), "{}", received);
+        assert!(received.contains("id: 1\n"), "{}", received);
+        assert!(received.contains(r#""order_id":7"#), "{}", received);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_subscriptions_and_status_updates() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, secret) = create_api_key(&db_pool, "scanner", Role::Warehouse).await.unwrap();
+        let (_, admin_secret) = create_api_key(&db_pool, "test-client", Role::Admin).await.unwrap();
+        let mut server = axum_test::TestServerConfig::builder()
+            .http_transport()
+            .build_server(create_router(SqliteStore::new(db_pool), AppConfig::default()))
+            .unwrap();
+        server.add_header("x-api-key", admin_secret);
+        add_test_order(&server, 1, "Widget", "pending", 1).await;
+        add_test_order(&server, 2, "Gadget", "pending", 1).await;
+        
+        let mut socket = server.get_websocket("/v1/ws")
+            .add_header("x-api-key", secret)
+            .await
+            .into_websocket()
+            .await;
+        
+        socket.send_json(&json!({"type": "subscribe", "id": 1, "order_ids": [1]})).await;
+        let ack: Value = socket.receive_json().await;
+        assert_eq!(ack, json!({"type": "ack", "id": 1, "order_ids": [1]}));
+        
+        socket.send_json(&json!({"type": "subscribe", "id": 2, "order_ids": [99]})).await;
+        let error: Value = socket.receive_json().await;
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["id"], 2);
+        assert_eq!(error["status"], 404);
+        
+        socket.send_json(&json!({"type": "update_status", "id": 3, "order_id": 2, "status": "lost"})).await;
+        let error: Value = socket.receive_json().await;
+        assert_eq!(error["status"], 400);
+        assert_eq!(error["field"], "status");
+        
+        // Order 2 is not subscribed to, so only the ack comes back
+        socket.send_json(&json!({"type": "update_status", "id": 4, "order_id": 2, "status": "processing"})).await;
+        let ack: Value = socket.receive_json().await;
+        assert_eq!(ack["id"], 4);
+        assert_eq!(ack["order"]["status"], "processing");
+        
+        // Changes made through the REST API are pushed as well
+        server.patch("/v1/orders/1/status").json(&json!({"status": "shipped"})).await.assert_status_ok();
+        let pushed: Value = socket.receive_json().await;
+        assert_eq!(pushed["type"], "status_changed");
+        assert_eq!(pushed["order_id"], 1);
+        assert_eq!(pushed["status"], "shipped");
+        assert_eq!(pushed["previous_status"], "pending");
+        
+        socket.send_json(&json!({"type": "unsubscribe", "id": 5, "order_ids": [1]})).await;
+        let ack: Value = socket.receive_json().await;
+        assert_eq!(ack, json!({"type": "ack", "id": 5, "order_ids": []}));
+    }
+
+    async fn test_export_orders_csv_honours_filters(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        add_test_order(&server, 1, "Bolts, \"M8\"", "pending", 2).await;
+        add_test_order(&server, 2, "Nuts", "shipped", 1).await;
+        
+        let response = server.get("/v1/orders/export.csv?status=pending").await;
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "text/csv; charset=utf-8");
+        assert_eq!(response.header("content-disposition"), "attachment; filename=\"orders.csv\"");
+        
+        let csv = response.text();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("id,item,status,quantity,"));
+        assert!(lines[1].starts_with(r#"1,"Bolts, ""M8""",pending,2,"#), "{}", lines[1]);
+        
+        // The listing negotiates CSV through Accept
+        let response = server.get("/v1/orders").add_header("accept", "text/csv").await;
+        response.assert_status_ok();
+        assert_eq!(response.text().lines().count(), 3);
+        
+        let orders: Vec<Order> = server.get("/v1/orders").add_header("accept", "application/json").await.json();
+        assert_eq!(orders.len(), 2);
+    }
+    
+    async fn test_import_orders_reports_invalid_rows_and_imports_nothing(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        add_test_order(&server, 1, "Existing", "pending", 1).await;
+        
+        let csv = "id,item,status,quantity\n\
+                   2,Widget,pending,2\n\
+                   3,,pending,1\n\
+                   4,Gadget,lost,1\n\
+                   5,Sprocket,pending,lots\n\
+                   2,Widget again,pending,1\n";
+        let response = server.post("/v1/orders/import").text(csv).await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let report: Value = response.json();
+        assert_eq!(report["rows"], 5);
+        assert_eq!(report["imported"], 0);
+        let errors: Vec<(u64, &str)> = report["errors"].as_array().unwrap().iter()
+            .map(|error| (error["row"].as_u64().unwrap(), error["field"].as_str().unwrap()))
+            .collect();
+        assert_eq!(errors, [(3, "item"), (4, "status"), (5, "quantity"), (6, "id")]);
+        
+        // A row clashing with a saved order rolls back the rows before it
+        let csv = "id,item,status,quantity\n2,Widget,pending,2\n1,Duplicate,pending,1\n";
+        let response = server.post("/v1/orders/import").text(csv).await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+        let report: Value = response.json();
+        assert_eq!(report["errors"][0]["row"], 3);
+        assert_eq!(report["errors"][0]["order_id"], 1);
+        
+        let orders: Vec<Order> = server.get("/v1/orders").await.json();
+        assert_eq!(orders.len(), 1);
+    }
+    
+    async fn test_import_orders_dry_run_then_commit(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        let csv = "id,item,status,quantity,currency\n1,Widget,pending,2,EUR\n2,\"Bolts, M8\",shipped,10,\n";
+        
+        let response = server.post("/v1/orders/import?dry_run=true").text(csv).await;
+        response.assert_status_ok();
+        let report: Value = response.json();
+        assert_eq!((report["dry_run"].as_bool(), report["imported"].as_u64()), (Some(true), Some(2)));
+        let orders: Vec<Order> = server.get("/v1/orders").await.json();
+        assert!(orders.is_empty());
+        
+        let response = server.post("/v1/orders/import").text(csv).await;
+        response.assert_status_ok();
+        assert_eq!(response.json::<Value>()["imported"], 2);
+        let orders: Vec<Order> = server.get("/v1/orders").await.json();
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].currency, "EUR");
+        assert_eq!(orders[1].item, "Bolts, M8");
+        
+        let response = server.post("/v1/orders/import").text("id,item\n1,Widget\n").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<Value>()["field"], "header");
+    }
+    
+    async fn test_get_orders_streams_ndjson(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        for id in 1..=3 {
+            add_test_order(&server, id, "Widget", if id == 2 { "shipped" } else { "pending" }, id).await;
+        }
+        
+        let response = server.get("/v1/orders?status=pending").add_header("accept", "application/x-ndjson").await;
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "application/x-ndjson");
+        let body = response.text();
+        assert!(body.ends_with('\n'));
+        let orders: Vec<Order> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(orders.iter().map(|order| order.id).collect::<Vec<_>>(), [1, 3]);
+        
+        let response = server.get("/v1/orders?status=cancelled").add_header("accept", "application/x-ndjson").await;
+        response.assert_status_ok();
+        assert!(response.text().is_empty());
+    }
+    
+    async fn test_every_resource_served_for_every_store(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        add_test_order(&server, 1, "Widget", "pending", 1).await;
+        
+        for path in ["/v1/customers", "/v1/products", "/v1/orders/1/payments", "/v1/orders/1/shipments", "/graphiql"] {
+            server.get(path).await.assert_status_ok();
+        }
+        let doc: Value = server.get("/api-docs/v1/openapi.json").await.json();
+        assert!(doc["paths"]["/v1/customers"].is_object());
+        
+        let order = json!({"id": 2, "item": "Widget", "status": "pending", "quantity": 1, "customer_id": 7});
+        let response = server.post("/v1/orders").json(&order).await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<Value>()["error"], "Customer with ID 7 does not exist");
+        
+        server.post("/v1/customers").json(&json!({"id": 7, "name": "Ada", "email": "ada@example.com"})).await
+            .assert_status_ok();
+        server.post("/v1/orders").json(&order).await.assert_status_ok();
+    }
+    
+    async fn test_get_orders_stats(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        add_test_order(&server, 1, "Widget", "pending", 4).await;
+        add_test_order(&server, 2, "Gadget", "pending", 1).await;
+        add_test_order(&server, 3, "Widget", "pending", 2).await;
+        server.patch("/v1/orders/3/status").json(&json!({"status": "delivered"})).await.assert_status_ok();
+        
+        let response = server.get("/v1/orders/stats?top_items=1&days=3").await;
+        response.assert_status_ok();
+        let stats: Value = response.json();
+        assert_eq!((stats["orders"].as_i64(), stats["quantity"].as_i64()), (Some(3), Some(7)));
+        assert_eq!(stats["by_status"], json!([
+            {"status": "delivered", "orders": 1, "quantity": 2},
+            {"status": "pending", "orders": 2, "quantity": 5}
+        ]));
+        assert_eq!(stats["top_items"], json!([{"item": "Widget", "orders": 2, "quantity": 6}]));
+        let daily = stats["daily"].as_array().unwrap();
+        assert_eq!(daily.len(), 3);
+        assert_eq!((daily[2]["created"].as_i64(), daily[2]["delivered"].as_i64()), (Some(3), Some(1)));
+        
+        let response = server.get("/v1/orders/stats?days=0").await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<Value>()["field"], "days");
+    }
+    
+    async fn graphql(server: &TestServer, query: &str, variables: Value) -> Value {
+        let response = server.post("/v1/graphql").json(&json!({"query": query, "variables": variables})).await;
+        response.assert_status_ok();
+        response.json()
+    }
+    
+    async fn test_graphql_orders_with_relations_and_pages(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        server.post("/v1/customers").json(&json!({"id": 7, "name": "Ada Lovelace", "email": "ada@example.com"}))
+            .await.assert_status_ok();
+        for id in 1..=3 {
+            let order = json!({"id": id, "item": "Widget", "status": "pending", "quantity": id, "customer_id": 7});
+            server.post("/v1/orders").json(&order).await.assert_status_ok();
+        }
+        
+        let query = "query($after: Int) { orders(first: 2, after: $after) { \
+                     nodes { id quantity customer { name } shipments { id } } endCursor hasNextPage } }";
+        let page = graphql(&server, query, json!({})).await;
+        assert!(page["errors"].is_null(), "{}", page);
+        let orders = &page["data"]["orders"];
+        assert_eq!(orders["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(orders["nodes"][0]["customer"]["name"], "Ada Lovelace");
+        assert_eq!(orders["nodes"][0]["shipments"], json!([]));
+        assert_eq!((orders["endCursor"].as_u64(), orders["hasNextPage"].as_bool()), (Some(2), Some(true)));
+        
+        let page = graphql(&server, query, json!({"after": 2})).await;
+        let orders = &page["data"]["orders"];
+        assert_eq!(orders["nodes"][0]["id"], 3);
+        assert_eq!(orders["hasNextPage"], false);
+        
+        let missing = graphql(&server, "{ order(id: 99) { id } }", json!({})).await;
+        assert_eq!(missing["data"]["order"], Value::Null);
+    }
+    
+    async fn test_graphql_mutations_match_rest(backend: Backend) {
+        let server = setup_test_server_for(backend).await;
+        let create = "mutation($input: OrderInput!) { createOrder(input: $input) { id status totalMinor currency } }";
+        let input = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2, "unitPriceMinor": 250});
+        let created = graphql(&server, create, json!({"input": input})).await;
+        assert_eq!(created["data"]["createOrder"], json!({"id": 1, "status": "pending", "totalMinor": 500, "currency": "USD"}));
+        
+        // Validation and conflicts are reported with the REST status and field
+        let invalid = graphql(&server, create, json!({"input": {"id": 2, "item": "", "status": "pending", "quantity": 1}})).await;
+        assert_eq!(invalid["errors"][0]["extensions"], json!({"status": 400, "field": "item"}));
+        let duplicate = graphql(&server, create, json!({"input": input})).await;
+        assert_eq!(duplicate["errors"][0]["extensions"]["field"], "id");
+        
+        let status = graphql(&server, "mutation { updateOrderStatus(id: 1, status: \"shipped\") { status } }", json!({})).await;
+        assert_eq!(status["data"]["updateOrderStatus"]["status"], "shipped");
+        let order: Order = server.get("/v1/orders/1").await.json();
+        assert_eq!(order.status, "shipped");
+        
+        let deleted = graphql(&server, "mutation { deleteOrder(id: 1) { id } }", json!({})).await;
+        assert_eq!(deleted["data"]["deleteOrder"]["id"], 1);
+        let gone = graphql(&server, "mutation { deleteOrder(id: 1) { id } }", json!({})).await;
+        assert_eq!(gone["errors"][0]["extensions"]["status"], 404);
+    }
+    
+    #[tokio::test]
+    async fn test_graphql_enforces_scopes() {
+        let db_pool = init_db().await.expect("Failed to initialize test database");
+        let (_, reader_key) = create_api_key(&db_pool, "reporting", Role::ReadOnly).await.unwrap();
+        let server = TestServer::new(create_router(SqliteStore::new(db_pool), AppConfig::default())).unwrap();
+        
+        server.post("/v1/graphql").json(&json!({"query": "{ orders { hasNextPage } }"}))
+            .await.assert_status(StatusCode::UNAUTHORIZED);
+        
+        let response = server.post("/v1/graphql").add_header("x-api-key", &reader_key)
+            .json(&json!({"query": "mutation { deleteOrder(id: 1) { id } }"})).await;
+        let body: Value = response.json();
+        assert_eq!(body["errors"][0]["extensions"]["status"], 403);
+        assert!(body["errors"][0]["message"].as_str().unwrap().contains("orders:delete"));
+        
+        // The playground is public; the endpoint it calls is not
+        let response = server.get("/graphiql").await;
+        response.assert_status_ok();
+        assert!(response.text().contains("/v1/graphql"));
+    }
+
+    /// Run each test body once per store
+    macro_rules! for_each_store {
+        ($($test:ident),* $(,)?) => {
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::Backend::Sqlite).await;
+                    }
+                )*
+            }
+
+            mod in_memory {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::Backend::Memory).await;
+                    }
+                )*
+            }
+
+            #[cfg(feature = "postgres")]
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::Backend::Postgres).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    for_each_store!(
+        test_get_orders_empty,
+        test_get_orders_with_data,
+        test_add_order_valid,
+        test_add_order_all_valid_statuses,
+        test_add_order_invalid_empty_item,
+        test_add_order_invalid_status,
+        test_add_order_zero_quantity,
+        test_add_order_excessive_quantity,
+        test_add_order_duplicate_id,
+        test_get_order_by_id_success,
+        test_get_order_by_id_not_found,
+        test_update_order_success,
+        test_update_order_not_found,
+        test_update_order_validation_error,
+        test_update_order_status_success,
+        test_update_order_status_not_found,
+        test_update_order_status_validation_error,
+        test_delete_order_success,
+        test_delete_order_not_found,
+        test_catch_all_route_different_paths,
+        test_invalid_id_format,
+        test_malformed_json,
+        test_missing_fields,
+        test_wrong_data_types,
+        test_content_type_headers,
+        test_integration_workflow,
+        test_v1_routes,
+        test_unversioned_routes_are_deprecated_aliases,
+        test_get_orders_streams_ndjson,
+        test_every_resource_served_for_every_store,
+        test_shipping_requires_captured_payment_when_configured,
+        test_catch_all_route,
+        test_healthz,
+        test_readyz,
+        test_openapi_documents_health_endpoints,
+        test_openapi_declares_security_schemes,
+        test_openapi_documents_required_scopes,
+        test_rate_limit_headers_on_success,
+        test_cors_disabled_by_default,
+        test_oversized_body_returns_413,
+        test_openapi_document_per_version,
+        test_customer_crud,
+        test_customer_validation,
+        test_customer_orders,
+        test_order_with_unknown_customer_is_rejected,
+        test_customers_require_scopes,
+        test_orders_reserve_product_stock,
+        test_product_restock,
+        test_order_pricing_and_totals,
+        test_promo_code_applied_at_order_creation,
+        test_shipments_advance_order_to_delivered,
+        test_order_with_shipments_cannot_be_deleted,
+        test_shipping_address_and_shipment_validation,
+        test_payment_lifecycle_endpoints,
+        test_order_with_payments_cannot_be_deleted,
+        test_order_events_rejects_invalid_filters,
+        test_export_orders_csv_honours_filters,
+        test_import_orders_reports_invalid_rows_and_imports_nothing,
+        test_import_orders_dry_run_then_commit,
+        test_get_orders_stats,
+        test_webhook_subscription_and_delivery_log,
+        test_graphql_orders_with_relations_and_pages,
+        test_graphql_mutations_match_rest,
+    );
 }
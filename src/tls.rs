@@ -0,0 +1,424 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    time::SystemTime
+};
+use axum::{middleware::AddExtension, Extension, Router};
+use axum_server::{
+    accept::{Accept, DefaultAcceptor},
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+    Handle
+};
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use x509_parser::prelude::{FromDer, X509Certificate};
+use crate::config::TlsConfig;
+
+/// Client certificate presented on a mutual TLS connection.
+/// Every request served over TLS carries an `Option<ClientCertificate>` extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCertificate {
+    /// Common name of the certificate subject, used to look up the caller identity
+    pub subject: String,
+}
+
+impl ClientCertificate {
+    /// Extract the subject common name from a DER encoded certificate
+    pub(crate) fn from_der(der: &CertificateDer<'_>) -> Option<Self> {
+        let (_, certificate) = X509Certificate::from_der(der.as_ref()).ok()?;
+        let common_name = certificate.subject().iter_common_name().next()?;
+        Some(ClientCertificate {
+            subject: common_name.as_str().ok()?.to_string(),
+        })
+    }
+}
+
+fn invalid_data<E: std::fmt::Display>(context: &str) -> impl FnOnce(E) -> io::Error + '_ {
+    move |e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", context, e))
+}
+
+/// Build a rustls server configuration from the configured PEM files
+pub fn load_server_config(tls: &TlsConfig) -> io::Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(&tls.cert_path)
+        .map_err(invalid_data("Failed to read TLS certificate"))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid_data("Failed to parse TLS certificate"))?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key_path)
+        .map_err(invalid_data("Failed to read TLS private key"))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid_data("Unsupported TLS protocol versions"))?;
+
+    let builder = match &tls.client_ca_path {
+        Some(client_ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(client_ca_path)
+                .map_err(invalid_data("Failed to read TLS client CA"))?
+            {
+                roots.add(cert.map_err(invalid_data("Failed to parse TLS client CA"))?)
+                    .map_err(invalid_data("Invalid TLS client CA"))?;
+            }
+            // Certificates are optional so API key clients can still connect
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()
+                .map_err(invalid_data("Invalid TLS client CA"))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_single_cert(certs, key)
+        .map_err(invalid_data("Invalid TLS certificate or key"))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn certificate_file_times(tls: &TlsConfig) -> Vec<Option<SystemTime>> {
+    let mut times = vec![modified_time(&tls.cert_path), modified_time(&tls.key_path)];
+    if let Some(client_ca_path) = &tls.client_ca_path {
+        times.push(modified_time(client_ca_path));
+    }
+    times
+}
+
+/// Swaps in a new server configuration when the certificate files change
+struct CertificateReloader {
+    rustls_config: RustlsConfig,
+    tls: TlsConfig,
+    last_seen: Vec<Option<SystemTime>>,
+}
+
+impl CertificateReloader {
+    fn new(rustls_config: RustlsConfig, tls: TlsConfig) -> Self {
+        let last_seen = certificate_file_times(&tls);
+        CertificateReloader { rustls_config, tls, last_seen }
+    }
+
+    /// Reload the configuration if any file's modification time changed since the last reload,
+    /// returning whether it was replaced
+    fn reload_if_changed(&mut self) -> bool {
+        let current = certificate_file_times(&self.tls);
+        if current == self.last_seen {
+            return false;
+        }
+
+        match load_server_config(&self.tls) {
+            Ok(config) => {
+                self.rustls_config.reload_from_config(Arc::new(config));
+                self.last_seen = current;
+                println!("Reloaded TLS certificate from {}", self.tls.cert_path.display());
+                true
+            }
+            // Keep serving the previous certificate; files may be mid-rotation
+            Err(e) => {
+                eprintln!("Failed to reload TLS certificate: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Periodically check the certificate files and swap in the new configuration when they change.
+/// New connections use the renewed certificate; established connections are unaffected.
+pub fn spawn_certificate_reloader(rustls_config: RustlsConfig, tls: TlsConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tls.reload_interval);
+        let mut reloader = CertificateReloader::new(rustls_config, tls);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            reloader.reload_if_changed();
+        }
+    })
+}
+
+/// Acceptor performing the TLS handshake and attaching the peer's client certificate
+/// to every request made on the connection
+#[derive(Debug, Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        ClientCertAcceptor {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, Option<ClientCertificate>>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor: RustlsAcceptor<DefaultAcceptor> = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let client_certificate = stream.get_ref().1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(ClientCertificate::from_der);
+            Ok((stream, Extension(client_certificate).layer(service)))
+        })
+    }
+}
+
+/// Serve the router over HTTPS on an already bound listener until the handle shuts it down
+pub async fn serve_tls(
+    listener: std::net::TcpListener,
+    app: Router,
+    tls: &TlsConfig,
+    handle: Handle,
+) -> io::Result<()> {
+    let rustls_config = RustlsConfig::from_config(Arc::new(load_server_config(tls)?));
+    let reloader = spawn_certificate_reloader(rustls_config.clone(), tls.clone());
+
+    let result = axum_server::from_tcp(listener)
+        .acceptor(ClientCertAcceptor::new(rustls_config))
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await;
+
+    reloader.abort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, time::Duration};
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose,
+        IsCa, KeyPair
+    };
+    use crate::config::AppConfig;
+    use crate::routes::create_router;
+    use crate::utils::{init_db, register_certificate_identity, Role, SqliteStore};
+
+    struct TestPki {
+        dir: PathBuf,
+        ca_cert: Certificate,
+        ca_key: KeyPair,
+    }
+
+    impl TestPki {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("orders-tls-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let ca_key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params.distinguished_name.push(DnType::CommonName, "Orders Test CA");
+            let ca_cert = params.self_signed(&ca_key).unwrap();
+            std::fs::write(dir.join("ca.pem"), ca_cert.pem()).unwrap();
+
+            TestPki { dir, ca_cert, ca_key }
+        }
+
+        /// Issue a certificate signed by the test CA, returning (cert PEM, key PEM)
+        fn issue(&self, common_name: &str, client: bool) -> (String, String) {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            params.distinguished_name.push(DnType::CommonName, common_name);
+            if client {
+                params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+            }
+            let cert = params.signed_by(&key, &self.ca_cert, &self.ca_key).unwrap();
+            (cert.pem(), key.serialize_pem())
+        }
+
+        fn write_server_cert(&self) {
+            let (cert, key) = self.issue("localhost", false);
+            std::fs::write(self.dir.join("server.pem"), cert).unwrap();
+            std::fs::write(self.dir.join("server.key"), key).unwrap();
+        }
+
+        fn tls_config(&self, mutual: bool) -> TlsConfig {
+            TlsConfig {
+                cert_path: self.dir.join("server.pem"),
+                key_path: self.dir.join("server.key"),
+                client_ca_path: mutual.then(|| self.dir.join("ca.pem")),
+                client_identities: Vec::new(),
+                reload_interval: Duration::from_millis(50),
+            }
+        }
+
+        fn client(&self, identity: Option<(String, String)>) -> reqwest::Client {
+            let ca = reqwest::Certificate::from_pem(self.ca_cert.pem().as_bytes()).unwrap();
+            let mut builder = reqwest::Client::builder()
+                .use_rustls_tls()
+                .add_root_certificate(ca);
+            if let Some((cert, key)) = identity {
+                builder = builder.identity(reqwest::Identity::from_pem(format!("{}{}", cert, key).as_bytes()).unwrap());
+            }
+            builder.build().unwrap()
+        }
+    }
+
+    impl Drop for TestPki {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    async fn start_server(tls: TlsConfig) -> (String, Handle) {
+        let db_pool = init_db().await.unwrap();
+        register_certificate_identity(&db_pool, "scanner-01", "Scanner 1", Role::Warehouse).await.unwrap();
+        let app = create_router(SqliteStore::new(db_pool), AppConfig::default());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = Handle::new();
+        let server_handle = handle.clone();
+        tokio::spawn(async move {
+            serve_tls(listener, app, &tls, server_handle).await.unwrap();
+        });
+        handle.listening().await.unwrap();
+
+        (format!("https://localhost:{}", port), handle)
+    }
+
+    #[tokio::test]
+    async fn test_serves_https() {
+        let pki = TestPki::new();
+        pki.write_server_cert();
+        let (base_url, handle) = start_server(pki.tls_config(false)).await;
+
+        let response = pki.client(None).get(format!("{}/healthz", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        // Order endpoints still require credentials
+        let response = pki.client(None).get(format!("{}/orders", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+
+        handle.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_maps_to_caller_identity() {
+        let pki = TestPki::new();
+        pki.write_server_cert();
+        let (base_url, handle) = start_server(pki.tls_config(true)).await;
+
+        let scanner = pki.client(Some(pki.issue("scanner-01", true)));
+        let response = scanner.get(format!("{}/orders", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        // The mapped warehouse role cannot delete orders
+        let response = scanner.delete(format!("{}/orders/1", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 403);
+
+        // A valid certificate without a mapped identity is not enough
+        let stranger = pki.client(Some(pki.issue("stranger", true)));
+        let response = stranger.get(format!("{}/orders", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+
+        // Certificates are optional; clients without one can still connect
+        let response = pki.client(None).get(format!("{}/healthz", base_url)).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        handle.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_served_over_tls_with_client_certificates() {
+        use tonic::{transport::{Channel, ClientTlsConfig}, Code};
+        use crate::grpc::{proto::{order_service_client::OrderServiceClient, GetOrderRequest}, serve_grpc};
+
+        let pki = TestPki::new();
+        pki.write_server_cert();
+        let db_pool = init_db().await.unwrap();
+        register_certificate_identity(&db_pool, "scanner-01", "Scanner 1", Role::Warehouse).await.unwrap();
+        let config = AppConfig { tls: Some(pki.tls_config(true)), ..AppConfig::default() };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { serve_grpc(listener, SqliteStore::new(db_pool), &config).await.unwrap() });
+
+        let connect = |identity: Option<(String, String)>| {
+            let mut tls = ClientTlsConfig::new()
+                .ca_certificate(tonic::transport::Certificate::from_pem(pki.ca_cert.pem()))
+                .domain_name("localhost");
+            if let Some((cert, key)) = identity {
+                tls = tls.identity(tonic::transport::Identity::from_pem(cert, key));
+            }
+            let endpoint = Channel::from_shared(format!("https://127.0.0.1:{}", port)).unwrap();
+            async move { OrderServiceClient::new(endpoint.tls_config(tls).unwrap().connect().await.unwrap()) }
+        };
+
+        // The mapped identity is authenticated; the order just does not exist
+        let mut scanner = connect(Some(pki.issue("scanner-01", true))).await;
+        let status = scanner.get_order(GetOrderRequest { id: 1 }).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        // Certificates are optional, but then an API key is needed
+        let mut anonymous = connect(None).await;
+        let status = anonymous.get_order(GetOrderRequest { id: 1 }).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[tokio::test]
+    async fn test_certificate_reload_without_restart() {
+        let pki = TestPki::new();
+        pki.write_server_cert();
+        let tls = pki.tls_config(false);
+        let rustls_config = RustlsConfig::from_config(Arc::new(load_server_config(&tls).unwrap()));
+        let initial = rustls_config.get_inner();
+        let mut reloader = CertificateReloader::new(rustls_config.clone(), tls.clone());
+
+        // Files that have not changed are not reloaded
+        assert!(!reloader.reload_if_changed());
+        assert!(Arc::ptr_eq(&initial, &rustls_config.get_inner()));
+
+        // Date the renewed files explicitly rather than relying on the filesystem's timestamp granularity
+        pki.write_server_cert();
+        let renewed_at = SystemTime::now() + Duration::from_secs(60);
+        for path in [&tls.cert_path, &tls.key_path] {
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(renewed_at).unwrap();
+        }
+        assert!(reloader.reload_if_changed());
+        assert!(!Arc::ptr_eq(&initial, &rustls_config.get_inner()));
+
+        // A reload happens once per change
+        assert!(!reloader.reload_if_changed());
+    }
+
+    #[test]
+    fn test_load_server_config_reports_missing_files() {
+        let tls = TlsConfig {
+            cert_path: PathBuf::from("/nonexistent/server.pem"),
+            key_path: PathBuf::from("/nonexistent/server.key"),
+            client_ca_path: None,
+            client_identities: Vec::new(),
+            reload_interval: Duration::from_secs(30),
+        };
+
+        let error = load_server_config(&tls).unwrap_err();
+        assert!(error.to_string().contains("Failed to read TLS certificate"));
+    }
+}
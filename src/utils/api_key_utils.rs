@@ -0,0 +1,369 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::utils::DbPool;
+use crate::validators::{ApiError, ServerError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+/// Role attached to a credential, determining which scopes it is granted
+pub enum Role {
+    /// Read-only integrations: may only list and fetch orders, customers and products
+    ReadOnly,
+    /// Warehouse clients: may read orders and customers, change order status and manage stock
+    Warehouse,
+    /// Administrators: full access, including replacing and deleting orders and managing customers,
+    /// products, promotions and payments
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Permission required to perform an operation
+pub enum Scope {
+    ReadOrders,
+    CreateOrders,
+    UpdateOrders,
+    UpdateOrderStatus,
+    DeleteOrders,
+    ReadCustomers,
+    ManageCustomers,
+    ReadProducts,
+    ManageProducts,
+    ReadPromotions,
+    ManagePromotions,
+    ReadPayments,
+    ManagePayments,
+    ReadWebhooks,
+    ManageWebhooks,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadOrders => "orders:read",
+            Scope::CreateOrders => "orders:create",
+            Scope::UpdateOrders => "orders:update",
+            Scope::UpdateOrderStatus => "orders:status",
+            Scope::DeleteOrders => "orders:delete",
+            Scope::ReadCustomers => "customers:read",
+            Scope::ManageCustomers => "customers:write",
+            Scope::ReadProducts => "products:read",
+            Scope::ManageProducts => "products:write",
+            Scope::ReadPromotions => "promotions:read",
+            Scope::ManagePromotions => "promotions:write",
+            Scope::ReadPayments => "payments:read",
+            Scope::ManagePayments => "payments:write",
+            Scope::ReadWebhooks => "webhooks:read",
+            Scope::ManageWebhooks => "webhooks:write",
+        }
+    }
+}
+
+impl Role {
+    /// Scopes granted to credentials holding this role
+    pub fn scopes(&self) -> &'static [Scope] {
+        match self {
+            Role::ReadOnly => &[Scope::ReadOrders, Scope::ReadCustomers, Scope::ReadProducts],
+            Role::Warehouse => &[
+                Scope::ReadOrders,
+                Scope::UpdateOrderStatus,
+                Scope::ReadCustomers,
+                Scope::ReadProducts,
+                Scope::ManageProducts,
+            ],
+            Role::Admin => &[
+                Scope::ReadOrders,
+                Scope::CreateOrders,
+                Scope::UpdateOrders,
+                Scope::UpdateOrderStatus,
+                Scope::DeleteOrders,
+                Scope::ReadCustomers,
+                Scope::ManageCustomers,
+                Scope::ReadProducts,
+                Scope::ManageProducts,
+                Scope::ReadPromotions,
+                Scope::ManagePromotions,
+                Scope::ReadPayments,
+                Scope::ManagePayments,
+                Scope::ReadWebhooks,
+                Scope::ManageWebhooks,
+            ],
+        }
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes().contains(&scope)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "read_only" => Ok(Role::ReadOnly),
+            "warehouse" => Ok(Role::Warehouse),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role: {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+/// API key record; the secret itself is never stored, only its SHA-256 hash
+pub struct ApiKey {
+    /// Unique identifier for the key
+    pub id: i64,
+    /// Human readable name of the client the key was issued to
+    pub name: String,
+    /// Role determining what the key is allowed to do
+    pub role: Role,
+    /// Whether the key has been revoked
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+/// Identity granted to clients presenting a TLS certificate with the given common name
+pub struct CertificateIdentity {
+    /// Common name of the client certificate subject
+    pub subject: String,
+    /// Human readable name of the client
+    pub name: String,
+    /// Role determining what the client is allowed to do
+    pub role: Role,
+}
+
+/// Hash an API key secret for storage and lookup
+pub fn hash_api_key(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Generate a new random API key secret
+pub fn generate_api_key() -> String {
+    format!("ok_{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+/// Store an API key with the given secret, returning the stored record.
+/// Registering a secret again renames it, sets its role and reinstates it if it was revoked,
+/// so a key configured at startup survives restarts against a persistent database.
+pub async fn register_api_key(pool: &DbPool, name: &str, role: Role, secret: &str) -> Result<ApiKey, ApiError> {
+    let key = sqlx::query_as::<_, ApiKey>(
+        "INSERT INTO api_keys (name, role, key_hash) VALUES (?, ?, ?) \
+         ON CONFLICT(key_hash) DO UPDATE SET name = excluded.name, role = excluded.role, revoked = 0 \
+         RETURNING id, name, role, revoked"
+    )
+        .bind(name)
+        .bind(role)
+        .bind(hash_api_key(secret))
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in register_api_key: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create API key".to_string(),
+            })
+        })?;
+
+    Ok(key)
+}
+
+/// Generate and store a new API key, returning the record and the plaintext secret.
+/// The secret cannot be recovered later, so it must be handed to the client now.
+pub async fn create_api_key(pool: &DbPool, name: &str, role: Role) -> Result<(ApiKey, String), ApiError> {
+    let secret = generate_api_key();
+    let key = register_api_key(pool, name, role, &secret).await?;
+    Ok((key, secret))
+}
+
+/// Look up an active (non-revoked) API key by its plaintext secret
+pub async fn find_api_key(pool: &DbPool, secret: &str) -> Result<Option<ApiKey>, ApiError> {
+    let key = sqlx::query_as::<_, ApiKey>(
+        "SELECT id, name, role, revoked FROM api_keys WHERE key_hash = ? AND revoked = 0"
+    )
+        .bind(hash_api_key(secret))
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in find_api_key: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to verify credentials".to_string(),
+            })
+        })?;
+
+    Ok(key)
+}
+
+/// Map a client certificate subject to a caller identity, replacing any existing mapping
+pub async fn register_certificate_identity(
+    pool: &DbPool,
+    subject: &str,
+    name: &str,
+    role: Role,
+) -> Result<CertificateIdentity, ApiError> {
+    let identity = sqlx::query_as::<_, CertificateIdentity>(
+        r#"
+        INSERT INTO client_certificates (subject, name, role) VALUES (?, ?, ?)
+        ON CONFLICT (subject) DO UPDATE SET name = excluded.name, role = excluded.role, revoked = 0
+        RETURNING subject, name, role
+        "#
+    )
+        .bind(subject)
+        .bind(name)
+        .bind(role)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in register_certificate_identity: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to register client certificate".to_string(),
+            })
+        })?;
+
+    Ok(identity)
+}
+
+/// Look up the identity mapped to an active client certificate subject
+pub async fn find_certificate_identity(pool: &DbPool, subject: &str) -> Result<Option<CertificateIdentity>, ApiError> {
+    let identity = sqlx::query_as::<_, CertificateIdentity>(
+        "SELECT subject, name, role FROM client_certificates WHERE subject = ? AND revoked = 0"
+    )
+        .bind(subject)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in find_certificate_identity: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to verify credentials".to_string(),
+            })
+        })?;
+
+    Ok(identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_db;
+
+    #[test]
+    fn test_hash_api_key_is_stable_hex() {
+        let hash = hash_api_key("secret");
+        assert_eq!(hash, hash_api_key("secret"));
+        assert_ne!(hash, hash_api_key("Secret"));
+        assert_eq!(hash.len(), 64);
+    }
+
+    #[test]
+    fn test_generate_api_key_is_unique() {
+        let first = generate_api_key();
+        let second = generate_api_key();
+        assert!(first.starts_with("ok_"));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_role_scopes() {
+        assert!(Role::ReadOnly.has_scope(Scope::ReadOrders));
+        assert!(!Role::ReadOnly.has_scope(Scope::UpdateOrderStatus));
+        assert!(!Role::ReadOnly.has_scope(Scope::CreateOrders));
+
+        assert!(Role::Warehouse.has_scope(Scope::ReadOrders));
+        assert!(Role::Warehouse.has_scope(Scope::UpdateOrderStatus));
+        assert!(!Role::Warehouse.has_scope(Scope::UpdateOrders));
+        assert!(!Role::Warehouse.has_scope(Scope::DeleteOrders));
+
+        assert!(Role::ReadOnly.has_scope(Scope::ReadCustomers));
+        assert!(!Role::Warehouse.has_scope(Scope::ManageCustomers));
+        assert!(Role::Admin.has_scope(Scope::ManageCustomers));
+
+        assert!(!Role::ReadOnly.has_scope(Scope::ManageProducts));
+        assert!(Role::Warehouse.has_scope(Scope::ManageProducts));
+
+        assert!(!Role::Warehouse.has_scope(Scope::ReadPromotions));
+        assert!(Role::Admin.has_scope(Scope::ManagePromotions));
+
+        assert!(!Role::Warehouse.has_scope(Scope::ReadPayments));
+        assert!(Role::Admin.has_scope(Scope::ManagePayments));
+
+        assert!(!Role::Warehouse.has_scope(Scope::ReadWebhooks));
+        assert!(Role::Admin.has_scope(Scope::ManageWebhooks));
+
+        for scope in [Scope::ReadOrders, Scope::CreateOrders, Scope::UpdateOrders, Scope::UpdateOrderStatus, Scope::DeleteOrders] {
+            assert!(Role::Admin.has_scope(scope), "admin should have {}", scope.as_str());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_api_key() {
+        let pool = init_db().await.unwrap();
+
+        let (key, secret) = create_api_key(&pool, "integration", Role::ReadOnly).await.unwrap();
+        assert_eq!(key.name, "integration");
+        assert_eq!(key.role, Role::ReadOnly);
+        assert!(!key.revoked);
+
+        let found = find_api_key(&pool, &secret).await.unwrap().unwrap();
+        assert_eq!(found.id, key.id);
+        assert_eq!(found.role, Role::ReadOnly);
+
+        assert!(find_api_key(&pool, "wrong-secret").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_secret_is_not_stored_in_plaintext() {
+        let pool = init_db().await.unwrap();
+
+        let (key, secret) = create_api_key(&pool, "integration", Role::ReadOnly).await.unwrap();
+        let stored: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE id = ?")
+            .bind(key.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_ne!(stored, secret);
+        assert_eq!(stored, hash_api_key(&secret));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_api_key_is_not_found() {
+        let pool = init_db().await.unwrap();
+
+        let (key, secret) = create_api_key(&pool, "integration", Role::ReadOnly).await.unwrap();
+        sqlx::query("UPDATE api_keys SET revoked = 1 WHERE id = ?")
+            .bind(key.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(find_api_key(&pool, &secret).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_role_from_str() {
+        assert_eq!("read_only".parse::<Role>(), Ok(Role::ReadOnly));
+        assert_eq!("warehouse".parse::<Role>(), Ok(Role::Warehouse));
+        assert_eq!("admin".parse::<Role>(), Ok(Role::Admin));
+        assert!("root".parse::<Role>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_register_and_find_certificate_identity() {
+        let pool = init_db().await.unwrap();
+
+        register_certificate_identity(&pool, "scanner-01", "Scanner 1", Role::ReadOnly).await.unwrap();
+        // Re-registering updates the mapping
+        let identity = register_certificate_identity(&pool, "scanner-01", "Scanner 1", Role::Warehouse).await.unwrap();
+        assert_eq!(identity.role, Role::Warehouse);
+
+        let found = find_certificate_identity(&pool, "scanner-01").await.unwrap().unwrap();
+        assert_eq!(found.name, "Scanner 1");
+        assert_eq!(found.role, Role::Warehouse);
+
+        assert!(find_certificate_identity(&pool, "unknown").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use crate::utils::{Order, ShippingAddress, DEFAULT_CURRENCY};
+use crate::validators::{ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// One order as a spreadsheet row: the shipping address is spread over `ship_*` columns
+/// and the computed totals follow the fields a client can set.
+/// When importing, only `id`, `item`, `status` and `quantity` are required and the totals are ignored.
+pub struct OrderCsvRow {
+    pub id: u32,
+    pub item: String,
+    pub status: String,
+    pub quantity: u32,
+    #[serde(default)]
+    pub customer_id: Option<u32>,
+    #[serde(default)]
+    pub sku: Option<String>,
+    #[serde(default)]
+    pub unit_price_minor: i64,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub promo_code: Option<String>,
+    #[serde(default)]
+    pub ship_recipient: Option<String>,
+    #[serde(default)]
+    pub ship_line1: Option<String>,
+    #[serde(default)]
+    pub ship_line2: Option<String>,
+    #[serde(default)]
+    pub ship_city: Option<String>,
+    #[serde(default)]
+    pub ship_region: Option<String>,
+    #[serde(default)]
+    pub ship_postal_code: Option<String>,
+    #[serde(default)]
+    pub ship_country: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub subtotal_minor: i64,
+    #[serde(default, skip_deserializing)]
+    pub discount_minor: i64,
+    #[serde(default, skip_deserializing)]
+    pub total_minor: i64,
+}
+
+impl From<&Order> for OrderCsvRow {
+    fn from(order: &Order) -> Self {
+        let address = order.shipping_address.as_ref();
+        OrderCsvRow {
+            id: order.id,
+            item: order.item.clone(),
+            status: order.status.clone(),
+            quantity: order.quantity,
+            customer_id: order.customer_id,
+            sku: order.sku.clone(),
+            unit_price_minor: order.unit_price_minor,
+            currency: Some(order.currency.clone()),
+            promo_code: order.promo_code.clone(),
+            ship_recipient: address.map(|address| address.recipient.clone()),
+            ship_line1: address.map(|address| address.line1.clone()),
+            ship_line2: address.and_then(|address| address.line2.clone()),
+            ship_city: address.map(|address| address.city.clone()),
+            ship_region: address.and_then(|address| address.region.clone()),
+            ship_postal_code: address.map(|address| address.postal_code.clone()),
+            ship_country: address.map(|address| address.country.clone()),
+            subtotal_minor: order.subtotal_minor,
+            discount_minor: order.discount_minor,
+            total_minor: order.total_minor,
+        }
+    }
+}
+
+impl From<OrderCsvRow> for Order {
+    fn from(row: OrderCsvRow) -> Self {
+        let ship_fields = [
+            &row.ship_recipient, &row.ship_line1, &row.ship_line2, &row.ship_city,
+            &row.ship_region, &row.ship_postal_code, &row.ship_country,
+        ];
+        // A partly filled address is kept so validation can name the missing fields
+        let shipping_address = ship_fields.iter().any(|field| field.is_some()).then(|| ShippingAddress {
+            recipient: row.ship_recipient.unwrap_or_default(),
+            line1: row.ship_line1.unwrap_or_default(),
+            line2: row.ship_line2,
+            city: row.ship_city.unwrap_or_default(),
+            region: row.ship_region,
+            postal_code: row.ship_postal_code.unwrap_or_default(),
+            country: row.ship_country.unwrap_or_default(),
+        });
+
+        Order {
+            id: row.id,
+            item: row.item,
+            status: row.status,
+            quantity: row.quantity,
+            customer_id: row.customer_id,
+            sku: row.sku,
+            unit_price_minor: row.unit_price_minor,
+            currency: row.currency.unwrap_or_else(|| DEFAULT_CURRENCY.to_string()),
+            promo_code: row.promo_code,
+            shipping_address,
+            ..Default::default()
+        }
+    }
+}
+
+/// Columns an imported file must have
+const REQUIRED_IMPORT_COLUMNS: &[&str] = &["id", "item", "status", "quantity"];
+
+/// Order read from one record of an imported file, or why it could not be read
+#[derive(Debug)]
+pub struct ImportedRow {
+    /// Line of the file the record starts on; the header is line 1
+    pub line: u64,
+    pub order: Result<Order, ValidationError>,
+}
+
+/// Read orders from CSV with a header line. Fails as a whole only when the header is unusable;
+/// records that cannot be read are returned with the reason.
+pub fn parse_orders_csv(data: &str) -> Result<Vec<ImportedRow>, ValidationError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data.as_bytes());
+
+    let headers = reader.headers()
+        .map_err(|e| ValidationError {
+            error: format!("Invalid CSV header: {}", e),
+            field: Some("header".to_string()),
+        })?
+        .clone();
+    let missing: Vec<&str> = REQUIRED_IMPORT_COLUMNS.iter()
+        .copied()
+        .filter(|column| !headers.iter().any(|header| header == *column))
+        .collect();
+    if !missing.is_empty() {
+        return Err(ValidationError {
+            error: format!("CSV header is missing the columns: {}", missing.join(", ")),
+            field: Some("header".to_string()),
+        });
+    }
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let line = reader.position().line();
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {
+                let line = record.position().map_or(line, |position| position.line());
+                let order = record.deserialize::<OrderCsvRow>(Some(&headers))
+                    .map(Order::from)
+                    .map_err(|e| row_error(&headers, e));
+                rows.push(ImportedRow { line, order });
+            }
+            // Malformed records, e.g. with an unterminated quote, are reported like invalid ones
+            Err(e) => rows.push(ImportedRow { line, order: Err(row_error(&headers, e)) }),
+        }
+    }
+    Ok(rows)
+}
+
+/// Describe a record that could not be read, naming its column when known
+fn row_error(headers: &csv::StringRecord, e: csv::Error) -> ValidationError {
+    match e.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => ValidationError {
+            error: err.kind().to_string(),
+            field: err.field()
+                .and_then(|index| headers.get(index as usize))
+                .map(str::to_string),
+        },
+        csv::ErrorKind::UnequalLengths { expected_len, len, .. } => ValidationError {
+            error: format!("Row has {} fields, expected {}", len, expected_len),
+            field: None,
+        },
+        _ => ValidationError {
+            error: e.to_string(),
+            field: None,
+        },
+    }
+}
+
+/// Header line of order CSV files, matching the fields of `OrderCsvRow`
+pub const ORDER_CSV_COLUMNS: &[&str] = &[
+    "id", "item", "status", "quantity", "customer_id", "sku", "unit_price_minor", "currency", "promo_code",
+    "ship_recipient", "ship_line1", "ship_line2", "ship_city", "ship_region", "ship_postal_code", "ship_country",
+    "subtotal_minor", "discount_minor", "total_minor",
+];
+
+/// Header line of an order CSV file
+pub fn order_csv_header() -> Result<Vec<u8>, ApiError> {
+    write_csv(|writer| writer.write_record(ORDER_CSV_COLUMNS))
+}
+
+/// One order as a CSV line, quoting fields that contain commas, quotes or newlines.
+/// Lines follow `order_csv_header` one order at a time, so exports are never built whole in memory.
+pub fn order_csv_line(order: &Order) -> Result<Vec<u8>, ApiError> {
+    write_csv(|writer| writer.serialize(OrderCsvRow::from(order)))
+}
+
+fn write_csv(write: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> Result<(), csv::Error>) -> Result<Vec<u8>, ApiError> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    write(&mut writer).map_err(csv_write_error)?;
+    writer.into_inner().map_err(|e| csv_write_error(e.into_error().into()))
+}
+
+fn csv_write_error(e: csv::Error) -> ApiError {
+    eprintln!("CSV error in write_csv: {}", e);
+    ApiError::Server(ServerError {
+        error: "Export error".to_string(),
+        message: "Failed to write orders as CSV".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ShippingAddress;
+
+    fn header() -> String {
+        format!("{}\n", ORDER_CSV_COLUMNS.join(","))
+    }
+
+    fn orders_to_csv(orders: &[Order]) -> String {
+        let mut csv = order_csv_header().unwrap();
+        for order in orders {
+            csv.extend(order_csv_line(order).unwrap());
+        }
+        String::from_utf8(csv).unwrap()
+    }
+
+    #[test]
+    fn test_orders_to_csv_quotes_special_characters() {
+        let order = Order {
+            id: 1,
+            item: "Bolts, \"M8\"\nzinc plated".to_string(),
+            status: "pending".to_string(),
+            quantity: 3,
+            unit_price_minor: 250,
+            subtotal_minor: 750,
+            total_minor: 750,
+            shipping_address: Some(ShippingAddress {
+                recipient: "Ada Lovelace".to_string(),
+                line1: "12 St James's Square".to_string(),
+                line2: None,
+                city: "London".to_string(),
+                region: None,
+                postal_code: "SW1Y 4JH".to_string(),
+                country: "GB".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let csv = orders_to_csv(&[order]);
+        assert_eq!(
+            csv,
+            format!(
+                "{}1,\"Bolts, \"\"M8\"\"\nzinc plated\",pending,3,,,250,USD,,Ada Lovelace,12 St James's Square,,London,,SW1Y 4JH,GB,750,0,750\n",
+                header()
+            )
+        );
+    }
+
+    #[test]
+    fn test_exported_orders_can_be_imported() {
+        let order = Order {
+            id: 7,
+            item: "Gears, brass".to_string(),
+            status: "pending".to_string(),
+            quantity: 2,
+            unit_price_minor: 995,
+            currency: "EUR".to_string(),
+            subtotal_minor: 1990,
+            total_minor: 1990,
+            ..Default::default()
+        };
+        let csv = orders_to_csv(std::slice::from_ref(&order));
+
+        let rows = parse_orders_csv(&csv).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].line, 2);
+        let imported = rows[0].order.as_ref().unwrap();
+        assert_eq!((imported.id, imported.item.as_str(), imported.currency.as_str()), (7, "Gears, brass", "EUR"));
+        assert_eq!(imported.unit_price_minor, 995);
+        // Totals are computed by the server, never imported
+        assert_eq!(imported.total_minor, 0);
+        assert!(imported.shipping_address.is_none());
+    }
+
+    #[test]
+    fn test_parse_orders_csv_reports_rows() {
+        let csv = "id,item,status,quantity,ship_city\n\
+                   1,Widget,pending,2,\n\
+                   2,Gadget,pending,many,\n\
+                   3,\"Multi\nline\",pending,1,Leeds\n\
+                   4,Sprocket,pending\n";
+
+        let rows = parse_orders_csv(csv).unwrap();
+        let lines: Vec<u64> = rows.iter().map(|row| row.line).collect();
+        assert_eq!(lines, [2, 3, 4, 6]);
+
+        assert_eq!(rows[0].order.as_ref().unwrap().currency, "USD");
+        let error = rows[1].order.as_ref().unwrap_err();
+        assert_eq!(error.field, Some("quantity".to_string()));
+        let address = rows[2].order.as_ref().unwrap().shipping_address.as_ref().unwrap();
+        assert_eq!((address.city.as_str(), address.country.as_str()), ("Leeds", ""));
+        assert!(rows[3].order.is_err());
+    }
+
+    #[test]
+    fn test_parse_orders_csv_requires_columns() {
+        let error = parse_orders_csv("id,item\n1,Widget\n").unwrap_err();
+        assert_eq!(error.field, Some("header".to_string()));
+        assert!(error.error.contains("status, quantity"));
+    }
+
+    #[test]
+    fn test_order_csv_header() {
+        let csv = String::from_utf8(order_csv_header().unwrap()).unwrap();
+        assert_eq!(csv, header());
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use crate::utils::DbPool;
+use crate::validators::{ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema, async_graphql::SimpleObject)]
+/// Customer placing orders
+pub struct Customer {
+    /// Unique identifier for the customer
+    pub id: u32,
+    /// Full name of the customer
+    pub name: String,
+    /// Contact email address, unique across customers
+    pub email: String,
+}
+
+/// Get all customers from the database, ordered by ID
+pub async fn get_all_customers(pool: &DbPool) -> Result<Vec<Customer>, ApiError> {
+    sqlx::query_as::<_, Customer>("SELECT id, name, email FROM customers ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_all_customers: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve customers".to_string(),
+            })
+        })
+}
+
+/// Get a specific customer by ID
+pub async fn get_customer_by_id<'e, E>(executor: E, customer_id: u32) -> Result<Option<Customer>, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_as::<_, Customer>("SELECT id, name, email FROM customers WHERE id = ?")
+        .bind(customer_id)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_customer_by_id: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve customer".to_string(),
+            })
+        })
+}
+
+/// Error for an order referencing a customer that does not exist
+pub fn unknown_customer(customer_id: u32) -> ApiError {
+    ApiError::Validation(ValidationError {
+        error: format!("Customer with ID {} does not exist", customer_id),
+        field: Some("customer_id".to_string()),
+    })
+}
+
+/// Reject an email address already used by a customer other than `customer_id`
+async fn ensure_email_available(pool: &DbPool, email: &str, customer_id: u32) -> Result<(), ApiError> {
+    let existing: Option<u32> = sqlx::query_scalar("SELECT id FROM customers WHERE email = ? AND id != ?")
+        .bind(email)
+        .bind(customer_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in ensure_email_available: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to check customer email".to_string(),
+            })
+        })?;
+
+    if existing.is_some() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("A customer with email {} already exists", email),
+            field: Some("email".to_string()),
+        }));
+    }
+    Ok(())
+}
+
+/// Create a new customer in the database
+pub async fn create_customer(pool: &DbPool, customer: &Customer) -> Result<Customer, ApiError> {
+    if get_customer_by_id(pool, customer.id).await?.is_some() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Customer with ID {} already exists", customer.id),
+            field: Some("id".to_string()),
+        }));
+    }
+    ensure_email_available(pool, &customer.email, customer.id).await?;
+
+    sqlx::query("INSERT INTO customers (id, name, email) VALUES (?, ?, ?)")
+        .bind(customer.id)
+        .bind(&customer.name)
+        .bind(&customer.email)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in create_customer: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create customer".to_string(),
+            })
+        })?;
+
+    Ok(customer.clone())
+}
+
+/// Update an existing customer in the database
+pub async fn update_customer(pool: &DbPool, customer_id: u32, customer: &Customer) -> Result<Customer, ApiError> {
+    ensure_email_available(pool, &customer.email, customer_id).await?;
+
+    let result = sqlx::query("UPDATE customers SET name = ?, email = ? WHERE id = ?")
+        .bind(&customer.name)
+        .bind(&customer.email)
+        .bind(customer_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in update_customer: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to update customer".to_string(),
+            })
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Customer not found".to_string()));
+    }
+
+    let mut updated_customer = customer.clone();
+    updated_customer.id = customer_id;
+    Ok(updated_customer)
+}
+
+/// Delete a customer; customers who still have orders cannot be deleted
+pub async fn delete_customer(pool: &DbPool, customer_id: u32) -> Result<Customer, ApiError> {
+    let customer = get_customer_by_id(pool, customer_id).await?
+        .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+
+    let order_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE customer_id = ?")
+        .bind(customer_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_customer: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete customer".to_string(),
+            })
+        })?;
+
+    if order_count > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Customer {} still has {} order(s)",
+            customer_id, order_count
+        )));
+    }
+
+    sqlx::query("DELETE FROM customers WHERE id = ?")
+        .bind(customer_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_customer: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete customer".to_string(),
+            })
+        })?;
+
+    Ok(customer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PaymentConfig;
+    use crate::utils::{create_order, init_db, Order};
+
+    fn customer(id: u32, email: &str) -> Customer {
+        Customer {
+            id,
+            name: "Ada Lovelace".to_string(),
+            email: email.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_customer_crud() {
+        let pool = init_db().await.unwrap();
+
+        create_customer(&pool, &customer(1, "ada@example.com")).await.unwrap();
+        assert_eq!(get_all_customers(&pool).await.unwrap().len(), 1);
+
+        let mut renamed = customer(1, "ada@example.org");
+        renamed.name = "Augusta Ada King".to_string();
+        update_customer(&pool, 1, &renamed).await.unwrap();
+
+        let stored = get_customer_by_id(&pool, 1).await.unwrap().unwrap();
+        assert_eq!(stored.name, "Augusta Ada King");
+        assert_eq!(stored.email, "ada@example.org");
+
+        delete_customer(&pool, 1).await.unwrap();
+        assert!(get_customer_by_id(&pool, 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_email_rejected() {
+        let pool = init_db().await.unwrap();
+        create_customer(&pool, &customer(1, "ada@example.com")).await.unwrap();
+
+        match create_customer(&pool, &customer(2, "ada@example.com")).await.unwrap_err() {
+            ApiError::Validation(err) => assert_eq!(err.field, Some("email".to_string())),
+            _ => panic!("Expected validation error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_customer_with_orders_cannot_be_deleted() {
+        let pool = init_db().await.unwrap();
+        create_customer(&pool, &customer(1, "ada@example.com")).await.unwrap();
+        create_order(&pool, &Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "pending".to_string(),
+            quantity: 1,
+            customer_id: Some(1),
+            ..Default::default()
+        }, &PaymentConfig::default()).await.unwrap();
+
+        assert!(matches!(delete_customer(&pool, 1).await, Err(ApiError::Conflict(_))));
+    }
+}
@@ -1,12 +1,29 @@
-use sqlx::{Pool, Sqlite, SqlitePool};
+use std::{collections::HashMap, str::FromStr};
+use chrono::{Days, NaiveDate, Utc};
+use futures_util::{stream, Stream, StreamExt};
+use sqlx::{Executor, Pool, QueryBuilder, Sqlite, SqliteConnection, SqlitePool, Transaction};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::types::Json;
 use serde::{Deserialize, Serialize};
-use crate::validators::{ApiError, ServerError};
+use tokio::sync::mpsc;
+use crate::config::PaymentConfig;
+use crate::utils::{
+    ensure_shipping_paid, get_customer_by_id, get_product_by_sku, get_promotion_by_code, promotion_discount, publish_order_changes,
+    record_order_change, redeem_promotion, release_stock, reserve_stock, unknown_customer, unknown_product,
+    OrderChange, WebhookEvent
+};
+use crate::validators::{ApiError, ServerError, ValidationError};
 
-// Database configuration  
-const DATABASE_URL: &str = "sqlite::memory:";
+/// Database used when `DATABASE_URL` is not set: an in-memory SQLite database
+pub const DEFAULT_DATABASE_URL: &str = "sqlite::memory:";
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
-/// Order structure representing a customer order
+/// Rows read ahead of a slow consumer of `stream_orders`
+const STREAM_BUFFER_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema, async_graphql::SimpleObject)]
+#[graphql(complex)]
+/// Order structure representing a customer order.
+/// Monetary amounts are integers in the minor unit of `currency` (e.g. cents), never floats.
 pub struct Order {
     /// Unique identifier for the order
     pub id: u32,
@@ -16,36 +33,400 @@ pub struct Order {
     pub status: String,
     /// Quantity of items ordered
     pub quantity: u32,
+    /// Customer who placed the order, if known
+    #[serde(default)]
+    pub customer_id: Option<u32>,
+    /// SKU of the catalog product ordered; stock is reserved for it unless the order is cancelled
+    #[serde(default)]
+    pub sku: Option<String>,
+    /// Price of one unit in minor units of `currency`
+    #[serde(default)]
+    pub unit_price_minor: i64,
+    /// ISO 4217 code of the currency the order is priced in
+    #[serde(default = "default_currency")]
+    #[schema(example = "USD")]
+    pub currency: String,
+    /// Promotion code applied when the order was created
+    #[serde(default)]
+    pub promo_code: Option<String>,
+    /// Address the order ships to; required before a shipment can be recorded
+    #[serde(default)]
+    #[sqlx(json(nullable))]
+    pub shipping_address: Option<ShippingAddress>,
+    /// Unit price times quantity, computed by the server
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub subtotal_minor: i64,
+    /// Discount granted by `promo_code`, computed by the server
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub discount_minor: i64,
+    /// Subtotal less discount, computed by the server
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub total_minor: i64,
+}
+
+/// Currency assumed for orders that do not specify one
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+impl Default for Order {
+    fn default() -> Self {
+        Order {
+            id: 0,
+            item: String::new(),
+            status: String::new(),
+            quantity: 0,
+            customer_id: None,
+            sku: None,
+            unit_price_minor: 0,
+            currency: default_currency(),
+            promo_code: None,
+            shipping_address: None,
+            subtotal_minor: 0,
+            discount_minor: 0,
+            total_minor: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema, async_graphql::SimpleObject, async_graphql::InputObject)]
+#[graphql(input_name = "ShippingAddressInput")]
+/// Postal address an order is shipped to
+pub struct ShippingAddress {
+    /// Name of the person or company receiving the parcel
+    pub recipient: String,
+    /// Street address
+    pub line1: String,
+    /// Apartment, suite, building or floor
+    #[serde(default)]
+    pub line2: Option<String>,
+    /// City or town
+    pub city: String,
+    /// State, province or territory; required for US, CA and AU
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Postal code in the format used by `country`
+    #[schema(example = "SW1Y 4JH")]
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    #[schema(example = "GB")]
+    pub country: String,
 }
 
+#[derive(Debug, Clone, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Optional filters applied when listing orders
+pub struct OrderFilter {
+    /// Only return orders with this status
+    pub status: Option<String>,
+    /// Only return orders placed by this customer
+    pub customer_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+/// Summary of all orders, computed on request
+pub struct OrderStats {
+    /// Number of orders
+    pub orders: i64,
+    /// Quantity ordered across all orders
+    pub quantity: i64,
+    /// Orders and quantity per status, by status name
+    pub by_status: Vec<StatusStats>,
+    /// Items with the largest quantity ordered, largest first
+    pub top_items: Vec<ItemStats>,
+    /// Orders created and delivered per UTC day, oldest first, including days without any
+    pub daily: Vec<DailyOrderStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Orders currently in one status
+pub struct StatusStats {
+    #[schema(example = "pending")]
+    pub status: String,
+    pub orders: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Orders for one item
+pub struct ItemStats {
+    #[schema(example = "Widget")]
+    pub item: String,
+    pub orders: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Orders created and delivered on one UTC day, taken from the order change log
+pub struct DailyOrderStats {
+    #[schema(value_type = String, format = Date, example = "2024-01-15")]
+    pub date: NaiveDate,
+    /// Orders created that day
+    pub created: i64,
+    /// Orders that reached `delivered` that day
+    pub delivered: i64,
+}
+
+/// Columns selected whenever an order is read back, including the computed totals
+const ORDER_COLUMNS: &str = "id, item, status, quantity, customer_id, sku, unit_price_minor, currency, \
+    promo_code, discount_minor, shipping_address, \
+    unit_price_minor * quantity AS subtotal_minor, \
+    unit_price_minor * quantity - discount_minor AS total_minor";
+
 pub type DbPool = Pool<Sqlite>;
 
-/// Initialize the database connection pool and create tables
+/// Schema migrations, applied in order by `init_db`.
+/// The migration version stored in `PRAGMA user_version` is the number of entries applied.
+const MIGRATIONS: &[&str] = &[
+    // 1: orders table
+    r#"
+    CREATE TABLE IF NOT EXISTS orders (
+        id INTEGER PRIMARY KEY,
+        item TEXT NOT NULL,
+        status TEXT NOT NULL,
+        quantity INTEGER NOT NULL
+    )
+    "#,
+    // 2: hashed API keys used for authentication
+    r#"
+    CREATE TABLE IF NOT EXISTS api_keys (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL UNIQUE,
+        revoked BOOLEAN NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 3: role attached to each API key; existing keys get the least privilege
+    r#"
+    ALTER TABLE api_keys ADD COLUMN role TEXT NOT NULL DEFAULT 'read_only'
+    "#,
+    // 4: TLS client certificate subjects mapped to caller identities
+    r#"
+    CREATE TABLE IF NOT EXISTS client_certificates (
+        subject TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        role TEXT NOT NULL,
+        revoked BOOLEAN NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 5: customers placing orders
+    r#"
+    CREATE TABLE IF NOT EXISTS customers (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        email TEXT NOT NULL UNIQUE,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 6: optional link from each order to the customer who placed it
+    r#"
+    ALTER TABLE orders ADD COLUMN customer_id INTEGER REFERENCES customers(id)
+    "#,
+    // 7: product catalog with stock on hand
+    r#"
+    CREATE TABLE IF NOT EXISTS products (
+        sku TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        stock INTEGER NOT NULL CHECK (stock >= 0),
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 8: optional link from each order to the product it reserves stock for
+    r#"
+    ALTER TABLE orders ADD COLUMN sku TEXT REFERENCES products(sku)
+    "#,
+    // 9: unit price in minor units; existing orders are unpriced
+    r#"
+    ALTER TABLE orders ADD COLUMN unit_price_minor INTEGER NOT NULL DEFAULT 0 CHECK (unit_price_minor >= 0)
+    "#,
+    // 10: ISO 4217 currency of the order's prices
+    r#"
+    ALTER TABLE orders ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD'
+    "#,
+    // 11: discount codes and their redemption counts
+    r#"
+    CREATE TABLE IF NOT EXISTS promotions (
+        code TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        percent_off INTEGER,
+        amount_off_minor INTEGER,
+        currency TEXT,
+        buy_quantity INTEGER,
+        free_quantity INTEGER,
+        starts_at TEXT,
+        ends_at TEXT,
+        max_redemptions INTEGER,
+        redemptions INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 12: promotion applied to each order
+    r#"
+    ALTER TABLE orders ADD COLUMN promo_code TEXT REFERENCES promotions(code)
+    "#,
+    // 13: discount recorded when the promotion was applied
+    r#"
+    ALTER TABLE orders ADD COLUMN discount_minor INTEGER NOT NULL DEFAULT 0
+    "#,
+    // 14: shipping address stored as a JSON document
+    r#"
+    ALTER TABLE orders ADD COLUMN shipping_address TEXT
+    "#,
+    // 15: shipments recorded against orders
+    r#"
+    CREATE TABLE IF NOT EXISTS shipments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id INTEGER NOT NULL REFERENCES orders(id),
+        carrier TEXT NOT NULL,
+        tracking_number TEXT NOT NULL,
+        shipped_at TEXT NOT NULL,
+        delivered_at TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 16: payments taken for orders through the payment gateway
+    r#"
+    CREATE TABLE IF NOT EXISTS payments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        order_id INTEGER NOT NULL REFERENCES orders(id),
+        status TEXT NOT NULL,
+        amount_minor INTEGER NOT NULL CHECK (amount_minor > 0),
+        currency TEXT NOT NULL,
+        refunded_minor INTEGER NOT NULL DEFAULT 0 CHECK (refunded_minor <= amount_minor),
+        gateway_reference TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 17: endpoints subscribed to order events
+    r#"
+    CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        url TEXT NOT NULL,
+        event_types TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 18: events waiting to be delivered to a subscription, written with the change that caused them
+    r#"
+    CREATE TABLE IF NOT EXISTS webhook_outbox (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        subscription_id INTEGER NOT NULL REFERENCES webhook_subscriptions(id),
+        event_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )
+    "#,
+    // 19: every delivery attempt and its outcome
+    r#"
+    CREATE TABLE IF NOT EXISTS webhook_deliveries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        outbox_id INTEGER NOT NULL REFERENCES webhook_outbox(id),
+        subscription_id INTEGER NOT NULL REFERENCES webhook_subscriptions(id),
+        event_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        attempt INTEGER NOT NULL,
+        response_status INTEGER,
+        error TEXT,
+        succeeded BOOLEAN NOT NULL,
+        attempted_at TEXT NOT NULL
+    )
+    "#,
+    // 20: sequenced log of order changes, replayed to event stream subscribers that reconnect
+    r#"
+    CREATE TABLE IF NOT EXISTS order_changes (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        event TEXT NOT NULL,
+        order_id INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        order_data TEXT NOT NULL,
+        previous_status TEXT,
+        changed_at TEXT NOT NULL
+    )
+    "#,
+];
+
+/// Initialize a connection pool to the default in-memory database and apply pending migrations
 pub async fn init_db() -> Result<DbPool, sqlx::Error> {
+    connect_db(DEFAULT_DATABASE_URL).await
+}
+
+/// Initialize a connection pool to the SQLite database at `url` and apply pending migrations
+pub async fn connect_db(url: &str) -> Result<DbPool, sqlx::Error> {
     // Create the database file if it doesn't exist
-    let pool = SqlitePool::connect(DATABASE_URL).await?;
+    let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
     
-    // Create the orders table
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS orders (
-            id INTEGER PRIMARY KEY,
-            item TEXT NOT NULL,
-            status TEXT NOT NULL,
-            quantity INTEGER NOT NULL
-        )
-        "#
-    )
-    .execute(&pool)
-    .await?;
+    run_migrations(&pool).await?;
     
     println!("Database initialized successfully");
     Ok(pool)
 }
 
-/// Get all orders from the database
-pub async fn get_all_orders(pool: &DbPool) -> Result<Vec<Order>, ApiError> {
-    let orders = sqlx::query_as::<_, Order>("SELECT id, item, status, quantity FROM orders")
+/// Apply every migration newer than the database's current version
+async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
+    let current: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await?;
+    
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration).execute(&mut *tx).await?;
+        // PRAGMA does not accept bound parameters
+        sqlx::query(&format!("PRAGMA user_version = {}", index + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    
+    Ok(())
+}
+
+/// Get the migration version the database schema is currently at
+pub async fn get_migration_version(pool: &DbPool) -> Result<i64, ApiError> {
+    sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_migration_version: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to read migration version".to_string(),
+            })
+        })
+}
+
+/// Perform a cheap round trip to verify the database is reachable
+pub async fn ping_db(pool: &DbPool) -> Result<(), ApiError> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in ping_db: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Database is not reachable".to_string(),
+            })
+        })?;
+    
+    Ok(())
+}
+
+/// Get all orders matching `filter` from the database, ordered by ID
+pub async fn get_all_orders(pool: &DbPool, filter: &OrderFilter) -> Result<Vec<Order>, ApiError> {
+    let orders = orders_query(filter, None)
+        .build_query_as::<Order>()
         .fetch_all(pool)
         .await
         .map_err(|e| {
@@ -59,11 +440,143 @@ pub async fn get_all_orders(pool: &DbPool) -> Result<Vec<Order>, ApiError> {
     Ok(orders)
 }
 
-/// Get a specific order by ID
-pub async fn get_order_by_id(pool: &DbPool, order_id: u32) -> Result<Option<Order>, ApiError> {
-    let order = sqlx::query_as::<_, Order>("SELECT id, item, status, quantity FROM orders WHERE id = ?")
+/// Stream the orders matching `filter` one row at a time, in ID order.
+/// Rows are read only as fast as the stream is consumed, so memory stays flat however many orders match;
+/// the read holds one pooled connection until the stream is finished or dropped.
+/// A database error is yielded once and ends the stream.
+pub fn stream_orders(pool: DbPool, filter: OrderFilter) -> impl Stream<Item = Result<Order, ApiError>> {
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+    tokio::spawn(async move {
+        let mut query = orders_query(&filter, None);
+        let mut rows = query.build_query_as::<Order>().fetch(&pool);
+        while let Some(row) = rows.next().await {
+            let row = row.map_err(|e| {
+                eprintln!("Database error in stream_orders: {}", e);
+                ApiError::Server(ServerError {
+                    error: "Database error".to_string(),
+                    message: "Failed to retrieve orders".to_string(),
+                })
+            });
+            let failed = row.is_err();
+            // Sending fails once the consumer is dropped, e.g. when the client disconnects
+            if sender.send(row).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|row| (row, receiver))
+    })
+}
+
+/// Get up to `limit` orders matching `filter` with IDs after `after_id`, in ID order
+pub async fn get_orders_page(
+    pool: &DbPool,
+    filter: &OrderFilter,
+    after_id: Option<u32>,
+    limit: u32,
+) -> Result<Vec<Order>, ApiError> {
+    let mut query = orders_query(filter, after_id);
+    query.push(" LIMIT ").push_bind(limit);
+
+    query.build_query_as::<Order>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_orders_page: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve orders".to_string(),
+            })
+        })
+}
+
+/// Query selecting the orders matching `filter` with IDs after `after_id`, in ID order
+fn orders_query(filter: &OrderFilter, after_id: Option<u32>) -> QueryBuilder<'_, Sqlite> {
+    let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM orders WHERE 1 = 1", ORDER_COLUMNS));
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(customer_id) = filter.customer_id {
+        query.push(" AND customer_id = ").push_bind(customer_id);
+    }
+    if let Some(after_id) = after_id {
+        query.push(" AND id > ").push_bind(after_id);
+    }
+    query.push(" ORDER BY id");
+    query
+}
+
+/// Summarize the orders with SQL aggregates: totals, a breakdown by status, the `top_items` items with the
+/// largest quantity ordered, and daily creations and deliveries over the last `days` days including today
+pub async fn get_order_stats(pool: &DbPool, top_items: u32, days: u32) -> Result<OrderStats, ApiError> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("Database error in get_order_stats: {}", e);
+        ApiError::Server(ServerError {
+            error: "Database error".to_string(),
+            message: "Failed to compute order statistics".to_string(),
+        })
+    };
+
+    let (orders, quantity): (i64, i64) = sqlx::query_as("SELECT COUNT(*), COALESCE(SUM(quantity), 0) FROM orders")
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+
+    let by_status = sqlx::query_as::<_, StatusStats>(
+        "SELECT status, COUNT(*) AS orders, SUM(quantity) AS quantity FROM orders GROUP BY status ORDER BY status"
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let top_items = sqlx::query_as::<_, ItemStats>(
+        "SELECT item, COUNT(*) AS orders, SUM(quantity) AS quantity FROM orders \
+         GROUP BY item ORDER BY quantity DESC, item LIMIT ?"
+    )
+        .bind(top_items)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    // Orders predating the change log have no creation or delivery day
+    let today = Utc::now().date_naive();
+    let first_day = today - Days::new(u64::from(days.saturating_sub(1)));
+    let active_days = sqlx::query_as::<_, DailyOrderStats>(
+        "SELECT date(changed_at) AS date, \
+         COUNT(DISTINCT CASE WHEN event = 'order.created' THEN order_id END) AS created, \
+         COUNT(DISTINCT CASE WHEN event = 'order.status_changed' AND status = 'delivered' THEN order_id END) AS delivered \
+         FROM order_changes WHERE date(changed_at) >= ? GROUP BY date(changed_at)"
+    )
+        .bind(first_day)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let daily = daily_series(first_day, today, active_days);
+    Ok(OrderStats { orders, quantity, by_status, top_items, daily })
+}
+
+/// Days from `first_day` through `today`, oldest first, with zeros for the days missing from `active_days`
+pub(crate) fn daily_series(first_day: NaiveDate, today: NaiveDate, active_days: Vec<DailyOrderStats>) -> Vec<DailyOrderStats> {
+    let mut active_days: HashMap<NaiveDate, DailyOrderStats> = active_days.into_iter()
+        .map(|day| (day.date, day))
+        .collect();
+    first_day.iter_days()
+        .take_while(|date| *date <= today)
+        .map(|date| active_days.remove(&date).unwrap_or(DailyOrderStats { date, created: 0, delivered: 0 }))
+        .collect()
+}
+
+/// Get a specific order by ID, using either the pool or an open transaction
+pub async fn get_order_by_id<'e, E>(executor: E, order_id: u32) -> Result<Option<Order>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let order = sqlx::query_as::<_, Order>(&format!("SELECT {} FROM orders WHERE id = ?", ORDER_COLUMNS))
         .bind(order_id)
-        .fetch_optional(pool)
+        .fetch_optional(executor)
         .await
         .map_err(|e| {
             eprintln!("Database error in get_order_by_id: {}", e);
@@ -76,22 +589,121 @@ pub async fn get_order_by_id(pool: &DbPool, order_id: u32) -> Result<Option<Orde
     Ok(order)
 }
 
-/// Create a new order in the database
-pub async fn create_order(pool: &DbPool, order: &Order) -> Result<Order, ApiError> {
+/// SKU whose stock the order holds; cancelled orders hold none
+pub(crate) fn reserved_sku(order: &Order) -> Option<&str> {
+    match order.status.as_str() {
+        "cancelled" => None,
+        _ => order.sku.as_deref(),
+    }
+}
+
+pub(crate) async fn begin_transaction(pool: &DbPool, function: &str) -> Result<Transaction<'static, Sqlite>, ApiError> {
+    // Take the write lock up front so concurrent transactions queue instead of deadlocking
+    // when they upgrade from reading to writing
+    pool.begin_with("BEGIN IMMEDIATE").await.map_err(|e| {
+        eprintln!("Database error in {}: {}", function, e);
+        ApiError::Server(ServerError {
+            error: "Database error".to_string(),
+            message: "Failed to start transaction".to_string(),
+        })
+    })
+}
+
+pub(crate) async fn commit_transaction(tx: Transaction<'static, Sqlite>, function: &str) -> Result<(), ApiError> {
+    tx.commit().await.map_err(|e| {
+        eprintln!("Database error in {}: {}", function, e);
+        ApiError::Server(ServerError {
+            error: "Database error".to_string(),
+            message: "Failed to commit transaction".to_string(),
+        })
+    })
+}
+
+/// Create a new order in the database, reserving stock for its SKU in the same transaction
+pub async fn create_order(pool: &DbPool, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+    let mut tx = begin_transaction(pool, "create_order").await?;
+    let (created, change) = insert_order(&mut tx, order, payments).await?;
+    commit_transaction(tx, "create_order").await?;
+    publish_order_changes([change]);
+    Ok(created)
+}
+
+/// Create several orders in one transaction, all or none.
+/// The outer error reports a database failure; the inner one the position of the first order that
+/// could not be created and why. With `dry_run` every check runs but the transaction is rolled back.
+pub async fn create_orders(
+    pool: &DbPool,
+    orders: &[Order],
+    dry_run: bool,
+    payments: &PaymentConfig,
+) -> Result<Result<Vec<Order>, (usize, ApiError)>, ApiError> {
+    let mut tx = begin_transaction(pool, "create_orders").await?;
+    let mut created = Vec::with_capacity(orders.len());
+    let mut changes = Vec::with_capacity(orders.len());
+    for (index, order) in orders.iter().enumerate() {
+        match insert_order(&mut tx, order, payments).await {
+            Ok((order, change)) => {
+                created.push(order);
+                changes.push(change);
+            }
+            // Dropping the transaction rolls back the orders inserted so far
+            Err(err) => return Ok(Err((index, err))),
+        }
+    }
+    
+    if !dry_run {
+        commit_transaction(tx, "create_orders").await?;
+        publish_order_changes(changes);
+    }
+    Ok(Ok(created))
+}
+
+/// Insert an order as part of the caller's transaction, reserving its stock and redeeming its
+/// promotion. Returns the order with its computed totals and the change to publish once committed.
+async fn insert_order(
+    conn: &mut SqliteConnection,
+    order: &Order,
+    payments: &PaymentConfig,
+) -> Result<(Order, OrderChange), ApiError> {
     // Check if order with this ID already exists
-    if let Some(_) = get_order_by_id(pool, order.id).await? {
-        return Err(ApiError::Validation(crate::validators::ValidationError {
+    if get_order_by_id(&mut *conn, order.id).await?.is_some() {
+        return Err(ApiError::Validation(ValidationError {
             error: format!("Order with ID {} already exists", order.id),
             field: Some("id".to_string()),
         }));
     }
     
-    sqlx::query("INSERT INTO orders (id, item, status, quantity) VALUES (?, ?, ?, ?)")
+    ensure_customer_exists(&mut *conn, order.customer_id).await?;
+    // A new order has no payment yet, so it cannot start out shipped when a captured one is required
+    ensure_shipping_paid(&mut *conn, payments, order.id, None, &order.status).await?;
+    
+    if let Some(sku) = reserved_sku(order) {
+        reserve_stock(&mut *conn, sku, order.quantity).await?;
+    } else {
+        ensure_product_exists(&mut *conn, order.sku.as_deref()).await?;
+    }
+    
+    let discount = match &order.promo_code {
+        Some(code) => redeem_promotion(&mut *conn, code, order).await?,
+        None => 0,
+    };
+    
+    sqlx::query(
+        "INSERT INTO orders (id, item, status, quantity, customer_id, sku, unit_price_minor, currency, \
+         promo_code, discount_minor, shipping_address) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
         .bind(order.id)
         .bind(&order.item)
         .bind(&order.status)
         .bind(order.quantity)
-        .execute(pool)
+        .bind(order.customer_id)
+        .bind(&order.sku)
+        .bind(order.unit_price_minor)
+        .bind(&order.currency)
+        .bind(&order.promo_code)
+        .bind(discount)
+        .bind(order.shipping_address.as_ref().map(Json))
+        .execute(&mut *conn)
         .await
         .map_err(|e| {
             eprintln!("Database error in create_order: {}", e);
@@ -101,17 +713,66 @@ pub async fn create_order(pool: &DbPool, order: &Order) -> Result<Order, ApiErro
             })
         })?;
     
-    Ok(order.clone())
+    // Read the order back so the response carries the computed totals
+    let created = get_order_by_id(&mut *conn, order.id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    let change = record_order_change(&mut *conn, WebhookEvent::Created, &created, None).await?;
+    Ok((created, change))
 }
 
-/// Update an existing order in the database
-pub async fn update_order(pool: &DbPool, order_id: u32, order: &Order) -> Result<Order, ApiError> {
-    let result = sqlx::query("UPDATE orders SET item = ?, status = ?, quantity = ? WHERE id = ?")
+/// Update an existing order in the database.
+/// Stock held by the previous version of the order is released before the new version reserves its own.
+/// The promotion applied at creation stays attached and its discount is recalculated.
+pub async fn update_order(
+    pool: &DbPool,
+    order_id: u32,
+    order: &Order,
+    payments: &PaymentConfig,
+) -> Result<Order, ApiError> {
+    let mut tx = begin_transaction(pool, "update_order").await?;
+    ensure_customer_exists(&mut tx, order.customer_id).await?;
+    
+    let current = get_order_by_id(&mut *tx, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    if order.promo_code.is_some() && order.promo_code != current.promo_code {
+        return Err(ApiError::Validation(ValidationError {
+            error: "Promo code cannot be changed after the order is created".to_string(),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+    ensure_shipping_paid(&mut tx, payments, order_id, Some(&current.status), &order.status).await?;
+    if let Some(sku) = reserved_sku(&current) {
+        release_stock(&mut tx, sku, current.quantity).await?;
+    }
+    if let Some(sku) = reserved_sku(order) {
+        reserve_stock(&mut tx, sku, order.quantity).await?;
+    } else {
+        ensure_product_exists(&mut tx, order.sku.as_deref()).await?;
+    }
+    
+    let discount = match &current.promo_code {
+        Some(code) => match get_promotion_by_code(&mut *tx, code).await? {
+            Some(promotion) => promotion_discount(&promotion, order)?,
+            None => 0,
+        },
+        None => 0,
+    };
+    
+    sqlx::query(
+        "UPDATE orders SET item = ?, status = ?, quantity = ?, customer_id = ?, sku = ?, \
+         unit_price_minor = ?, currency = ?, discount_minor = ?, shipping_address = ? WHERE id = ?"
+    )
         .bind(&order.item)
         .bind(&order.status)
         .bind(order.quantity)
+        .bind(order.customer_id)
+        .bind(&order.sku)
+        .bind(order.unit_price_minor)
+        .bind(&order.currency)
+        .bind(discount)
+        .bind(order.shipping_address.as_ref().map(Json))
         .bind(order_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             eprintln!("Database error in update_order: {}", e);
@@ -121,22 +782,86 @@ pub async fn update_order(pool: &DbPool, order_id: u32, order: &Order) -> Result
             })
         })?;
     
-    if result.rows_affected() == 0 {
-        return Err(ApiError::NotFound("Order not found".to_string()));
+    // Read the order back so the response carries the computed totals
+    let updated = get_order_by_id(&mut *tx, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    let mut changes = vec![record_order_change(&mut tx, WebhookEvent::Updated, &updated, None).await?];
+    if updated.status != current.status {
+        changes.push(record_order_change(&mut tx, WebhookEvent::StatusChanged, &updated, Some(&current.status)).await?);
     }
     
-    // Return the updated order
-    let mut updated_order = order.clone();
-    updated_order.id = order_id;
-    Ok(updated_order)
+    commit_transaction(tx, "update_order").await?;
+    publish_order_changes(changes);
+    Ok(updated)
+}
+
+/// Reject orders referencing a customer that does not exist, as part of the caller's transaction
+/// so the customer cannot be deleted before the order is written
+async fn ensure_customer_exists(conn: &mut SqliteConnection, customer_id: Option<u32>) -> Result<(), ApiError> {
+    let Some(customer_id) = customer_id else {
+        return Ok(());
+    };
+
+    if get_customer_by_id(&mut *conn, customer_id).await?.is_none() {
+        return Err(unknown_customer(customer_id));
+    }
+    Ok(())
+}
+
+/// Reject orders referencing a product that does not exist. Orders holding stock are checked when
+/// it is reserved; this covers cancelled orders, which hold none.
+async fn ensure_product_exists(conn: &mut SqliteConnection, sku: Option<&str>) -> Result<(), ApiError> {
+    let Some(sku) = sku else {
+        return Ok(());
+    };
+
+    if get_product_by_sku(&mut *conn, sku).await?.is_none() {
+        return Err(unknown_product(sku));
+    }
+    Ok(())
 }
 
-/// Update only the status of an order
-pub async fn update_order_status(pool: &DbPool, order_id: u32, status: &str) -> Result<Order, ApiError> {
-    let result = sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+/// Update only the status of an order.
+/// Cancelling an order returns its stock; reopening a cancelled order reserves it again.
+pub async fn update_order_status(
+    pool: &DbPool,
+    order_id: u32,
+    status: &str,
+    payments: &PaymentConfig,
+) -> Result<Order, ApiError> {
+    let mut tx = begin_transaction(pool, "update_order_status").await?;
+    let (order, change) = apply_order_status(&mut tx, order_id, status, payments).await?;
+    commit_transaction(tx, "update_order_status").await?;
+    publish_order_changes(change);
+    Ok(order)
+}
+
+/// Change an order's status as part of the caller's transaction, moving stock on transitions
+/// into or out of `cancelled` and checking the payment before the order ships.
+/// Returns the updated order and the change logged if the status differed,
+/// to be published once the transaction commits.
+pub async fn apply_order_status(
+    conn: &mut SqliteConnection,
+    order_id: u32,
+    status: &str,
+    payments: &PaymentConfig,
+) -> Result<(Order, Option<OrderChange>), ApiError> {
+    let mut order = get_order_by_id(&mut *conn, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    ensure_shipping_paid(&mut *conn, payments, order_id, Some(&order.status), status).await?;
+    let previously_reserved = reserved_sku(&order).map(str::to_string);
+    let previous_status = std::mem::replace(&mut order.status, status.to_string());
+    
+    match (previously_reserved.as_deref(), reserved_sku(&order)) {
+        (Some(sku), None) => release_stock(&mut *conn, sku, order.quantity).await?,
+        (None, Some(sku)) => reserve_stock(&mut *conn, sku, order.quantity).await?,
+        _ => {}
+    }
+    
+    sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
         .bind(status)
         .bind(order_id)
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(|e| {
             eprintln!("Database error in update_order_status: {}", e);
@@ -146,24 +871,78 @@ pub async fn update_order_status(pool: &DbPool, order_id: u32, status: &str) ->
             })
         })?;
     
-    if result.rows_affected() == 0 {
-        return Err(ApiError::NotFound("Order not found".to_string()));
-    }
-    
-    // Get and return the updated order
-    get_order_by_id(pool, order_id).await?
-        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))
+    let change = if previous_status != order.status {
+        Some(record_order_change(&mut *conn, WebhookEvent::StatusChanged, &order, Some(&previous_status)).await?)
+    } else {
+        None
+    };
+    Ok((order, change))
 }
 
 /// Delete an order from the database
 pub async fn delete_order(pool: &DbPool, order_id: u32) -> Result<Order, ApiError> {
+    let mut tx = begin_transaction(pool, "delete_order").await?;
+    
     // First, get the order to return it
-    let order = get_order_by_id(pool, order_id).await?
+    let order = get_order_by_id(&mut *tx, order_id).await?
         .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
     
+    let shipment_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shipments WHERE order_id = ?")
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_order: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete order".to_string(),
+            })
+        })?;
+    
+    if shipment_count > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Order {} still has {} shipment(s)",
+            order_id, shipment_count
+        )));
+    }
+    
+    // Payment records stay with their order; an active one has to be voided or refunded first
+    let (payment_count, active_payments): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COUNT(*) FILTER (WHERE status IN ('authorized', 'captured', 'partially_refunded'))
+         FROM payments WHERE order_id = ?"
+    )
+        .bind(order_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_order: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete order".to_string(),
+            })
+        })?;
+    
+    if active_payments > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Order {} has an active payment; void or refund it first",
+            order_id
+        )));
+    }
+    if payment_count > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Order {} still has {} payment(s)",
+            order_id, payment_count
+        )));
+    }
+    
+    // The order's reservation goes back to stock along with it
+    if let Some(sku) = reserved_sku(&order) {
+        release_stock(&mut tx, sku, order.quantity).await?;
+    }
+    
     let result = sqlx::query("DELETE FROM orders WHERE id = ?")
         .bind(order_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
         .map_err(|e| {
             eprintln!("Database error in delete_order: {}", e);
@@ -176,7 +955,10 @@ pub async fn delete_order(pool: &DbPool, order_id: u32) -> Result<Order, ApiErro
     if result.rows_affected() == 0 {
         return Err(ApiError::NotFound("Order not found".to_string()));
     }
+    let change = record_order_change(&mut tx, WebhookEvent::Deleted, &order, None).await?;
     
+    commit_transaction(tx, "delete_order").await?;
+    publish_order_changes([change]);
     Ok(order)
 }
 
@@ -184,24 +966,14 @@ pub async fn delete_order(pool: &DbPool, order_id: u32) -> Result<Order, ApiErro
 mod tests {
     use super::*;
     use sqlx::SqlitePool;
+    use crate::utils::{
+        create_customer, create_product, create_promotion, get_product_by_sku, Customer, Product, Promotion,
+        PromotionKind
+    };
     
     async fn setup_test_db() -> DbPool {
         let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        
-        sqlx::query(
-            r#"
-            CREATE TABLE orders (
-                id INTEGER PRIMARY KEY,
-                item TEXT NOT NULL,
-                status TEXT NOT NULL,
-                quantity INTEGER NOT NULL
-            )
-            "#
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-        
+        run_migrations(&pool).await.unwrap();
         pool
     }
     
@@ -214,10 +986,11 @@ mod tests {
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         };
         
         // Create order
-        let created = create_order(&pool, &order).await.unwrap();
+        let created = create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
         assert_eq!(created.id, 1);
         assert_eq!(created.item, "Test Item");
         
@@ -234,16 +1007,120 @@ mod tests {
         let pool = setup_test_db().await;
         
         let orders = vec![
-            Order { id: 1, item: "Item 1".to_string(), status: "pending".to_string(), quantity: 1 },
-            Order { id: 2, item: "Item 2".to_string(), status: "processing".to_string(), quantity: 2 },
+            Order { id: 1, item: "Item 1".to_string(), status: "pending".to_string(), quantity: 1, ..Default::default() },
+            Order { id: 2, item: "Item 2".to_string(), status: "processing".to_string(), quantity: 2, ..Default::default() },
         ];
         
         for order in &orders {
-            create_order(&pool, order).await.unwrap();
+            create_order(&pool, order, &PaymentConfig::default()).await.unwrap();
         }
         
-        let all_orders = get_all_orders(&pool).await.unwrap();
+        let all_orders = get_all_orders(&pool, &OrderFilter::default()).await.unwrap();
         assert_eq!(all_orders.len(), 2);
+        
+        let filter = OrderFilter { status: Some("processing".to_string()), ..Default::default() };
+        let processing = get_all_orders(&pool, &filter).await.unwrap();
+        assert_eq!(processing.len(), 1);
+        assert_eq!(processing[0].id, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_stream_orders() {
+        let pool = setup_test_db().await;
+        for id in 1..=(STREAM_BUFFER_SIZE as u32 * 2) {
+            let status = if id % 2 == 0 { "shipped" } else { "pending" };
+            create_order(&pool, &Order { id, item: format!("Item {}", id), status: status.to_string(), quantity: 1, ..Default::default() }, &PaymentConfig::default()).await.unwrap();
+        }
+        
+        let filter = OrderFilter { status: Some("shipped".to_string()), ..Default::default() };
+        let streamed: Vec<Order> = stream_orders(pool.clone(), filter.clone())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let listed = get_all_orders(&pool, &filter).await.unwrap();
+        assert_eq!(streamed.len(), STREAM_BUFFER_SIZE);
+        assert_eq!(
+            streamed.iter().map(|order| order.id).collect::<Vec<_>>(),
+            listed.iter().map(|order| order.id).collect::<Vec<_>>()
+        );
+        
+        // Dropping the stream part way releases its connection
+        let mut partial = Box::pin(stream_orders(pool.clone(), OrderFilter::default()));
+        assert_eq!(partial.next().await.unwrap().unwrap().id, 1);
+        drop(partial);
+        delete_order(&pool, 1).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_get_order_stats() {
+        let pool = setup_test_db().await;
+        let orders = [(1, "Widget", 5), (2, "Gadget", 2), (3, "Widget", 1), (4, "Sprocket", 3)];
+        for (id, item, quantity) in orders {
+            create_order(&pool, &Order { id, item: item.to_string(), status: "pending".to_string(), quantity, ..Default::default() }, &PaymentConfig::default()).await.unwrap();
+        }
+        update_order_status(&pool, 2, "delivered", &PaymentConfig::default()).await.unwrap();
+        
+        let stats = get_order_stats(&pool, 2, 7).await.unwrap();
+        assert_eq!((stats.orders, stats.quantity), (4, 11));
+        let by_status: Vec<(&str, i64, i64)> = stats.by_status.iter()
+            .map(|group| (group.status.as_str(), group.orders, group.quantity))
+            .collect();
+        assert_eq!(by_status, [("delivered", 1, 2), ("pending", 3, 9)]);
+        let top_items: Vec<(&str, i64, i64)> = stats.top_items.iter()
+            .map(|group| (group.item.as_str(), group.orders, group.quantity))
+            .collect();
+        assert_eq!(top_items, [("Widget", 2, 6), ("Sprocket", 1, 3)]);
+        
+        assert_eq!(stats.daily.len(), 7);
+        assert!(stats.daily[..6].iter().all(|day| day.created == 0 && day.delivered == 0));
+        let today = &stats.daily[6];
+        assert_eq!((today.date, today.created, today.delivered), (Utc::now().date_naive(), 4, 1));
+    }
+    
+    #[tokio::test]
+    async fn test_orders_filtered_by_customer() {
+        let pool = setup_test_db().await;
+        let customer = create_customer(&pool, &Customer {
+            id: 7,
+            name: "Ada Lovelace".to_string(),
+            email: "ada@example.com".to_string(),
+        }).await.unwrap();
+        
+        let orders = vec![
+            Order { id: 1, item: "Item 1".to_string(), status: "pending".to_string(), quantity: 1, customer_id: Some(customer.id), ..Default::default() },
+            Order { id: 2, item: "Item 2".to_string(), status: "pending".to_string(), quantity: 2, ..Default::default() },
+        ];
+        for order in &orders {
+            create_order(&pool, order, &PaymentConfig::default()).await.unwrap();
+        }
+        
+        let filter = OrderFilter { customer_id: Some(customer.id), ..Default::default() };
+        let customer_orders = get_all_orders(&pool, &filter).await.unwrap();
+        assert_eq!(customer_orders.len(), 1);
+        assert_eq!(customer_orders[0].id, 1);
+        assert_eq!(customer_orders[0].customer_id, Some(7));
+    }
+    
+    #[tokio::test]
+    async fn test_unknown_customer_error() {
+        let pool = setup_test_db().await;
+        
+        let order = Order {
+            id: 1,
+            item: "Test Item".to_string(),
+            status: "pending".to_string(),
+            quantity: 1,
+            customer_id: Some(42),
+            ..Default::default()
+        };
+        
+        match create_order(&pool, &order, &PaymentConfig::default()).await.unwrap_err() {
+            ApiError::Validation(err) => {
+                assert_eq!(err.error, "Customer with ID 42 does not exist");
+                assert_eq!(err.field, Some("customer_id".to_string()));
+            },
+            _ => panic!("Expected validation error"),
+        }
     }
     
     #[tokio::test]
@@ -255,18 +1132,20 @@ mod tests {
             item: "Original Item".to_string(),
             status: "pending".to_string(),
             quantity: 1,
+            ..Default::default()
         };
         
-        create_order(&pool, &order).await.unwrap();
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
         
         let updated_order = Order {
             id: 1, // This will be ignored in update
             item: "Updated Item".to_string(),
             status: "processing".to_string(),
             quantity: 2,
+            ..Default::default()
         };
         
-        let result = update_order(&pool, 1, &updated_order).await.unwrap();
+        let result = update_order(&pool, 1, &updated_order, &PaymentConfig::default()).await.unwrap();
         assert_eq!(result.item, "Updated Item");
         assert_eq!(result.status, "processing");
         assert_eq!(result.quantity, 2);
@@ -281,11 +1160,12 @@ mod tests {
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 1,
+            ..Default::default()
         };
         
-        create_order(&pool, &order).await.unwrap();
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
         
-        let updated = update_order_status(&pool, 1, "shipped").await.unwrap();
+        let updated = update_order_status(&pool, 1, "shipped", &PaymentConfig::default()).await.unwrap();
         assert_eq!(updated.status, "shipped");
         assert_eq!(updated.item, "Test Item"); // Other fields unchanged
     }
@@ -299,9 +1179,10 @@ mod tests {
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 1,
+            ..Default::default()
         };
         
-        create_order(&pool, &order).await.unwrap();
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
         
         let deleted = delete_order(&pool, 1).await.unwrap();
         assert_eq!(deleted.id, 1);
@@ -320,12 +1201,13 @@ mod tests {
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 1,
+            ..Default::default()
         };
         
-        create_order(&pool, &order).await.unwrap();
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
         
         // Try to create another order with the same ID
-        let result = create_order(&pool, &order).await;
+        let result = create_order(&pool, &order, &PaymentConfig::default()).await;
         assert!(result.is_err());
         
         match result.unwrap_err() {
@@ -336,4 +1218,241 @@ mod tests {
             _ => panic!("Expected validation error"),
         }
     }
+    
+    async fn stock_of(pool: &DbPool, sku: &str) -> u32 {
+        get_product_by_sku(pool, sku).await.unwrap().unwrap().stock
+    }
+    
+    #[tokio::test]
+    async fn test_order_reserves_and_releases_stock() {
+        let pool = setup_test_db().await;
+        create_product(&pool, &Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 10 }).await.unwrap();
+        
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity: 4,
+            sku: Some("WID-1".to_string()),
+            ..Default::default()
+        };
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 6);
+        
+        // Replacing the order swaps its reservation
+        let larger = Order { quantity: 9, ..order.clone() };
+        update_order(&pool, 1, &larger, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 1);
+        
+        update_order_status(&pool, 1, "cancelled", &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 10);
+        
+        // Cancelling twice does not release twice
+        update_order_status(&pool, 1, "cancelled", &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 10);
+        
+        update_order_status(&pool, 1, "pending", &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_deleting_order_releases_stock() {
+        let pool = setup_test_db().await;
+        create_product(&pool, &Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 10 }).await.unwrap();
+        
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity: 4,
+            sku: Some("WID-1".to_string()),
+            ..Default::default()
+        };
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 6);
+        
+        delete_order(&pool, 1).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 10);
+        
+        // A cancelled order has nothing reserved left to release
+        create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
+        update_order_status(&pool, 1, "cancelled", &PaymentConfig::default()).await.unwrap();
+        delete_order(&pool, 1).await.unwrap();
+        assert_eq!(stock_of(&pool, "WID-1").await, 10);
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_order_with_unknown_sku_is_rejected() {
+        let pool = setup_test_db().await;
+        
+        // Cancelled orders reserve no stock, so the SKU is checked on its own
+        let cancelled = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "cancelled".to_string(),
+            quantity: 1,
+            sku: Some("NOPE".to_string()),
+            ..Default::default()
+        };
+        match create_order(&pool, &cancelled, &PaymentConfig::default()).await.unwrap_err() {
+            ApiError::Validation(err) => {
+                assert_eq!(err.error, "Product with SKU NOPE does not exist");
+                assert_eq!(err.field, Some("sku".to_string()));
+            },
+            _ => panic!("Expected validation error"),
+        }
+        
+        create_order(&pool, &Order { sku: None, ..cancelled.clone() }, &PaymentConfig::default()).await.unwrap();
+        let result = update_order(&pool, 1, &cancelled, &PaymentConfig::default()).await;
+        assert!(matches!(result, Err(ApiError::Validation(err)) if err.field.as_deref() == Some("sku")));
+    }
+
+    #[tokio::test]
+    async fn test_order_exceeding_stock_is_rejected() {
+        let pool = setup_test_db().await;
+        create_product(&pool, &Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 3 }).await.unwrap();
+        
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity: 4,
+            sku: Some("WID-1".to_string()),
+            ..Default::default()
+        };
+        match create_order(&pool, &order, &PaymentConfig::default()).await.unwrap_err() {
+            ApiError::Conflict(message) => {
+                assert_eq!(message, "Insufficient stock for SKU WID-1: requested 4, available 3");
+            },
+            _ => panic!("Expected conflict"),
+        }
+        
+        // Nothing was written
+        assert!(get_order_by_id(&pool, 1).await.unwrap().is_none());
+        assert_eq!(stock_of(&pool, "WID-1").await, 3);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_orders_never_oversell() {
+        let pool = init_db().await.unwrap();
+        create_product(&pool, &Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 5 }).await.unwrap();
+        
+        let attempts: Vec<_> = (1..=10).map(|id| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let order = Order {
+                    id,
+                    item: "Widget".to_string(),
+                    status: "pending".to_string(),
+                    quantity: 1,
+                    sku: Some("WID-1".to_string()),
+                    ..Default::default()
+                };
+                create_order(&pool, &order, &PaymentConfig::default()).await
+            })
+        }).collect();
+        let mut created = 0;
+        for attempt in attempts {
+            if attempt.await.unwrap().is_ok() {
+                created += 1;
+            }
+        }
+        
+        assert_eq!(created, 5);
+        assert_eq!(stock_of(&pool, "WID-1").await, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_order_totals_are_computed() {
+        let pool = setup_test_db().await;
+        
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity: 3,
+            unit_price_minor: 1999,
+            currency: "EUR".to_string(),
+            ..Default::default()
+        };
+        let created = create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(created.subtotal_minor, 5997);
+        assert_eq!(created.total_minor, 5997);
+        
+        let fetched = get_order_by_id(&pool, 1).await.unwrap().unwrap();
+        assert_eq!(fetched.unit_price_minor, 1999);
+        assert_eq!(fetched.currency, "EUR");
+        assert_eq!(fetched.total_minor, 5997);
+        
+        let listed = get_all_orders(&pool, &OrderFilter::default()).await.unwrap();
+        assert_eq!(listed[0].subtotal_minor, 5997);
+        
+        let updated = update_order(&pool, 1, &Order { quantity: 4, ..order }, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(updated.total_minor, 7996);
+    }
+    
+    #[tokio::test]
+    async fn test_promo_code_discount_is_recorded() {
+        let pool = setup_test_db().await;
+        create_product(&pool, &Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 10 }).await.unwrap();
+        create_promotion(&pool, &Promotion {
+            code: "HALF".to_string(),
+            kind: PromotionKind::PercentOff,
+            percent_off: Some(50),
+            amount_off_minor: None,
+            currency: None,
+            buy_quantity: None,
+            free_quantity: None,
+            starts_at: None,
+            ends_at: None,
+            max_redemptions: Some(1),
+            redemptions: 0,
+        }).await.unwrap();
+        
+        let order = Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity: 2,
+            sku: Some("WID-1".to_string()),
+            unit_price_minor: 1000,
+            promo_code: Some("HALF".to_string()),
+            ..Default::default()
+        };
+        let created = create_order(&pool, &order, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(created.subtotal_minor, 2000);
+        assert_eq!(created.discount_minor, 1000);
+        assert_eq!(created.total_minor, 1000);
+        
+        // The discount follows changes to the order
+        let updated = update_order(&pool, 1, &Order { quantity: 4, ..order.clone() }, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(updated.discount_minor, 2000);
+        
+        // An exhausted code fails the whole order, including its stock reservation
+        let second = Order { id: 2, ..order };
+        assert!(matches!(create_order(&pool, &second, &PaymentConfig::default()).await, Err(ApiError::Validation(_))));
+        assert!(get_order_by_id(&pool, 2).await.unwrap().is_none());
+        assert_eq!(stock_of(&pool, "WID-1").await, 6);
+    }
+    
+    #[tokio::test]
+    async fn test_init_db_applies_all_migrations() {
+        let pool = init_db().await.unwrap();
+        
+        let version = get_migration_version(&pool).await.unwrap();
+        assert_eq!(version, MIGRATIONS.len() as i64);
+        
+        // Re-running migrations is a no-op once the schema is current
+        run_migrations(&pool).await.unwrap();
+        assert_eq!(get_migration_version(&pool).await.unwrap(), MIGRATIONS.len() as i64);
+    }
+    
+    #[tokio::test]
+    async fn test_ping_db() {
+        let pool = setup_test_db().await;
+        assert!(ping_db(&pool).await.is_ok());
+        
+        pool.close().await;
+        assert!(ping_db(&pool).await.is_err());
+    }
 }
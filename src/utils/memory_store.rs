@@ -0,0 +1,1120 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use async_trait::async_trait;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use crate::config::PaymentConfig;
+use crate::payments::PaymentGateway;
+use crate::utils::{
+    daily_series, hash_api_key, order_event_payload, payment_not_captured, promotion_discount, publish_order_changes,
+    requires_captured_payment, reserved_sku, unknown_customer, unknown_product, unknown_promotion, ApiKey,
+    CertificateIdentity, CredentialRepository, Customer, CustomerRepository, DailyOrderStats, DeliveryOutcome,
+    ItemStats, Order, OrderChange, OrderChangeFilter, OrderFilter, OrderRepository, OrderStats, OutboxEntry, Payment,
+    PaymentRepository, PaymentStatus, PoolStatus, Product, ProductRepository, Promotion, PromotionRepository, Role,
+    Shipment, ShipmentRepository, StatusStats, Store, WebhookDelivery, WebhookEvent, WebhookRepository,
+    WebhookSubscription
+};
+use crate::validators::{validate_delivered_at, ApiError, ValidationError};
+
+/// Everything kept in process memory and lost on restart, enforcing the same rules as the SQLite database.
+/// Each write checks everything it depends on before changing anything, so a rejected write leaves no trace.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    data: Arc<Mutex<MemoryData>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryData {
+    orders: BTreeMap<u32, Order>,
+    customers: BTreeMap<u32, Customer>,
+    products: BTreeMap<String, Product>,
+    promotions: BTreeMap<String, Promotion>,
+    shipments: BTreeMap<u32, Shipment>,
+    payments: BTreeMap<u32, Payment>,
+    subscriptions: BTreeMap<u32, WebhookSubscription>,
+    outbox: BTreeMap<u32, OutboxEvent>,
+    deliveries: BTreeMap<u32, (u32, WebhookDelivery)>,
+    changes: Vec<OrderChange>,
+    /// API keys by the hash of their secret
+    api_keys: HashMap<String, ApiKey>,
+    certificates: HashMap<String, CertificateIdentity>,
+    last_ids: LastIds,
+}
+
+/// Last identifier handed out per kind of record; like SQLite's AUTOINCREMENT, identifiers are never reused
+#[derive(Debug, Clone, Default)]
+struct LastIds {
+    shipment: u32,
+    payment: u32,
+    subscription: u32,
+    outbox: u32,
+    delivery: u32,
+    api_key: i64,
+}
+
+#[derive(Debug, Clone)]
+/// Event waiting in the outbox for delivery to a subscription
+struct OutboxEvent {
+    subscription_id: u32,
+    event_id: String,
+    event_type: String,
+    payload: String,
+    status: &'static str,
+    attempts: u32,
+    next_attempt_at: DateTime<Utc>,
+}
+
+fn next_id(last: &mut u32) -> u32 {
+    *last += 1;
+    *last
+}
+
+fn order_not_found() -> ApiError {
+    ApiError::NotFound("Order not found".to_string())
+}
+
+/// Fill in the totals the database computes
+fn with_totals(mut order: Order, discount_minor: i64) -> Order {
+    order.subtotal_minor = order.unit_price_minor * i64::from(order.quantity);
+    order.discount_minor = discount_minor;
+    order.total_minor = order.subtotal_minor - discount_minor;
+    order
+}
+
+fn is_active(payment: &Payment) -> bool {
+    matches!(payment.status, PaymentStatus::Authorized | PaymentStatus::Captured | PaymentStatus::PartiallyRefunded)
+}
+
+impl MemoryData {
+    fn matching_orders(&self, filter: &OrderFilter, after_id: Option<u32>) -> impl Iterator<Item = &Order> {
+        let status = filter.status.clone();
+        let customer_id = filter.customer_id;
+        self.orders.values()
+            .filter(move |order| after_id.is_none_or(|after_id| order.id > after_id))
+            .filter(move |order| status.as_ref().is_none_or(|status| &order.status == status))
+            .filter(move |order| customer_id.is_none_or(|customer_id| order.customer_id == Some(customer_id)))
+    }
+
+    fn ensure_customer_exists(&self, customer_id: Option<u32>) -> Result<(), ApiError> {
+        match customer_id {
+            Some(customer_id) if !self.customers.contains_key(&customer_id) => Err(unknown_customer(customer_id)),
+            _ => Ok(()),
+        }
+    }
+
+    fn ensure_product_exists(&self, sku: Option<&str>) -> Result<(), ApiError> {
+        match sku {
+            Some(sku) if !self.products.contains_key(sku) => Err(unknown_product(sku)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check `quantity` units of `sku` can be reserved once `released` units of it have gone back to stock
+    fn ensure_stock(&self, sku: &str, quantity: u32, released: u32) -> Result<(), ApiError> {
+        let product = self.products.get(sku).ok_or_else(|| unknown_product(sku))?;
+        let available = product.stock + released;
+        if available < quantity {
+            return Err(ApiError::Conflict(format!(
+                "Insufficient stock for SKU {}: requested {}, available {}",
+                sku, quantity, available
+            )));
+        }
+        Ok(())
+    }
+
+    /// Add `quantity` units of `sku` to stock, or take them out when negative
+    fn adjust_stock(&mut self, sku: &str, quantity: i64) {
+        if let Some(product) = self.products.get_mut(sku) {
+            product.stock = (i64::from(product.stock) + quantity) as u32;
+        }
+    }
+
+    fn has_captured_payment(&self, order_id: u32) -> bool {
+        self.payments.values().any(|payment| {
+            payment.order_id == order_id
+                && matches!(payment.status, PaymentStatus::Captured | PaymentStatus::PartiallyRefunded)
+        })
+    }
+
+    fn ensure_shipping_paid(
+        &self,
+        payments: &PaymentConfig,
+        order_id: u32,
+        previous_status: Option<&str>,
+        status: &str,
+    ) -> Result<(), ApiError> {
+        if requires_captured_payment(payments, previous_status, status) && !self.has_captured_payment(order_id) {
+            return Err(payment_not_captured(order_id));
+        }
+        Ok(())
+    }
+
+    /// Discount `code` gives on `order`, provided it can still be redeemed
+    fn redeemable_discount(&self, code: &str, order: &Order) -> Result<i64, ApiError> {
+        let promotion = self.promotions.get(code).ok_or_else(|| unknown_promotion(code))?;
+
+        let now = Utc::now();
+        if let Some(starts_at) = promotion.starts_at.filter(|starts_at| *starts_at > now) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Promo code {} is not valid until {}", code, starts_at.to_rfc3339()),
+                field: Some("promo_code".to_string()),
+            }));
+        }
+        if let Some(ends_at) = promotion.ends_at.filter(|ends_at| *ends_at < now) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Promo code {} expired on {}", code, ends_at.to_rfc3339()),
+                field: Some("promo_code".to_string()),
+            }));
+        }
+
+        let discount = promotion_discount(promotion, order)?;
+        if promotion.max_redemptions.is_some_and(|max| promotion.redemptions >= max) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Promo code {} has reached its redemption limit", code),
+                field: Some("promo_code".to_string()),
+            }));
+        }
+        Ok(discount)
+    }
+
+    /// Log a change and queue the matching webhook event for every subscription to it
+    fn record_change(&mut self, event: WebhookEvent, order: &Order, previous_status: Option<&str>) -> OrderChange {
+        let changed_at = Utc::now();
+        let change = OrderChange {
+            seq: self.changes.last().map_or(0, |change| change.seq) + 1,
+            event: event.as_str().to_string(),
+            order_id: order.id,
+            order: order.clone(),
+            previous_status: previous_status.map(str::to_string),
+            changed_at,
+        };
+        self.changes.push(change.clone());
+
+        let event_id = uuid::Uuid::new_v4().to_string();
+        let payload = order_event_payload(&event_id, event, order, previous_status, changed_at).to_string();
+        let subscribers: Vec<u32> = self.subscriptions.values()
+            .filter(|subscription| subscription.event_types.contains(&event))
+            .map(|subscription| subscription.id)
+            .collect();
+        for subscription_id in subscribers {
+            let id = next_id(&mut self.last_ids.outbox);
+            self.outbox.insert(id, OutboxEvent {
+                subscription_id,
+                event_id: event_id.clone(),
+                event_type: event.as_str().to_string(),
+                payload: payload.clone(),
+                status: "pending",
+                attempts: 0,
+                next_attempt_at: changed_at,
+            });
+        }
+
+        change
+    }
+
+    /// Insert an order, reserving its stock and redeeming its promotion
+    fn insert_order(&mut self, order: &Order, payments: &PaymentConfig) -> Result<(Order, OrderChange), ApiError> {
+        if self.orders.contains_key(&order.id) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Order with ID {} already exists", order.id),
+                field: Some("id".to_string()),
+            }));
+        }
+
+        self.ensure_customer_exists(order.customer_id)?;
+        self.ensure_shipping_paid(payments, order.id, None, &order.status)?;
+        match reserved_sku(order) {
+            Some(sku) => self.ensure_stock(sku, order.quantity, 0)?,
+            None => self.ensure_product_exists(order.sku.as_deref())?,
+        }
+        let discount = match &order.promo_code {
+            Some(code) => self.redeemable_discount(code, order)?,
+            None => 0,
+        };
+
+        if let Some(sku) = reserved_sku(order) {
+            self.adjust_stock(sku, -i64::from(order.quantity));
+        }
+        if let Some(promotion) = order.promo_code.as_ref().and_then(|code| self.promotions.get_mut(code)) {
+            promotion.redemptions += 1;
+        }
+        let created = with_totals(order.clone(), discount);
+        self.orders.insert(created.id, created.clone());
+        let change = self.record_change(WebhookEvent::Created, &created, None);
+        Ok((created, change))
+    }
+
+    /// Check an order can move to `status`: a captured payment before it ships, and stock when it is reopened
+    fn ensure_status_change(&self, order: &Order, status: &str, payments: &PaymentConfig) -> Result<(), ApiError> {
+        self.ensure_shipping_paid(payments, order.id, Some(&order.status), status)?;
+        let reopened = Order { status: status.to_string(), ..order.clone() };
+        if let (None, Some(sku)) = (reserved_sku(order), reserved_sku(&reopened)) {
+            self.ensure_stock(sku, order.quantity, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Move an order checked with `ensure_status_change` to `status`, moving stock on transitions into or
+    /// out of `cancelled`. Returns the updated order and the change logged if the status differed.
+    fn set_status(&mut self, order_id: u32, status: &str) -> Result<(Order, Option<OrderChange>), ApiError> {
+        let order = self.orders.get_mut(&order_id).ok_or_else(order_not_found)?;
+        let previously_reserved = reserved_sku(order).map(str::to_string);
+        let previous_status = std::mem::replace(&mut order.status, status.to_string());
+        let order = order.clone();
+
+        match (previously_reserved.as_deref(), reserved_sku(&order)) {
+            (Some(sku), None) => self.adjust_stock(sku, i64::from(order.quantity)),
+            (None, Some(sku)) => self.adjust_stock(sku, -i64::from(order.quantity)),
+            _ => {}
+        }
+
+        let change = (previous_status != order.status)
+            .then(|| self.record_change(WebhookEvent::StatusChanged, &order, Some(&previous_status)));
+        Ok((order, change))
+    }
+
+    /// Status shipping moves an order to: `delivered` once every shipment has been, `shipped` before
+    fn shipped_status(&self, order_id: u32) -> &'static str {
+        let delivered = self.shipments.values()
+            .filter(|shipment| shipment.order_id == order_id)
+            .all(|shipment| shipment.delivered_at.is_some());
+        if delivered { "delivered" } else { "shipped" }
+    }
+}
+
+impl InMemoryStore {
+    fn data(&self) -> MutexGuard<'_, MemoryData> {
+        self.data.lock().unwrap()
+    }
+
+    /// Fetch a payment and check it is in `expected` state before acting on it
+    fn payment_in_state(
+        &self,
+        order_id: u32,
+        payment_id: u32,
+        expected: &[PaymentStatus],
+        action: &str,
+    ) -> Result<Payment, ApiError> {
+        let payment = self.data().payments.get(&payment_id)
+            .filter(|payment| payment.order_id == order_id)
+            .cloned()
+            .ok_or_else(|| ApiError::NotFound("Payment not found".to_string()))?;
+        if !expected.contains(&payment.status) {
+            return Err(ApiError::Conflict(format!(
+                "Payment {} is {} and cannot be {}",
+                payment_id, payment.status.as_str(), action
+            )));
+        }
+        Ok(payment)
+    }
+
+    /// Record a change made at the gateway, provided the payment still is as it was read
+    fn record_transition(&self, previous: &Payment, payment: &Payment) -> Result<(), ApiError> {
+        let mut data = self.data();
+        match data.payments.get_mut(&payment.id) {
+            Some(stored) if stored.status == previous.status && stored.refunded_minor == previous.refunded_minor => {
+                *stored = payment.clone();
+                Ok(())
+            }
+            _ => Err(ApiError::Conflict(format!("Payment {} was changed concurrently", payment.id))),
+        }
+    }
+}
+
+#[async_trait]
+impl OrderRepository for InMemoryStore {
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, ApiError> {
+        Ok(self.data().matching_orders(filter, None).cloned().collect())
+    }
+
+    fn stream_orders(&self, filter: OrderFilter) -> BoxStream<'static, Result<Order, ApiError>> {
+        let orders: Vec<Order> = self.data().matching_orders(&filter, None).cloned().collect();
+        stream::iter(orders.into_iter().map(Ok)).boxed()
+    }
+
+    async fn orders_page(&self, filter: &OrderFilter, after_id: Option<u32>, limit: u32) -> Result<Vec<Order>, ApiError> {
+        Ok(self.data().matching_orders(filter, after_id).take(limit as usize).cloned().collect())
+    }
+
+    async fn get_order(&self, id: u32) -> Result<Option<Order>, ApiError> {
+        Ok(self.data().orders.get(&id).cloned())
+    }
+
+    async fn create_order(&self, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let (created, change) = self.data().insert_order(order, payments)?;
+        publish_order_changes([change]);
+        Ok(created)
+    }
+
+    async fn create_orders(
+        &self,
+        orders: &[Order],
+        dry_run: bool,
+        payments: &PaymentConfig,
+    ) -> Result<Result<Vec<Order>, (usize, ApiError)>, ApiError> {
+        let mut data = self.data();
+        // Orders are inserted into a copy that replaces the data only once all of them are in
+        let mut draft = data.clone();
+        let mut created = Vec::with_capacity(orders.len());
+        let mut changes = Vec::with_capacity(orders.len());
+        for (index, order) in orders.iter().enumerate() {
+            match draft.insert_order(order, payments) {
+                Ok((order, change)) => {
+                    created.push(order);
+                    changes.push(change);
+                }
+                Err(err) => return Ok(Err((index, err))),
+            }
+        }
+
+        if !dry_run {
+            *data = draft;
+            drop(data);
+            publish_order_changes(changes);
+        }
+        Ok(Ok(created))
+    }
+
+    async fn update_order(&self, id: u32, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let mut data = self.data();
+        data.ensure_customer_exists(order.customer_id)?;
+
+        let current = data.orders.get(&id).cloned().ok_or_else(order_not_found)?;
+        if order.promo_code.is_some() && order.promo_code != current.promo_code {
+            return Err(ApiError::Validation(ValidationError {
+                error: "Promo code cannot be changed after the order is created".to_string(),
+                field: Some("promo_code".to_string()),
+            }));
+        }
+        data.ensure_shipping_paid(payments, id, Some(&current.status), &order.status)?;
+        match reserved_sku(order) {
+            Some(sku) => {
+                // Stock held by the previous version of the order goes back before the new version reserves its own
+                let released = match reserved_sku(&current) {
+                    Some(current_sku) if current_sku == sku => current.quantity,
+                    _ => 0,
+                };
+                data.ensure_stock(sku, order.quantity, released)?;
+            }
+            None => data.ensure_product_exists(order.sku.as_deref())?,
+        }
+
+        // The promotion applied at creation stays attached and its discount is recalculated
+        let discount = match current.promo_code.as_ref().and_then(|code| data.promotions.get(code)) {
+            Some(promotion) => promotion_discount(promotion, order)?,
+            None => 0,
+        };
+
+        if let Some(sku) = reserved_sku(&current) {
+            data.adjust_stock(sku, i64::from(current.quantity));
+        }
+        if let Some(sku) = reserved_sku(order) {
+            data.adjust_stock(sku, -i64::from(order.quantity));
+        }
+        let updated = with_totals(Order { id, promo_code: current.promo_code.clone(), ..order.clone() }, discount);
+        data.orders.insert(id, updated.clone());
+
+        let mut changes = vec![data.record_change(WebhookEvent::Updated, &updated, None)];
+        if updated.status != current.status {
+            changes.push(data.record_change(WebhookEvent::StatusChanged, &updated, Some(&current.status)));
+        }
+        drop(data);
+        publish_order_changes(changes);
+        Ok(updated)
+    }
+
+    async fn update_order_status(&self, id: u32, status: &str, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let mut data = self.data();
+        let order = data.orders.get(&id).cloned().ok_or_else(order_not_found)?;
+        data.ensure_status_change(&order, status, payments)?;
+        let (order, change) = data.set_status(id, status)?;
+        drop(data);
+        publish_order_changes(change);
+        Ok(order)
+    }
+
+    async fn delete_order(&self, id: u32) -> Result<Order, ApiError> {
+        let mut data = self.data();
+        let order = data.orders.get(&id).cloned().ok_or_else(order_not_found)?;
+
+        let shipment_count = data.shipments.values().filter(|shipment| shipment.order_id == id).count();
+        if shipment_count > 0 {
+            return Err(ApiError::Conflict(format!("Order {} still has {} shipment(s)", id, shipment_count)));
+        }
+
+        // Payment records stay with their order; an active one has to be voided or refunded first
+        let payments: Vec<&Payment> = data.payments.values().filter(|payment| payment.order_id == id).collect();
+        if payments.iter().any(|payment| is_active(payment)) {
+            return Err(ApiError::Conflict(format!("Order {} has an active payment; void or refund it first", id)));
+        }
+        if !payments.is_empty() {
+            return Err(ApiError::Conflict(format!("Order {} still has {} payment(s)", id, payments.len())));
+        }
+
+        if let Some(sku) = reserved_sku(&order) {
+            data.adjust_stock(sku, i64::from(order.quantity));
+        }
+        data.orders.remove(&id);
+        let change = data.record_change(WebhookEvent::Deleted, &order, None);
+        drop(data);
+        publish_order_changes([change]);
+        Ok(order)
+    }
+
+    async fn order_stats(&self, top_items: u32, days: u32) -> Result<OrderStats, ApiError> {
+        let data = self.data();
+        let orders = data.orders.len() as i64;
+        let quantity = data.orders.values().map(|order| i64::from(order.quantity)).sum();
+
+        let mut by_status: BTreeMap<&str, StatusStats> = BTreeMap::new();
+        let mut by_item: BTreeMap<&str, ItemStats> = BTreeMap::new();
+        for order in data.orders.values() {
+            let status = by_status.entry(&order.status)
+                .or_insert_with(|| StatusStats { status: order.status.clone(), orders: 0, quantity: 0 });
+            status.orders += 1;
+            status.quantity += i64::from(order.quantity);
+            let item = by_item.entry(&order.item)
+                .or_insert_with(|| ItemStats { item: order.item.clone(), orders: 0, quantity: 0 });
+            item.orders += 1;
+            item.quantity += i64::from(order.quantity);
+        }
+        let mut items: Vec<ItemStats> = by_item.into_values().collect();
+        // Sorting is stable, so items with the same quantity stay in name order
+        items.sort_by_key(|item| Reverse(item.quantity));
+        items.truncate(top_items as usize);
+
+        let today = Utc::now().date_naive();
+        let first_day = today - Days::new(u64::from(days.saturating_sub(1)));
+        let mut activity: BTreeMap<NaiveDate, (BTreeSet<u32>, BTreeSet<u32>)> = BTreeMap::new();
+        for change in data.changes.iter().filter(|change| change.changed_at.date_naive() >= first_day) {
+            let (created, delivered) = activity.entry(change.changed_at.date_naive()).or_default();
+            match change.event.as_str() {
+                "order.created" => {
+                    created.insert(change.order_id);
+                }
+                "order.status_changed" if change.order.status == "delivered" => {
+                    delivered.insert(change.order_id);
+                }
+                _ => {}
+            }
+        }
+        let active_days = activity.into_iter()
+            .map(|(date, (created, delivered))| DailyOrderStats {
+                date,
+                created: created.len() as i64,
+                delivered: delivered.len() as i64,
+            })
+            .collect();
+
+        Ok(OrderStats {
+            orders,
+            quantity,
+            by_status: by_status.into_values().collect(),
+            top_items: items,
+            daily: daily_series(first_day, today, active_days),
+        })
+    }
+
+    async fn latest_order_change_seq(&self) -> Result<i64, ApiError> {
+        Ok(self.data().changes.last().map_or(0, |change| change.seq))
+    }
+
+    async fn order_changes_since(
+        &self,
+        after_seq: i64,
+        filter: &OrderChangeFilter,
+        limit: u32,
+    ) -> Result<Vec<OrderChange>, ApiError> {
+        Ok(self.data().changes.iter()
+            .filter(|change| change.seq > after_seq)
+            .filter(|change| filter.status.as_ref().is_none_or(|status| &change.order.status == status))
+            .filter(|change| filter.id.is_none_or(|id| change.order_id == id))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for InMemoryStore {
+    async fn list_customers(&self) -> Result<Vec<Customer>, ApiError> {
+        Ok(self.data().customers.values().cloned().collect())
+    }
+
+    async fn get_customer(&self, id: u32) -> Result<Option<Customer>, ApiError> {
+        Ok(self.data().customers.get(&id).cloned())
+    }
+
+    async fn create_customer(&self, customer: &Customer) -> Result<Customer, ApiError> {
+        let mut data = self.data();
+        if data.customers.contains_key(&customer.id) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Customer with ID {} already exists", customer.id),
+                field: Some("id".to_string()),
+            }));
+        }
+        ensure_email_available(&data, &customer.email, customer.id)?;
+
+        data.customers.insert(customer.id, customer.clone());
+        Ok(customer.clone())
+    }
+
+    async fn update_customer(&self, id: u32, customer: &Customer) -> Result<Customer, ApiError> {
+        let mut data = self.data();
+        ensure_email_available(&data, &customer.email, id)?;
+
+        let stored = data.customers.get_mut(&id)
+            .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+        *stored = Customer { id, ..customer.clone() };
+        Ok(stored.clone())
+    }
+
+    async fn delete_customer(&self, id: u32) -> Result<Customer, ApiError> {
+        let mut data = self.data();
+        if !data.customers.contains_key(&id) {
+            return Err(ApiError::NotFound("Customer not found".to_string()));
+        }
+
+        let order_count = data.orders.values().filter(|order| order.customer_id == Some(id)).count();
+        if order_count > 0 {
+            return Err(ApiError::Conflict(format!("Customer {} still has {} order(s)", id, order_count)));
+        }
+        data.customers.remove(&id).ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))
+    }
+}
+
+/// Reject an email address already used by a customer other than `customer_id`
+fn ensure_email_available(data: &MemoryData, email: &str, customer_id: u32) -> Result<(), ApiError> {
+    if data.customers.values().any(|customer| customer.email == email && customer.id != customer_id) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("A customer with email {} already exists", email),
+            field: Some("email".to_string()),
+        }));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl ProductRepository for InMemoryStore {
+    async fn list_products(&self) -> Result<Vec<Product>, ApiError> {
+        Ok(self.data().products.values().cloned().collect())
+    }
+
+    async fn get_product(&self, sku: &str) -> Result<Option<Product>, ApiError> {
+        Ok(self.data().products.get(sku).cloned())
+    }
+
+    async fn create_product(&self, product: &Product) -> Result<Product, ApiError> {
+        let mut data = self.data();
+        if data.products.contains_key(&product.sku) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Product with SKU {} already exists", product.sku),
+                field: Some("sku".to_string()),
+            }));
+        }
+
+        data.products.insert(product.sku.clone(), product.clone());
+        Ok(product.clone())
+    }
+
+    async fn update_product(&self, sku: &str, product: &Product) -> Result<Product, ApiError> {
+        let mut data = self.data();
+        let stored = data.products.get_mut(sku)
+            .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+        *stored = Product { sku: sku.to_string(), ..product.clone() };
+        Ok(stored.clone())
+    }
+
+    async fn delete_product(&self, sku: &str) -> Result<Product, ApiError> {
+        let mut data = self.data();
+        if !data.products.contains_key(sku) {
+            return Err(ApiError::NotFound("Product not found".to_string()));
+        }
+
+        let order_count = data.orders.values().filter(|order| order.sku.as_deref() == Some(sku)).count();
+        if order_count > 0 {
+            return Err(ApiError::Conflict(format!("Product {} is referenced by {} order(s)", sku, order_count)));
+        }
+        data.products.remove(sku).ok_or_else(|| ApiError::NotFound("Product not found".to_string()))
+    }
+}
+
+#[async_trait]
+impl PromotionRepository for InMemoryStore {
+    async fn list_promotions(&self) -> Result<Vec<Promotion>, ApiError> {
+        Ok(self.data().promotions.values().cloned().collect())
+    }
+
+    async fn get_promotion(&self, code: &str) -> Result<Option<Promotion>, ApiError> {
+        Ok(self.data().promotions.get(code).cloned())
+    }
+
+    async fn create_promotion(&self, promotion: &Promotion) -> Result<Promotion, ApiError> {
+        let mut data = self.data();
+        if data.promotions.contains_key(&promotion.code) {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Promotion with code {} already exists", promotion.code),
+                field: Some("code".to_string()),
+            }));
+        }
+
+        let created = Promotion { redemptions: 0, ..promotion.clone() };
+        data.promotions.insert(created.code.clone(), created.clone());
+        Ok(created)
+    }
+
+    async fn delete_promotion(&self, code: &str) -> Result<Promotion, ApiError> {
+        let mut data = self.data();
+        let promotion = data.promotions.get(code)
+            .ok_or_else(|| ApiError::NotFound("Promotion not found".to_string()))?;
+        if promotion.redemptions > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Promotion {} has been redeemed {} time(s)",
+                code, promotion.redemptions
+            )));
+        }
+        data.promotions.remove(code).ok_or_else(|| ApiError::NotFound("Promotion not found".to_string()))
+    }
+}
+
+#[async_trait]
+impl ShipmentRepository for InMemoryStore {
+    async fn list_shipments(&self, order_id: u32) -> Result<Vec<Shipment>, ApiError> {
+        Ok(self.data().shipments.values().filter(|shipment| shipment.order_id == order_id).cloned().collect())
+    }
+
+    async fn create_shipment(&self, order_id: u32, shipment: &Shipment, payments: &PaymentConfig) -> Result<Shipment, ApiError> {
+        let mut data = self.data();
+        let order = data.orders.get(&order_id).cloned().ok_or_else(order_not_found)?;
+        if matches!(order.status.as_str(), "cancelled" | "delivered") {
+            return Err(ApiError::Conflict(format!(
+                "Order {} is {} and cannot be shipped",
+                order_id, order.status
+            )));
+        }
+        if order.shipping_address.is_none() {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Order {} has no shipping address", order_id),
+                field: Some("shipping_address".to_string()),
+            }));
+        }
+
+        // The order moves to `delivered` only if this and every earlier shipment have been delivered
+        let status = match (data.shipped_status(order_id), shipment.delivered_at) {
+            ("delivered", Some(_)) => "delivered",
+            _ => "shipped",
+        };
+        if order.status != status {
+            data.ensure_status_change(&order, status, payments)?;
+        }
+
+        let id = next_id(&mut data.last_ids.shipment);
+        let created = Shipment { id, order_id, ..shipment.clone() };
+        data.shipments.insert(id, created.clone());
+        let change = match order.status != status {
+            true => data.set_status(order_id, status)?.1,
+            false => None,
+        };
+        drop(data);
+        publish_order_changes(change);
+        Ok(created)
+    }
+
+    async fn mark_shipment_delivered(
+        &self,
+        order_id: u32,
+        shipment_id: u32,
+        delivered_at: DateTime<Utc>,
+        payments: &PaymentConfig,
+    ) -> Result<Shipment, ApiError> {
+        let mut data = self.data();
+        let shipment = data.shipments.get(&shipment_id)
+            .filter(|shipment| shipment.order_id == order_id)
+            .cloned()
+            .ok_or_else(|| ApiError::NotFound("Shipment not found".to_string()))?;
+        validate_delivered_at(shipment.shipped_at, delivered_at)?;
+        let order = data.orders.get(&order_id).cloned().ok_or_else(order_not_found)?;
+
+        let delivered = Shipment { delivered_at: Some(delivered_at), ..shipment.clone() };
+        data.shipments.insert(shipment_id, delivered.clone());
+        let status = data.shipped_status(order_id);
+        if order.status == "cancelled" || order.status == status {
+            return Ok(delivered);
+        }
+
+        if let Err(err) = data.ensure_status_change(&order, status, payments) {
+            data.shipments.insert(shipment_id, shipment);
+            return Err(err);
+        }
+        let (_, change) = data.set_status(order_id, status)?;
+        drop(data);
+        publish_order_changes(change);
+        Ok(delivered)
+    }
+}
+
+#[async_trait]
+impl PaymentRepository for InMemoryStore {
+    async fn list_payments(&self, order_id: u32) -> Result<Vec<Payment>, ApiError> {
+        Ok(self.data().payments.values().filter(|payment| payment.order_id == order_id).cloned().collect())
+    }
+
+    async fn authorize_payment(&self, gateway: &dyn PaymentGateway, order_id: u32) -> Result<Payment, ApiError> {
+        let order = {
+            let data = self.data();
+            let order = data.orders.get(&order_id).cloned().ok_or_else(order_not_found)?;
+            if order.status == "cancelled" {
+                return Err(ApiError::Conflict(format!("Order {} is cancelled", order_id)));
+            }
+            if order.total_minor <= 0 {
+                return Err(ApiError::Validation(ValidationError {
+                    error: format!("Order {} has no amount to pay", order_id),
+                    field: Some("total_minor".to_string()),
+                }));
+            }
+            if data.payments.values().any(|payment| payment.order_id == order_id && is_active(payment)) {
+                return Err(ApiError::Conflict(format!("Order {} already has an active payment", order_id)));
+            }
+            order
+        };
+
+        let reference = gateway.authorize(order.total_minor, &order.currency).await?;
+
+        // Another authorization may have been recorded while the gateway was called
+        let payment = {
+            let mut data = self.data();
+            if data.payments.values().any(|payment| payment.order_id == order_id && is_active(payment)) {
+                None
+            } else {
+                let payment = Payment {
+                    id: next_id(&mut data.last_ids.payment),
+                    order_id,
+                    status: PaymentStatus::Authorized,
+                    amount_minor: order.total_minor,
+                    currency: order.currency,
+                    refunded_minor: 0,
+                    gateway_reference: reference.clone(),
+                };
+                data.payments.insert(payment.id, payment.clone());
+                Some(payment)
+            }
+        };
+
+        match payment {
+            Some(payment) => Ok(payment),
+            None => {
+                if let Err(e) = gateway.void(&reference).await {
+                    eprintln!("Failed to void duplicate authorization {}: {:?}", reference, e);
+                }
+                Err(ApiError::Conflict(format!("Order {} already has an active payment", order_id)))
+            }
+        }
+    }
+
+    async fn capture_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(order_id, payment_id, &[PaymentStatus::Authorized], "captured")?;
+
+        gateway.capture(&previous.gateway_reference, previous.amount_minor).await?;
+        let payment = Payment { status: PaymentStatus::Captured, ..previous.clone() };
+        self.record_transition(&previous, &payment)?;
+        Ok(payment)
+    }
+
+    async fn void_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(order_id, payment_id, &[PaymentStatus::Authorized], "voided")?;
+
+        gateway.void(&previous.gateway_reference).await?;
+        let payment = Payment { status: PaymentStatus::Voided, ..previous.clone() };
+        self.record_transition(&previous, &payment)?;
+        Ok(payment)
+    }
+
+    async fn refund_payment(
+        &self,
+        gateway: &dyn PaymentGateway,
+        order_id: u32,
+        payment_id: u32,
+        amount_minor: Option<i64>,
+    ) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(
+            order_id,
+            payment_id,
+            &[PaymentStatus::Captured, PaymentStatus::PartiallyRefunded],
+            "refunded",
+        )?;
+
+        let refundable = previous.amount_minor - previous.refunded_minor;
+        let amount_minor = amount_minor.unwrap_or(refundable);
+        if amount_minor <= 0 || amount_minor > refundable {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Refund amount must be between 1 and {} minor units", refundable),
+                field: Some("amount_minor".to_string()),
+            }));
+        }
+
+        gateway.refund(&previous.gateway_reference, amount_minor).await?;
+        let refunded_minor = previous.refunded_minor + amount_minor;
+        let status = if refunded_minor == previous.amount_minor {
+            PaymentStatus::Refunded
+        } else {
+            PaymentStatus::PartiallyRefunded
+        };
+        let payment = Payment { status, refunded_minor, ..previous.clone() };
+        self.record_transition(&previous, &payment)?;
+        Ok(payment)
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for InMemoryStore {
+    async fn list_webhook_subscriptions(&self) -> Result<Vec<WebhookSubscription>, ApiError> {
+        Ok(self.data().subscriptions.values().cloned().collect())
+    }
+
+    async fn get_webhook_subscription(&self, id: u32) -> Result<Option<WebhookSubscription>, ApiError> {
+        Ok(self.data().subscriptions.get(&id).cloned())
+    }
+
+    async fn create_webhook_subscription(&self, subscription: &WebhookSubscription) -> Result<WebhookSubscription, ApiError> {
+        let mut data = self.data();
+        let created = WebhookSubscription { id: next_id(&mut data.last_ids.subscription), ..subscription.clone() };
+        data.subscriptions.insert(created.id, created.clone());
+        Ok(created)
+    }
+
+    async fn delete_webhook_subscription(&self, id: u32) -> Result<WebhookSubscription, ApiError> {
+        let mut data = self.data();
+        let subscription = data.subscriptions.remove(&id)
+            .ok_or_else(|| ApiError::NotFound("Webhook subscription not found".to_string()))?;
+        data.deliveries.retain(|_, (subscription_id, _)| *subscription_id != id);
+        data.outbox.retain(|_, event| event.subscription_id != id);
+        Ok(subscription)
+    }
+
+    async fn webhook_deliveries(&self, subscription_id: u32) -> Result<Vec<WebhookDelivery>, ApiError> {
+        Ok(self.data().deliveries.values()
+            .rev()
+            .filter(|(id, _)| *id == subscription_id)
+            .map(|(_, delivery)| delivery.clone())
+            .collect())
+    }
+
+    async fn due_webhooks(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<OutboxEntry>, ApiError> {
+        let data = self.data();
+        Ok(data.outbox.iter()
+            .filter(|(_, event)| event.status == "pending" && event.next_attempt_at <= now)
+            .filter_map(|(id, event)| {
+                let subscription = data.subscriptions.get(&event.subscription_id)?;
+                Some(OutboxEntry {
+                    id: *id,
+                    subscription_id: event.subscription_id,
+                    event_id: event.event_id.clone(),
+                    event_type: event.event_type.clone(),
+                    payload: event.payload.clone(),
+                    attempts: event.attempts,
+                    url: subscription.url.clone(),
+                    secret: subscription.secret.clone(),
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn record_webhook_attempt(
+        &self,
+        entry: &OutboxEntry,
+        outcome: &DeliveryOutcome,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), ApiError> {
+        let attempt = entry.attempts + 1;
+        let status = match (outcome.succeeded(), retry_at) {
+            (true, _) => "delivered",
+            (false, Some(_)) => "pending",
+            (false, None) => "failed",
+        };
+
+        let mut data = self.data();
+        let id = next_id(&mut data.last_ids.delivery);
+        data.deliveries.insert(id, (entry.subscription_id, WebhookDelivery {
+            id,
+            event_id: entry.event_id.clone(),
+            event_type: entry.event_type.clone(),
+            attempt,
+            response_status: outcome.response_status,
+            error: outcome.error.clone(),
+            succeeded: outcome.succeeded(),
+            attempted_at: Utc::now(),
+        }));
+        if let Some(event) = data.outbox.get_mut(&entry.id) {
+            event.status = status;
+            event.attempts = attempt;
+            event.next_attempt_at = retry_at.unwrap_or(event.next_attempt_at);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for InMemoryStore {
+    async fn register_api_key(&self, name: &str, role: Role, secret: &str) -> Result<ApiKey, ApiError> {
+        let mut data = self.data();
+        data.last_ids.api_key += 1;
+        let key = ApiKey { id: data.last_ids.api_key, name: name.to_string(), role, revoked: false };
+        data.api_keys.insert(hash_api_key(secret), key.clone());
+        Ok(key)
+    }
+
+    async fn find_api_key(&self, secret: &str) -> Result<Option<ApiKey>, ApiError> {
+        Ok(self.data().api_keys.get(&hash_api_key(secret)).filter(|key| !key.revoked).cloned())
+    }
+
+    async fn register_certificate_identity(&self, subject: &str, name: &str, role: Role) -> Result<CertificateIdentity, ApiError> {
+        let identity = CertificateIdentity { subject: subject.to_string(), name: name.to_string(), role };
+        self.data().certificates.insert(subject.to_string(), identity.clone());
+        Ok(identity)
+    }
+
+    async fn find_certificate_identity(&self, subject: &str) -> Result<Option<CertificateIdentity>, ApiError> {
+        Ok(self.data().certificates.get(subject).cloned())
+    }
+}
+
+#[async_trait]
+impl Store for InMemoryStore {
+    async fn ping(&self) -> Result<(), ApiError> {
+        Ok(())
+    }
+
+    async fn migration_version(&self) -> Result<Option<i64>, ApiError> {
+        Ok(None)
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+    use crate::payments::FakePaymentGateway;
+    use crate::utils::PromotionKind;
+
+    fn order(id: u32, status: &str) -> Order {
+        Order {
+            id,
+            item: format!("Item {}", id),
+            status: status.to_string(),
+            quantity: 3,
+            unit_price_minor: 250,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_computes_totals_and_rejects_duplicates() {
+        let store = InMemoryStore::default();
+
+        let created = store.create_order(&order(1, "pending"), &PaymentConfig::default()).await.unwrap();
+        assert_eq!((created.subtotal_minor, created.discount_minor, created.total_minor), (750, 0, 750));
+
+        let result = store.create_order(&order(1, "processing"), &PaymentConfig::default()).await;
+        assert!(matches!(result, Err(ApiError::Validation(err)) if err.field.as_deref() == Some("id")));
+        assert_eq!(store.get_order(1).await.unwrap().unwrap().status, "pending");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_lists_and_streams_in_id_order() {
+        let store = InMemoryStore::default();
+        for (id, status) in [(3, "shipped"), (1, "pending"), (2, "shipped")] {
+            store.create_order(&order(id, status), &PaymentConfig::default()).await.unwrap();
+        }
+
+        let filter = OrderFilter { status: Some("shipped".to_string()), customer_id: None };
+        let listed: Vec<u32> = store.list_orders(&filter).await.unwrap().iter().map(|order| order.id).collect();
+        assert_eq!(listed, vec![2, 3]);
+
+        let streamed: Vec<Order> = store.stream_orders(OrderFilter::default()).try_collect().await.unwrap();
+        assert_eq!(streamed.iter().map(|order| order.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        let page = store.orders_page(&OrderFilter::default(), Some(1), 1).await.unwrap();
+        assert_eq!(page.iter().map(|order| order.id).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_checks_references_and_stock() {
+        let store = InMemoryStore::default();
+        let references = [
+            (Order { customer_id: Some(7), ..order(1, "pending") }, "customer_id", "Customer with ID 7 does not exist"),
+            (Order { sku: Some("WID-1".to_string()), ..order(1, "cancelled") }, "sku", "Product with SKU WID-1 does not exist"),
+            (Order { promo_code: Some("SPRING".to_string()), ..order(1, "pending") }, "promo_code", "Promo code SPRING does not exist"),
+        ];
+        for (order, field, message) in &references {
+            match store.create_order(order, &PaymentConfig::default()).await.unwrap_err() {
+                ApiError::Validation(err) => assert_eq!((err.field.as_deref(), err.error.as_str()), (Some(*field), *message)),
+                other => panic!("Expected validation error, got {:?}", other),
+            }
+        }
+        assert!(store.get_order(1).await.unwrap().is_none());
+
+        store.create_customer(&Customer { id: 7, name: "Ada".to_string(), email: "ada@example.com".to_string() }).await.unwrap();
+        store.create_product(&Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 4 }).await.unwrap();
+        store.create_promotion(&Promotion {
+            code: "SPRING".to_string(),
+            kind: PromotionKind::PercentOff,
+            percent_off: Some(10),
+            amount_off_minor: None,
+            currency: None,
+            buy_quantity: None,
+            free_quantity: None,
+            starts_at: None,
+            ends_at: None,
+            max_redemptions: Some(1),
+            redemptions: 0,
+        }).await.unwrap();
+
+        let referencing = Order {
+            customer_id: Some(7),
+            sku: Some("WID-1".to_string()),
+            promo_code: Some("SPRING".to_string()),
+            ..order(1, "pending")
+        };
+        let created = store.create_order(&referencing, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(created.discount_minor, 75);
+        assert_eq!(store.get_product("WID-1").await.unwrap().unwrap().stock, 1);
+        assert_eq!(store.get_promotion("SPRING").await.unwrap().unwrap().redemptions, 1);
+
+        // Neither a second redemption nor more stock than is left leaves anything behind
+        let result = store.create_order(&Order { id: 2, ..referencing.clone() }, &PaymentConfig::default()).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+        let result = store.create_order(&Order { id: 2, quantity: 1, ..referencing }, &PaymentConfig::default()).await;
+        assert!(matches!(result, Err(ApiError::Validation(err)) if err.field.as_deref() == Some("promo_code")));
+        assert_eq!(store.get_product("WID-1").await.unwrap().unwrap().stock, 1);
+
+        store.update_order_status(1, "cancelled", &PaymentConfig::default()).await.unwrap();
+        assert_eq!(store.get_product("WID-1").await.unwrap().unwrap().stock, 4);
+        assert!(matches!(store.delete_customer(7).await, Err(ApiError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_shipping_requires_captured_payment_in_memory() {
+        let store = InMemoryStore::default();
+        let payments = PaymentConfig { require_capture_to_ship: true, ..PaymentConfig::default() };
+        assert!(matches!(store.create_order(&order(1, "shipped"), &payments).await, Err(ApiError::Conflict(_))));
+        store.create_order(&order(1, "pending"), &payments).await.unwrap();
+
+        store.update_order_status(1, "processing", &payments).await.unwrap();
+        let result = store.update_order_status(1, "shipped", &payments).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+        assert_eq!(store.get_order(1).await.unwrap().unwrap().status, "processing");
+
+        let gateway = FakePaymentGateway::default();
+        let payment = store.authorize_payment(&gateway, 1).await.unwrap();
+        store.capture_payment(&gateway, 1, payment.id).await.unwrap();
+        store.update_order_status(1, "shipped", &payments).await.unwrap();
+
+        let changes = store.order_changes_since(0, &OrderChangeFilter::default(), 10).await.unwrap();
+        assert_eq!(changes.iter().map(|change| change.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+}
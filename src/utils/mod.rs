@@ -1,2 +1,30 @@
 pub mod db_utils;
+pub mod api_key_utils;
+pub mod customer_utils;
+pub mod product_utils;
+pub mod promotion_utils;
+pub mod shipment_utils;
+pub mod payment_utils;
+pub mod webhook_utils;
+pub mod order_change_utils;
+pub mod csv_utils;
+pub mod store;
+pub mod sqlite_store;
+pub mod memory_store;
+#[cfg(feature = "postgres")]
+pub mod pg_store;
 pub use db_utils::*;
+pub use api_key_utils::*;
+pub use customer_utils::*;
+pub use product_utils::*;
+pub use promotion_utils::*;
+pub use shipment_utils::*;
+pub use payment_utils::*;
+pub use webhook_utils::*;
+pub use order_change_utils::*;
+pub use csv_utils::*;
+pub use store::*;
+pub use sqlite_store::*;
+pub use memory_store::*;
+#[cfg(feature = "postgres")]
+pub use pg_store::*;
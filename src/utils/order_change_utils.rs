@@ -0,0 +1,250 @@
+use std::{collections::VecDeque, sync::LazyLock};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, QueryBuilder, Sqlite, SqliteConnection};
+use tokio::sync::broadcast;
+use crate::utils::{enqueue_order_event, DbPool, Order, OrderRepository, WebhookEvent};
+use crate::validators::{ApiError, ServerError};
+
+/// Changes buffered for each subscriber before it starts lagging behind the channel
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Changes read from the log per query when a subscriber catches up
+const REPLAY_BATCH_SIZE: u32 = 100;
+
+/// Committed order changes, published by every store once its writes are committed
+static ORDER_CHANGES: LazyLock<broadcast::Sender<OrderChange>> =
+    LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Change made to an order, numbered in the order changes were committed
+pub struct OrderChange {
+    /// Sequence number of the change, sent as the SSE event ID
+    pub seq: i64,
+    /// Kind of change: `order.created`, `order.updated`, `order.status_changed` or `order.deleted`
+    #[schema(example = "order.status_changed")]
+    pub event: String,
+    /// ID of the order changed
+    pub order_id: u32,
+    /// Order as it was after the change, or before it for `order.deleted`
+    #[sqlx(rename = "order_data", json)]
+    pub order: Order,
+    /// Status before the change, for `order.status_changed`
+    pub previous_status: Option<String>,
+    /// When the change was committed
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+/// Optional filters applied to the order event stream
+pub struct OrderChangeFilter {
+    /// Only send changes leaving the order in this status
+    pub status: Option<String>,
+    /// Only send changes to this order
+    pub id: Option<u32>,
+}
+
+/// Log a change as part of the caller's transaction and queue the matching webhook event.
+/// The change must be published with `publish_order_changes` once the transaction commits.
+pub async fn record_order_change(
+    conn: &mut SqliteConnection,
+    event: WebhookEvent,
+    order: &Order,
+    previous_status: Option<&str>,
+) -> Result<OrderChange, ApiError> {
+    let changed_at = Utc::now();
+    let result = sqlx::query(
+        "INSERT INTO order_changes (event, order_id, status, order_data, previous_status, changed_at) \
+         VALUES (?, ?, ?, ?, ?, ?)"
+    )
+        .bind(event.as_str())
+        .bind(order.id)
+        .bind(&order.status)
+        .bind(Json(order))
+        .bind(previous_status)
+        .bind(changed_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in record_order_change: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to record order change".to_string(),
+            })
+        })?;
+
+    enqueue_order_event(conn, event, order, previous_status).await?;
+
+    Ok(OrderChange {
+        seq: result.last_insert_rowid(),
+        event: event.as_str().to_string(),
+        order_id: order.id,
+        order: order.clone(),
+        previous_status: previous_status.map(str::to_string),
+        changed_at,
+    })
+}
+
+/// Announce committed changes to every subscriber
+pub fn publish_order_changes(changes: impl IntoIterator<Item = OrderChange>) {
+    for change in changes {
+        // Sending only fails when nobody is subscribed
+        let _ = ORDER_CHANGES.send(change);
+    }
+}
+
+/// Receive changes published from now on
+pub fn subscribe_order_changes() -> broadcast::Receiver<OrderChange> {
+    ORDER_CHANGES.subscribe()
+}
+
+/// Get the sequence number of the latest change, or 0 if there is none
+pub async fn get_latest_order_change_seq(pool: &DbPool) -> Result<i64, ApiError> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM order_changes")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_latest_order_change_seq: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve order changes".to_string(),
+            })
+        })
+}
+
+/// Get up to `limit` changes after `after_seq` matching `filter`, oldest first
+pub async fn get_order_changes_since(
+    pool: &DbPool,
+    after_seq: i64,
+    filter: &OrderChangeFilter,
+    limit: u32,
+) -> Result<Vec<OrderChange>, ApiError> {
+    let mut query = QueryBuilder::<Sqlite>::new(
+        "SELECT seq, event, order_id, order_data, previous_status, changed_at FROM order_changes WHERE seq > "
+    );
+    query.push_bind(after_seq);
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(order_id) = filter.id {
+        query.push(" AND order_id = ").push_bind(order_id);
+    }
+    query.push(" ORDER BY seq LIMIT ").push_bind(limit);
+
+    query.build_query_as::<OrderChange>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_order_changes_since: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve order changes".to_string(),
+            })
+        })
+}
+
+struct ChangeStream<R> {
+    orders: R,
+    receiver: broadcast::Receiver<OrderChange>,
+    filter: OrderChangeFilter,
+    last_seq: i64,
+    pending: VecDeque<OrderChange>,
+}
+
+/// Endless stream of the changes after `after_seq` matching `filter`.
+/// Published changes only wake the stream up; the changes themselves are read back from the log,
+/// so a subscriber that lagged behind the channel or resumed from an old sequence number misses nothing.
+/// The stream ends if the log cannot be read.
+pub fn order_change_stream<R: OrderRepository>(orders: R, after_seq: i64, filter: OrderChangeFilter) -> impl Stream<Item = OrderChange> {
+    // Subscribe before the first read so nothing committed in between is missed
+    let state = ChangeStream {
+        orders,
+        receiver: subscribe_order_changes(),
+        filter,
+        last_seq: after_seq,
+        pending: VecDeque::new(),
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(change) = state.pending.pop_front() {
+                state.last_seq = change.seq;
+                return Some((change, state));
+            }
+
+            match state.orders.order_changes_since(state.last_seq, &state.filter, REPLAY_BATCH_SIZE).await {
+                Ok(changes) if !changes.is_empty() => {
+                    state.pending.extend(changes);
+                    continue;
+                }
+                Ok(_) => {}
+                Err(_) => return None,
+            }
+
+            match state.receiver.recv().await {
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use futures_util::StreamExt;
+    use crate::config::PaymentConfig;
+    use crate::utils::{create_order, delete_order, init_db, update_order_status, SqliteStore};
+
+    fn order(id: u32) -> Order {
+        Order {
+            id,
+            item: "Jacquard Loom".to_string(),
+            status: "pending".to_string(),
+            quantity: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_logged_in_sequence() {
+        let pool = init_db().await.unwrap();
+        create_order(&pool, &order(1), &PaymentConfig::default()).await.unwrap();
+        update_order_status(&pool, 1, "processing", &PaymentConfig::default()).await.unwrap();
+        delete_order(&pool, 1).await.unwrap();
+
+        let changes = get_order_changes_since(&pool, 0, &OrderChangeFilter::default(), 10).await.unwrap();
+        let events: Vec<&str> = changes.iter().map(|change| change.event.as_str()).collect();
+        assert_eq!(events, ["order.created", "order.status_changed", "order.deleted"]);
+        assert!(changes.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+        assert_eq!(changes[1].previous_status.as_deref(), Some("pending"));
+        assert_eq!(get_latest_order_change_seq(&pool).await.unwrap(), changes[2].seq);
+
+        let filter = OrderChangeFilter { status: Some("processing".to_string()), id: None };
+        let changes = get_order_changes_since(&pool, 0, &filter, 10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_replays_then_follows_live_changes() {
+        let pool = init_db().await.unwrap();
+        create_order(&pool, &order(1), &PaymentConfig::default()).await.unwrap();
+        create_order(&pool, &order(2), &PaymentConfig::default()).await.unwrap();
+        let resume_from = get_latest_order_change_seq(&pool).await.unwrap() - 1;
+
+        let filter = OrderChangeFilter { status: None, id: Some(2) };
+        let mut changes = Box::pin(order_change_stream(SqliteStore::new(pool.clone()), resume_from, filter));
+
+        let replayed = changes.next().await.unwrap();
+        assert_eq!((replayed.event.as_str(), replayed.order_id), ("order.created", 2));
+
+        update_order_status(&pool, 1, "shipped", &PaymentConfig::default()).await.unwrap();
+        update_order_status(&pool, 2, "shipped", &PaymentConfig::default()).await.unwrap();
+        let live = tokio::time::timeout(Duration::from_secs(5), changes.next()).await.unwrap().unwrap();
+        assert_eq!((live.event.as_str(), live.order_id), ("order.status_changed", 2));
+        assert_eq!(live.order.status, "shipped");
+    }
+}
@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use crate::config::PaymentConfig;
+use crate::payments::PaymentGateway;
+use crate::utils::{begin_transaction, commit_transaction, get_order_by_id, DbPool};
+use crate::validators::{ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema, async_graphql::Enum)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+/// Where a payment is in its lifecycle
+pub enum PaymentStatus {
+    /// Funds are held but not yet collected
+    Authorized,
+    /// Funds have been collected
+    Captured,
+    /// Part of the captured funds has been returned
+    PartiallyRefunded,
+    /// All captured funds have been returned
+    Refunded,
+    /// The hold was released without collecting anything
+    Voided,
+}
+
+impl PaymentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Authorized => "authorized",
+            PaymentStatus::Captured => "captured",
+            PaymentStatus::PartiallyRefunded => "partially_refunded",
+            PaymentStatus::Refunded => "refunded",
+            PaymentStatus::Voided => "voided",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema, async_graphql::SimpleObject)]
+/// Money taken for an order through the payment gateway
+pub struct Payment {
+    /// Unique identifier for the payment
+    pub id: u32,
+    /// Order the payment is for
+    pub order_id: u32,
+    /// Current state of the payment
+    pub status: PaymentStatus,
+    /// Amount authorized and captured, in minor units of `currency`; the order total when authorized
+    pub amount_minor: i64,
+    /// ISO 4217 code of the payment's currency
+    pub currency: String,
+    /// Amount returned to the customer so far
+    pub refunded_minor: i64,
+    /// Reference of the charge at the payment gateway
+    pub gateway_reference: String,
+}
+
+/// Columns selected whenever a payment is read back
+const PAYMENT_COLUMNS: &str = "id, order_id, status, amount_minor, currency, refunded_minor, gateway_reference";
+
+/// Statuses of payments holding or having collected money
+const ACTIVE_STATUSES: &str = "('authorized', 'captured', 'partially_refunded')";
+
+/// Get all payments taken for an order, oldest first
+pub async fn get_payments_for_order(pool: &DbPool, order_id: u32) -> Result<Vec<Payment>, ApiError> {
+    sqlx::query_as::<_, Payment>(&format!("SELECT {} FROM payments WHERE order_id = ? ORDER BY id", PAYMENT_COLUMNS))
+        .bind(order_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_payments_for_order: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve payments".to_string(),
+            })
+        })
+}
+
+/// Get a specific payment of an order
+pub async fn get_payment(pool: &DbPool, order_id: u32, payment_id: u32) -> Result<Option<Payment>, ApiError> {
+    sqlx::query_as::<_, Payment>(&format!("SELECT {} FROM payments WHERE id = ? AND order_id = ?", PAYMENT_COLUMNS))
+        .bind(payment_id)
+        .bind(order_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_payment: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve payment".to_string(),
+            })
+        })
+}
+
+async fn count_active_payments<'e, E>(executor: E, order_id: u32) -> Result<i64, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM payments WHERE order_id = ? AND status IN {}",
+        ACTIVE_STATUSES
+    ))
+        .bind(order_id)
+        .fetch_one(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in count_active_payments: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve payments".to_string(),
+            })
+        })
+}
+
+/// Authorize the order's total with the gateway and record the payment.
+/// An order can only have one payment holding or having collected money at a time.
+pub async fn authorize_payment(pool: &DbPool, gateway: &dyn PaymentGateway, order_id: u32) -> Result<Payment, ApiError> {
+    let order = get_order_by_id(pool, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    if order.status == "cancelled" {
+        return Err(ApiError::Conflict(format!("Order {} is cancelled", order_id)));
+    }
+    if order.total_minor <= 0 {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Order {} has no amount to pay", order_id),
+            field: Some("total_minor".to_string()),
+        }));
+    }
+    if count_active_payments(pool, order_id).await? > 0 {
+        return Err(ApiError::Conflict(format!("Order {} already has an active payment", order_id)));
+    }
+
+    let reference = gateway.authorize(order.total_minor, &order.currency).await?;
+
+    // Another authorization may have been recorded while the gateway was called
+    let mut tx = begin_transaction(pool, "authorize_payment").await?;
+    if count_active_payments(&mut *tx, order_id).await? > 0 {
+        drop(tx);
+        if let Err(e) = gateway.void(&reference).await {
+            eprintln!("Failed to void duplicate authorization {}: {:?}", reference, e);
+        }
+        return Err(ApiError::Conflict(format!("Order {} already has an active payment", order_id)));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO payments (order_id, status, amount_minor, currency, gateway_reference) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(PaymentStatus::Authorized)
+        .bind(order.total_minor)
+        .bind(&order.currency)
+        .bind(&reference)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in authorize_payment: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to record payment".to_string(),
+            })
+        })?;
+    commit_transaction(tx, "authorize_payment").await?;
+
+    Ok(Payment {
+        id: result.last_insert_rowid() as u32,
+        order_id,
+        status: PaymentStatus::Authorized,
+        amount_minor: order.total_minor,
+        currency: order.currency,
+        refunded_minor: 0,
+        gateway_reference: reference,
+    })
+}
+
+/// Fetch a payment and check it is in `expected` state before acting on it
+async fn payment_in_state(
+    pool: &DbPool,
+    order_id: u32,
+    payment_id: u32,
+    expected: &[PaymentStatus],
+    action: &str,
+) -> Result<Payment, ApiError> {
+    let payment = get_payment(pool, order_id, payment_id).await?
+        .ok_or_else(|| ApiError::NotFound("Payment not found".to_string()))?;
+    if !expected.contains(&payment.status) {
+        return Err(ApiError::Conflict(format!(
+            "Payment {} is {} and cannot be {}",
+            payment_id, payment.status.as_str(), action
+        )));
+    }
+    Ok(payment)
+}
+
+/// Record a change made at the gateway, provided the payment still is as it was read
+async fn record_transition(pool: &DbPool, previous: &Payment, payment: &Payment, function: &str) -> Result<(), ApiError> {
+    let result = sqlx::query(
+        "UPDATE payments SET status = ?, refunded_minor = ? WHERE id = ? AND status = ? AND refunded_minor = ?"
+    )
+        .bind(payment.status)
+        .bind(payment.refunded_minor)
+        .bind(payment.id)
+        .bind(previous.status)
+        .bind(previous.refunded_minor)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in {}: {}", function, e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to update payment".to_string(),
+            })
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::Conflict(format!("Payment {} was changed concurrently", payment.id)));
+    }
+    Ok(())
+}
+
+/// Collect an authorized payment
+pub async fn capture_payment(
+    pool: &DbPool,
+    gateway: &dyn PaymentGateway,
+    order_id: u32,
+    payment_id: u32,
+) -> Result<Payment, ApiError> {
+    let previous = payment_in_state(pool, order_id, payment_id, &[PaymentStatus::Authorized], "captured").await?;
+
+    gateway.capture(&previous.gateway_reference, previous.amount_minor).await?;
+    let payment = Payment { status: PaymentStatus::Captured, ..previous.clone() };
+    record_transition(pool, &previous, &payment, "capture_payment").await?;
+    Ok(payment)
+}
+
+/// Release an authorized payment without collecting it
+pub async fn void_payment(
+    pool: &DbPool,
+    gateway: &dyn PaymentGateway,
+    order_id: u32,
+    payment_id: u32,
+) -> Result<Payment, ApiError> {
+    let previous = payment_in_state(pool, order_id, payment_id, &[PaymentStatus::Authorized], "voided").await?;
+
+    gateway.void(&previous.gateway_reference).await?;
+    let payment = Payment { status: PaymentStatus::Voided, ..previous.clone() };
+    record_transition(pool, &previous, &payment, "void_payment").await?;
+    Ok(payment)
+}
+
+/// Return `amount_minor` of a captured payment, or everything not yet refunded
+pub async fn refund_payment(
+    pool: &DbPool,
+    gateway: &dyn PaymentGateway,
+    order_id: u32,
+    payment_id: u32,
+    amount_minor: Option<i64>,
+) -> Result<Payment, ApiError> {
+    let previous = payment_in_state(
+        pool,
+        order_id,
+        payment_id,
+        &[PaymentStatus::Captured, PaymentStatus::PartiallyRefunded],
+        "refunded",
+    ).await?;
+
+    let refundable = previous.amount_minor - previous.refunded_minor;
+    let amount_minor = amount_minor.unwrap_or(refundable);
+    if amount_minor <= 0 || amount_minor > refundable {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Refund amount must be between 1 and {} minor units", refundable),
+            field: Some("amount_minor".to_string()),
+        }));
+    }
+
+    gateway.refund(&previous.gateway_reference, amount_minor).await?;
+    let refunded_minor = previous.refunded_minor + amount_minor;
+    let status = if refunded_minor == previous.amount_minor {
+        PaymentStatus::Refunded
+    } else {
+        PaymentStatus::PartiallyRefunded
+    };
+    let payment = Payment { status, refunded_minor, ..previous.clone() };
+    record_transition(pool, &previous, &payment, "refund_payment").await?;
+    Ok(payment)
+}
+
+/// Whether `payments` requires a captured payment to move an order from `previous_status` (none for a
+/// new order) to `status`: the order ships, reaching `shipped` or `delivered` without having been in either
+pub(crate) fn requires_captured_payment(payments: &PaymentConfig, previous_status: Option<&str>, status: &str) -> bool {
+    let shipped = |status: &str| matches!(status, "shipped" | "delivered");
+    payments.require_capture_to_ship && shipped(status) && !previous_status.is_some_and(shipped)
+}
+
+/// Error for shipping an order whose payment has not been captured
+pub(crate) fn payment_not_captured(order_id: u32) -> ApiError {
+    ApiError::Conflict(format!("Order {} cannot be shipped before its payment is captured", order_id))
+}
+
+/// Reject shipping an order before a payment for it has been captured, when the payment configuration
+/// requires one. Runs in the caller's transaction so the payment cannot change before the order is written.
+pub async fn ensure_shipping_paid(
+    conn: &mut SqliteConnection,
+    payments: &PaymentConfig,
+    order_id: u32,
+    previous_status: Option<&str>,
+    status: &str,
+) -> Result<(), ApiError> {
+    if requires_captured_payment(payments, previous_status, status) && !has_captured_payment(conn, order_id).await? {
+        return Err(payment_not_captured(order_id));
+    }
+    Ok(())
+}
+
+/// Whether a payment for the order has been captured, including one refunded only in part
+pub async fn has_captured_payment<'e, E>(executor: E, order_id: u32) -> Result<bool, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let captured: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM payments WHERE order_id = ? AND status IN ('captured', 'partially_refunded')"
+    )
+        .bind(order_id)
+        .fetch_one(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in has_captured_payment: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve payments".to_string(),
+            })
+        })?;
+
+    Ok(captured > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payments::FakePaymentGateway;
+    use crate::utils::{create_order, init_db, update_order, update_order_status, Order};
+
+    async fn setup_order(pool: &DbPool, unit_price_minor: i64) {
+        create_order(pool, &Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "pending".to_string(),
+            quantity: 2,
+            unit_price_minor,
+            ..Default::default()
+        }, &PaymentConfig::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_payment_lifecycle() {
+        let pool = init_db().await.unwrap();
+        let gateway = FakePaymentGateway::default();
+        setup_order(&pool, 500).await;
+
+        let payment = authorize_payment(&pool, &gateway, 1).await.unwrap();
+        assert_eq!(payment.status, PaymentStatus::Authorized);
+        assert_eq!(payment.amount_minor, 1000);
+        assert!(matches!(authorize_payment(&pool, &gateway, 1).await, Err(ApiError::Conflict(_))));
+
+        let captured = capture_payment(&pool, &gateway, 1, payment.id).await.unwrap();
+        assert_eq!(captured.status, PaymentStatus::Captured);
+        assert!(matches!(void_payment(&pool, &gateway, 1, payment.id).await, Err(ApiError::Conflict(_))));
+
+        let partial = refund_payment(&pool, &gateway, 1, payment.id, Some(300)).await.unwrap();
+        assert_eq!(partial.status, PaymentStatus::PartiallyRefunded);
+        match refund_payment(&pool, &gateway, 1, payment.id, Some(701)).await.unwrap_err() {
+            ApiError::Validation(err) => assert_eq!(err.field, Some("amount_minor".to_string())),
+            _ => panic!("Expected validation error"),
+        }
+
+        let refunded = refund_payment(&pool, &gateway, 1, payment.id, None).await.unwrap();
+        assert_eq!(refunded.status, PaymentStatus::Refunded);
+        assert_eq!(refunded.refunded_minor, 1000);
+
+        let stored = get_payments_for_order(&pool, 1).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].status, PaymentStatus::Refunded);
+
+        // A fully refunded payment no longer blocks a new authorization
+        authorize_payment(&pool, &gateway, 1).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_declined_authorization_is_not_recorded() {
+        let pool = init_db().await.unwrap();
+        let gateway = FakePaymentGateway::declining_above(999);
+        setup_order(&pool, 500).await;
+
+        assert!(matches!(authorize_payment(&pool, &gateway, 1).await, Err(ApiError::PaymentDeclined(_))));
+        assert!(get_payments_for_order(&pool, 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unpriced_order_cannot_be_paid() {
+        let pool = init_db().await.unwrap();
+        setup_order(&pool, 0).await;
+
+        let result = authorize_payment(&pool, &FakePaymentGateway::default(), 1).await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_shipping_requires_captured_payment() {
+        let pool = init_db().await.unwrap();
+        let payments = PaymentConfig { require_capture_to_ship: true, ..PaymentConfig::default() };
+        setup_order(&pool, 500).await;
+
+        update_order_status(&pool, 1, "processing", &payments).await.unwrap();
+        for status in ["shipped", "delivered"] {
+            let result = update_order_status(&pool, 1, status, &payments).await;
+            assert!(matches!(result, Err(ApiError::Conflict(_))), "{} should need a captured payment", status);
+        }
+        let shipped = Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "shipped".to_string(),
+            quantity: 2,
+            unit_price_minor: 500,
+            ..Default::default()
+        };
+        assert!(matches!(update_order(&pool, 1, &shipped, &payments).await, Err(ApiError::Conflict(_))));
+        // A new order has no payment yet
+        let result = create_order(&pool, &Order { id: 2, ..shipped.clone() }, &payments).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+
+        let payment = authorize_payment(&pool, payments.gateway.as_ref(), 1).await.unwrap();
+        assert!(update_order_status(&pool, 1, "shipped", &payments).await.is_err());
+        assert_eq!(get_order_by_id(&pool, 1).await.unwrap().unwrap().status, "processing");
+
+        capture_payment(&pool, payments.gateway.as_ref(), 1, payment.id).await.unwrap();
+        update_order_status(&pool, 1, "shipped", &payments).await.unwrap();
+        update_order_status(&pool, 1, "delivered", &payments).await.unwrap();
+
+        create_order(&pool, &Order { id: 2, ..shipped }, &PaymentConfig::default()).await.unwrap();
+    }
+}
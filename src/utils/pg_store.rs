@@ -0,0 +1,2195 @@
+use std::{str::FromStr, sync::Arc};
+use async_trait::async_trait;
+use chrono::{DateTime, Days, Utc};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::types::Json;
+use sqlx::{Executor, PgConnection, Pool, Postgres, QueryBuilder, Transaction};
+use tokio::sync::{mpsc, OnceCell};
+use crate::config::PaymentConfig;
+use crate::payments::PaymentGateway;
+use crate::utils::{
+    daily_series, hash_api_key, order_event_payload, payment_not_captured, promotion_discount, publish_order_changes,
+    requires_captured_payment, reserved_sku, unknown_customer, unknown_product, unknown_promotion, ApiKey,
+    CertificateIdentity, CredentialRepository, Customer, CustomerRepository, DailyOrderStats, DeliveryOutcome,
+    ItemStats, Order, OrderChange, OrderChangeFilter, OrderFilter, OrderRepository, OrderStats, OutboxEntry, Payment,
+    PaymentRepository, PaymentStatus, PoolStatus, Product, ProductRepository, Promotion, PromotionKind,
+    PromotionRepository, Role, Shipment, ShipmentRepository, ShippingAddress, StatusStats, Store, WebhookDelivery,
+    WebhookEvent, WebhookRepository, WebhookSubscription
+};
+use crate::validators::{validate_delivered_at, validate_status, ApiError, ServerError, ValidationError};
+
+pub type PgDbPool = Pool<Postgres>;
+
+/// Rows read ahead of a slow consumer of `stream_orders`
+const STREAM_BUFFER_SIZE: usize = 64;
+
+/// Key of the advisory lock held while migrations run, so concurrent instances apply them once
+const MIGRATION_LOCK_KEY: i64 = 0x6f72_6465_7273;
+
+/// Key of the advisory lock taken by every write transaction before anything else. Writers queue on it
+/// like SQLite's `BEGIN IMMEDIATE`, so checks made in a transaction hold until it commits and change log
+/// sequence numbers become visible in the order they were handed out.
+const WRITE_LOCK_KEY: i64 = 0x6f72_6465_7277;
+
+/// Schema migrations, applied in order on first use.
+/// The migration version stored in `schema_version` is the number of entries applied.
+/// IDs, quantities and stock are `BIGINT` constrained to the `u32` range the API accepts.
+/// Keys that listings sort by use the "C" collation, so they sort by byte like in SQLite.
+const MIGRATIONS: &[&str] = &[
+    // 1: order statuses as a native enum
+    r#"
+    CREATE TYPE order_status AS ENUM ('pending', 'processing', 'shipped', 'delivered', 'cancelled')
+    "#,
+    // 2: orders table
+    r#"
+    CREATE TABLE orders (
+        id BIGINT PRIMARY KEY CHECK (id BETWEEN 0 AND 4294967295),
+        item TEXT NOT NULL,
+        status order_status NOT NULL,
+        quantity BIGINT NOT NULL CHECK (quantity BETWEEN 0 AND 4294967295),
+        customer_id BIGINT CHECK (customer_id BETWEEN 0 AND 4294967295),
+        sku TEXT,
+        unit_price_minor BIGINT NOT NULL DEFAULT 0 CHECK (unit_price_minor >= 0),
+        currency TEXT NOT NULL DEFAULT 'USD',
+        promo_code TEXT,
+        discount_minor BIGINT NOT NULL DEFAULT 0,
+        shipping_address JSONB
+    )
+    "#,
+    // 3: listings filter by status and by customer
+    r#"
+    CREATE INDEX orders_status ON orders (status)
+    "#,
+    r#"
+    CREATE INDEX orders_customer_id ON orders (customer_id)
+    "#,
+    // 5: hashed API keys used for authentication
+    r#"
+    CREATE TABLE api_keys (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL UNIQUE,
+        role TEXT NOT NULL DEFAULT 'read_only',
+        revoked BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 6: TLS client certificate subjects mapped to caller identities
+    r#"
+    CREATE TABLE client_certificates (
+        subject TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        role TEXT NOT NULL,
+        revoked BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 7: customers placing orders
+    r#"
+    CREATE TABLE customers (
+        id BIGINT PRIMARY KEY CHECK (id BETWEEN 0 AND 4294967295),
+        name TEXT NOT NULL,
+        email TEXT NOT NULL UNIQUE,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 8: product catalog with stock on hand
+    r#"
+    CREATE TABLE products (
+        sku TEXT COLLATE "C" PRIMARY KEY,
+        name TEXT NOT NULL,
+        stock BIGINT NOT NULL CHECK (stock BETWEEN 0 AND 4294967295),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 9: discount codes and their redemption counts
+    r#"
+    CREATE TABLE promotions (
+        code TEXT COLLATE "C" PRIMARY KEY,
+        kind TEXT NOT NULL,
+        percent_off BIGINT CHECK (percent_off BETWEEN 0 AND 4294967295),
+        amount_off_minor BIGINT,
+        currency TEXT,
+        buy_quantity BIGINT CHECK (buy_quantity BETWEEN 0 AND 4294967295),
+        free_quantity BIGINT CHECK (free_quantity BETWEEN 0 AND 4294967295),
+        starts_at TIMESTAMPTZ,
+        ends_at TIMESTAMPTZ,
+        max_redemptions BIGINT CHECK (max_redemptions BETWEEN 0 AND 4294967295),
+        redemptions BIGINT NOT NULL DEFAULT 0 CHECK (redemptions BETWEEN 0 AND 4294967295),
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 10: orders reference the customer who placed them, the product they reserve stock for and their promotion
+    r#"
+    ALTER TABLE orders
+        ALTER COLUMN sku TYPE TEXT COLLATE "C",
+        ALTER COLUMN promo_code TYPE TEXT COLLATE "C",
+        ADD FOREIGN KEY (customer_id) REFERENCES customers (id),
+        ADD FOREIGN KEY (sku) REFERENCES products (sku),
+        ADD FOREIGN KEY (promo_code) REFERENCES promotions (code)
+    "#,
+    // 11: shipments recorded against orders
+    r#"
+    CREATE TABLE shipments (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        order_id BIGINT NOT NULL REFERENCES orders (id),
+        carrier TEXT NOT NULL,
+        tracking_number TEXT NOT NULL,
+        shipped_at TIMESTAMPTZ NOT NULL,
+        delivered_at TIMESTAMPTZ,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 12: payments taken for orders through the payment gateway
+    r#"
+    CREATE TABLE payments (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        order_id BIGINT NOT NULL REFERENCES orders (id),
+        status TEXT NOT NULL,
+        amount_minor BIGINT NOT NULL CHECK (amount_minor > 0),
+        currency TEXT NOT NULL,
+        refunded_minor BIGINT NOT NULL DEFAULT 0 CHECK (refunded_minor <= amount_minor),
+        gateway_reference TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 13: endpoints subscribed to order events
+    r#"
+    CREATE TABLE webhook_subscriptions (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        url TEXT NOT NULL,
+        event_types JSONB NOT NULL,
+        secret TEXT NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 14: events waiting to be delivered to a subscription, written with the change that caused them.
+    // The payload stays text so it is delivered and signed byte for byte as it was written.
+    r#"
+    CREATE TABLE webhook_outbox (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        subscription_id BIGINT NOT NULL REFERENCES webhook_subscriptions (id),
+        event_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        attempts BIGINT NOT NULL DEFAULT 0,
+        next_attempt_at TIMESTAMPTZ NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    )
+    "#,
+    // 15: the dispatcher only looks for pending events that are due
+    r#"
+    CREATE INDEX webhook_outbox_due ON webhook_outbox (next_attempt_at) WHERE status = 'pending'
+    "#,
+    // 16: every delivery attempt and its outcome
+    r#"
+    CREATE TABLE webhook_deliveries (
+        id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        outbox_id BIGINT NOT NULL REFERENCES webhook_outbox (id),
+        subscription_id BIGINT NOT NULL REFERENCES webhook_subscriptions (id),
+        event_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        attempt BIGINT NOT NULL,
+        response_status INTEGER,
+        error TEXT,
+        succeeded BOOLEAN NOT NULL,
+        attempted_at TIMESTAMPTZ NOT NULL
+    )
+    "#,
+    // 17: sequenced log of order changes, replayed to event stream subscribers that reconnect
+    r#"
+    CREATE TABLE order_changes (
+        seq BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+        event TEXT NOT NULL,
+        order_id BIGINT NOT NULL,
+        status order_status NOT NULL,
+        order_data JSONB NOT NULL,
+        previous_status order_status,
+        changed_at TIMESTAMPTZ NOT NULL
+    )
+    "#,
+];
+
+/// Columns selected whenever an order is read back, including the computed totals
+const ORDER_COLUMNS: &str = "id, item, status, quantity, customer_id, sku, unit_price_minor, currency, \
+    promo_code, discount_minor, shipping_address, \
+    unit_price_minor * quantity AS subtotal_minor, \
+    unit_price_minor * quantity - discount_minor AS total_minor";
+
+/// Columns selected whenever a promotion is read back
+const PROMOTION_COLUMNS: &str = "code, kind, percent_off, amount_off_minor, currency, buy_quantity, \
+    free_quantity, starts_at, ends_at, max_redemptions, redemptions";
+
+/// Columns selected whenever a shipment is read back
+const SHIPMENT_COLUMNS: &str = "id, order_id, carrier, tracking_number, shipped_at, delivered_at";
+
+/// Columns selected whenever a payment is read back
+const PAYMENT_COLUMNS: &str = "id, order_id, status, amount_minor, currency, refunded_minor, gateway_reference";
+
+/// Statuses of payments holding or having collected money
+const ACTIVE_STATUSES: &str = "('authorized', 'captured', 'partially_refunded')";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "order_status", rename_all = "lowercase")]
+/// Status of an order as stored in the `order_status` enum
+enum OrderStatus {
+    Pending,
+    Processing,
+    Shipped,
+    Delivered,
+    Cancelled,
+}
+
+impl OrderStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Processing => "processing",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = ValidationError;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        validate_status(status)?;
+        Ok(match status {
+            "pending" => OrderStatus::Pending,
+            "processing" => OrderStatus::Processing,
+            "shipped" => OrderStatus::Shipped,
+            "delivered" => OrderStatus::Delivered,
+            _ => OrderStatus::Cancelled,
+        })
+    }
+}
+
+/// Narrow an integer read from PostgreSQL to the type the API uses for `column`.
+/// The table constraints keep it in range; a failure means the schema was changed by hand.
+fn narrow<T: TryFrom<i64>>(value: i64, column: &'static str) -> Result<T, ApiError> {
+    T::try_from(value).map_err(|_| {
+        eprintln!("Database error in narrow: {} is out of range for {}", value, column);
+        ApiError::Server(ServerError {
+            error: "Database error".to_string(),
+            message: "Stored value is out of range".to_string(),
+        })
+    })
+}
+
+#[derive(Debug, sqlx::FromRow)]
+/// Order as read from PostgreSQL, before its integers are narrowed to the API's types
+struct OrderRow {
+    id: i64,
+    item: String,
+    status: OrderStatus,
+    quantity: i64,
+    customer_id: Option<i64>,
+    sku: Option<String>,
+    unit_price_minor: i64,
+    currency: String,
+    promo_code: Option<String>,
+    discount_minor: i64,
+    shipping_address: Option<Json<ShippingAddress>>,
+    subtotal_minor: i64,
+    total_minor: i64,
+}
+
+impl TryFrom<OrderRow> for Order {
+    type Error = ApiError;
+
+    fn try_from(row: OrderRow) -> Result<Self, Self::Error> {
+        Ok(Order {
+            id: narrow(row.id, "orders.id")?,
+            item: row.item,
+            status: row.status.as_str().to_string(),
+            quantity: narrow(row.quantity, "orders.quantity")?,
+            customer_id: row.customer_id.map(|id| narrow(id, "orders.customer_id")).transpose()?,
+            sku: row.sku,
+            unit_price_minor: row.unit_price_minor,
+            currency: row.currency,
+            promo_code: row.promo_code,
+            shipping_address: row.shipping_address.map(|address| address.0),
+            subtotal_minor: row.subtotal_minor,
+            discount_minor: row.discount_minor,
+            total_minor: row.total_minor,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct CustomerRow {
+    id: i64,
+    name: String,
+    email: String,
+}
+
+impl TryFrom<CustomerRow> for Customer {
+    type Error = ApiError;
+
+    fn try_from(row: CustomerRow) -> Result<Self, Self::Error> {
+        Ok(Customer { id: narrow(row.id, "customers.id")?, name: row.name, email: row.email })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ProductRow {
+    sku: String,
+    name: String,
+    stock: i64,
+}
+
+impl TryFrom<ProductRow> for Product {
+    type Error = ApiError;
+
+    fn try_from(row: ProductRow) -> Result<Self, Self::Error> {
+        Ok(Product { sku: row.sku, name: row.name, stock: narrow(row.stock, "products.stock")? })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PromotionRow {
+    code: String,
+    kind: PromotionKind,
+    percent_off: Option<i64>,
+    amount_off_minor: Option<i64>,
+    currency: Option<String>,
+    buy_quantity: Option<i64>,
+    free_quantity: Option<i64>,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    max_redemptions: Option<i64>,
+    redemptions: i64,
+}
+
+impl TryFrom<PromotionRow> for Promotion {
+    type Error = ApiError;
+
+    fn try_from(row: PromotionRow) -> Result<Self, Self::Error> {
+        Ok(Promotion {
+            code: row.code,
+            kind: row.kind,
+            percent_off: row.percent_off.map(|value| narrow(value, "promotions.percent_off")).transpose()?,
+            amount_off_minor: row.amount_off_minor,
+            currency: row.currency,
+            buy_quantity: row.buy_quantity.map(|value| narrow(value, "promotions.buy_quantity")).transpose()?,
+            free_quantity: row.free_quantity.map(|value| narrow(value, "promotions.free_quantity")).transpose()?,
+            starts_at: row.starts_at,
+            ends_at: row.ends_at,
+            max_redemptions: row.max_redemptions.map(|value| narrow(value, "promotions.max_redemptions")).transpose()?,
+            redemptions: narrow(row.redemptions, "promotions.redemptions")?,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ShipmentRow {
+    id: i64,
+    order_id: i64,
+    carrier: String,
+    tracking_number: String,
+    shipped_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ShipmentRow> for Shipment {
+    type Error = ApiError;
+
+    fn try_from(row: ShipmentRow) -> Result<Self, Self::Error> {
+        Ok(Shipment {
+            id: narrow(row.id, "shipments.id")?,
+            order_id: narrow(row.order_id, "shipments.order_id")?,
+            carrier: row.carrier,
+            tracking_number: row.tracking_number,
+            shipped_at: row.shipped_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PaymentRow {
+    id: i64,
+    order_id: i64,
+    status: PaymentStatus,
+    amount_minor: i64,
+    currency: String,
+    refunded_minor: i64,
+    gateway_reference: String,
+}
+
+impl TryFrom<PaymentRow> for Payment {
+    type Error = ApiError;
+
+    fn try_from(row: PaymentRow) -> Result<Self, Self::Error> {
+        Ok(Payment {
+            id: narrow(row.id, "payments.id")?,
+            order_id: narrow(row.order_id, "payments.order_id")?,
+            status: row.status,
+            amount_minor: row.amount_minor,
+            currency: row.currency,
+            refunded_minor: row.refunded_minor,
+            gateway_reference: row.gateway_reference,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct SubscriptionRow {
+    id: i64,
+    url: String,
+    event_types: Json<Vec<WebhookEvent>>,
+    secret: String,
+}
+
+impl TryFrom<SubscriptionRow> for WebhookSubscription {
+    type Error = ApiError;
+
+    fn try_from(row: SubscriptionRow) -> Result<Self, Self::Error> {
+        Ok(WebhookSubscription {
+            id: narrow(row.id, "webhook_subscriptions.id")?,
+            url: row.url,
+            event_types: row.event_types.0,
+            secret: row.secret,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct DeliveryRow {
+    id: i64,
+    event_id: String,
+    event_type: String,
+    attempt: i64,
+    response_status: Option<i32>,
+    error: Option<String>,
+    succeeded: bool,
+    attempted_at: DateTime<Utc>,
+}
+
+impl TryFrom<DeliveryRow> for WebhookDelivery {
+    type Error = ApiError;
+
+    fn try_from(row: DeliveryRow) -> Result<Self, Self::Error> {
+        Ok(WebhookDelivery {
+            id: narrow(row.id, "webhook_deliveries.id")?,
+            event_id: row.event_id,
+            event_type: row.event_type,
+            attempt: narrow(row.attempt, "webhook_deliveries.attempt")?,
+            response_status: row.response_status
+                .map(|status| narrow(i64::from(status), "webhook_deliveries.response_status"))
+                .transpose()?,
+            error: row.error,
+            succeeded: row.succeeded,
+            attempted_at: row.attempted_at,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct OutboxRow {
+    id: i64,
+    subscription_id: i64,
+    event_id: String,
+    event_type: String,
+    payload: String,
+    attempts: i64,
+    url: String,
+    secret: String,
+}
+
+impl TryFrom<OutboxRow> for OutboxEntry {
+    type Error = ApiError;
+
+    fn try_from(row: OutboxRow) -> Result<Self, Self::Error> {
+        Ok(OutboxEntry {
+            id: narrow(row.id, "webhook_outbox.id")?,
+            subscription_id: narrow(row.subscription_id, "webhook_outbox.subscription_id")?,
+            event_id: row.event_id,
+            event_type: row.event_type,
+            payload: row.payload,
+            attempts: narrow(row.attempts, "webhook_outbox.attempts")?,
+            url: row.url,
+            secret: row.secret,
+        })
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ChangeRow {
+    seq: i64,
+    event: String,
+    order_id: i64,
+    order_data: Json<Order>,
+    previous_status: Option<OrderStatus>,
+    changed_at: DateTime<Utc>,
+}
+
+impl TryFrom<ChangeRow> for OrderChange {
+    type Error = ApiError;
+
+    fn try_from(row: ChangeRow) -> Result<Self, Self::Error> {
+        Ok(OrderChange {
+            seq: row.seq,
+            event: row.event,
+            order_id: narrow(row.order_id, "order_changes.order_id")?,
+            order: row.order_data.0,
+            previous_status: row.previous_status.map(|status| status.as_str().to_string()),
+            changed_at: row.changed_at,
+        })
+    }
+}
+
+/// Convert every row read, failing on the first that does not fit the API's types
+fn convert<R, T: TryFrom<R, Error = ApiError>>(rows: Vec<R>) -> Result<Vec<T>, ApiError> {
+    rows.into_iter().map(T::try_from).collect()
+}
+
+/// Map a database error to the API's server error, logging where it happened
+fn db_error(function: &'static str, message: &'static str) -> impl Fn(sqlx::Error) -> ApiError {
+    move |e| {
+        eprintln!("Database error in {}: {}", function, e);
+        ApiError::Server(ServerError {
+            error: "Database error".to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+fn order_not_found() -> ApiError {
+    ApiError::NotFound("Order not found".to_string())
+}
+
+/// Everything kept in a PostgreSQL database, with the same rules as `SqliteStore`: stock, promotions,
+/// payments, the change log and the webhook outbox are handled in the same transaction as every order write.
+/// The pool connects when first used and the schema is migrated before the first query.
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pool: PgDbPool,
+    migrated: Arc<OnceCell<()>>,
+}
+
+impl PostgresStore {
+    /// Store that connects when first used and applies pending migrations before its first query
+    pub fn connect_lazy(options: PgConnectOptions) -> Self {
+        PostgresStore {
+            pool: PgPoolOptions::new().connect_lazy_with(options),
+            migrated: Default::default(),
+        }
+    }
+
+    /// Pool with the schema migrated; a failed migration is retried on the next call
+    async fn pool(&self) -> Result<&PgDbPool, ApiError> {
+        self.migrated.get_or_try_init(|| run_migrations(&self.pool)).await
+            .map_err(db_error("run_migrations", "Failed to prepare the database"))?;
+        Ok(&self.pool)
+    }
+
+    /// Start a write transaction, queued behind every other one
+    async fn begin_transaction(&self, function: &'static str) -> Result<Transaction<'static, Postgres>, ApiError> {
+        let mut tx = self.pool().await?.begin().await
+            .map_err(db_error(function, "Failed to start transaction"))?;
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(WRITE_LOCK_KEY)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error(function, "Failed to start transaction"))?;
+        Ok(tx)
+    }
+
+    /// Fetch a payment and check it is in `expected` state before acting on it
+    async fn payment_in_state(
+        &self,
+        order_id: u32,
+        payment_id: u32,
+        expected: &[PaymentStatus],
+        action: &str,
+    ) -> Result<Payment, ApiError> {
+        let payment = fetch_payment(self.pool().await?, order_id, payment_id).await?
+            .ok_or_else(|| ApiError::NotFound("Payment not found".to_string()))?;
+        if !expected.contains(&payment.status) {
+            return Err(ApiError::Conflict(format!(
+                "Payment {} is {} and cannot be {}",
+                payment_id, payment.status.as_str(), action
+            )));
+        }
+        Ok(payment)
+    }
+
+    /// Record a change made at the gateway, provided the payment still is as it was read
+    async fn record_transition(&self, previous: &Payment, payment: &Payment, function: &'static str) -> Result<(), ApiError> {
+        let mut tx = self.begin_transaction(function).await?;
+        let result = sqlx::query(
+            "UPDATE payments SET status = $1, refunded_minor = $2 \
+             WHERE id = $3 AND status = $4 AND refunded_minor = $5"
+        )
+            .bind(payment.status)
+            .bind(payment.refunded_minor)
+            .bind(i64::from(payment.id))
+            .bind(previous.status)
+            .bind(previous.refunded_minor)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error(function, "Failed to update payment"))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiError::Conflict(format!("Payment {} was changed concurrently", payment.id)));
+        }
+        commit_transaction(tx, function).await
+    }
+}
+
+/// Apply every migration newer than the database's current version
+async fn run_migrations(pool: &PgDbPool) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("CREATE TABLE IF NOT EXISTS schema_version (version BIGINT NOT NULL)")
+        .execute(&mut *tx)
+        .await?;
+    let current: Option<i64> = sqlx::query_scalar("SELECT version FROM schema_version")
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    for migration in MIGRATIONS.iter().skip(current.unwrap_or(0) as usize) {
+        sqlx::query(migration).execute(&mut *tx).await?;
+    }
+    sqlx::query("DELETE FROM schema_version").execute(&mut *tx).await?;
+    sqlx::query("INSERT INTO schema_version (version) VALUES ($1)")
+        .bind(MIGRATIONS.len() as i64)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+async fn commit_transaction(tx: Transaction<'static, Postgres>, function: &'static str) -> Result<(), ApiError> {
+    tx.commit().await.map_err(db_error(function, "Failed to commit transaction"))
+}
+
+/// Query selecting the orders matching `filter` with IDs after `after_id`, in ID order
+fn orders_query(filter: &OrderFilter, after_id: Option<u32>) -> QueryBuilder<'static, Postgres> {
+    let mut query = QueryBuilder::<Postgres>::new(format!("SELECT {} FROM orders WHERE TRUE", ORDER_COLUMNS));
+    if let Some(status) = &filter.status {
+        match status.parse::<OrderStatus>() {
+            Ok(status) => query.push(" AND status = ").push_bind(status),
+            // No order can have a status outside the enum
+            Err(_) => query.push(" AND FALSE"),
+        };
+    }
+    if let Some(customer_id) = filter.customer_id {
+        query.push(" AND customer_id = ").push_bind(i64::from(customer_id));
+    }
+    if let Some(after_id) = after_id {
+        query.push(" AND id > ").push_bind(i64::from(after_id));
+    }
+    query.push(" ORDER BY id");
+    query
+}
+
+async fn fetch_order<'e, E>(executor: E, order_id: u32) -> Result<Option<Order>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, OrderRow>(&format!("SELECT {} FROM orders WHERE id = $1", ORDER_COLUMNS))
+        .bind(i64::from(order_id))
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_order_by_id", "Failed to retrieve order"))?
+        .map(Order::try_from)
+        .transpose()
+}
+
+async fn fetch_customer<'e, E>(executor: E, customer_id: u32) -> Result<Option<Customer>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, CustomerRow>("SELECT id, name, email FROM customers WHERE id = $1")
+        .bind(i64::from(customer_id))
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_customer_by_id", "Failed to retrieve customer"))?
+        .map(Customer::try_from)
+        .transpose()
+}
+
+async fn fetch_product<'e, E>(executor: E, sku: &str) -> Result<Option<Product>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, ProductRow>("SELECT sku, name, stock FROM products WHERE sku = $1")
+        .bind(sku)
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_product_by_sku", "Failed to retrieve product"))?
+        .map(Product::try_from)
+        .transpose()
+}
+
+async fn fetch_promotion<'e, E>(executor: E, code: &str) -> Result<Option<Promotion>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, PromotionRow>(&format!("SELECT {} FROM promotions WHERE code = $1", PROMOTION_COLUMNS))
+        .bind(code)
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_promotion_by_code", "Failed to retrieve promotion"))?
+        .map(Promotion::try_from)
+        .transpose()
+}
+
+async fn fetch_shipments<'e, E>(executor: E, order_id: u32) -> Result<Vec<Shipment>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rows = sqlx::query_as::<_, ShipmentRow>(&format!(
+        "SELECT {} FROM shipments WHERE order_id = $1 ORDER BY id",
+        SHIPMENT_COLUMNS
+    ))
+        .bind(i64::from(order_id))
+        .fetch_all(executor)
+        .await
+        .map_err(db_error("get_shipments_for_order", "Failed to retrieve shipments"))?;
+    convert(rows)
+}
+
+async fn fetch_payment<'e, E>(executor: E, order_id: u32, payment_id: u32) -> Result<Option<Payment>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, PaymentRow>(&format!("SELECT {} FROM payments WHERE id = $1 AND order_id = $2", PAYMENT_COLUMNS))
+        .bind(i64::from(payment_id))
+        .bind(i64::from(order_id))
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_payment", "Failed to retrieve payment"))?
+        .map(Payment::try_from)
+        .transpose()
+}
+
+async fn fetch_subscription<'e, E>(executor: E, subscription_id: u32) -> Result<Option<WebhookSubscription>, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as::<_, SubscriptionRow>("SELECT id, url, event_types, secret FROM webhook_subscriptions WHERE id = $1")
+        .bind(i64::from(subscription_id))
+        .fetch_optional(executor)
+        .await
+        .map_err(db_error("get_webhook_subscription", "Failed to retrieve webhook subscription"))?
+        .map(WebhookSubscription::try_from)
+        .transpose()
+}
+
+async fn count_active_payments<'e, E>(executor: E, order_id: u32) -> Result<i64, ApiError>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM payments WHERE order_id = $1 AND status IN {}",
+        ACTIVE_STATUSES
+    ))
+        .bind(i64::from(order_id))
+        .fetch_one(executor)
+        .await
+        .map_err(db_error("count_active_payments", "Failed to retrieve payments"))
+}
+
+/// Whether a payment for the order has been captured, including one refunded only in part
+async fn has_captured_payment(conn: &mut PgConnection, order_id: u32) -> Result<bool, ApiError> {
+    let captured: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM payments WHERE order_id = $1 AND status IN ('captured', 'partially_refunded')"
+    )
+        .bind(i64::from(order_id))
+        .fetch_one(conn)
+        .await
+        .map_err(db_error("has_captured_payment", "Failed to retrieve payments"))?;
+
+    Ok(captured > 0)
+}
+
+/// Reject shipping an order before a payment for it has been captured, when the payment configuration requires one
+async fn ensure_shipping_paid(
+    conn: &mut PgConnection,
+    payments: &PaymentConfig,
+    order_id: u32,
+    previous_status: Option<&str>,
+    status: &str,
+) -> Result<(), ApiError> {
+    if requires_captured_payment(payments, previous_status, status) && !has_captured_payment(conn, order_id).await? {
+        return Err(payment_not_captured(order_id));
+    }
+    Ok(())
+}
+
+async fn ensure_customer_exists(conn: &mut PgConnection, customer_id: Option<u32>) -> Result<(), ApiError> {
+    let Some(customer_id) = customer_id else {
+        return Ok(());
+    };
+
+    if fetch_customer(conn, customer_id).await?.is_none() {
+        return Err(unknown_customer(customer_id));
+    }
+    Ok(())
+}
+
+/// Orders holding stock are checked when it is reserved; this covers cancelled orders, which hold none
+async fn ensure_product_exists(conn: &mut PgConnection, sku: Option<&str>) -> Result<(), ApiError> {
+    let Some(sku) = sku else {
+        return Ok(());
+    };
+
+    if fetch_product(conn, sku).await?.is_none() {
+        return Err(unknown_product(sku));
+    }
+    Ok(())
+}
+
+/// Reject an email address already used by a customer other than `customer_id`
+async fn ensure_email_available(conn: &mut PgConnection, email: &str, customer_id: u32) -> Result<(), ApiError> {
+    let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM customers WHERE email = $1 AND id <> $2")
+        .bind(email)
+        .bind(i64::from(customer_id))
+        .fetch_optional(conn)
+        .await
+        .map_err(db_error("ensure_email_available", "Failed to check customer email"))?;
+
+    if existing.is_some() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("A customer with email {} already exists", email),
+            field: Some("email".to_string()),
+        }));
+    }
+    Ok(())
+}
+
+/// Take `quantity` units of `sku` out of stock, failing without changing anything
+/// if the product is unknown or has too little stock
+async fn reserve_stock(conn: &mut PgConnection, sku: &str, quantity: u32) -> Result<(), ApiError> {
+    let result = sqlx::query("UPDATE products SET stock = stock - $1 WHERE sku = $2 AND stock >= $1")
+        .bind(i64::from(quantity))
+        .bind(sku)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error("reserve_stock", "Failed to reserve stock"))?;
+
+    if result.rows_affected() > 0 {
+        return Ok(());
+    }
+
+    let available: Option<i64> = sqlx::query_scalar("SELECT stock FROM products WHERE sku = $1")
+        .bind(sku)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_error("reserve_stock", "Failed to reserve stock"))?;
+
+    match available {
+        None => Err(unknown_product(sku)),
+        Some(available) => Err(ApiError::Conflict(format!(
+            "Insufficient stock for SKU {}: requested {}, available {}",
+            sku, quantity, available
+        ))),
+    }
+}
+
+async fn release_stock(conn: &mut PgConnection, sku: &str, quantity: u32) -> Result<(), ApiError> {
+    sqlx::query("UPDATE products SET stock = stock + $1 WHERE sku = $2")
+        .bind(i64::from(quantity))
+        .bind(sku)
+        .execute(conn)
+        .await
+        .map_err(db_error("release_stock", "Failed to release stock"))?;
+
+    Ok(())
+}
+
+/// Apply `code` to `order`, returning the discount.
+/// The redemption count is incremented only while the code is within its usage cap.
+async fn redeem_promotion(conn: &mut PgConnection, code: &str, order: &Order) -> Result<i64, ApiError> {
+    let promotion = fetch_promotion(&mut *conn, code).await?
+        .ok_or_else(|| unknown_promotion(code))?;
+
+    let now = Utc::now();
+    if let Some(starts_at) = promotion.starts_at.filter(|starts_at| *starts_at > now) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} is not valid until {}", code, starts_at.to_rfc3339()),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+    if let Some(ends_at) = promotion.ends_at.filter(|ends_at| *ends_at < now) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} expired on {}", code, ends_at.to_rfc3339()),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+
+    let discount = promotion_discount(&promotion, order)?;
+
+    let result = sqlx::query(
+        "UPDATE promotions SET redemptions = redemptions + 1 \
+         WHERE code = $1 AND (max_redemptions IS NULL OR redemptions < max_redemptions)"
+    )
+        .bind(code)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error("redeem_promotion", "Failed to redeem promotion"))?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} has reached its redemption limit", code),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+
+    Ok(discount)
+}
+
+/// Queue `event` for every subscription to it, so an event is recorded if and only if
+/// the change it describes is committed
+async fn enqueue_order_event(
+    conn: &mut PgConnection,
+    event: WebhookEvent,
+    order: &Order,
+    previous_status: Option<&str>,
+) -> Result<(), ApiError> {
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let payload = order_event_payload(&event_id, event, order, previous_status, now);
+
+    sqlx::query(
+        "INSERT INTO webhook_outbox (subscription_id, event_id, event_type, payload, next_attempt_at) \
+         SELECT id, $1, $2, $3, $4 FROM webhook_subscriptions WHERE event_types @> jsonb_build_array($2::text)"
+    )
+        .bind(&event_id)
+        .bind(event.as_str())
+        .bind(payload.to_string())
+        .bind(now)
+        .execute(conn)
+        .await
+        .map_err(db_error("enqueue_order_event", "Failed to queue webhook event"))?;
+
+    Ok(())
+}
+
+/// Log a change and queue the matching webhook event; publish it once the transaction commits
+async fn record_order_change(
+    conn: &mut PgConnection,
+    event: WebhookEvent,
+    order: &Order,
+    previous_status: Option<&str>,
+) -> Result<OrderChange, ApiError> {
+    let changed_at = Utc::now();
+    let seq: i64 = sqlx::query_scalar(
+        "INSERT INTO order_changes (event, order_id, status, order_data, previous_status, changed_at) \
+         VALUES ($1, $2, $3, $4, $5, $6) RETURNING seq"
+    )
+        .bind(event.as_str())
+        .bind(i64::from(order.id))
+        .bind(order.status.parse::<OrderStatus>()?)
+        .bind(Json(order))
+        .bind(previous_status.map(OrderStatus::from_str).transpose()?)
+        .bind(changed_at)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(db_error("record_order_change", "Failed to record order change"))?;
+
+    enqueue_order_event(conn, event, order, previous_status).await?;
+
+    Ok(OrderChange {
+        seq,
+        event: event.as_str().to_string(),
+        order_id: order.id,
+        order: order.clone(),
+        previous_status: previous_status.map(str::to_string),
+        changed_at,
+    })
+}
+
+/// Insert an order, reserving its stock and redeeming its promotion.
+/// Returns the order with its computed totals and the change to publish once committed.
+async fn insert_order(
+    conn: &mut PgConnection,
+    order: &Order,
+    payments: &PaymentConfig,
+) -> Result<(Order, OrderChange), ApiError> {
+    let status: OrderStatus = order.status.parse()?;
+    let duplicate = || ApiError::Validation(ValidationError {
+        error: format!("Order with ID {} already exists", order.id),
+        field: Some("id".to_string()),
+    });
+    if fetch_order(&mut *conn, order.id).await?.is_some() {
+        return Err(duplicate());
+    }
+
+    ensure_customer_exists(&mut *conn, order.customer_id).await?;
+    // A new order has no payment yet, so it cannot start out shipped when a captured one is required
+    ensure_shipping_paid(&mut *conn, payments, order.id, None, &order.status).await?;
+
+    if let Some(sku) = reserved_sku(order) {
+        reserve_stock(&mut *conn, sku, order.quantity).await?;
+    } else {
+        ensure_product_exists(&mut *conn, order.sku.as_deref()).await?;
+    }
+
+    let discount = match &order.promo_code {
+        Some(code) => redeem_promotion(&mut *conn, code, order).await?,
+        None => 0,
+    };
+
+    let created = sqlx::query_as::<_, OrderRow>(&format!(
+        "INSERT INTO orders (id, item, status, quantity, customer_id, sku, unit_price_minor, currency, \
+         promo_code, discount_minor, shipping_address) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+         ON CONFLICT (id) DO NOTHING RETURNING {}",
+        ORDER_COLUMNS
+    ))
+        .bind(i64::from(order.id))
+        .bind(&order.item)
+        .bind(status)
+        .bind(i64::from(order.quantity))
+        .bind(order.customer_id.map(i64::from))
+        .bind(&order.sku)
+        .bind(order.unit_price_minor)
+        .bind(&order.currency)
+        .bind(&order.promo_code)
+        .bind(discount)
+        .bind(order.shipping_address.as_ref().map(Json))
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_error("create_order", "Failed to create order"))?
+        .ok_or_else(duplicate)?;
+
+    let created = Order::try_from(created)?;
+    let change = record_order_change(conn, WebhookEvent::Created, &created, None).await?;
+    Ok((created, change))
+}
+
+/// Change an order's status, moving stock on transitions into or out of `cancelled` and checking the
+/// payment before the order ships. Returns the updated order and the change logged if the status differed.
+async fn apply_order_status(
+    conn: &mut PgConnection,
+    order_id: u32,
+    status: &str,
+    payments: &PaymentConfig,
+) -> Result<(Order, Option<OrderChange>), ApiError> {
+    let new_status: OrderStatus = status.parse()?;
+    let current = fetch_order(&mut *conn, order_id).await?.ok_or_else(order_not_found)?;
+    ensure_shipping_paid(&mut *conn, payments, order_id, Some(&current.status), status).await?;
+
+    let mut order = current.clone();
+    order.status = status.to_string();
+    match (reserved_sku(&current), reserved_sku(&order)) {
+        (Some(sku), None) => release_stock(&mut *conn, sku, order.quantity).await?,
+        (None, Some(sku)) => reserve_stock(&mut *conn, sku, order.quantity).await?,
+        _ => {}
+    }
+
+    sqlx::query("UPDATE orders SET status = $1 WHERE id = $2")
+        .bind(new_status)
+        .bind(i64::from(order_id))
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error("update_order_status", "Failed to update order status"))?;
+
+    let change = if current.status != order.status {
+        Some(record_order_change(conn, WebhookEvent::StatusChanged, &order, Some(&current.status)).await?)
+    } else {
+        None
+    };
+    Ok((order, change))
+}
+
+/// Move the order to `shipped`, or to `delivered` once every shipment has been delivered,
+/// through the same path as a manual status change so stock stays consistent
+async fn advance_order(
+    conn: &mut PgConnection,
+    order: &Order,
+    payments: &PaymentConfig,
+) -> Result<Option<OrderChange>, ApiError> {
+    let shipments = fetch_shipments(&mut *conn, order.id).await?;
+    let status = if shipments.iter().all(|shipment| shipment.delivered_at.is_some()) {
+        "delivered"
+    } else {
+        "shipped"
+    };
+
+    if order.status == status {
+        return Ok(None);
+    }
+    let (_, change) = apply_order_status(conn, order.id, status, payments).await?;
+    Ok(change)
+}
+
+#[async_trait]
+impl OrderRepository for PostgresStore {
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, ApiError> {
+        let rows = orders_query(filter, None)
+            .build_query_as::<OrderRow>()
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_all_orders", "Failed to retrieve orders"))?;
+        convert(rows)
+    }
+
+    fn stream_orders(&self, filter: OrderFilter) -> BoxStream<'static, Result<Order, ApiError>> {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let store = self.clone();
+        tokio::spawn(async move {
+            let pool = match store.pool().await {
+                Ok(pool) => pool,
+                Err(err) => {
+                    let _ = sender.send(Err(err)).await;
+                    return;
+                }
+            };
+            let mut query = orders_query(&filter, None);
+            let mut rows = query.build_query_as::<OrderRow>().fetch(pool);
+            while let Some(row) = rows.next().await {
+                let order = row
+                    .map_err(db_error("stream_orders", "Failed to retrieve orders"))
+                    .and_then(Order::try_from);
+                let failed = order.is_err();
+                // Sending fails once the consumer is dropped, e.g. when the client disconnects
+                if sender.send(order).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|order| (order, receiver))
+        }).boxed()
+    }
+
+    async fn orders_page(&self, filter: &OrderFilter, after_id: Option<u32>, limit: u32) -> Result<Vec<Order>, ApiError> {
+        let mut query = orders_query(filter, after_id);
+        query.push(" LIMIT ").push_bind(i64::from(limit));
+
+        let rows = query.build_query_as::<OrderRow>()
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_orders_page", "Failed to retrieve orders"))?;
+        convert(rows)
+    }
+
+    async fn get_order(&self, id: u32) -> Result<Option<Order>, ApiError> {
+        fetch_order(self.pool().await?, id).await
+    }
+
+    async fn create_order(&self, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let mut tx = self.begin_transaction("create_order").await?;
+        let (created, change) = insert_order(&mut tx, order, payments).await?;
+        commit_transaction(tx, "create_order").await?;
+        publish_order_changes([change]);
+        Ok(created)
+    }
+
+    async fn create_orders(
+        &self,
+        orders: &[Order],
+        dry_run: bool,
+        payments: &PaymentConfig,
+    ) -> Result<Result<Vec<Order>, (usize, ApiError)>, ApiError> {
+        let mut tx = self.begin_transaction("create_orders").await?;
+        let mut created = Vec::with_capacity(orders.len());
+        let mut changes = Vec::with_capacity(orders.len());
+        for (index, order) in orders.iter().enumerate() {
+            match insert_order(&mut tx, order, payments).await {
+                Ok((order, change)) => {
+                    created.push(order);
+                    changes.push(change);
+                }
+                // Dropping the transaction rolls back the orders inserted so far
+                Err(err) => return Ok(Err((index, err))),
+            }
+        }
+
+        if !dry_run {
+            commit_transaction(tx, "create_orders").await?;
+            publish_order_changes(changes);
+        }
+        Ok(Ok(created))
+    }
+
+    async fn update_order(&self, id: u32, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let status: OrderStatus = order.status.parse()?;
+        let mut tx = self.begin_transaction("update_order").await?;
+        ensure_customer_exists(&mut tx, order.customer_id).await?;
+
+        let current = fetch_order(&mut *tx, id).await?.ok_or_else(order_not_found)?;
+        if order.promo_code.is_some() && order.promo_code != current.promo_code {
+            return Err(ApiError::Validation(ValidationError {
+                error: "Promo code cannot be changed after the order is created".to_string(),
+                field: Some("promo_code".to_string()),
+            }));
+        }
+        ensure_shipping_paid(&mut tx, payments, id, Some(&current.status), &order.status).await?;
+        if let Some(sku) = reserved_sku(&current) {
+            release_stock(&mut tx, sku, current.quantity).await?;
+        }
+        if let Some(sku) = reserved_sku(order) {
+            reserve_stock(&mut tx, sku, order.quantity).await?;
+        } else {
+            ensure_product_exists(&mut tx, order.sku.as_deref()).await?;
+        }
+
+        let discount = match &current.promo_code {
+            Some(code) => match fetch_promotion(&mut *tx, code).await? {
+                Some(promotion) => promotion_discount(&promotion, order)?,
+                None => 0,
+            },
+            None => 0,
+        };
+
+        let updated = sqlx::query_as::<_, OrderRow>(&format!(
+            "UPDATE orders SET item = $1, status = $2, quantity = $3, customer_id = $4, sku = $5, \
+             unit_price_minor = $6, currency = $7, discount_minor = $8, shipping_address = $9 \
+             WHERE id = $10 RETURNING {}",
+            ORDER_COLUMNS
+        ))
+            .bind(&order.item)
+            .bind(status)
+            .bind(i64::from(order.quantity))
+            .bind(order.customer_id.map(i64::from))
+            .bind(&order.sku)
+            .bind(order.unit_price_minor)
+            .bind(&order.currency)
+            .bind(discount)
+            .bind(order.shipping_address.as_ref().map(Json))
+            .bind(i64::from(id))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("update_order", "Failed to update order"))?;
+
+        let updated = Order::try_from(updated)?;
+        let mut changes = vec![record_order_change(&mut tx, WebhookEvent::Updated, &updated, None).await?];
+        if updated.status != current.status {
+            changes.push(record_order_change(&mut tx, WebhookEvent::StatusChanged, &updated, Some(&current.status)).await?);
+        }
+
+        commit_transaction(tx, "update_order").await?;
+        publish_order_changes(changes);
+        Ok(updated)
+    }
+
+    async fn update_order_status(&self, id: u32, status: &str, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        let mut tx = self.begin_transaction("update_order_status").await?;
+        let (order, change) = apply_order_status(&mut tx, id, status, payments).await?;
+        commit_transaction(tx, "update_order_status").await?;
+        publish_order_changes(change);
+        Ok(order)
+    }
+
+    async fn delete_order(&self, id: u32) -> Result<Order, ApiError> {
+        let mut tx = self.begin_transaction("delete_order").await?;
+        let order = fetch_order(&mut *tx, id).await?.ok_or_else(order_not_found)?;
+
+        let shipment_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shipments WHERE order_id = $1")
+            .bind(i64::from(id))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("delete_order", "Failed to delete order"))?;
+        if shipment_count > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Order {} still has {} shipment(s)",
+                id, shipment_count
+            )));
+        }
+
+        // Payment records stay with their order; an active one has to be voided or refunded first
+        let (payment_count, active_payments): (i64, i64) = sqlx::query_as(&format!(
+            "SELECT COUNT(*), COUNT(*) FILTER (WHERE status IN {}) FROM payments WHERE order_id = $1",
+            ACTIVE_STATUSES
+        ))
+            .bind(i64::from(id))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("delete_order", "Failed to delete order"))?;
+        if active_payments > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Order {} has an active payment; void or refund it first",
+                id
+            )));
+        }
+        if payment_count > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Order {} still has {} payment(s)",
+                id, payment_count
+            )));
+        }
+
+        // The order's reservation goes back to stock along with it
+        if let Some(sku) = reserved_sku(&order) {
+            release_stock(&mut tx, sku, order.quantity).await?;
+        }
+
+        sqlx::query("DELETE FROM orders WHERE id = $1")
+            .bind(i64::from(id))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("delete_order", "Failed to delete order"))?;
+        let change = record_order_change(&mut tx, WebhookEvent::Deleted, &order, None).await?;
+
+        commit_transaction(tx, "delete_order").await?;
+        publish_order_changes([change]);
+        Ok(order)
+    }
+
+    async fn order_stats(&self, top_items: u32, days: u32) -> Result<OrderStats, ApiError> {
+        let pool = self.pool().await?;
+        let db_error = db_error("get_order_stats", "Failed to compute order statistics");
+
+        let (orders, quantity): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(quantity), 0)::BIGINT FROM orders"
+        )
+            .fetch_one(pool)
+            .await
+            .map_err(&db_error)?;
+
+        let by_status = sqlx::query_as::<_, StatusStats>(
+            "SELECT status::TEXT AS status, COUNT(*) AS orders, SUM(quantity)::BIGINT AS quantity FROM orders \
+             GROUP BY status ORDER BY status::TEXT"
+        )
+            .fetch_all(pool)
+            .await
+            .map_err(&db_error)?;
+
+        let top_items = sqlx::query_as::<_, ItemStats>(
+            "SELECT item, COUNT(*) AS orders, SUM(quantity)::BIGINT AS quantity FROM orders \
+             GROUP BY item ORDER BY quantity DESC, item COLLATE \"C\" LIMIT $1"
+        )
+            .bind(i64::from(top_items))
+            .fetch_all(pool)
+            .await
+            .map_err(&db_error)?;
+
+        // Orders predating the change log have no creation or delivery day
+        let today = Utc::now().date_naive();
+        let first_day = today - Days::new(u64::from(days.saturating_sub(1)));
+        let active_days = sqlx::query_as::<_, DailyOrderStats>(
+            "SELECT (changed_at AT TIME ZONE 'UTC')::DATE AS date, \
+             COUNT(DISTINCT order_id) FILTER (WHERE event = 'order.created') AS created, \
+             COUNT(DISTINCT order_id) FILTER (WHERE event = 'order.status_changed' AND status = 'delivered') AS delivered \
+             FROM order_changes WHERE (changed_at AT TIME ZONE 'UTC')::DATE >= $1 GROUP BY 1"
+        )
+            .bind(first_day)
+            .fetch_all(pool)
+            .await
+            .map_err(&db_error)?;
+
+        let daily = daily_series(first_day, today, active_days);
+        Ok(OrderStats { orders, quantity, by_status, top_items, daily })
+    }
+
+    async fn latest_order_change_seq(&self) -> Result<i64, ApiError> {
+        sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM order_changes")
+            .fetch_one(self.pool().await?)
+            .await
+            .map_err(db_error("get_latest_order_change_seq", "Failed to retrieve order changes"))
+    }
+
+    async fn order_changes_since(
+        &self,
+        after_seq: i64,
+        filter: &OrderChangeFilter,
+        limit: u32,
+    ) -> Result<Vec<OrderChange>, ApiError> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT seq, event, order_id, order_data, previous_status, changed_at FROM order_changes WHERE seq > "
+        );
+        query.push_bind(after_seq);
+        if let Some(status) = &filter.status {
+            match status.parse::<OrderStatus>() {
+                Ok(status) => query.push(" AND status = ").push_bind(status),
+                // No change can leave an order in a status outside the enum
+                Err(_) => query.push(" AND FALSE"),
+            };
+        }
+        if let Some(order_id) = filter.id {
+            query.push(" AND order_id = ").push_bind(i64::from(order_id));
+        }
+        query.push(" ORDER BY seq LIMIT ").push_bind(i64::from(limit));
+
+        let rows = query.build_query_as::<ChangeRow>()
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_order_changes_since", "Failed to retrieve order changes"))?;
+        convert(rows)
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for PostgresStore {
+    async fn list_customers(&self) -> Result<Vec<Customer>, ApiError> {
+        let rows = sqlx::query_as::<_, CustomerRow>("SELECT id, name, email FROM customers ORDER BY id")
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_all_customers", "Failed to retrieve customers"))?;
+        convert(rows)
+    }
+
+    async fn get_customer(&self, id: u32) -> Result<Option<Customer>, ApiError> {
+        fetch_customer(self.pool().await?, id).await
+    }
+
+    async fn create_customer(&self, customer: &Customer) -> Result<Customer, ApiError> {
+        let mut tx = self.begin_transaction("create_customer").await?;
+        if fetch_customer(&mut *tx, customer.id).await?.is_some() {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Customer with ID {} already exists", customer.id),
+                field: Some("id".to_string()),
+            }));
+        }
+        ensure_email_available(&mut tx, &customer.email, customer.id).await?;
+
+        sqlx::query("INSERT INTO customers (id, name, email) VALUES ($1, $2, $3)")
+            .bind(i64::from(customer.id))
+            .bind(&customer.name)
+            .bind(&customer.email)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("create_customer", "Failed to create customer"))?;
+
+        commit_transaction(tx, "create_customer").await?;
+        Ok(customer.clone())
+    }
+
+    async fn update_customer(&self, id: u32, customer: &Customer) -> Result<Customer, ApiError> {
+        let mut tx = self.begin_transaction("update_customer").await?;
+        ensure_email_available(&mut tx, &customer.email, id).await?;
+
+        let result = sqlx::query("UPDATE customers SET name = $1, email = $2 WHERE id = $3")
+            .bind(&customer.name)
+            .bind(&customer.email)
+            .bind(i64::from(id))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("update_customer", "Failed to update customer"))?;
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Customer not found".to_string()));
+        }
+
+        commit_transaction(tx, "update_customer").await?;
+        Ok(Customer { id, ..customer.clone() })
+    }
+
+    async fn delete_customer(&self, id: u32) -> Result<Customer, ApiError> {
+        let mut tx = self.begin_transaction("delete_customer").await?;
+        let customer = fetch_customer(&mut *tx, id).await?
+            .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+
+        let order_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE customer_id = $1")
+            .bind(i64::from(id))
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("delete_customer", "Failed to delete customer"))?;
+        if order_count > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Customer {} still has {} order(s)",
+                id, order_count
+            )));
+        }
+
+        sqlx::query("DELETE FROM customers WHERE id = $1")
+            .bind(i64::from(id))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("delete_customer", "Failed to delete customer"))?;
+
+        commit_transaction(tx, "delete_customer").await?;
+        Ok(customer)
+    }
+}
+
+#[async_trait]
+impl ProductRepository for PostgresStore {
+    async fn list_products(&self) -> Result<Vec<Product>, ApiError> {
+        let rows = sqlx::query_as::<_, ProductRow>("SELECT sku, name, stock FROM products ORDER BY sku")
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_all_products", "Failed to retrieve products"))?;
+        convert(rows)
+    }
+
+    async fn get_product(&self, sku: &str) -> Result<Option<Product>, ApiError> {
+        fetch_product(self.pool().await?, sku).await
+    }
+
+    async fn create_product(&self, product: &Product) -> Result<Product, ApiError> {
+        let mut tx = self.begin_transaction("create_product").await?;
+        if fetch_product(&mut *tx, &product.sku).await?.is_some() {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Product with SKU {} already exists", product.sku),
+                field: Some("sku".to_string()),
+            }));
+        }
+
+        sqlx::query("INSERT INTO products (sku, name, stock) VALUES ($1, $2, $3)")
+            .bind(&product.sku)
+            .bind(&product.name)
+            .bind(i64::from(product.stock))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("create_product", "Failed to create product"))?;
+
+        commit_transaction(tx, "create_product").await?;
+        Ok(product.clone())
+    }
+
+    async fn update_product(&self, sku: &str, product: &Product) -> Result<Product, ApiError> {
+        let result = sqlx::query("UPDATE products SET name = $1, stock = $2 WHERE sku = $3")
+            .bind(&product.name)
+            .bind(i64::from(product.stock))
+            .bind(sku)
+            .execute(self.pool().await?)
+            .await
+            .map_err(db_error("update_product", "Failed to update product"))?;
+        if result.rows_affected() == 0 {
+            return Err(ApiError::NotFound("Product not found".to_string()));
+        }
+
+        Ok(Product { sku: sku.to_string(), ..product.clone() })
+    }
+
+    async fn delete_product(&self, sku: &str) -> Result<Product, ApiError> {
+        let mut tx = self.begin_transaction("delete_product").await?;
+        let product = fetch_product(&mut *tx, sku).await?
+            .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+        let order_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE sku = $1")
+            .bind(sku)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("delete_product", "Failed to delete product"))?;
+        if order_count > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Product {} is referenced by {} order(s)",
+                sku, order_count
+            )));
+        }
+
+        sqlx::query("DELETE FROM products WHERE sku = $1")
+            .bind(sku)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("delete_product", "Failed to delete product"))?;
+
+        commit_transaction(tx, "delete_product").await?;
+        Ok(product)
+    }
+}
+
+#[async_trait]
+impl PromotionRepository for PostgresStore {
+    async fn list_promotions(&self) -> Result<Vec<Promotion>, ApiError> {
+        let rows = sqlx::query_as::<_, PromotionRow>(&format!("SELECT {} FROM promotions ORDER BY code", PROMOTION_COLUMNS))
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_all_promotions", "Failed to retrieve promotions"))?;
+        convert(rows)
+    }
+
+    async fn get_promotion(&self, code: &str) -> Result<Option<Promotion>, ApiError> {
+        fetch_promotion(self.pool().await?, code).await
+    }
+
+    async fn create_promotion(&self, promotion: &Promotion) -> Result<Promotion, ApiError> {
+        let mut tx = self.begin_transaction("create_promotion").await?;
+        if fetch_promotion(&mut *tx, &promotion.code).await?.is_some() {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Promotion with code {} already exists", promotion.code),
+                field: Some("code".to_string()),
+            }));
+        }
+
+        sqlx::query(
+            "INSERT INTO promotions (code, kind, percent_off, amount_off_minor, currency, buy_quantity, \
+             free_quantity, starts_at, ends_at, max_redemptions) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+        )
+            .bind(&promotion.code)
+            .bind(promotion.kind)
+            .bind(promotion.percent_off.map(i64::from))
+            .bind(promotion.amount_off_minor)
+            .bind(&promotion.currency)
+            .bind(promotion.buy_quantity.map(i64::from))
+            .bind(promotion.free_quantity.map(i64::from))
+            .bind(promotion.starts_at)
+            .bind(promotion.ends_at)
+            .bind(promotion.max_redemptions.map(i64::from))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("create_promotion", "Failed to create promotion"))?;
+
+        commit_transaction(tx, "create_promotion").await?;
+        Ok(Promotion { redemptions: 0, ..promotion.clone() })
+    }
+
+    async fn delete_promotion(&self, code: &str) -> Result<Promotion, ApiError> {
+        let mut tx = self.begin_transaction("delete_promotion").await?;
+        let promotion = fetch_promotion(&mut *tx, code).await?
+            .ok_or_else(|| ApiError::NotFound("Promotion not found".to_string()))?;
+        if promotion.redemptions > 0 {
+            return Err(ApiError::Conflict(format!(
+                "Promotion {} has been redeemed {} time(s)",
+                code, promotion.redemptions
+            )));
+        }
+
+        sqlx::query("DELETE FROM promotions WHERE code = $1")
+            .bind(code)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("delete_promotion", "Failed to delete promotion"))?;
+
+        commit_transaction(tx, "delete_promotion").await?;
+        Ok(promotion)
+    }
+}
+
+#[async_trait]
+impl ShipmentRepository for PostgresStore {
+    async fn list_shipments(&self, order_id: u32) -> Result<Vec<Shipment>, ApiError> {
+        fetch_shipments(self.pool().await?, order_id).await
+    }
+
+    async fn create_shipment(&self, order_id: u32, shipment: &Shipment, payments: &PaymentConfig) -> Result<Shipment, ApiError> {
+        let mut tx = self.begin_transaction("create_shipment").await?;
+        let order = fetch_order(&mut *tx, order_id).await?.ok_or_else(order_not_found)?;
+        if matches!(order.status.as_str(), "cancelled" | "delivered") {
+            return Err(ApiError::Conflict(format!(
+                "Order {} is {} and cannot be shipped",
+                order_id, order.status
+            )));
+        }
+        if order.shipping_address.is_none() {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Order {} has no shipping address", order_id),
+                field: Some("shipping_address".to_string()),
+            }));
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO shipments (order_id, carrier, tracking_number, shipped_at, delivered_at) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING id"
+        )
+            .bind(i64::from(order_id))
+            .bind(&shipment.carrier)
+            .bind(&shipment.tracking_number)
+            .bind(shipment.shipped_at)
+            .bind(shipment.delivered_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("create_shipment", "Failed to create shipment"))?;
+
+        let change = advance_order(&mut tx, &order, payments).await?;
+        commit_transaction(tx, "create_shipment").await?;
+        publish_order_changes(change);
+
+        Ok(Shipment { id: narrow(id, "shipments.id")?, order_id, ..shipment.clone() })
+    }
+
+    async fn mark_shipment_delivered(
+        &self,
+        order_id: u32,
+        shipment_id: u32,
+        delivered_at: DateTime<Utc>,
+        payments: &PaymentConfig,
+    ) -> Result<Shipment, ApiError> {
+        let mut tx = self.begin_transaction("mark_shipment_delivered").await?;
+        let mut shipment = sqlx::query_as::<_, ShipmentRow>(&format!(
+            "SELECT {} FROM shipments WHERE id = $1 AND order_id = $2",
+            SHIPMENT_COLUMNS
+        ))
+            .bind(i64::from(shipment_id))
+            .bind(i64::from(order_id))
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db_error("get_shipment", "Failed to retrieve shipment"))?
+            .map(Shipment::try_from)
+            .transpose()?
+            .ok_or_else(|| ApiError::NotFound("Shipment not found".to_string()))?;
+        validate_delivered_at(shipment.shipped_at, delivered_at)?;
+        shipment.delivered_at = Some(delivered_at);
+
+        sqlx::query("UPDATE shipments SET delivered_at = $1 WHERE id = $2")
+            .bind(delivered_at)
+            .bind(i64::from(shipment_id))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("mark_shipment_delivered", "Failed to update shipment"))?;
+
+        let order = fetch_order(&mut *tx, order_id).await?.ok_or_else(order_not_found)?;
+        let change = if order.status != "cancelled" {
+            advance_order(&mut tx, &order, payments).await?
+        } else {
+            None
+        };
+
+        commit_transaction(tx, "mark_shipment_delivered").await?;
+        publish_order_changes(change);
+        Ok(shipment)
+    }
+}
+
+#[async_trait]
+impl PaymentRepository for PostgresStore {
+    async fn list_payments(&self, order_id: u32) -> Result<Vec<Payment>, ApiError> {
+        let rows = sqlx::query_as::<_, PaymentRow>(&format!(
+            "SELECT {} FROM payments WHERE order_id = $1 ORDER BY id",
+            PAYMENT_COLUMNS
+        ))
+            .bind(i64::from(order_id))
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_payments_for_order", "Failed to retrieve payments"))?;
+        convert(rows)
+    }
+
+    async fn authorize_payment(&self, gateway: &dyn PaymentGateway, order_id: u32) -> Result<Payment, ApiError> {
+        let order = fetch_order(self.pool().await?, order_id).await?.ok_or_else(order_not_found)?;
+        if order.status == "cancelled" {
+            return Err(ApiError::Conflict(format!("Order {} is cancelled", order_id)));
+        }
+        if order.total_minor <= 0 {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Order {} has no amount to pay", order_id),
+                field: Some("total_minor".to_string()),
+            }));
+        }
+        if count_active_payments(self.pool().await?, order_id).await? > 0 {
+            return Err(ApiError::Conflict(format!("Order {} already has an active payment", order_id)));
+        }
+
+        let reference = gateway.authorize(order.total_minor, &order.currency).await?;
+
+        // The order may have been deleted, or another authorization recorded, while the gateway was called
+        let mut tx = self.begin_transaction("authorize_payment").await?;
+        let rejection = if fetch_order(&mut *tx, order_id).await?.is_none() {
+            Some(order_not_found())
+        } else if count_active_payments(&mut *tx, order_id).await? > 0 {
+            Some(ApiError::Conflict(format!("Order {} already has an active payment", order_id)))
+        } else {
+            None
+        };
+        if let Some(rejection) = rejection {
+            drop(tx);
+            if let Err(e) = gateway.void(&reference).await {
+                eprintln!("Failed to void duplicate authorization {}: {:?}", reference, e);
+            }
+            return Err(rejection);
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO payments (order_id, status, amount_minor, currency, gateway_reference) \
+             VALUES ($1, $2, $3, $4, $5) RETURNING id"
+        )
+            .bind(i64::from(order_id))
+            .bind(PaymentStatus::Authorized)
+            .bind(order.total_minor)
+            .bind(&order.currency)
+            .bind(&reference)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error("authorize_payment", "Failed to record payment"))?;
+        commit_transaction(tx, "authorize_payment").await?;
+
+        Ok(Payment {
+            id: narrow(id, "payments.id")?,
+            order_id,
+            status: PaymentStatus::Authorized,
+            amount_minor: order.total_minor,
+            currency: order.currency,
+            refunded_minor: 0,
+            gateway_reference: reference,
+        })
+    }
+
+    async fn capture_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(order_id, payment_id, &[PaymentStatus::Authorized], "captured").await?;
+
+        gateway.capture(&previous.gateway_reference, previous.amount_minor).await?;
+        let payment = Payment { status: PaymentStatus::Captured, ..previous.clone() };
+        self.record_transition(&previous, &payment, "capture_payment").await?;
+        Ok(payment)
+    }
+
+    async fn void_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(order_id, payment_id, &[PaymentStatus::Authorized], "voided").await?;
+
+        gateway.void(&previous.gateway_reference).await?;
+        let payment = Payment { status: PaymentStatus::Voided, ..previous.clone() };
+        self.record_transition(&previous, &payment, "void_payment").await?;
+        Ok(payment)
+    }
+
+    async fn refund_payment(
+        &self,
+        gateway: &dyn PaymentGateway,
+        order_id: u32,
+        payment_id: u32,
+        amount_minor: Option<i64>,
+    ) -> Result<Payment, ApiError> {
+        let previous = self.payment_in_state(
+            order_id,
+            payment_id,
+            &[PaymentStatus::Captured, PaymentStatus::PartiallyRefunded],
+            "refunded",
+        ).await?;
+
+        let refundable = previous.amount_minor - previous.refunded_minor;
+        let amount_minor = amount_minor.unwrap_or(refundable);
+        if amount_minor <= 0 || amount_minor > refundable {
+            return Err(ApiError::Validation(ValidationError {
+                error: format!("Refund amount must be between 1 and {} minor units", refundable),
+                field: Some("amount_minor".to_string()),
+            }));
+        }
+
+        gateway.refund(&previous.gateway_reference, amount_minor).await?;
+        let refunded_minor = previous.refunded_minor + amount_minor;
+        let status = if refunded_minor == previous.amount_minor {
+            PaymentStatus::Refunded
+        } else {
+            PaymentStatus::PartiallyRefunded
+        };
+        let payment = Payment { status, refunded_minor, ..previous.clone() };
+        self.record_transition(&previous, &payment, "refund_payment").await?;
+        Ok(payment)
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for PostgresStore {
+    async fn list_webhook_subscriptions(&self) -> Result<Vec<WebhookSubscription>, ApiError> {
+        let rows = sqlx::query_as::<_, SubscriptionRow>("SELECT id, url, event_types, secret FROM webhook_subscriptions ORDER BY id")
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_all_webhook_subscriptions", "Failed to retrieve webhook subscriptions"))?;
+        convert(rows)
+    }
+
+    async fn get_webhook_subscription(&self, id: u32) -> Result<Option<WebhookSubscription>, ApiError> {
+        fetch_subscription(self.pool().await?, id).await
+    }
+
+    async fn create_webhook_subscription(&self, subscription: &WebhookSubscription) -> Result<WebhookSubscription, ApiError> {
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO webhook_subscriptions (url, event_types, secret) VALUES ($1, $2, $3) RETURNING id"
+        )
+            .bind(&subscription.url)
+            .bind(Json(&subscription.event_types))
+            .bind(&subscription.secret)
+            .fetch_one(self.pool().await?)
+            .await
+            .map_err(db_error("create_webhook_subscription", "Failed to create webhook subscription"))?;
+
+        Ok(WebhookSubscription { id: narrow(id, "webhook_subscriptions.id")?, ..subscription.clone() })
+    }
+
+    async fn delete_webhook_subscription(&self, id: u32) -> Result<WebhookSubscription, ApiError> {
+        let mut tx = self.begin_transaction("delete_webhook_subscription").await?;
+        let subscription = fetch_subscription(&mut *tx, id).await?
+            .ok_or_else(|| ApiError::NotFound("Webhook subscription not found".to_string()))?;
+
+        for statement in [
+            "DELETE FROM webhook_deliveries WHERE subscription_id = $1",
+            "DELETE FROM webhook_outbox WHERE subscription_id = $1",
+            "DELETE FROM webhook_subscriptions WHERE id = $1",
+        ] {
+            sqlx::query(statement)
+                .bind(i64::from(id))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error("delete_webhook_subscription", "Failed to delete webhook subscription"))?;
+        }
+
+        commit_transaction(tx, "delete_webhook_subscription").await?;
+        Ok(subscription)
+    }
+
+    async fn webhook_deliveries(&self, subscription_id: u32) -> Result<Vec<WebhookDelivery>, ApiError> {
+        let rows = sqlx::query_as::<_, DeliveryRow>(
+            "SELECT id, event_id, event_type, attempt, response_status, error, succeeded, attempted_at \
+             FROM webhook_deliveries WHERE subscription_id = $1 ORDER BY id DESC"
+        )
+            .bind(i64::from(subscription_id))
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_webhook_deliveries", "Failed to retrieve webhook deliveries"))?;
+        convert(rows)
+    }
+
+    async fn due_webhooks(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<OutboxEntry>, ApiError> {
+        let rows = sqlx::query_as::<_, OutboxRow>(
+            "SELECT o.id, o.subscription_id, o.event_id, o.event_type, o.payload, o.attempts, s.url, s.secret \
+             FROM webhook_outbox o JOIN webhook_subscriptions s ON s.id = o.subscription_id \
+             WHERE o.status = 'pending' AND o.next_attempt_at <= $1 \
+             ORDER BY o.id LIMIT $2"
+        )
+            .bind(now)
+            .bind(i64::from(limit))
+            .fetch_all(self.pool().await?)
+            .await
+            .map_err(db_error("get_due_webhooks", "Failed to retrieve webhook outbox"))?;
+        convert(rows)
+    }
+
+    async fn record_webhook_attempt(
+        &self,
+        entry: &OutboxEntry,
+        outcome: &DeliveryOutcome,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), ApiError> {
+        let attempt = entry.attempts + 1;
+        let status = match (outcome.succeeded(), retry_at) {
+            (true, _) => "delivered",
+            (false, Some(_)) => "pending",
+            (false, None) => "failed",
+        };
+
+        let mut tx = self.begin_transaction("record_webhook_attempt").await?;
+        sqlx::query(
+            "INSERT INTO webhook_deliveries \
+             (outbox_id, subscription_id, event_id, event_type, attempt, response_status, error, succeeded, attempted_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+        )
+            .bind(i64::from(entry.id))
+            .bind(i64::from(entry.subscription_id))
+            .bind(&entry.event_id)
+            .bind(&entry.event_type)
+            .bind(i64::from(attempt))
+            .bind(outcome.response_status.map(i32::from))
+            .bind(&outcome.error)
+            .bind(outcome.succeeded())
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("record_webhook_attempt", "Failed to record webhook delivery"))?;
+
+        sqlx::query(
+            "UPDATE webhook_outbox SET status = $1, attempts = $2, next_attempt_at = COALESCE($3, next_attempt_at) \
+             WHERE id = $4"
+        )
+            .bind(status)
+            .bind(i64::from(attempt))
+            .bind(retry_at)
+            .bind(i64::from(entry.id))
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error("record_webhook_attempt", "Failed to update webhook outbox"))?;
+
+        commit_transaction(tx, "record_webhook_attempt").await
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for PostgresStore {
+    async fn register_api_key(&self, name: &str, role: Role, secret: &str) -> Result<ApiKey, ApiError> {
+        sqlx::query_as::<_, ApiKey>(
+            "INSERT INTO api_keys (name, role, key_hash) VALUES ($1, $2, $3) \
+             ON CONFLICT (key_hash) DO UPDATE SET name = excluded.name, role = excluded.role, revoked = FALSE \
+             RETURNING id, name, role, revoked"
+        )
+            .bind(name)
+            .bind(role)
+            .bind(hash_api_key(secret))
+            .fetch_one(self.pool().await?)
+            .await
+            .map_err(db_error("register_api_key", "Failed to create API key"))
+    }
+
+    async fn find_api_key(&self, secret: &str) -> Result<Option<ApiKey>, ApiError> {
+        sqlx::query_as::<_, ApiKey>("SELECT id, name, role, revoked FROM api_keys WHERE key_hash = $1 AND NOT revoked")
+            .bind(hash_api_key(secret))
+            .fetch_optional(self.pool().await?)
+            .await
+            .map_err(db_error("find_api_key", "Failed to verify credentials"))
+    }
+
+    async fn register_certificate_identity(&self, subject: &str, name: &str, role: Role) -> Result<CertificateIdentity, ApiError> {
+        sqlx::query_as::<_, CertificateIdentity>(
+            "INSERT INTO client_certificates (subject, name, role) VALUES ($1, $2, $3) \
+             ON CONFLICT (subject) DO UPDATE SET name = excluded.name, role = excluded.role, revoked = FALSE \
+             RETURNING subject, name, role"
+        )
+            .bind(subject)
+            .bind(name)
+            .bind(role)
+            .fetch_one(self.pool().await?)
+            .await
+            .map_err(db_error("register_certificate_identity", "Failed to register client certificate"))
+    }
+
+    async fn find_certificate_identity(&self, subject: &str) -> Result<Option<CertificateIdentity>, ApiError> {
+        sqlx::query_as::<_, CertificateIdentity>(
+            "SELECT subject, name, role FROM client_certificates WHERE subject = $1 AND NOT revoked"
+        )
+            .bind(subject)
+            .fetch_optional(self.pool().await?)
+            .await
+            .map_err(db_error("find_certificate_identity", "Failed to verify credentials"))
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn ping(&self) -> Result<(), ApiError> {
+        sqlx::query("SELECT 1")
+            .execute(self.pool().await?)
+            .await
+            .map_err(db_error("ping_db", "Database is not reachable"))?;
+        Ok(())
+    }
+
+    async fn migration_version(&self) -> Result<Option<i64>, ApiError> {
+        sqlx::query_scalar("SELECT version FROM schema_version")
+            .fetch_one(self.pool().await?)
+            .await
+            .map(Some)
+            .map_err(db_error("get_migration_version", "Failed to read migration version"))
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle(),
+            closed: self.pool.is_closed(),
+        }
+    }
+}
+
+/// Connection options for a fresh schema in the database at `TEST_POSTGRES_URL`, so every test starts empty
+#[cfg(test)]
+pub(crate) async fn test_connect_options() -> PgConnectOptions {
+    use sqlx::ConnectOptions;
+
+    let url = std::env::var("TEST_POSTGRES_URL")
+        .expect("TEST_POSTGRES_URL must point at a PostgreSQL database to run the tests with the postgres feature");
+    let options = PgConnectOptions::from_str(&url).expect("TEST_POSTGRES_URL is not a valid PostgreSQL URL");
+    let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+
+    let mut conn = options.connect().await.expect("Failed to connect to TEST_POSTGRES_URL");
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&mut conn)
+        .await
+        .expect("Failed to create test schema");
+    options.options([("search_path", schema)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    async fn setup_store() -> PostgresStore {
+        PostgresStore::connect_lazy(test_connect_options().await)
+    }
+
+    fn order(id: u32, status: &str) -> Order {
+        Order {
+            id,
+            item: format!("Item {}", id),
+            status: status.to_string(),
+            quantity: 3,
+            unit_price_minor: 250,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_postgres_round_trips_full_u32_range() {
+        let store = setup_store().await;
+        let customer = Customer { id: u32::MAX, name: "Ada Lovelace".to_string(), email: "ada@example.com".to_string() };
+        store.create_customer(&customer).await.unwrap();
+        store.create_product(&Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: u32::MAX }).await.unwrap();
+        let order = Order {
+            customer_id: Some(u32::MAX),
+            sku: Some("WID-1".to_string()),
+            shipping_address: Some(ShippingAddress {
+                recipient: "Ada Lovelace".to_string(),
+                line1: "12 St James's Square".to_string(),
+                line2: None,
+                city: "London".to_string(),
+                region: None,
+                postal_code: "SW1Y 4JH".to_string(),
+                country: "GB".to_string(),
+            }),
+            quantity: u32::MAX,
+            ..order(u32::MAX, "pending")
+        };
+
+        let created = store.create_order(&order, &PaymentConfig::default()).await.unwrap();
+        assert_eq!(created.id, u32::MAX);
+        assert_eq!(created.subtotal_minor, 250 * i64::from(u32::MAX));
+
+        let fetched = store.get_order(u32::MAX).await.unwrap().unwrap();
+        assert_eq!(fetched.quantity, u32::MAX);
+        assert_eq!(fetched.customer_id, Some(u32::MAX));
+        assert_eq!(fetched.shipping_address, order.shipping_address);
+        assert_eq!(store.get_product("WID-1").await.unwrap().unwrap().stock, 0);
+    }
+
+    #[tokio::test]
+    async fn test_postgres_orders_reject_duplicates_and_unknown_ids() {
+        let store = setup_store().await;
+        store.create_order(&order(1, "pending"), &PaymentConfig::default()).await.unwrap();
+
+        let result = store.create_order(&order(1, "processing"), &PaymentConfig::default()).await;
+        assert!(matches!(result, Err(ApiError::Validation(err)) if err.field.as_deref() == Some("id")));
+        assert!(matches!(store.update_order_status(2, "shipped", &PaymentConfig::default()).await, Err(ApiError::NotFound(_))));
+        assert!(matches!(store.update_order(2, &order(2, "shipped"), &PaymentConfig::default()).await, Err(ApiError::NotFound(_))));
+        assert!(matches!(store.delete_order(2).await, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_postgres_orders_filter_by_native_status() {
+        let store = setup_store().await;
+        for (id, status) in [(3, "shipped"), (1, "pending"), (2, "shipped")] {
+            store.create_order(&order(id, status), &PaymentConfig::default()).await.unwrap();
+        }
+
+        let shipped = OrderFilter { status: Some("shipped".to_string()), customer_id: None };
+        let listed: Vec<u32> = store.list_orders(&shipped).await.unwrap().iter().map(|order| order.id).collect();
+        assert_eq!(listed, vec![2, 3]);
+
+        let unknown = OrderFilter { status: Some("lost".to_string()), customer_id: None };
+        assert!(store.list_orders(&unknown).await.unwrap().is_empty());
+        let unknown = OrderChangeFilter { status: Some("lost".to_string()), id: None };
+        assert!(store.order_changes_since(0, &unknown, 10).await.unwrap().is_empty());
+
+        let streamed: Vec<Order> = store.stream_orders(OrderFilter::default()).try_collect().await.unwrap();
+        assert_eq!(streamed.iter().map(|order| order.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_postgres_rejected_order_leaves_no_trace() {
+        let store = setup_store().await;
+        store.create_product(&Product { sku: "WID-1".to_string(), name: "Widget".to_string(), stock: 5 }).await.unwrap();
+
+        let orders = [
+            Order { sku: Some("WID-1".to_string()), ..order(1, "pending") },
+            Order { sku: Some("WID-1".to_string()), ..order(2, "pending") },
+        ];
+        let result = store.create_orders(&orders, false, &PaymentConfig::default()).await.unwrap();
+        assert!(matches!(result, Err((1, ApiError::Conflict(_)))));
+        assert_eq!(store.get_product("WID-1").await.unwrap().unwrap().stock, 5);
+        assert!(store.get_order(1).await.unwrap().is_none());
+        assert_eq!(store.latest_order_change_seq().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_postgres_migrations_apply_once() {
+        let options = test_connect_options().await;
+        let first = PostgresStore::connect_lazy(options.clone());
+        first.create_order(&order(1, "pending"), &PaymentConfig::default()).await.unwrap();
+
+        // A second instance finds the schema current and keeps the data
+        let second = PostgresStore::connect_lazy(options);
+        assert!(second.get_order(1).await.unwrap().is_some());
+        assert_eq!(second.migration_version().await.unwrap(), Some(MIGRATIONS.len() as i64));
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use crate::utils::DbPool;
+use crate::validators::{ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Product in the catalog that orders can reference by SKU
+pub struct Product {
+    /// Stock keeping unit, the unique product code
+    pub sku: String,
+    /// Display name of the product
+    pub name: String,
+    /// Units on hand and available to new orders
+    pub stock: u32,
+}
+
+/// Get all products from the database, ordered by SKU
+pub async fn get_all_products(pool: &DbPool) -> Result<Vec<Product>, ApiError> {
+    sqlx::query_as::<_, Product>("SELECT sku, name, stock FROM products ORDER BY sku")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_all_products: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve products".to_string(),
+            })
+        })
+}
+
+/// Get a specific product by SKU, using either the pool or an open transaction
+pub async fn get_product_by_sku<'e, E>(executor: E, sku: &str) -> Result<Option<Product>, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_as::<_, Product>("SELECT sku, name, stock FROM products WHERE sku = ?")
+        .bind(sku)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_product_by_sku: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve product".to_string(),
+            })
+        })
+}
+
+/// Create a new product in the database
+pub async fn create_product(pool: &DbPool, product: &Product) -> Result<Product, ApiError> {
+    if get_product_by_sku(pool, &product.sku).await?.is_some() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Product with SKU {} already exists", product.sku),
+            field: Some("sku".to_string()),
+        }));
+    }
+
+    sqlx::query("INSERT INTO products (sku, name, stock) VALUES (?, ?, ?)")
+        .bind(&product.sku)
+        .bind(&product.name)
+        .bind(product.stock)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in create_product: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create product".to_string(),
+            })
+        })?;
+
+    Ok(product.clone())
+}
+
+/// Update a product's name and stock on hand, e.g. after a delivery from a supplier
+pub async fn update_product(pool: &DbPool, sku: &str, product: &Product) -> Result<Product, ApiError> {
+    let result = sqlx::query("UPDATE products SET name = ?, stock = ? WHERE sku = ?")
+        .bind(&product.name)
+        .bind(product.stock)
+        .bind(sku)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in update_product: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to update product".to_string(),
+            })
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::NotFound("Product not found".to_string()));
+    }
+
+    let mut updated_product = product.clone();
+    updated_product.sku = sku.to_string();
+    Ok(updated_product)
+}
+
+/// Delete a product; products referenced by orders cannot be deleted
+pub async fn delete_product(pool: &DbPool, sku: &str) -> Result<Product, ApiError> {
+    let product = get_product_by_sku(pool, sku).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+    let order_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE sku = ?")
+        .bind(sku)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_product: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete product".to_string(),
+            })
+        })?;
+
+    if order_count > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Product {} is referenced by {} order(s)",
+            sku, order_count
+        )));
+    }
+
+    sqlx::query("DELETE FROM products WHERE sku = ?")
+        .bind(sku)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_product: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete product".to_string(),
+            })
+        })?;
+
+    Ok(product)
+}
+
+/// Error for an order referencing a product that does not exist
+pub fn unknown_product(sku: &str) -> ApiError {
+    ApiError::Validation(ValidationError {
+        error: format!("Product with SKU {} does not exist", sku),
+        field: Some("sku".to_string()),
+    })
+}
+
+/// Take `quantity` units of `sku` out of stock as part of the caller's transaction.
+/// Fails without changing anything if the product is unknown or has too little stock.
+pub async fn reserve_stock(conn: &mut SqliteConnection, sku: &str, quantity: u32) -> Result<(), ApiError> {
+    let result = sqlx::query("UPDATE products SET stock = stock - ? WHERE sku = ? AND stock >= ?")
+        .bind(quantity)
+        .bind(sku)
+        .bind(quantity)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in reserve_stock: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to reserve stock".to_string(),
+            })
+        })?;
+
+    if result.rows_affected() > 0 {
+        return Ok(());
+    }
+
+    let available: Option<u32> = sqlx::query_scalar("SELECT stock FROM products WHERE sku = ?")
+        .bind(sku)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in reserve_stock: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to reserve stock".to_string(),
+            })
+        })?;
+
+    match available {
+        None => Err(unknown_product(sku)),
+        Some(available) => Err(ApiError::Conflict(format!(
+            "Insufficient stock for SKU {}: requested {}, available {}",
+            sku, quantity, available
+        ))),
+    }
+}
+
+/// Return `quantity` units of `sku` to stock as part of the caller's transaction
+pub async fn release_stock(conn: &mut SqliteConnection, sku: &str, quantity: u32) -> Result<(), ApiError> {
+    sqlx::query("UPDATE products SET stock = stock + ? WHERE sku = ?")
+        .bind(quantity)
+        .bind(sku)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in release_stock: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to release stock".to_string(),
+            })
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::init_db;
+
+    fn product(sku: &str, stock: u32) -> Product {
+        Product {
+            sku: sku.to_string(),
+            name: "Widget".to_string(),
+            stock,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reserve_and_release_stock() {
+        let pool = init_db().await.unwrap();
+        create_product(&pool, &product("WID-1", 5)).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        reserve_stock(&mut conn, "WID-1", 3).await.unwrap();
+        assert_eq!(get_product_by_sku(&pool, "WID-1").await.unwrap().unwrap().stock, 2);
+
+        match reserve_stock(&mut conn, "WID-1", 3).await.unwrap_err() {
+            ApiError::Conflict(message) => {
+                assert_eq!(message, "Insufficient stock for SKU WID-1: requested 3, available 2");
+            }
+            _ => panic!("Expected conflict"),
+        }
+        assert_eq!(get_product_by_sku(&pool, "WID-1").await.unwrap().unwrap().stock, 2);
+
+        release_stock(&mut conn, "WID-1", 3).await.unwrap();
+        assert_eq!(get_product_by_sku(&pool, "WID-1").await.unwrap().unwrap().stock, 5);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_unknown_sku() {
+        let pool = init_db().await.unwrap();
+        let mut conn = pool.acquire().await.unwrap();
+
+        match reserve_stock(&mut conn, "NOPE", 1).await.unwrap_err() {
+            ApiError::Validation(err) => assert_eq!(err.field, Some("sku".to_string())),
+            _ => panic!("Expected validation error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_product_crud() {
+        let pool = init_db().await.unwrap();
+        create_product(&pool, &product("WID-1", 5)).await.unwrap();
+        assert!(create_product(&pool, &product("WID-1", 5)).await.is_err());
+
+        update_product(&pool, "WID-1", &product("ignored", 12)).await.unwrap();
+        let stored = get_product_by_sku(&pool, "WID-1").await.unwrap().unwrap();
+        assert_eq!(stored.stock, 12);
+        assert_eq!(get_all_products(&pool).await.unwrap().len(), 1);
+
+        delete_product(&pool, "WID-1").await.unwrap();
+        assert!(get_product_by_sku(&pool, "WID-1").await.unwrap().is_none());
+    }
+}
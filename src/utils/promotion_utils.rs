@@ -0,0 +1,354 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use crate::utils::{DbPool, Order};
+use crate::validators::{ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+/// How a promotion reduces the price of an order
+pub enum PromotionKind {
+    /// A percentage of the subtotal, rounded to the nearest minor unit
+    PercentOff,
+    /// A fixed amount, never more than the subtotal
+    FixedOff,
+    /// For every `buy_quantity` units paid for, `free_quantity` more are free
+    BuyXGetY,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Discount code that can be applied once per order
+pub struct Promotion {
+    /// Code customers enter at checkout
+    pub code: String,
+    /// How the discount is calculated
+    pub kind: PromotionKind,
+    /// Percentage taken off the subtotal (`percent_off`), 1-100
+    #[serde(default)]
+    pub percent_off: Option<u32>,
+    /// Amount taken off in minor units of `currency` (`fixed_off`)
+    #[serde(default)]
+    pub amount_off_minor: Option<i64>,
+    /// Currency of `amount_off_minor`; fixed discounts only apply to orders in this currency
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Units that must be paid for to earn free units (`buy_x_get_y`)
+    #[serde(default)]
+    pub buy_quantity: Option<u32>,
+    /// Units given free for every `buy_quantity` paid for (`buy_x_get_y`)
+    #[serde(default)]
+    pub free_quantity: Option<u32>,
+    /// The code cannot be redeemed before this instant
+    #[serde(default)]
+    pub starts_at: Option<DateTime<Utc>>,
+    /// The code cannot be redeemed after this instant
+    #[serde(default)]
+    pub ends_at: Option<DateTime<Utc>>,
+    /// Maximum number of orders the code can be applied to; unlimited when absent
+    #[serde(default)]
+    pub max_redemptions: Option<u32>,
+    /// Number of orders the code has been applied to
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub redemptions: u32,
+}
+
+/// Columns selected whenever a promotion is read back
+const PROMOTION_COLUMNS: &str = "code, kind, percent_off, amount_off_minor, currency, buy_quantity, \
+    free_quantity, starts_at, ends_at, max_redemptions, redemptions";
+
+/// Get all promotions from the database, ordered by code
+pub async fn get_all_promotions(pool: &DbPool) -> Result<Vec<Promotion>, ApiError> {
+    sqlx::query_as::<_, Promotion>(&format!("SELECT {} FROM promotions ORDER BY code", PROMOTION_COLUMNS))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_all_promotions: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve promotions".to_string(),
+            })
+        })
+}
+
+/// Error for an order referencing a promo code that does not exist
+pub fn unknown_promotion(code: &str) -> ApiError {
+    ApiError::Validation(ValidationError {
+        error: format!("Promo code {} does not exist", code),
+        field: Some("promo_code".to_string()),
+    })
+}
+
+/// Get a specific promotion by code, using either the pool or an open transaction
+pub async fn get_promotion_by_code<'e, E>(executor: E, code: &str) -> Result<Option<Promotion>, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_as::<_, Promotion>(&format!("SELECT {} FROM promotions WHERE code = ?", PROMOTION_COLUMNS))
+        .bind(code)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_promotion_by_code: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve promotion".to_string(),
+            })
+        })
+}
+
+/// Create a new promotion in the database
+pub async fn create_promotion(pool: &DbPool, promotion: &Promotion) -> Result<Promotion, ApiError> {
+    if get_promotion_by_code(pool, &promotion.code).await?.is_some() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promotion with code {} already exists", promotion.code),
+            field: Some("code".to_string()),
+        }));
+    }
+
+    sqlx::query(
+        "INSERT INTO promotions (code, kind, percent_off, amount_off_minor, currency, buy_quantity, \
+         free_quantity, starts_at, ends_at, max_redemptions) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(&promotion.code)
+        .bind(promotion.kind)
+        .bind(promotion.percent_off)
+        .bind(promotion.amount_off_minor)
+        .bind(&promotion.currency)
+        .bind(promotion.buy_quantity)
+        .bind(promotion.free_quantity)
+        .bind(promotion.starts_at)
+        .bind(promotion.ends_at)
+        .bind(promotion.max_redemptions)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in create_promotion: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create promotion".to_string(),
+            })
+        })?;
+
+    let mut created = promotion.clone();
+    created.redemptions = 0;
+    Ok(created)
+}
+
+/// Delete a promotion; promotions that have been redeemed cannot be deleted
+pub async fn delete_promotion(pool: &DbPool, code: &str) -> Result<Promotion, ApiError> {
+    let promotion = get_promotion_by_code(pool, code).await?
+        .ok_or_else(|| ApiError::NotFound("Promotion not found".to_string()))?;
+
+    if promotion.redemptions > 0 {
+        return Err(ApiError::Conflict(format!(
+            "Promotion {} has been redeemed {} time(s)",
+            code, promotion.redemptions
+        )));
+    }
+
+    sqlx::query("DELETE FROM promotions WHERE code = ?")
+        .bind(code)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in delete_promotion: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to delete promotion".to_string(),
+            })
+        })?;
+
+    Ok(promotion)
+}
+
+/// Discount in minor units that `promotion` gives on `order`, never more than its subtotal
+pub fn promotion_discount(promotion: &Promotion, order: &Order) -> Result<i64, ValidationError> {
+    let subtotal = order.unit_price_minor * order.quantity as i64;
+
+    let discount = match promotion.kind {
+        PromotionKind::PercentOff => {
+            let percent = promotion.percent_off.unwrap_or(0) as i64;
+            (subtotal * percent + 50) / 100
+        }
+        PromotionKind::FixedOff => {
+            if promotion.currency.as_deref() != Some(order.currency.as_str()) {
+                return Err(ValidationError {
+                    error: format!(
+                        "Promo code {} only applies to orders in {}",
+                        promotion.code,
+                        promotion.currency.as_deref().unwrap_or("another currency")
+                    ),
+                    field: Some("promo_code".to_string()),
+                });
+            }
+            promotion.amount_off_minor.unwrap_or(0)
+        }
+        PromotionKind::BuyXGetY => {
+            let buy = promotion.buy_quantity.unwrap_or(0);
+            let free = promotion.free_quantity.unwrap_or(0);
+            let free_units = match buy + free {
+                0 => 0,
+                bundle => order.quantity / bundle * free,
+            };
+            order.unit_price_minor * free_units as i64
+        }
+    };
+
+    Ok(discount.clamp(0, subtotal))
+}
+
+/// Apply `code` to `order` as part of the caller's transaction, returning the discount.
+/// The redemption count is incremented only while the code is within its usage cap.
+pub async fn redeem_promotion(conn: &mut SqliteConnection, code: &str, order: &Order) -> Result<i64, ApiError> {
+    let promotion = get_promotion_by_code(&mut *conn, code).await?
+        .ok_or_else(|| unknown_promotion(code))?;
+
+    let now = Utc::now();
+    if let Some(starts_at) = promotion.starts_at.filter(|starts_at| *starts_at > now) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} is not valid until {}", code, starts_at.to_rfc3339()),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+    if let Some(ends_at) = promotion.ends_at.filter(|ends_at| *ends_at < now) {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} expired on {}", code, ends_at.to_rfc3339()),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+
+    let discount = promotion_discount(&promotion, order)?;
+
+    let result = sqlx::query(
+        "UPDATE promotions SET redemptions = redemptions + 1 \
+         WHERE code = ? AND (max_redemptions IS NULL OR redemptions < max_redemptions)"
+    )
+        .bind(code)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in redeem_promotion: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to redeem promotion".to_string(),
+            })
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Promo code {} has reached its redemption limit", code),
+            field: Some("promo_code".to_string()),
+        }));
+    }
+
+    Ok(discount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::utils::init_db;
+
+    fn promotion(code: &str, kind: PromotionKind) -> Promotion {
+        Promotion {
+            code: code.to_string(),
+            kind,
+            percent_off: None,
+            amount_off_minor: None,
+            currency: None,
+            buy_quantity: None,
+            free_quantity: None,
+            starts_at: None,
+            ends_at: None,
+            max_redemptions: None,
+            redemptions: 0,
+        }
+    }
+
+    fn order(quantity: u32, unit_price_minor: i64) -> Order {
+        Order {
+            id: 1,
+            item: "Widget".to_string(),
+            status: "pending".to_string(),
+            quantity,
+            unit_price_minor,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_percent_off_rounds_to_nearest_minor_unit() {
+        let promotion = Promotion { percent_off: Some(15), ..promotion("SAVE15", PromotionKind::PercentOff) };
+        // 15% of 3 x 333 = 149.85
+        assert_eq!(promotion_discount(&promotion, &order(3, 333)).unwrap(), 150);
+    }
+
+    #[test]
+    fn test_fixed_off_is_capped_and_currency_bound() {
+        let promotion = Promotion {
+            amount_off_minor: Some(1000),
+            currency: Some("USD".to_string()),
+            ..promotion("TENOFF", PromotionKind::FixedOff)
+        };
+        assert_eq!(promotion_discount(&promotion, &order(1, 2500)).unwrap(), 1000);
+        assert_eq!(promotion_discount(&promotion, &order(1, 400)).unwrap(), 400);
+
+        let euro_order = Order { currency: "EUR".to_string(), ..order(1, 2500) };
+        let error = promotion_discount(&promotion, &euro_order).unwrap_err();
+        assert_eq!(error.error, "Promo code TENOFF only applies to orders in USD");
+    }
+
+    #[test]
+    fn test_buy_x_get_y() {
+        let promotion = Promotion {
+            buy_quantity: Some(2),
+            free_quantity: Some(1),
+            ..promotion("3FOR2", PromotionKind::BuyXGetY)
+        };
+        assert_eq!(promotion_discount(&promotion, &order(2, 100)).unwrap(), 0);
+        assert_eq!(promotion_discount(&promotion, &order(3, 100)).unwrap(), 100);
+        assert_eq!(promotion_discount(&promotion, &order(7, 100)).unwrap(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_redeem_enforces_window_and_cap() {
+        let pool = init_db().await.unwrap();
+        let now = Utc::now();
+        create_promotion(&pool, &Promotion {
+            percent_off: Some(10),
+            max_redemptions: Some(1),
+            ..promotion("ONCE", PromotionKind::PercentOff)
+        }).await.unwrap();
+        create_promotion(&pool, &Promotion {
+            percent_off: Some(10),
+            ends_at: Some(now - Duration::days(1)),
+            ..promotion("OLD", PromotionKind::PercentOff)
+        }).await.unwrap();
+        create_promotion(&pool, &Promotion {
+            percent_off: Some(10),
+            starts_at: Some(now + Duration::days(1)),
+            ..promotion("SOON", PromotionKind::PercentOff)
+        }).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert_eq!(redeem_promotion(&mut conn, "ONCE", &order(1, 1000)).await.unwrap(), 100);
+
+        let expect_error = |result: Result<i64, ApiError>, expected: &str| match result {
+            Err(ApiError::Validation(err)) => {
+                assert!(err.error.contains(expected), "unexpected error: {}", err.error);
+                assert_eq!(err.field, Some("promo_code".to_string()));
+            }
+            _ => panic!("Expected validation error"),
+        };
+        expect_error(redeem_promotion(&mut conn, "ONCE", &order(1, 1000)).await, "reached its redemption limit");
+        expect_error(redeem_promotion(&mut conn, "OLD", &order(1, 1000)).await, "expired on");
+        expect_error(redeem_promotion(&mut conn, "SOON", &order(1, 1000)).await, "is not valid until");
+        expect_error(redeem_promotion(&mut conn, "NOPE", &order(1, 1000)).await, "does not exist");
+
+        let once = get_promotion_by_code(&pool, "ONCE").await.unwrap().unwrap();
+        assert_eq!(once.redemptions, 1);
+    }
+}
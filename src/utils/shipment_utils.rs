@@ -0,0 +1,299 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use crate::config::PaymentConfig;
+use crate::utils::{apply_order_status, begin_transaction, commit_transaction, get_order_by_id, publish_order_changes, DbPool,
+                   Order, OrderChange};
+use crate::validators::{validate_delivered_at, ApiError, ServerError, ValidationError};
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema, async_graphql::SimpleObject)]
+/// Parcel handed to a carrier for an order
+pub struct Shipment {
+    /// Unique identifier for the shipment, assigned by the server
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub id: u32,
+    /// Order the shipment belongs to
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub order_id: u32,
+    /// Carrier transporting the parcel
+    #[schema(example = "UPS")]
+    pub carrier: String,
+    /// Carrier's tracking number for the parcel
+    #[schema(example = "1Z999AA10123456784")]
+    pub tracking_number: String,
+    /// When the parcel was handed to the carrier; defaults to now
+    #[serde(default = "Utc::now")]
+    pub shipped_at: DateTime<Utc>,
+    /// When the parcel was delivered, if it has been
+    #[serde(default)]
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Columns selected whenever a shipment is read back
+const SHIPMENT_COLUMNS: &str = "id, order_id, carrier, tracking_number, shipped_at, delivered_at";
+
+/// Get all shipments recorded for an order, oldest first
+pub async fn get_shipments_for_order<'e, E>(executor: E, order_id: u32) -> Result<Vec<Shipment>, ApiError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    sqlx::query_as::<_, Shipment>(&format!("SELECT {} FROM shipments WHERE order_id = ? ORDER BY id", SHIPMENT_COLUMNS))
+        .bind(order_id)
+        .fetch_all(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_shipments_for_order: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve shipments".to_string(),
+            })
+        })
+}
+
+/// Get a specific shipment of an order
+async fn get_shipment(conn: &mut SqliteConnection, order_id: u32, shipment_id: u32) -> Result<Option<Shipment>, ApiError> {
+    sqlx::query_as::<_, Shipment>(&format!("SELECT {} FROM shipments WHERE id = ? AND order_id = ?", SHIPMENT_COLUMNS))
+        .bind(shipment_id)
+        .bind(order_id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_shipment: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve shipment".to_string(),
+            })
+        })
+}
+
+/// Move the order to `shipped`, or to `delivered` once every shipment has been delivered,
+/// through the same path as a manual status change so stock stays consistent.
+/// Returns the change to publish once the transaction commits, if the status moved.
+async fn advance_order(
+    conn: &mut SqliteConnection,
+    order: &Order,
+    payments: &PaymentConfig,
+) -> Result<Option<OrderChange>, ApiError> {
+    let shipments = get_shipments_for_order(&mut *conn, order.id).await?;
+    let status = if shipments.iter().all(|shipment| shipment.delivered_at.is_some()) {
+        "delivered"
+    } else {
+        "shipped"
+    };
+
+    if order.status == status {
+        return Ok(None);
+    }
+    let (_, change) = apply_order_status(conn, order.id, status, payments).await?;
+    Ok(change)
+}
+
+/// Record a shipment for an order and advance the order's status accordingly.
+/// Cancelled and delivered orders cannot be shipped, and the order needs a shipping address.
+pub async fn create_shipment(
+    pool: &DbPool,
+    order_id: u32,
+    shipment: &Shipment,
+    payments: &PaymentConfig,
+) -> Result<Shipment, ApiError> {
+    let mut tx = begin_transaction(pool, "create_shipment").await?;
+
+    let order = get_order_by_id(&mut *tx, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    if matches!(order.status.as_str(), "cancelled" | "delivered") {
+        return Err(ApiError::Conflict(format!(
+            "Order {} is {} and cannot be shipped",
+            order_id, order.status
+        )));
+    }
+    if order.shipping_address.is_none() {
+        return Err(ApiError::Validation(ValidationError {
+            error: format!("Order {} has no shipping address", order_id),
+            field: Some("shipping_address".to_string()),
+        }));
+    }
+
+    let result = sqlx::query(
+        "INSERT INTO shipments (order_id, carrier, tracking_number, shipped_at, delivered_at) VALUES (?, ?, ?, ?, ?)"
+    )
+        .bind(order_id)
+        .bind(&shipment.carrier)
+        .bind(&shipment.tracking_number)
+        .bind(shipment.shipped_at)
+        .bind(shipment.delivered_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in create_shipment: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create shipment".to_string(),
+            })
+        })?;
+
+    let change = advance_order(&mut tx, &order, payments).await?;
+    commit_transaction(tx, "create_shipment").await?;
+    publish_order_changes(change);
+
+    let mut created = shipment.clone();
+    created.id = result.last_insert_rowid() as u32;
+    created.order_id = order_id;
+    Ok(created)
+}
+
+/// Record the delivery of a shipment; the order becomes `delivered` once all its shipments are
+pub async fn mark_shipment_delivered(
+    pool: &DbPool,
+    order_id: u32,
+    shipment_id: u32,
+    delivered_at: DateTime<Utc>,
+    payments: &PaymentConfig,
+) -> Result<Shipment, ApiError> {
+    let mut tx = begin_transaction(pool, "mark_shipment_delivered").await?;
+
+    let mut shipment = get_shipment(&mut tx, order_id, shipment_id).await?
+        .ok_or_else(|| ApiError::NotFound("Shipment not found".to_string()))?;
+    validate_delivered_at(shipment.shipped_at, delivered_at)?;
+    shipment.delivered_at = Some(delivered_at);
+
+    sqlx::query("UPDATE shipments SET delivered_at = ? WHERE id = ?")
+        .bind(delivered_at)
+        .bind(shipment_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in mark_shipment_delivered: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to update shipment".to_string(),
+            })
+        })?;
+
+    let order = get_order_by_id(&mut *tx, order_id).await?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    let change = if order.status != "cancelled" {
+        advance_order(&mut tx, &order, payments).await?
+    } else {
+        None
+    };
+
+    commit_transaction(tx, "mark_shipment_delivered").await?;
+    publish_order_changes(change);
+    Ok(shipment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{create_order, create_product, get_product_by_sku, init_db, update_order_status, Product, ShippingAddress};
+
+    fn address() -> ShippingAddress {
+        ShippingAddress {
+            recipient: "Ada Lovelace".to_string(),
+            line1: "12 St James's Square".to_string(),
+            line2: None,
+            city: "London".to_string(),
+            region: None,
+            postal_code: "SW1Y 4JH".to_string(),
+            country: "GB".to_string(),
+        }
+    }
+
+    fn shipment(tracking_number: &str) -> Shipment {
+        Shipment {
+            id: 0,
+            order_id: 0,
+            carrier: "Royal Mail".to_string(),
+            tracking_number: tracking_number.to_string(),
+            shipped_at: Utc::now(),
+            delivered_at: None,
+        }
+    }
+
+    async fn setup_order(pool: &DbPool, shipping_address: Option<ShippingAddress>) {
+        create_order(pool, &Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "processing".to_string(),
+            quantity: 1,
+            shipping_address,
+            ..Default::default()
+        }, &PaymentConfig::default()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shipments_advance_order_status() {
+        let pool = init_db().await.unwrap();
+        setup_order(&pool, Some(address())).await;
+
+        let first = create_shipment(&pool, 1, &shipment("RM-1"), &PaymentConfig::default()).await.unwrap();
+        let second = create_shipment(&pool, 1, &shipment("RM-2"), &PaymentConfig::default()).await.unwrap();
+        assert_eq!(get_order_by_id(&pool, 1).await.unwrap().unwrap().status, "shipped");
+        assert_eq!(get_shipments_for_order(&pool, 1).await.unwrap().len(), 2);
+
+        mark_shipment_delivered(&pool, 1, first.id, Utc::now(), &PaymentConfig::default()).await.unwrap();
+        assert_eq!(get_order_by_id(&pool, 1).await.unwrap().unwrap().status, "shipped");
+
+        let delivered = mark_shipment_delivered(&pool, 1, second.id, Utc::now(), &PaymentConfig::default()).await.unwrap();
+        assert!(delivered.delivered_at.is_some());
+        assert_eq!(get_order_by_id(&pool, 1).await.unwrap().unwrap().status, "delivered");
+    }
+
+    #[tokio::test]
+    async fn test_shipment_requires_shipping_address() {
+        let pool = init_db().await.unwrap();
+        setup_order(&pool, None).await;
+
+        match create_shipment(&pool, 1, &shipment("RM-1"), &PaymentConfig::default()).await.unwrap_err() {
+            ApiError::Validation(err) => assert_eq!(err.field, Some("shipping_address".to_string())),
+            _ => panic!("Expected validation error"),
+        }
+        assert!(get_shipments_for_order(&pool, 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_order_cannot_be_shipped() {
+        let pool = init_db().await.unwrap();
+        setup_order(&pool, Some(address())).await;
+        update_order_status(&pool, 1, "cancelled", &PaymentConfig::default()).await.unwrap();
+
+        assert!(matches!(create_shipment(&pool, 1, &shipment("RM-1"), &PaymentConfig::default()).await, Err(ApiError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_shipping_keeps_stock_reserved() {
+        let pool = init_db().await.unwrap();
+        create_product(&pool, &Product { sku: "AE-1".to_string(), name: "Engine".to_string(), stock: 3 }).await.unwrap();
+        create_order(&pool, &Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "pending".to_string(),
+            quantity: 2,
+            sku: Some("AE-1".to_string()),
+            shipping_address: Some(address()),
+            ..Default::default()
+        }, &PaymentConfig::default()).await.unwrap();
+
+        create_shipment(&pool, 1, &shipment("RM-1"), &PaymentConfig::default()).await.unwrap();
+        assert_eq!(get_product_by_sku(&pool, "AE-1").await.unwrap().unwrap().stock, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_before_shipping_rejected() {
+        let pool = init_db().await.unwrap();
+        setup_order(&pool, Some(address())).await;
+        let created = create_shipment(&pool, 1, &shipment("RM-1"), &PaymentConfig::default()).await.unwrap();
+
+        let too_early = created.shipped_at - chrono::Duration::days(1);
+        match mark_shipment_delivered(&pool, 1, created.id, too_early, &PaymentConfig::default()).await.unwrap_err() {
+            ApiError::Validation(err) => assert_eq!(err.field, Some("delivered_at".to_string())),
+            _ => panic!("Expected validation error"),
+        }
+        assert!(matches!(
+            mark_shipment_delivered(&pool, 1, created.id + 1, Utc::now(), &PaymentConfig::default()).await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::stream::{BoxStream, StreamExt};
+use crate::config::PaymentConfig;
+use crate::payments::PaymentGateway;
+use crate::utils::{
+    authorize_payment, capture_payment, create_api_key, create_customer, create_order, create_orders,
+    create_product, create_promotion, create_shipment, create_webhook_subscription, delete_customer, delete_order,
+    delete_product, delete_promotion, delete_webhook_subscription, find_api_key, find_certificate_identity,
+    get_all_customers, get_all_orders, get_all_products, get_all_promotions, get_all_webhook_subscriptions,
+    get_customer_by_id, get_due_webhooks, get_latest_order_change_seq, get_migration_version, get_order_by_id,
+    get_order_changes_since, get_order_stats, get_orders_page, get_payments_for_order, get_product_by_sku,
+    get_promotion_by_code, get_shipments_for_order, get_webhook_deliveries, get_webhook_subscription,
+    mark_shipment_delivered, ping_db, record_webhook_attempt, refund_payment, register_api_key,
+    register_certificate_identity, stream_orders, update_customer, update_order, update_order_status,
+    update_product, void_payment, ApiKey, CertificateIdentity, CredentialRepository, Customer, CustomerRepository,
+    DbPool, DeliveryOutcome, Order, OrderChange, OrderChangeFilter, OrderFilter, OrderRepository, OrderStats,
+    OutboxEntry, Payment, PaymentRepository, PoolStatus, Product, ProductRepository, Promotion, PromotionRepository,
+    Role, Shipment, ShipmentRepository, Store, WebhookDelivery, WebhookRepository, WebhookSubscription
+};
+use crate::validators::ApiError;
+
+/// Everything kept in the SQLite database, with stock, promotions, payments and the change log
+/// handled in the same transaction as every order write
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pool: DbPool,
+}
+
+impl SqliteStore {
+    pub fn new(pool: DbPool) -> Self {
+        SqliteStore { pool }
+    }
+
+    /// Connection pool of the database
+    pub fn pool(&self) -> &DbPool {
+        &self.pool
+    }
+}
+
+#[async_trait]
+impl OrderRepository for SqliteStore {
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, ApiError> {
+        get_all_orders(&self.pool, filter).await
+    }
+
+    fn stream_orders(&self, filter: OrderFilter) -> BoxStream<'static, Result<Order, ApiError>> {
+        stream_orders(self.pool.clone(), filter).boxed()
+    }
+
+    async fn orders_page(&self, filter: &OrderFilter, after_id: Option<u32>, limit: u32) -> Result<Vec<Order>, ApiError> {
+        get_orders_page(&self.pool, filter, after_id, limit).await
+    }
+
+    async fn get_order(&self, id: u32) -> Result<Option<Order>, ApiError> {
+        get_order_by_id(&self.pool, id).await
+    }
+
+    async fn create_order(&self, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        create_order(&self.pool, order, payments).await
+    }
+
+    async fn create_orders(
+        &self,
+        orders: &[Order],
+        dry_run: bool,
+        payments: &PaymentConfig,
+    ) -> Result<Result<Vec<Order>, (usize, ApiError)>, ApiError> {
+        create_orders(&self.pool, orders, dry_run, payments).await
+    }
+
+    async fn update_order(&self, id: u32, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        update_order(&self.pool, id, order, payments).await
+    }
+
+    async fn update_order_status(&self, id: u32, status: &str, payments: &PaymentConfig) -> Result<Order, ApiError> {
+        update_order_status(&self.pool, id, status, payments).await
+    }
+
+    async fn delete_order(&self, id: u32) -> Result<Order, ApiError> {
+        delete_order(&self.pool, id).await
+    }
+
+    async fn order_stats(&self, top_items: u32, days: u32) -> Result<OrderStats, ApiError> {
+        get_order_stats(&self.pool, top_items, days).await
+    }
+
+    async fn latest_order_change_seq(&self) -> Result<i64, ApiError> {
+        get_latest_order_change_seq(&self.pool).await
+    }
+
+    async fn order_changes_since(
+        &self,
+        after_seq: i64,
+        filter: &OrderChangeFilter,
+        limit: u32,
+    ) -> Result<Vec<OrderChange>, ApiError> {
+        get_order_changes_since(&self.pool, after_seq, filter, limit).await
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for SqliteStore {
+    async fn list_customers(&self) -> Result<Vec<Customer>, ApiError> {
+        get_all_customers(&self.pool).await
+    }
+
+    async fn get_customer(&self, id: u32) -> Result<Option<Customer>, ApiError> {
+        get_customer_by_id(&self.pool, id).await
+    }
+
+    async fn create_customer(&self, customer: &Customer) -> Result<Customer, ApiError> {
+        create_customer(&self.pool, customer).await
+    }
+
+    async fn update_customer(&self, id: u32, customer: &Customer) -> Result<Customer, ApiError> {
+        update_customer(&self.pool, id, customer).await
+    }
+
+    async fn delete_customer(&self, id: u32) -> Result<Customer, ApiError> {
+        delete_customer(&self.pool, id).await
+    }
+}
+
+#[async_trait]
+impl ProductRepository for SqliteStore {
+    async fn list_products(&self) -> Result<Vec<Product>, ApiError> {
+        get_all_products(&self.pool).await
+    }
+
+    async fn get_product(&self, sku: &str) -> Result<Option<Product>, ApiError> {
+        get_product_by_sku(&self.pool, sku).await
+    }
+
+    async fn create_product(&self, product: &Product) -> Result<Product, ApiError> {
+        create_product(&self.pool, product).await
+    }
+
+    async fn update_product(&self, sku: &str, product: &Product) -> Result<Product, ApiError> {
+        update_product(&self.pool, sku, product).await
+    }
+
+    async fn delete_product(&self, sku: &str) -> Result<Product, ApiError> {
+        delete_product(&self.pool, sku).await
+    }
+}
+
+#[async_trait]
+impl PromotionRepository for SqliteStore {
+    async fn list_promotions(&self) -> Result<Vec<Promotion>, ApiError> {
+        get_all_promotions(&self.pool).await
+    }
+
+    async fn get_promotion(&self, code: &str) -> Result<Option<Promotion>, ApiError> {
+        get_promotion_by_code(&self.pool, code).await
+    }
+
+    async fn create_promotion(&self, promotion: &Promotion) -> Result<Promotion, ApiError> {
+        create_promotion(&self.pool, promotion).await
+    }
+
+    async fn delete_promotion(&self, code: &str) -> Result<Promotion, ApiError> {
+        delete_promotion(&self.pool, code).await
+    }
+}
+
+#[async_trait]
+impl ShipmentRepository for SqliteStore {
+    async fn list_shipments(&self, order_id: u32) -> Result<Vec<Shipment>, ApiError> {
+        get_shipments_for_order(&self.pool, order_id).await
+    }
+
+    async fn create_shipment(&self, order_id: u32, shipment: &Shipment, payments: &PaymentConfig) -> Result<Shipment, ApiError> {
+        create_shipment(&self.pool, order_id, shipment, payments).await
+    }
+
+    async fn mark_shipment_delivered(
+        &self,
+        order_id: u32,
+        shipment_id: u32,
+        delivered_at: DateTime<Utc>,
+        payments: &PaymentConfig,
+    ) -> Result<Shipment, ApiError> {
+        mark_shipment_delivered(&self.pool, order_id, shipment_id, delivered_at, payments).await
+    }
+}
+
+#[async_trait]
+impl PaymentRepository for SqliteStore {
+    async fn list_payments(&self, order_id: u32) -> Result<Vec<Payment>, ApiError> {
+        get_payments_for_order(&self.pool, order_id).await
+    }
+
+    async fn authorize_payment(&self, gateway: &dyn PaymentGateway, order_id: u32) -> Result<Payment, ApiError> {
+        authorize_payment(&self.pool, gateway, order_id).await
+    }
+
+    async fn capture_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        capture_payment(&self.pool, gateway, order_id, payment_id).await
+    }
+
+    async fn void_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError> {
+        void_payment(&self.pool, gateway, order_id, payment_id).await
+    }
+
+    async fn refund_payment(
+        &self,
+        gateway: &dyn PaymentGateway,
+        order_id: u32,
+        payment_id: u32,
+        amount_minor: Option<i64>,
+    ) -> Result<Payment, ApiError> {
+        refund_payment(&self.pool, gateway, order_id, payment_id, amount_minor).await
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for SqliteStore {
+    async fn list_webhook_subscriptions(&self) -> Result<Vec<WebhookSubscription>, ApiError> {
+        get_all_webhook_subscriptions(&self.pool).await
+    }
+
+    async fn get_webhook_subscription(&self, id: u32) -> Result<Option<WebhookSubscription>, ApiError> {
+        get_webhook_subscription(&self.pool, id).await
+    }
+
+    async fn create_webhook_subscription(&self, subscription: &WebhookSubscription) -> Result<WebhookSubscription, ApiError> {
+        create_webhook_subscription(&self.pool, subscription).await
+    }
+
+    async fn delete_webhook_subscription(&self, id: u32) -> Result<WebhookSubscription, ApiError> {
+        delete_webhook_subscription(&self.pool, id).await
+    }
+
+    async fn webhook_deliveries(&self, subscription_id: u32) -> Result<Vec<WebhookDelivery>, ApiError> {
+        get_webhook_deliveries(&self.pool, subscription_id).await
+    }
+
+    async fn due_webhooks(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<OutboxEntry>, ApiError> {
+        get_due_webhooks(&self.pool, now, limit).await
+    }
+
+    async fn record_webhook_attempt(
+        &self,
+        entry: &OutboxEntry,
+        outcome: &DeliveryOutcome,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), ApiError> {
+        record_webhook_attempt(&self.pool, entry, outcome, retry_at).await
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for SqliteStore {
+    async fn register_api_key(&self, name: &str, role: Role, secret: &str) -> Result<ApiKey, ApiError> {
+        register_api_key(&self.pool, name, role, secret).await
+    }
+
+    async fn find_api_key(&self, secret: &str) -> Result<Option<ApiKey>, ApiError> {
+        find_api_key(&self.pool, secret).await
+    }
+
+    async fn create_api_key(&self, name: &str, role: Role) -> Result<(ApiKey, String), ApiError> {
+        create_api_key(&self.pool, name, role).await
+    }
+
+    async fn register_certificate_identity(&self, subject: &str, name: &str, role: Role) -> Result<CertificateIdentity, ApiError> {
+        register_certificate_identity(&self.pool, subject, name, role).await
+    }
+
+    async fn find_certificate_identity(&self, subject: &str) -> Result<Option<CertificateIdentity>, ApiError> {
+        find_certificate_identity(&self.pool, subject).await
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn ping(&self) -> Result<(), ApiError> {
+        ping_db(&self.pool).await
+    }
+
+    async fn migration_version(&self) -> Result<Option<i64>, ApiError> {
+        get_migration_version(&self.pool).await.map(Some)
+    }
+
+    fn pool_status(&self) -> PoolStatus {
+        PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle(),
+            closed: self.pool.is_closed(),
+        }
+    }
+}
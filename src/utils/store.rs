@@ -0,0 +1,198 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use crate::config::PaymentConfig;
+use crate::payments::PaymentGateway;
+use crate::utils::{
+    generate_api_key, ApiKey, CertificateIdentity, Customer, DeliveryOutcome, Order, OrderChange, OrderChangeFilter,
+    OrderFilter, OrderStats, OutboxEntry, Payment, Product, Promotion, Role, Shipment, WebhookDelivery,
+    WebhookSubscription
+};
+use crate::validators::ApiError;
+
+/// Orders and what is derived from them: listings, statistics and the change log.
+/// Implementations enforce the same rules as the SQLite database: unique IDs, `Order not found` for unknown IDs,
+/// computed totals, stock reserved for the order's SKU, promotions redeemed at creation and never changed after,
+/// customers, products and promotions that must exist, and a captured payment before shipping when `payments`
+/// requires one. Every committed change is logged, queued for webhooks and published.
+/// Input is validated by the caller.
+#[async_trait]
+pub trait OrderRepository: Send + Sync + 'static {
+    /// All orders matching `filter`, ordered by ID
+    async fn list_orders(&self, filter: &OrderFilter) -> Result<Vec<Order>, ApiError>;
+    /// Orders matching `filter` one at a time, ordered by ID
+    fn stream_orders(&self, filter: OrderFilter) -> BoxStream<'static, Result<Order, ApiError>>;
+    /// Up to `limit` orders matching `filter` with IDs after `after_id`, ordered by ID
+    async fn orders_page(&self, filter: &OrderFilter, after_id: Option<u32>, limit: u32) -> Result<Vec<Order>, ApiError>;
+    async fn get_order(&self, id: u32) -> Result<Option<Order>, ApiError>;
+    /// Save a new order and return it with its computed totals
+    async fn create_order(&self, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError>;
+    /// Save several orders, all or none. The outer error reports a storage failure; the inner one the position
+    /// of the first order that could not be created and why. With `dry_run` every check runs but nothing is saved.
+    async fn create_orders(
+        &self,
+        orders: &[Order],
+        dry_run: bool,
+        payments: &PaymentConfig,
+    ) -> Result<Result<Vec<Order>, (usize, ApiError)>, ApiError>;
+    /// Replace an existing order and return it with its computed totals
+    async fn update_order(&self, id: u32, order: &Order, payments: &PaymentConfig) -> Result<Order, ApiError>;
+    async fn update_order_status(&self, id: u32, status: &str, payments: &PaymentConfig) -> Result<Order, ApiError>;
+    /// Remove an order and return it as it was
+    async fn delete_order(&self, id: u32) -> Result<Order, ApiError>;
+    /// Totals, a breakdown by status, the `top_items` items with the largest quantity ordered, and daily
+    /// creations and deliveries over the last `days` days including today
+    async fn order_stats(&self, top_items: u32, days: u32) -> Result<OrderStats, ApiError>;
+    /// Sequence number of the latest change, or 0 if there is none
+    async fn latest_order_change_seq(&self) -> Result<i64, ApiError>;
+    /// Up to `limit` changes after `after_seq` matching `filter`, oldest first
+    async fn order_changes_since(
+        &self,
+        after_seq: i64,
+        filter: &OrderChangeFilter,
+        limit: u32,
+    ) -> Result<Vec<OrderChange>, ApiError>;
+}
+
+/// Customers placing orders; customers who still have orders cannot be deleted
+#[async_trait]
+pub trait CustomerRepository: Send + Sync + 'static {
+    /// All customers, ordered by ID
+    async fn list_customers(&self) -> Result<Vec<Customer>, ApiError>;
+    async fn get_customer(&self, id: u32) -> Result<Option<Customer>, ApiError>;
+    async fn create_customer(&self, customer: &Customer) -> Result<Customer, ApiError>;
+    async fn update_customer(&self, id: u32, customer: &Customer) -> Result<Customer, ApiError>;
+    async fn delete_customer(&self, id: u32) -> Result<Customer, ApiError>;
+}
+
+/// Product catalog with stock on hand; products referenced by orders cannot be deleted
+#[async_trait]
+pub trait ProductRepository: Send + Sync + 'static {
+    /// All products, ordered by SKU
+    async fn list_products(&self) -> Result<Vec<Product>, ApiError>;
+    async fn get_product(&self, sku: &str) -> Result<Option<Product>, ApiError>;
+    async fn create_product(&self, product: &Product) -> Result<Product, ApiError>;
+    async fn update_product(&self, sku: &str, product: &Product) -> Result<Product, ApiError>;
+    async fn delete_product(&self, sku: &str) -> Result<Product, ApiError>;
+}
+
+/// Discount codes; promotions that have been redeemed cannot be deleted
+#[async_trait]
+pub trait PromotionRepository: Send + Sync + 'static {
+    /// All promotions, ordered by code
+    async fn list_promotions(&self) -> Result<Vec<Promotion>, ApiError>;
+    async fn get_promotion(&self, code: &str) -> Result<Option<Promotion>, ApiError>;
+    async fn create_promotion(&self, promotion: &Promotion) -> Result<Promotion, ApiError>;
+    async fn delete_promotion(&self, code: &str) -> Result<Promotion, ApiError>;
+}
+
+/// Shipments of orders, advancing the order to `shipped` and then `delivered` like a status change would
+#[async_trait]
+pub trait ShipmentRepository: Send + Sync + 'static {
+    /// Shipments recorded for an order, oldest first
+    async fn list_shipments(&self, order_id: u32) -> Result<Vec<Shipment>, ApiError>;
+    async fn create_shipment(&self, order_id: u32, shipment: &Shipment, payments: &PaymentConfig) -> Result<Shipment, ApiError>;
+    async fn mark_shipment_delivered(
+        &self,
+        order_id: u32,
+        shipment_id: u32,
+        delivered_at: DateTime<Utc>,
+        payments: &PaymentConfig,
+    ) -> Result<Shipment, ApiError>;
+}
+
+/// Payments taken for orders through a gateway; an order has at most one active payment at a time
+#[async_trait]
+pub trait PaymentRepository: Send + Sync + 'static {
+    /// Payments taken for an order, oldest first
+    async fn list_payments(&self, order_id: u32) -> Result<Vec<Payment>, ApiError>;
+    /// Authorize the order's total with the gateway and record the payment
+    async fn authorize_payment(&self, gateway: &dyn PaymentGateway, order_id: u32) -> Result<Payment, ApiError>;
+    async fn capture_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError>;
+    async fn void_payment(&self, gateway: &dyn PaymentGateway, order_id: u32, payment_id: u32) -> Result<Payment, ApiError>;
+    /// Return `amount_minor` of a captured payment, or everything not yet refunded
+    async fn refund_payment(
+        &self,
+        gateway: &dyn PaymentGateway,
+        order_id: u32,
+        payment_id: u32,
+        amount_minor: Option<i64>,
+    ) -> Result<Payment, ApiError>;
+}
+
+/// Webhook subscriptions, the outbox of events waiting to be delivered to them, and the delivery log
+#[async_trait]
+pub trait WebhookRepository: Send + Sync + 'static {
+    /// All subscriptions, ordered by ID
+    async fn list_webhook_subscriptions(&self) -> Result<Vec<WebhookSubscription>, ApiError>;
+    async fn get_webhook_subscription(&self, id: u32) -> Result<Option<WebhookSubscription>, ApiError>;
+    /// Create a subscription; it receives events for changes made from now on
+    async fn create_webhook_subscription(&self, subscription: &WebhookSubscription) -> Result<WebhookSubscription, ApiError>;
+    /// Delete a subscription together with its undelivered events and delivery log
+    async fn delete_webhook_subscription(&self, id: u32) -> Result<WebhookSubscription, ApiError>;
+    /// Delivery log of a subscription, most recent attempt first
+    async fn webhook_deliveries(&self, subscription_id: u32) -> Result<Vec<WebhookDelivery>, ApiError>;
+    /// Up to `limit` pending events whose next attempt is due at `now`, oldest first
+    async fn due_webhooks(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<OutboxEntry>, ApiError>;
+    /// Log a delivery attempt and settle the event: delivered on success, otherwise retried
+    /// at `retry_at`, or given up on when there is no retry left
+    async fn record_webhook_attempt(
+        &self,
+        entry: &OutboxEntry,
+        outcome: &DeliveryOutcome,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), ApiError>;
+}
+
+/// API keys, stored as hashes only, and the identities of TLS client certificates
+#[async_trait]
+pub trait CredentialRepository: Send + Sync + 'static {
+    /// Store an API key with the given secret, returning the stored record
+    async fn register_api_key(&self, name: &str, role: Role, secret: &str) -> Result<ApiKey, ApiError>;
+    /// Look up an active (non-revoked) API key by its plaintext secret
+    async fn find_api_key(&self, secret: &str) -> Result<Option<ApiKey>, ApiError>;
+    /// Map a client certificate subject to a caller identity, replacing any existing mapping
+    async fn register_certificate_identity(&self, subject: &str, name: &str, role: Role) -> Result<CertificateIdentity, ApiError>;
+    /// Look up the identity mapped to an active client certificate subject
+    async fn find_certificate_identity(&self, subject: &str) -> Result<Option<CertificateIdentity>, ApiError>;
+
+    /// Generate and store a new API key, returning the record and the plaintext secret.
+    /// The secret cannot be recovered later, so it must be handed to the client now.
+    async fn create_api_key(&self, name: &str, role: Role) -> Result<(ApiKey, String), ApiError> {
+        let secret = generate_api_key();
+        let key = self.register_api_key(name, role, &secret).await?;
+        Ok((key, secret))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, utoipa::ToSchema)]
+/// Connection pool state reported by the readiness probe
+pub struct PoolStatus {
+    /// Number of connections currently open
+    pub size: u32,
+    /// Number of open connections that are idle
+    pub idle: usize,
+    /// Whether the pool has been closed
+    pub closed: bool,
+}
+
+/// Everything the API stores, behind one handle the router is built on
+#[async_trait]
+pub trait Store:
+    OrderRepository
+    + CustomerRepository
+    + ProductRepository
+    + PromotionRepository
+    + ShipmentRepository
+    + PaymentRepository
+    + WebhookRepository
+    + CredentialRepository
+{
+    /// Perform a cheap round trip to verify the storage is reachable
+    async fn ping(&self) -> Result<(), ApiError>;
+    /// Schema migration version the storage is at, or `None` for storage without a schema
+    async fn migration_version(&self) -> Result<Option<i64>, ApiError>;
+    /// State of the connection pool; storage without connections reports an empty, open pool
+    fn pool_status(&self) -> PoolStatus;
+}
@@ -0,0 +1,448 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{types::Json, SqliteConnection};
+use crate::utils::{begin_transaction, commit_transaction, DbPool, Order};
+use crate::validators::{ApiError, ServerError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+/// Order lifecycle event a webhook can subscribe to
+pub enum WebhookEvent {
+    /// An order was created
+    #[serde(rename = "order.created")]
+    Created,
+    /// An order was replaced with `PUT`
+    #[serde(rename = "order.updated")]
+    Updated,
+    /// An order's status changed, by any means
+    #[serde(rename = "order.status_changed")]
+    StatusChanged,
+    /// An order was deleted
+    #[serde(rename = "order.deleted")]
+    Deleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Created => "order.created",
+            WebhookEvent::Updated => "order.updated",
+            WebhookEvent::StatusChanged => "order.status_changed",
+            WebhookEvent::Deleted => "order.deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// Endpoint receiving order events
+pub struct WebhookSubscription {
+    /// Unique identifier for the subscription, assigned by the server
+    #[serde(default, skip_deserializing)]
+    #[schema(read_only)]
+    pub id: u32,
+    /// HTTP or HTTPS URL events are POSTed to
+    #[schema(example = "https://erp.example.com/hooks/orders")]
+    pub url: String,
+    /// Events delivered to the URL
+    #[sqlx(json)]
+    pub event_types: Vec<WebhookEvent>,
+    /// Key used to sign deliveries; never returned
+    #[serde(default, skip_serializing)]
+    #[schema(write_only)]
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, utoipa::ToSchema)]
+/// One attempt to deliver an event to a subscription
+pub struct WebhookDelivery {
+    /// Unique identifier for the attempt
+    pub id: u32,
+    /// Identifier of the event, also sent in the `X-Webhook-Id` header
+    pub event_id: String,
+    /// Type of the event
+    pub event_type: String,
+    /// Number of this attempt for the event, starting at 1
+    pub attempt: u32,
+    /// HTTP status the receiver answered with, if it answered
+    pub response_status: Option<u16>,
+    /// Why the attempt failed, if it did
+    pub error: Option<String>,
+    /// Whether the receiver accepted the event with a 2xx status
+    pub succeeded: bool,
+    /// When the attempt was made
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+/// Event in the outbox that is due for delivery, with where and how to deliver it
+pub struct OutboxEntry {
+    pub id: u32,
+    pub subscription_id: u32,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub attempts: u32,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Result of one delivery attempt
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryOutcome {
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl DeliveryOutcome {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none() && self.response_status.is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// Columns selected whenever a subscription is read back
+const SUBSCRIPTION_COLUMNS: &str = "id, url, event_types, secret";
+
+/// Get all webhook subscriptions, ordered by ID
+pub async fn get_all_webhook_subscriptions(pool: &DbPool) -> Result<Vec<WebhookSubscription>, ApiError> {
+    sqlx::query_as::<_, WebhookSubscription>(&format!("SELECT {} FROM webhook_subscriptions ORDER BY id", SUBSCRIPTION_COLUMNS))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_all_webhook_subscriptions: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve webhook subscriptions".to_string(),
+            })
+        })
+}
+
+/// Get a specific webhook subscription by ID
+pub async fn get_webhook_subscription(pool: &DbPool, subscription_id: u32) -> Result<Option<WebhookSubscription>, ApiError> {
+    sqlx::query_as::<_, WebhookSubscription>(&format!("SELECT {} FROM webhook_subscriptions WHERE id = ?", SUBSCRIPTION_COLUMNS))
+        .bind(subscription_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_webhook_subscription: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve webhook subscription".to_string(),
+            })
+        })
+}
+
+/// Create a webhook subscription; it receives events for changes made from now on
+pub async fn create_webhook_subscription(pool: &DbPool, subscription: &WebhookSubscription) -> Result<WebhookSubscription, ApiError> {
+    let result = sqlx::query("INSERT INTO webhook_subscriptions (url, event_types, secret) VALUES (?, ?, ?)")
+        .bind(&subscription.url)
+        .bind(Json(&subscription.event_types))
+        .bind(&subscription.secret)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in create_webhook_subscription: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to create webhook subscription".to_string(),
+            })
+        })?;
+
+    let mut created = subscription.clone();
+    created.id = result.last_insert_rowid() as u32;
+    Ok(created)
+}
+
+/// Delete a webhook subscription together with its undelivered events and delivery log
+pub async fn delete_webhook_subscription(pool: &DbPool, subscription_id: u32) -> Result<WebhookSubscription, ApiError> {
+    let subscription = get_webhook_subscription(pool, subscription_id).await?
+        .ok_or_else(|| ApiError::NotFound("Webhook subscription not found".to_string()))?;
+
+    let mut tx = begin_transaction(pool, "delete_webhook_subscription").await?;
+    for statement in [
+        "DELETE FROM webhook_deliveries WHERE subscription_id = ?",
+        "DELETE FROM webhook_outbox WHERE subscription_id = ?",
+        "DELETE FROM webhook_subscriptions WHERE id = ?",
+    ] {
+        sqlx::query(statement)
+            .bind(subscription_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                eprintln!("Database error in delete_webhook_subscription: {}", e);
+                ApiError::Server(ServerError {
+                    error: "Database error".to_string(),
+                    message: "Failed to delete webhook subscription".to_string(),
+                })
+            })?;
+    }
+    commit_transaction(tx, "delete_webhook_subscription").await?;
+
+    Ok(subscription)
+}
+
+/// Body POSTed to the subscribers of `event`
+pub(crate) fn order_event_payload(
+    event_id: &str,
+    event: WebhookEvent,
+    order: &Order,
+    previous_status: Option<&str>,
+    created_at: DateTime<Utc>,
+) -> serde_json::Value {
+    let mut data = json!({ "order": order });
+    if let Some(previous_status) = previous_status {
+        data["previous_status"] = json!(previous_status);
+    }
+    json!({
+        "id": event_id,
+        "type": event.as_str(),
+        "created_at": created_at,
+        "data": data,
+    })
+}
+
+/// Queue `event` for every subscription to it, as part of the caller's transaction,
+/// so an event is recorded if and only if the change it describes is committed
+pub async fn enqueue_order_event(
+    conn: &mut SqliteConnection,
+    event: WebhookEvent,
+    order: &Order,
+    previous_status: Option<&str>,
+) -> Result<(), ApiError> {
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let payload = order_event_payload(&event_id, event, order, previous_status, now);
+
+    sqlx::query(
+        "INSERT INTO webhook_outbox (subscription_id, event_id, event_type, payload, next_attempt_at) \
+         SELECT id, ?, ?, ?, ? FROM webhook_subscriptions \
+         WHERE EXISTS (SELECT 1 FROM json_each(webhook_subscriptions.event_types) WHERE value = ?)"
+    )
+        .bind(&event_id)
+        .bind(event.as_str())
+        .bind(payload.to_string())
+        .bind(now)
+        .bind(event.as_str())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in enqueue_order_event: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to queue webhook event".to_string(),
+            })
+        })?;
+
+    Ok(())
+}
+
+/// Get up to `limit` pending events whose next attempt is due at `now`, oldest first
+pub async fn get_due_webhooks(pool: &DbPool, now: DateTime<Utc>, limit: u32) -> Result<Vec<OutboxEntry>, ApiError> {
+    sqlx::query_as::<_, OutboxEntry>(
+        "SELECT o.id, o.subscription_id, o.event_id, o.event_type, o.payload, o.attempts, s.url, s.secret \
+         FROM webhook_outbox o JOIN webhook_subscriptions s ON s.id = o.subscription_id \
+         WHERE o.status = 'pending' AND julianday(o.next_attempt_at) <= julianday(?) \
+         ORDER BY o.id LIMIT ?"
+    )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_due_webhooks: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve webhook outbox".to_string(),
+            })
+        })
+}
+
+/// Log a delivery attempt and settle the event: delivered on success, otherwise retried
+/// at `retry_at`, or given up on when there is no retry left
+pub async fn record_webhook_attempt(
+    pool: &DbPool,
+    entry: &OutboxEntry,
+    outcome: &DeliveryOutcome,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<(), ApiError> {
+    let attempt = entry.attempts + 1;
+    let status = match (outcome.succeeded(), retry_at) {
+        (true, _) => "delivered",
+        (false, Some(_)) => "pending",
+        (false, None) => "failed",
+    };
+
+    let mut tx = begin_transaction(pool, "record_webhook_attempt").await?;
+    sqlx::query(
+        "INSERT INTO webhook_deliveries \
+         (outbox_id, subscription_id, event_id, event_type, attempt, response_status, error, succeeded, attempted_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+        .bind(entry.id)
+        .bind(entry.subscription_id)
+        .bind(&entry.event_id)
+        .bind(&entry.event_type)
+        .bind(attempt)
+        .bind(outcome.response_status)
+        .bind(&outcome.error)
+        .bind(outcome.succeeded())
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in record_webhook_attempt: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to record webhook delivery".to_string(),
+            })
+        })?;
+
+    sqlx::query("UPDATE webhook_outbox SET status = ?, attempts = ?, next_attempt_at = COALESCE(?, next_attempt_at) WHERE id = ?")
+        .bind(status)
+        .bind(attempt)
+        .bind(retry_at)
+        .bind(entry.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in record_webhook_attempt: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to update webhook outbox".to_string(),
+            })
+        })?;
+    commit_transaction(tx, "record_webhook_attempt").await
+}
+
+/// Get the delivery log of a subscription, most recent attempt first
+pub async fn get_webhook_deliveries(pool: &DbPool, subscription_id: u32) -> Result<Vec<WebhookDelivery>, ApiError> {
+    sqlx::query_as::<_, WebhookDelivery>(
+        "SELECT id, event_id, event_type, attempt, response_status, error, succeeded, attempted_at \
+         FROM webhook_deliveries WHERE subscription_id = ? ORDER BY id DESC"
+    )
+        .bind(subscription_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error in get_webhook_deliveries: {}", e);
+            ApiError::Server(ServerError {
+                error: "Database error".to_string(),
+                message: "Failed to retrieve webhook deliveries".to_string(),
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PaymentConfig;
+    use crate::utils::{create_order, delete_order, init_db, update_order, update_order_status};
+
+    fn subscription(event_types: Vec<WebhookEvent>) -> WebhookSubscription {
+        WebhookSubscription {
+            id: 0,
+            url: "http://127.0.0.1:9/hooks".to_string(),
+            event_types,
+            secret: "0123456789abcdef".to_string(),
+        }
+    }
+
+    fn order() -> Order {
+        Order {
+            id: 1,
+            item: "Analytical Engine".to_string(),
+            status: "pending".to_string(),
+            quantity: 1,
+            ..Default::default()
+        }
+    }
+
+    async fn queued_types(pool: &DbPool, subscription_id: u32) -> Vec<String> {
+        sqlx::query_scalar("SELECT event_type FROM webhook_outbox WHERE subscription_id = ? ORDER BY id")
+            .bind(subscription_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_order_mutations_enqueue_subscribed_events() {
+        let pool = init_db().await.unwrap();
+        let all = create_webhook_subscription(&pool, &subscription(vec![
+            WebhookEvent::Created,
+            WebhookEvent::Updated,
+            WebhookEvent::StatusChanged,
+            WebhookEvent::Deleted,
+        ])).await.unwrap();
+        let status_only = create_webhook_subscription(&pool, &subscription(vec![WebhookEvent::StatusChanged])).await.unwrap();
+
+        create_order(&pool, &order(), &PaymentConfig::default()).await.unwrap();
+        update_order(&pool, 1, &Order { quantity: 2, status: "processing".to_string(), ..order() }, &PaymentConfig::default()).await.unwrap();
+        update_order_status(&pool, 1, "shipped", &PaymentConfig::default()).await.unwrap();
+        // Setting the status it already has is not a change
+        update_order_status(&pool, 1, "shipped", &PaymentConfig::default()).await.unwrap();
+        delete_order(&pool, 1).await.unwrap();
+
+        assert_eq!(queued_types(&pool, all.id).await, [
+            "order.created",
+            "order.updated",
+            "order.status_changed",
+            "order.status_changed",
+            "order.deleted",
+        ]);
+        assert_eq!(queued_types(&pool, status_only.id).await, ["order.status_changed", "order.status_changed"]);
+
+        let payload: String = sqlx::query_scalar(
+            "SELECT payload FROM webhook_outbox WHERE subscription_id = ? ORDER BY id DESC LIMIT 1"
+        )
+            .bind(status_only.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["type"], "order.status_changed");
+        assert_eq!(payload["data"]["order"]["status"], "shipped");
+        assert_eq!(payload["data"]["previous_status"], "processing");
+    }
+
+    #[tokio::test]
+    async fn test_failed_mutation_enqueues_nothing() {
+        let pool = init_db().await.unwrap();
+        let subscription = create_webhook_subscription(&pool, &subscription(vec![WebhookEvent::Created])).await.unwrap();
+
+        create_order(&pool, &order(), &PaymentConfig::default()).await.unwrap();
+        assert!(create_order(&pool, &order(), &PaymentConfig::default()).await.is_err());
+
+        assert_eq!(queued_types(&pool, subscription.id).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_attempts_settle_outbox_entries() {
+        let pool = init_db().await.unwrap();
+        let subscription = create_webhook_subscription(&pool, &subscription(vec![WebhookEvent::Created])).await.unwrap();
+        create_order(&pool, &order(), &PaymentConfig::default()).await.unwrap();
+
+        let due = get_due_webhooks(&pool, Utc::now(), 10).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].url, subscription.url);
+
+        let failure = DeliveryOutcome { response_status: Some(503), error: None };
+        let retry_at = Utc::now() + chrono::Duration::minutes(5);
+        record_webhook_attempt(&pool, &due[0], &failure, Some(retry_at)).await.unwrap();
+        assert!(get_due_webhooks(&pool, Utc::now(), 10).await.unwrap().is_empty());
+
+        let due = get_due_webhooks(&pool, retry_at, 10).await.unwrap();
+        assert_eq!(due[0].attempts, 1);
+        let success = DeliveryOutcome { response_status: Some(204), error: None };
+        record_webhook_attempt(&pool, &due[0], &success, None).await.unwrap();
+        assert!(get_due_webhooks(&pool, retry_at, 10).await.unwrap().is_empty());
+
+        let log = get_webhook_deliveries(&pool, subscription.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].succeeded);
+        assert_eq!(log[0].attempt, 2);
+        assert_eq!(log[1].response_status, Some(503));
+
+        delete_webhook_subscription(&pool, subscription.id).await.unwrap();
+        assert!(get_webhook_deliveries(&pool, subscription.id).await.unwrap().is_empty());
+    }
+}
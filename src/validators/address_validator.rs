@@ -0,0 +1,165 @@
+use crate::utils::ShippingAddress;
+use crate::validators::ValidationError;
+
+/// Postal code formats per ISO 3166-1 alpha-2 country: `9` is a digit, `A` a letter,
+/// anything else must match literally. Countries not listed here cannot be shipped to.
+const POSTAL_CODE_FORMATS: &[(&str, &[&str])] = &[
+    ("US", &["99999", "99999-9999"]),
+    ("CA", &["A9A 9A9"]),
+    ("GB", &["A9 9AA", "A99 9AA", "A9A 9AA", "AA9 9AA", "AA99 9AA", "AA9A 9AA"]),
+    ("DE", &["99999"]),
+    ("FR", &["99999"]),
+    ("ES", &["99999"]),
+    ("IT", &["99999"]),
+    ("NL", &["9999 AA"]),
+    ("CH", &["9999"]),
+    ("AU", &["9999"]),
+    ("JP", &["999-9999"]),
+];
+
+/// Countries whose addresses must name a state, province or territory
+const REGION_REQUIRED: &[&str] = &["US", "CA", "AU"];
+
+fn matches_format(value: &str, format: &str) -> bool {
+    value.len() == format.len()
+        && value.chars().zip(format.chars()).all(|(c, f)| match f {
+            '9' => c.is_ascii_digit(),
+            'A' => c.is_ascii_uppercase(),
+            literal => c == literal,
+        })
+}
+
+fn required_text(value: &str, field: &str, label: &str, max_len: usize) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError {
+            error: format!("{} cannot be empty", label),
+            field: Some(format!("shipping_address.{}", field)),
+        });
+    }
+
+    if value.len() > max_len {
+        return Err(ValidationError {
+            error: format!("{} cannot exceed {} characters", label, max_len),
+            field: Some(format!("shipping_address.{}", field)),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates a shipping address, checking the postal code against the country's format
+pub fn validate_shipping_address(address: &ShippingAddress) -> Result<(), ValidationError> {
+    required_text(&address.recipient, "recipient", "Recipient", 100)?;
+    required_text(&address.line1, "line1", "Address line 1", 100)?;
+    if let Some(line2) = &address.line2
+        && line2.len() > 100
+    {
+        return Err(ValidationError {
+            error: "Address line 2 cannot exceed 100 characters".to_string(),
+            field: Some("shipping_address.line2".to_string()),
+        });
+    }
+    required_text(&address.city, "city", "City", 100)?;
+
+    let Some((_, formats)) = POSTAL_CODE_FORMATS.iter().find(|(country, _)| *country == address.country) else {
+        let supported: Vec<&str> = POSTAL_CODE_FORMATS.iter().map(|(country, _)| *country).collect();
+        return Err(ValidationError {
+            error: format!("Country must be one of: {}", supported.join(", ")),
+            field: Some("shipping_address.country".to_string()),
+        });
+    };
+
+    if !formats.iter().any(|format| matches_format(&address.postal_code, format)) {
+        return Err(ValidationError {
+            error: format!(
+                "Postal code for {} must match one of: {}",
+                address.country,
+                formats.join(", ")
+            ),
+            field: Some("shipping_address.postal_code".to_string()),
+        });
+    }
+
+    let region = address.region.as_deref().unwrap_or("");
+    if REGION_REQUIRED.contains(&address.country.as_str()) {
+        required_text(region, "region", "Region", 50)?;
+    }
+    if address.country == "US" && !matches_format(region, "AA") {
+        return Err(ValidationError {
+            error: "Region for US must be a two-letter state code".to_string(),
+            field: Some("shipping_address.region".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(country: &str, postal_code: &str, region: Option<&str>) -> ShippingAddress {
+        ShippingAddress {
+            recipient: "Ada Lovelace".to_string(),
+            line1: "12 St James's Square".to_string(),
+            line2: None,
+            city: "London".to_string(),
+            region: region.map(str::to_string),
+            postal_code: postal_code.to_string(),
+            country: country.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_valid_addresses() {
+        for address in [
+            address("GB", "SW1Y 4JH", None),
+            address("GB", "M1 1AE", None),
+            address("US", "94103", Some("CA")),
+            address("US", "94103-1234", Some("CA")),
+            address("CA", "K1A 0B1", Some("ON")),
+            address("NL", "1012 AB", None),
+            address("JP", "100-0001", None),
+        ] {
+            assert!(validate_shipping_address(&address).is_ok(), "{:?} should be valid", address);
+        }
+    }
+
+    #[test]
+    fn test_postal_code_must_match_country() {
+        for address in [
+            address("US", "SW1Y 4JH", Some("CA")),
+            address("GB", "94103", None),
+            address("CA", "k1a 0b1", Some("ON")),
+            address("DE", "1234", None),
+        ] {
+            let error = validate_shipping_address(&address).unwrap_err();
+            assert_eq!(error.field, Some("shipping_address.postal_code".to_string()), "{:?}", address);
+        }
+    }
+
+    #[test]
+    fn test_region_rules() {
+        let error = validate_shipping_address(&address("US", "94103", None)).unwrap_err();
+        assert_eq!(error.field, Some("shipping_address.region".to_string()));
+
+        let error = validate_shipping_address(&address("US", "94103", Some("California"))).unwrap_err();
+        assert_eq!(error.error, "Region for US must be a two-letter state code");
+    }
+
+    #[test]
+    fn test_unsupported_country() {
+        let error = validate_shipping_address(&address("XX", "12345", None)).unwrap_err();
+        assert_eq!(error.field, Some("shipping_address.country".to_string()));
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let mut missing_city = address("GB", "SW1Y 4JH", None);
+        missing_city.city = " ".to_string();
+
+        let error = validate_shipping_address(&missing_city).unwrap_err();
+        assert_eq!(error.error, "City cannot be empty");
+        assert_eq!(error.field, Some("shipping_address.city".to_string()));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::utils::Customer;
+use crate::validators::ValidationError;
+
+/// Validates a customer to ensure all fields meet the required criteria
+pub fn validate_customer(customer: &Customer) -> Result<(), ValidationError> {
+    // Validate ID
+    if customer.id == 0 {
+        return Err(ValidationError {
+            error: "Customer ID must be greater than 0".to_string(),
+            field: Some("id".to_string()),
+        });
+    }
+
+    // Validate name
+    if customer.name.trim().is_empty() {
+        return Err(ValidationError {
+            error: "Customer name cannot be empty".to_string(),
+            field: Some("name".to_string()),
+        });
+    }
+
+    if customer.name.len() > 100 {
+        return Err(ValidationError {
+            error: "Customer name cannot exceed 100 characters".to_string(),
+            field: Some("name".to_string()),
+        });
+    }
+
+    // Validate email: a single '@' with a non-empty local part and a dotted domain
+    let valid_email = match customer.email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !customer.email.contains(char::is_whitespace)
+        }
+        None => false,
+    };
+    if !valid_email || customer.email.len() > 254 {
+        return Err(ValidationError {
+            error: "Email must be a valid email address".to_string(),
+            field: Some("email".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_valid_customer() -> Customer {
+        Customer {
+            id: 1,
+            name: "Ada Lovelace".to_string(),
+            email: "ada@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_customer_success() {
+        assert!(validate_customer(&create_valid_customer()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_customer_zero_id() {
+        let mut customer = create_valid_customer();
+        customer.id = 0;
+
+        let error = validate_customer(&customer).unwrap_err();
+        assert_eq!(error.field, Some("id".to_string()));
+    }
+
+    #[test]
+    fn test_validate_customer_empty_name() {
+        let mut customer = create_valid_customer();
+        customer.name = "  ".to_string();
+
+        let error = validate_customer(&customer).unwrap_err();
+        assert_eq!(error.error, "Customer name cannot be empty");
+        assert_eq!(error.field, Some("name".to_string()));
+    }
+
+    #[test]
+    fn test_validate_customer_invalid_emails() {
+        for email in ["", "ada", "@example.com", "ada@", "ada@example", "ada@@example.com", "ada @example.com", "ada@example."] {
+            let mut customer = create_valid_customer();
+            customer.email = email.to_string();
+
+            let error = validate_customer(&customer).unwrap_err();
+            assert_eq!(error.field, Some("email".to_string()), "Email '{}' should be invalid", email);
+        }
+    }
+}
@@ -1,2 +1,14 @@
 pub mod order_validator;
-pub use order_validator::{validate_order, validate_status, ValidationError, ApiError, ServerError};
+pub mod customer_validator;
+pub mod product_validator;
+pub mod promotion_validator;
+pub mod address_validator;
+pub mod shipment_validator;
+pub mod webhook_validator;
+pub use order_validator::{validate_order, validate_status, ValidationError, ApiError, ServerError, SUPPORTED_CURRENCIES};
+pub use customer_validator::validate_customer;
+pub use product_validator::{validate_product, validate_sku};
+pub use promotion_validator::{validate_promo_code, validate_promotion};
+pub use address_validator::validate_shipping_address;
+pub use shipment_validator::{validate_delivered_at, validate_shipment};
+pub use webhook_validator::validate_webhook_subscription;
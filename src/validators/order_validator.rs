@@ -1,11 +1,12 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json
 };
 use serde::{Serialize};
 use serde_json::json;
 use crate::utils::Order;
+use crate::validators::{validate_promo_code, validate_shipping_address, validate_sku};
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
 /// Validation error response
@@ -50,6 +51,33 @@ pub enum ApiError {
     Validation(ValidationError),
     Server(ServerError),
     NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    TooManyRequests(String),
+    PayloadTooLarge(String),
+    Timeout(String),
+    Conflict(String),
+    PaymentDeclined(String),
+    BadGateway(String),
+}
+
+impl ApiError {
+    /// Message and offending field, as carried in the response body
+    pub fn describe(&self) -> (&str, Option<&str>) {
+        match self {
+            ApiError::Validation(err) => (&err.error, err.field.as_deref()),
+            ApiError::Server(err) => (&err.message, None),
+            ApiError::NotFound(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::TooManyRequests(message)
+            | ApiError::PayloadTooLarge(message)
+            | ApiError::Timeout(message)
+            | ApiError::Conflict(message)
+            | ApiError::PaymentDeclined(message)
+            | ApiError::BadGateway(message) => (message, None),
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
@@ -63,6 +91,66 @@ impl IntoResponse for ApiError {
                 }));
                 (StatusCode::NOT_FOUND, body).into_response()
             }
+            ApiError::Unauthorized(message) => {
+                let body = Json(json!({
+                    "error": "Unauthorized",
+                    "message": message
+                }));
+                (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                    body
+                ).into_response()
+            }
+            ApiError::Forbidden(message) => {
+                let body = Json(json!({
+                    "error": "Forbidden",
+                    "message": message
+                }));
+                (StatusCode::FORBIDDEN, body).into_response()
+            }
+            ApiError::TooManyRequests(message) => {
+                let body = Json(json!({
+                    "error": "Too many requests",
+                    "message": message
+                }));
+                (StatusCode::TOO_MANY_REQUESTS, body).into_response()
+            }
+            ApiError::PayloadTooLarge(message) => {
+                let body = Json(json!({
+                    "error": "Payload too large",
+                    "message": message
+                }));
+                (StatusCode::PAYLOAD_TOO_LARGE, body).into_response()
+            }
+            ApiError::Timeout(message) => {
+                let body = Json(json!({
+                    "error": "Gateway timeout",
+                    "message": message
+                }));
+                (StatusCode::GATEWAY_TIMEOUT, body).into_response()
+            }
+            ApiError::Conflict(message) => {
+                let body = Json(json!({
+                    "error": "Conflict",
+                    "message": message
+                }));
+                (StatusCode::CONFLICT, body).into_response()
+            }
+            ApiError::PaymentDeclined(message) => {
+                let body = Json(json!({
+                    "error": "Payment declined",
+                    "message": message
+                }));
+                (StatusCode::PAYMENT_REQUIRED, body).into_response()
+            }
+            ApiError::BadGateway(message) => {
+                let body = Json(json!({
+                    "error": "Bad gateway",
+                    "message": message
+                }));
+                (StatusCode::BAD_GATEWAY, body).into_response()
+            }
         }
     }
 }
@@ -79,6 +167,12 @@ impl From<ServerError> for ApiError {
     }
 }
 
+/// ISO 4217 currencies orders and promotions can be priced in
+pub const SUPPORTED_CURRENCIES: &[&str] = &["USD", "EUR", "GBP", "CHF", "CAD", "AUD", "JPY"];
+
+/// Highest accepted unit price, in minor units; keeps totals for the largest quantity well within `i64`
+const MAX_UNIT_PRICE_MINOR: i64 = 1_000_000_000;
+
 /// Validates an order to ensure all fields meet the required criteria
 pub fn validate_order(order: &Order) -> Result<(), ValidationError> {
     // Validate ID
@@ -129,6 +223,52 @@ pub fn validate_order(order: &Order) -> Result<(), ValidationError> {
         });
     }
 
+    // Validate customer reference; its existence is checked when the order is stored
+    if order.customer_id == Some(0) {
+        return Err(ValidationError {
+            error: "Customer ID must be greater than 0".to_string(),
+            field: Some("customer_id".to_string()),
+        });
+    }
+
+    // Validate product reference; stock is checked when the order is stored
+    if let Some(sku) = &order.sku {
+        validate_sku(sku)?;
+    }
+
+    // Validate price, kept in minor units so totals are exact
+    if order.unit_price_minor < 0 {
+        return Err(ValidationError {
+            error: "Unit price cannot be negative".to_string(),
+            field: Some("unit_price_minor".to_string()),
+        });
+    }
+
+    if order.unit_price_minor > MAX_UNIT_PRICE_MINOR {
+        return Err(ValidationError {
+            error: format!("Unit price cannot exceed {} minor units", MAX_UNIT_PRICE_MINOR),
+            field: Some("unit_price_minor".to_string()),
+        });
+    }
+
+    // Validate currency
+    if !SUPPORTED_CURRENCIES.contains(&order.currency.as_str()) {
+        return Err(ValidationError {
+            error: format!("Currency must be one of: {}", SUPPORTED_CURRENCIES.join(", ")),
+            field: Some("currency".to_string()),
+        });
+    }
+
+    // Validate promo code format; whether it can be redeemed is checked when the order is stored
+    if let Some(code) = &order.promo_code {
+        validate_promo_code(code)?;
+    }
+
+    // Validate shipping address against its country's format
+    if let Some(address) = &order.shipping_address {
+        validate_shipping_address(address)?;
+    }
+
     Ok(())
 }
 
@@ -147,7 +287,7 @@ pub fn validate_status(status: &str) -> Result<(), ValidationError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::Order;
+    use crate::utils::{Order, ShippingAddress};
 
     fn create_valid_order() -> Order {
         Order {
@@ -155,6 +295,7 @@ mod tests {
             item: "Test Item".to_string(),
             status: "pending".to_string(),
             quantity: 5,
+            ..Default::default()
         }
     }
 
@@ -286,6 +427,84 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_order_zero_customer_id() {
+        let mut order = create_valid_order();
+        order.customer_id = Some(0);
+        
+        let error = validate_order(&order).unwrap_err();
+        assert_eq!(error.error, "Customer ID must be greater than 0");
+        assert_eq!(error.field, Some("customer_id".to_string()));
+        
+        order.customer_id = Some(1);
+        assert!(validate_order(&order).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order_invalid_sku() {
+        let mut order = create_valid_order();
+        order.sku = Some("".to_string());
+        
+        let error = validate_order(&order).unwrap_err();
+        assert_eq!(error.field, Some("sku".to_string()));
+        
+        order.sku = Some("WID-001".to_string());
+        assert!(validate_order(&order).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order_negative_price() {
+        let mut order = create_valid_order();
+        order.unit_price_minor = -1;
+        
+        let error = validate_order(&order).unwrap_err();
+        assert_eq!(error.error, "Unit price cannot be negative");
+        assert_eq!(error.field, Some("unit_price_minor".to_string()));
+        
+        order.unit_price_minor = 0;
+        assert!(validate_order(&order).is_ok());
+        
+        order.unit_price_minor = MAX_UNIT_PRICE_MINOR + 1;
+        assert_eq!(validate_order(&order).unwrap_err().field, Some("unit_price_minor".to_string()));
+    }
+
+    #[test]
+    fn test_validate_order_currency() {
+        for currency in ["USD", "EUR", "JPY"] {
+            let mut order = create_valid_order();
+            order.currency = currency.to_string();
+            assert!(validate_order(&order).is_ok(), "Currency '{}' should be valid", currency);
+        }
+        
+        for currency in ["usd", "XYZ", "", "US Dollar"] {
+            let mut order = create_valid_order();
+            order.currency = currency.to_string();
+            
+            let error = validate_order(&order).unwrap_err();
+            assert!(error.error.contains("Currency must be one of:"));
+            assert_eq!(error.field, Some("currency".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_validate_order_shipping_address() {
+        let mut order = create_valid_order();
+        order.shipping_address = Some(ShippingAddress {
+            recipient: "Ada Lovelace".to_string(),
+            line1: "12 St James's Square".to_string(),
+            line2: None,
+            city: "London".to_string(),
+            region: None,
+            postal_code: "SW1Y 4JH".to_string(),
+            country: "GB".to_string(),
+        });
+        assert!(validate_order(&order).is_ok());
+
+        order.shipping_address.as_mut().unwrap().postal_code = "94103".to_string();
+        let error = validate_order(&order).unwrap_err();
+        assert_eq!(error.field, Some("shipping_address.postal_code".to_string()));
+    }
+
     #[test]
     fn test_validation_error_serialization() {
         let error = ValidationError {
@@ -386,6 +605,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_api_error_unauthorized_response() {
+        let response = ApiError::Unauthorized("Missing credentials".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[test]
+    fn test_api_error_forbidden_response() {
+        let response = ApiError::Forbidden("Missing scope".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_api_error_conflict_response() {
+        let response = ApiError::Conflict("Customer has orders".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_api_error_payment_responses() {
+        let response = ApiError::PaymentDeclined("Card declined".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+
+        let response = ApiError::BadGateway("Gateway unreachable".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
     #[test]
     fn test_api_error_not_found() {
         let api_error = ApiError::NotFound("Resource not found".to_string());
@@ -0,0 +1,81 @@
+use crate::utils::Product;
+use crate::validators::ValidationError;
+
+/// Validates a SKU: 1-64 characters of letters, digits, `-` and `_`
+pub fn validate_sku(sku: &str) -> Result<(), ValidationError> {
+    if sku.is_empty() || sku.len() > 64 {
+        return Err(ValidationError {
+            error: "SKU must be between 1 and 64 characters".to_string(),
+            field: Some("sku".to_string()),
+        });
+    }
+
+    if !sku.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(ValidationError {
+            error: "SKU may only contain letters, digits, '-' and '_'".to_string(),
+            field: Some("sku".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates a product to ensure all fields meet the required criteria
+pub fn validate_product(product: &Product) -> Result<(), ValidationError> {
+    validate_sku(&product.sku)?;
+
+    // Validate name
+    if product.name.trim().is_empty() {
+        return Err(ValidationError {
+            error: "Product name cannot be empty".to_string(),
+            field: Some("name".to_string()),
+        });
+    }
+
+    if product.name.len() > 100 {
+        return Err(ValidationError {
+            error: "Product name cannot exceed 100 characters".to_string(),
+            field: Some("name".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_valid_product() -> Product {
+        Product {
+            sku: "WID-001".to_string(),
+            name: "Widget".to_string(),
+            stock: 10,
+        }
+    }
+
+    #[test]
+    fn test_validate_product_success() {
+        assert!(validate_product(&create_valid_product()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_product_invalid_skus() {
+        for sku in ["", "has space", "semi;colon", &"A".repeat(65)] {
+            let mut product = create_valid_product();
+            product.sku = sku.to_string();
+
+            let error = validate_product(&product).unwrap_err();
+            assert_eq!(error.field, Some("sku".to_string()), "SKU '{}' should be invalid", sku);
+        }
+    }
+
+    #[test]
+    fn test_validate_product_empty_name() {
+        let mut product = create_valid_product();
+        product.name = " ".to_string();
+
+        let error = validate_product(&product).unwrap_err();
+        assert_eq!(error.error, "Product name cannot be empty");
+    }
+}
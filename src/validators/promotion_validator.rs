@@ -0,0 +1,154 @@
+use crate::utils::{Promotion, PromotionKind};
+use crate::validators::{ValidationError, SUPPORTED_CURRENCIES};
+
+/// Validates a promo code: 1-32 characters of letters, digits, `-` and `_`
+pub fn validate_promo_code(code: &str) -> Result<(), ValidationError> {
+    if code.is_empty() || code.len() > 32 {
+        return Err(ValidationError {
+            error: "Promo code must be between 1 and 32 characters".to_string(),
+            field: Some("promo_code".to_string()),
+        });
+    }
+
+    if !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(ValidationError {
+            error: "Promo code may only contain letters, digits, '-' and '_'".to_string(),
+            field: Some("promo_code".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates a promotion, including the parameters its kind requires
+pub fn validate_promotion(promotion: &Promotion) -> Result<(), ValidationError> {
+    validate_promo_code(&promotion.code).map_err(|err| ValidationError {
+        field: Some("code".to_string()),
+        ..err
+    })?;
+
+    match promotion.kind {
+        PromotionKind::PercentOff => {
+            if !matches!(promotion.percent_off, Some(1..=100)) {
+                return Err(ValidationError {
+                    error: "Percent off must be between 1 and 100".to_string(),
+                    field: Some("percent_off".to_string()),
+                });
+            }
+        }
+        PromotionKind::FixedOff => {
+            if promotion.amount_off_minor.is_none_or(|amount| amount <= 0) {
+                return Err(ValidationError {
+                    error: "Amount off must be greater than 0".to_string(),
+                    field: Some("amount_off_minor".to_string()),
+                });
+            }
+            if !promotion.currency.as_deref().is_some_and(|currency| SUPPORTED_CURRENCIES.contains(&currency)) {
+                return Err(ValidationError {
+                    error: format!("Currency must be one of: {}", SUPPORTED_CURRENCIES.join(", ")),
+                    field: Some("currency".to_string()),
+                });
+            }
+        }
+        PromotionKind::BuyXGetY => {
+            if promotion.buy_quantity.is_none_or(|quantity| quantity == 0) {
+                return Err(ValidationError {
+                    error: "Buy quantity must be greater than 0".to_string(),
+                    field: Some("buy_quantity".to_string()),
+                });
+            }
+            if promotion.free_quantity.is_none_or(|quantity| quantity == 0) {
+                return Err(ValidationError {
+                    error: "Free quantity must be greater than 0".to_string(),
+                    field: Some("free_quantity".to_string()),
+                });
+            }
+        }
+    }
+
+    if let (Some(starts_at), Some(ends_at)) = (promotion.starts_at, promotion.ends_at)
+        && ends_at <= starts_at
+    {
+        return Err(ValidationError {
+            error: "End of the validity window must be after its start".to_string(),
+            field: Some("ends_at".to_string()),
+        });
+    }
+
+    if promotion.max_redemptions == Some(0) {
+        return Err(ValidationError {
+            error: "Maximum redemptions must be greater than 0".to_string(),
+            field: Some("max_redemptions".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn create_valid_promotion() -> Promotion {
+        Promotion {
+            code: "SPRING-10".to_string(),
+            kind: PromotionKind::PercentOff,
+            percent_off: Some(10),
+            amount_off_minor: None,
+            currency: None,
+            buy_quantity: None,
+            free_quantity: None,
+            starts_at: None,
+            ends_at: None,
+            max_redemptions: Some(100),
+            redemptions: 0,
+        }
+    }
+
+    #[test]
+    fn test_validate_promotion_success() {
+        assert!(validate_promotion(&create_valid_promotion()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_promotion_invalid_code() {
+        let mut promotion = create_valid_promotion();
+        promotion.code = "10% OFF".to_string();
+
+        let error = validate_promotion(&promotion).unwrap_err();
+        assert_eq!(error.field, Some("code".to_string()));
+    }
+
+    #[test]
+    fn test_validate_promotion_kind_parameters() {
+        let mut promotion = create_valid_promotion();
+        promotion.percent_off = Some(101);
+        assert_eq!(validate_promotion(&promotion).unwrap_err().field, Some("percent_off".to_string()));
+
+        promotion.kind = PromotionKind::FixedOff;
+        promotion.amount_off_minor = Some(500);
+        assert_eq!(validate_promotion(&promotion).unwrap_err().field, Some("currency".to_string()));
+        promotion.currency = Some("EUR".to_string());
+        assert!(validate_promotion(&promotion).is_ok());
+
+        promotion.kind = PromotionKind::BuyXGetY;
+        promotion.buy_quantity = Some(2);
+        assert_eq!(validate_promotion(&promotion).unwrap_err().field, Some("free_quantity".to_string()));
+        promotion.free_quantity = Some(1);
+        assert!(validate_promotion(&promotion).is_ok());
+    }
+
+    #[test]
+    fn test_validate_promotion_window_and_cap() {
+        let now = Utc::now();
+        let mut promotion = create_valid_promotion();
+        promotion.starts_at = Some(now);
+        promotion.ends_at = Some(now - Duration::hours(1));
+        assert_eq!(validate_promotion(&promotion).unwrap_err().field, Some("ends_at".to_string()));
+
+        promotion.ends_at = None;
+        promotion.max_redemptions = Some(0);
+        assert_eq!(validate_promotion(&promotion).unwrap_err().field, Some("max_redemptions".to_string()));
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use crate::utils::Shipment;
+use crate::validators::ValidationError;
+
+/// Validates that a delivery is not recorded before the parcel was shipped
+pub fn validate_delivered_at(shipped_at: DateTime<Utc>, delivered_at: DateTime<Utc>) -> Result<(), ValidationError> {
+    if delivered_at < shipped_at {
+        return Err(ValidationError {
+            error: "Delivery time cannot be before the shipping time".to_string(),
+            field: Some("delivered_at".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates a shipment to ensure all fields meet the required criteria
+pub fn validate_shipment(shipment: &Shipment) -> Result<(), ValidationError> {
+    // Validate carrier
+    if shipment.carrier.trim().is_empty() {
+        return Err(ValidationError {
+            error: "Carrier cannot be empty".to_string(),
+            field: Some("carrier".to_string()),
+        });
+    }
+
+    if shipment.carrier.len() > 50 {
+        return Err(ValidationError {
+            error: "Carrier cannot exceed 50 characters".to_string(),
+            field: Some("carrier".to_string()),
+        });
+    }
+
+    // Validate tracking number
+    if shipment.tracking_number.is_empty() || shipment.tracking_number.len() > 64 {
+        return Err(ValidationError {
+            error: "Tracking number must be between 1 and 64 characters".to_string(),
+            field: Some("tracking_number".to_string()),
+        });
+    }
+
+    if !shipment.tracking_number.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(ValidationError {
+            error: "Tracking number may only contain letters, digits and '-'".to_string(),
+            field: Some("tracking_number".to_string()),
+        });
+    }
+
+    if let Some(delivered_at) = shipment.delivered_at {
+        validate_delivered_at(shipment.shipped_at, delivered_at)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn create_valid_shipment() -> Shipment {
+        Shipment {
+            id: 0,
+            order_id: 0,
+            carrier: "UPS".to_string(),
+            tracking_number: "1Z999AA10123456784".to_string(),
+            shipped_at: Utc::now(),
+            delivered_at: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_shipment_success() {
+        assert!(validate_shipment(&create_valid_shipment()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_shipment_empty_carrier() {
+        let mut shipment = create_valid_shipment();
+        shipment.carrier = "  ".to_string();
+
+        let error = validate_shipment(&shipment).unwrap_err();
+        assert_eq!(error.field, Some("carrier".to_string()));
+    }
+
+    #[test]
+    fn test_validate_shipment_tracking_number() {
+        for tracking_number in ["", "1Z 999", "1Z/999"] {
+            let mut shipment = create_valid_shipment();
+            shipment.tracking_number = tracking_number.to_string();
+
+            let error = validate_shipment(&shipment).unwrap_err();
+            assert_eq!(error.field, Some("tracking_number".to_string()), "{:?}", tracking_number);
+        }
+    }
+
+    #[test]
+    fn test_validate_shipment_delivered_before_shipped() {
+        let mut shipment = create_valid_shipment();
+        shipment.delivered_at = Some(shipment.shipped_at - Duration::hours(1));
+
+        let error = validate_shipment(&shipment).unwrap_err();
+        assert_eq!(error.field, Some("delivered_at".to_string()));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::utils::WebhookSubscription;
+use crate::validators::ValidationError;
+
+/// Validates a webhook subscription to ensure all fields meet the required criteria
+pub fn validate_webhook_subscription(subscription: &WebhookSubscription) -> Result<(), ValidationError> {
+    // Validate URL
+    if !(subscription.url.starts_with("http://") || subscription.url.starts_with("https://")) {
+        return Err(ValidationError {
+            error: "URL must start with http:// or https://".to_string(),
+            field: Some("url".to_string()),
+        });
+    }
+
+    if subscription.url.len() > 2048 || subscription.url.chars().any(char::is_whitespace) {
+        return Err(ValidationError {
+            error: "URL must be at most 2048 characters without whitespace".to_string(),
+            field: Some("url".to_string()),
+        });
+    }
+
+    // Validate event types
+    if subscription.event_types.is_empty() {
+        return Err(ValidationError {
+            error: "At least one event type is required".to_string(),
+            field: Some("event_types".to_string()),
+        });
+    }
+
+    // Validate secret
+    if subscription.secret.len() < 16 || subscription.secret.len() > 128 {
+        return Err(ValidationError {
+            error: "Secret must be between 16 and 128 characters".to_string(),
+            field: Some("secret".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::WebhookEvent;
+
+    fn create_valid_subscription() -> WebhookSubscription {
+        WebhookSubscription {
+            id: 0,
+            url: "https://erp.example.com/hooks/orders".to_string(),
+            event_types: vec![WebhookEvent::Created],
+            secret: "0123456789abcdef".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_webhook_subscription_success() {
+        assert!(validate_webhook_subscription(&create_valid_subscription()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_webhook_subscription_url() {
+        for url in ["", "ftp://example.com/hooks", "https://example.com/my hooks"] {
+            let mut subscription = create_valid_subscription();
+            subscription.url = url.to_string();
+
+            let error = validate_webhook_subscription(&subscription).unwrap_err();
+            assert_eq!(error.field, Some("url".to_string()), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn test_validate_webhook_subscription_event_types_and_secret() {
+        let mut subscription = create_valid_subscription();
+        subscription.event_types.clear();
+        let error = validate_webhook_subscription(&subscription).unwrap_err();
+        assert_eq!(error.field, Some("event_types".to_string()));
+
+        let mut subscription = create_valid_subscription();
+        subscription.secret = "short".to_string();
+        let error = validate_webhook_subscription(&subscription).unwrap_err();
+        assert_eq!(error.field, Some("secret".to_string()));
+    }
+}
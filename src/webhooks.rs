@@ -0,0 +1,259 @@
+use std::time::Duration;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::config::WebhookConfig;
+use crate::utils::{DeliveryOutcome, OutboxEntry, WebhookRepository};
+use crate::validators::ApiError;
+
+/// Header carrying `sha256=<hex HMAC of "<timestamp>.<body>">`
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+/// Header carrying the Unix time the delivery was signed at
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// Header carrying the event type, e.g. `order.created`
+pub const EVENT_HEADER: &str = "x-webhook-event";
+/// Header carrying the event ID; retries of an event reuse it
+pub const ID_HEADER: &str = "x-webhook-id";
+
+/// Events sent per poll of the outbox
+const DELIVERY_BATCH_SIZE: u32 = 50;
+
+/// Sign a delivery body the way receivers are expected to verify it: HMAC-SHA256 keyed with the
+/// subscription secret over the timestamp and body joined by a dot, hex encoded.
+/// Including the timestamp lets receivers reject replayed deliveries.
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Delay before the next attempt after `attempts` failed ones: the base delay doubled for every
+/// failure after the first, capped at the maximum delay
+pub fn retry_delay(config: &WebhookConfig, attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    config.retry_base_delay.saturating_mul(factor).min(config.max_retry_delay)
+}
+
+/// POST one event to its subscription
+async fn deliver(client: &reqwest::Client, entry: &OutboxEntry) -> DeliveryOutcome {
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_payload(&entry.secret, timestamp, &entry.payload);
+
+    let response = client.post(&entry.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(EVENT_HEADER, &entry.event_type)
+        .header(ID_HEADER, &entry.event_id)
+        .body(entry.payload.clone())
+        .send()
+        .await;
+
+    match response {
+        Ok(response) => DeliveryOutcome {
+            response_status: Some(response.status().as_u16()),
+            error: None,
+        },
+        Err(e) => DeliveryOutcome {
+            response_status: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Attempt every event in the outbox that is due, scheduling a retry for each failure until the
+/// event runs out of attempts. Returns the number of attempts made.
+pub async fn deliver_due_webhooks<W: WebhookRepository + ?Sized>(
+    webhooks: &W,
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+) -> Result<usize, ApiError> {
+    let due = webhooks.due_webhooks(Utc::now(), DELIVERY_BATCH_SIZE).await?;
+
+    for entry in &due {
+        let outcome = deliver(client, entry).await;
+        let attempts = entry.attempts + 1;
+        let retry_at = (!outcome.succeeded() && attempts < config.max_attempts)
+            .then(|| Utc::now() + retry_delay(config, attempts));
+        webhooks.record_webhook_attempt(entry, &outcome, retry_at).await?;
+    }
+
+    Ok(due.len())
+}
+
+/// Periodically deliver due webhook events in the background.
+/// Events stay in the store's outbox, so deliveries interrupted by a restart of a persistent store resume afterwards.
+pub fn spawn_webhook_dispatcher<W: WebhookRepository>(webhooks: W, config: WebhookConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .build()
+            .expect("Failed to build webhook HTTP client");
+        let mut interval = tokio::time::interval(config.poll_interval);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = deliver_due_webhooks(&webhooks, &client, &config).await {
+                eprintln!("Failed to deliver webhooks: {:?}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Router};
+    use tokio::net::TcpListener;
+    use crate::config::PaymentConfig;
+    use crate::utils::{create_order, create_webhook_subscription, get_webhook_deliveries, init_db, DbPool, Order,
+                       SqliteStore, WebhookEvent, WebhookSubscription};
+
+    const SECRET: &str = "0123456789abcdef";
+
+    #[derive(Clone, Default)]
+    struct Receiver {
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        /// Statuses answered before the receiver starts accepting
+        failures: Arc<Mutex<Vec<StatusCode>>>,
+    }
+
+    async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: String) -> StatusCode {
+        receiver.requests.lock().unwrap().push((headers, body));
+        receiver.failures.lock().unwrap().pop().unwrap_or(StatusCode::NO_CONTENT)
+    }
+
+    /// Start a local HTTP receiver and return its URL
+    async fn start_receiver(receiver: Receiver) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/hooks", post(receive)).with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/hooks", addr)
+    }
+
+    fn config() -> WebhookConfig {
+        WebhookConfig {
+            retry_base_delay: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    async fn subscribe(pool: &DbPool, url: String) -> WebhookSubscription {
+        create_webhook_subscription(pool, &WebhookSubscription {
+            id: 0,
+            url,
+            event_types: vec![WebhookEvent::Created],
+            secret: SECRET.to_string(),
+        }).await.unwrap()
+    }
+
+    async fn create_test_order(pool: &DbPool) {
+        create_order(pool, &Order {
+            id: 1,
+            item: "Difference Engine".to_string(),
+            status: "pending".to_string(),
+            quantity: 1,
+            ..Default::default()
+        }, &PaymentConfig::default()).await.unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_cap() {
+        let config = WebhookConfig {
+            retry_base_delay: Duration::from_secs(10),
+            max_retry_delay: Duration::from_secs(60),
+            ..Default::default()
+        };
+
+        assert_eq!(retry_delay(&config, 1), Duration::from_secs(10));
+        assert_eq!(retry_delay(&config, 2), Duration::from_secs(20));
+        assert_eq!(retry_delay(&config, 3), Duration::from_secs(40));
+        assert_eq!(retry_delay(&config, 4), Duration::from_secs(60));
+        assert_eq!(retry_delay(&config, 100), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_sign_payload_matches_known_vector() {
+        // echo -n '1700000000.{"a":1}' | openssl dgst -sha256 -hmac 0123456789abcdef
+        assert_eq!(
+            sign_payload(SECRET, 1700000000, r#"{"a":1}"#),
+            "9eb18f493f8ec135d9eb2dad817c369bb4e9cbfa818657897a7437c1cd8c3a23"
+        );
+        assert_ne!(sign_payload(SECRET, 1700000000, "{}"), sign_payload(SECRET, 1700000001, "{}"));
+    }
+
+    #[tokio::test]
+    async fn test_delivers_signed_event() {
+        let store = SqliteStore::new(init_db().await.unwrap());
+        let pool = store.pool();
+        let receiver = Receiver::default();
+        subscribe(pool, start_receiver(receiver.clone()).await).await;
+        create_test_order(pool).await;
+
+        let attempts = deliver_due_webhooks(&store, &reqwest::Client::new(), &config()).await.unwrap();
+        assert_eq!(attempts, 1);
+
+        let requests = receiver.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        let (headers, body) = &requests[0];
+        assert_eq!(headers[EVENT_HEADER], "order.created");
+        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            format!("sha256={}", sign_payload(SECRET, timestamp, body))
+        );
+
+        let event: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(event["type"], "order.created");
+        assert_eq!(event["id"], headers[ID_HEADER].to_str().unwrap());
+        assert_eq!(event["data"]["order"]["item"], "Difference Engine");
+
+        // Delivered events are not sent again
+        assert_eq!(deliver_due_webhooks(&store, &reqwest::Client::new(), &config()).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_delivery_with_same_event_id() {
+        let store = SqliteStore::new(init_db().await.unwrap());
+        let pool = store.pool();
+        let receiver = Receiver::default();
+        receiver.failures.lock().unwrap().push(StatusCode::SERVICE_UNAVAILABLE);
+        let subscription = subscribe(pool, start_receiver(receiver.clone()).await).await;
+        create_test_order(pool).await;
+
+        let client = reqwest::Client::new();
+        deliver_due_webhooks(&store, &client, &config()).await.unwrap();
+        deliver_due_webhooks(&store, &client, &config()).await.unwrap();
+
+        let requests = receiver.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0[ID_HEADER], requests[1].0[ID_HEADER]);
+
+        let log = get_webhook_deliveries(pool, subscription.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log[0].succeeded);
+        assert_eq!(log[1].response_status, Some(503));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let store = SqliteStore::new(init_db().await.unwrap());
+        let pool = store.pool();
+        // Nothing listens on the discard port, so every attempt fails to connect
+        let subscription = subscribe(pool, "http://127.0.0.1:9/hooks".to_string()).await;
+        create_test_order(pool).await;
+
+        let config = WebhookConfig { max_attempts: 2, ..config() };
+        let client = reqwest::Client::new();
+        assert_eq!(deliver_due_webhooks(&store, &client, &config).await.unwrap(), 1);
+        assert_eq!(deliver_due_webhooks(&store, &client, &config).await.unwrap(), 1);
+        assert_eq!(deliver_due_webhooks(&store, &client, &config).await.unwrap(), 0);
+
+        let log = get_webhook_deliveries(pool, subscription.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log.iter().all(|delivery| !delivery.succeeded && delivery.error.is_some()));
+    }
+}
@@ -0,0 +1,83 @@
+use axum::{routing::get, Router};
+use axum_test::TestServer;
+use rustapi::{
+    init_db, validate_order, AppBuilder, InMemoryStore, Order, OrderRepository, RateLimitConfig,
+    Role, SqliteStore, Store
+};
+use serde_json::{json, Value};
+
+/// Host application with the order API over `store` nested under `/orders-api`, authenticated with a fresh admin key
+async fn embedded_server<S: Store + Clone>(store: S, builder: impl FnOnce(AppBuilder<S>) -> Router) -> TestServer {
+    let (_, secret) = store.create_api_key("host-app", Role::Admin).await.unwrap();
+    let app = Router::new()
+        .route("/", get(|| async { "host application" }))
+        .nest("/orders-api", builder(AppBuilder::new(store)));
+
+    let mut server = TestServer::new(app).unwrap();
+    server.add_header("x-api-key", secret);
+    server
+}
+
+async fn sqlite_store() -> SqliteStore {
+    SqliteStore::new(init_db().await.expect("Failed to initialize test database"))
+}
+
+#[tokio::test]
+async fn test_order_api_nested_in_host_application() {
+    let server = embedded_server(sqlite_store().await, |builder| builder.build()).await;
+
+    server.get("/").await.assert_text("host application");
+    let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 2, "unit_price_minor": 150});
+    // Totals are computed by the server and never read back into an `Order`, so check the raw body
+    let created: Value = server.post("/orders-api/v1/orders").json(&order).await.json();
+    assert_eq!(created["total_minor"], 300);
+
+    let orders: Vec<Order> = server.get("/orders-api/v1/orders").await.json();
+    assert_eq!(orders.len(), 1);
+    server.get("/orders-api/healthz").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_builder_takes_store_and_middleware_settings() {
+    let store = InMemoryStore::default();
+    let server = embedded_server(store.clone(), |builder| {
+        builder
+            .rate_limit(RateLimitConfig::default())
+            .max_body_bytes(256)
+            .build()
+    }).await;
+
+    let order = json!({"id": 7, "item": "Gadget", "status": "pending", "quantity": 1});
+    server.post("/orders-api/v1/orders").json(&order).await.assert_status_ok();
+    assert!(store.get_order(7).await.unwrap().is_some());
+
+    let oversized = json!({"id": 8, "item": "x".repeat(512), "status": "pending", "quantity": 1});
+    server.post("/orders-api/v1/orders").json(&oversized).await.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_in_memory_store_serves_every_resource() {
+    let server = embedded_server(InMemoryStore::default(), |builder| builder.build()).await;
+
+    let order = json!({"id": 1, "item": "Widget", "status": "pending", "quantity": 1, "sku": "WID-1", "customer_id": 3});
+    server.post("/orders-api/v1/orders").json(&order).await.assert_status_bad_request();
+
+    // References are accepted once they exist, as they are with SQLite
+    server.post("/orders-api/v1/products").json(&json!({"sku": "WID-1", "name": "Widget", "stock": 5})).await
+        .assert_status_ok();
+    server.post("/orders-api/v1/customers").json(&json!({"id": 3, "name": "Ada", "email": "ada@example.com"})).await
+        .assert_status_ok();
+    server.post("/orders-api/v1/orders").json(&order).await.assert_status_ok();
+
+    let product: Value = server.get("/orders-api/v1/products/WID-1").await.json();
+    assert_eq!(product["stock"], 4);
+    server.get("/orders-api/v1/customers/3/orders").await.assert_status_ok();
+    server.get("/orders-api/v1/orders/1/payments").await.assert_status_ok();
+}
+
+#[test]
+fn test_validators_are_exported() {
+    let order = Order { id: 1, item: String::new(), status: "pending".to_string(), quantity: 1, ..Default::default() };
+    let error = validate_order(&order).unwrap_err();
+    assert_eq!(error.field.as_deref(), Some("item"));
+}